@@ -0,0 +1,66 @@
+//! Comparing small fractions to large rationals, compared to converting the
+//! fraction to a multi-precision rational first. GMP allocates outside of the
+//! Rust allocator, so only the times are comparable.
+//!
+//! Run with `cargo bench --bench coefficient`.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::bench;
+use rug::{Integer, Rational as MultiPrecisionRational};
+use symbolica::{
+    coefficient::CoefficientView,
+    representations::{Atom, AtomView},
+};
+
+fn views(atoms: &[Atom]) -> Vec<CoefficientView<'_>> {
+    atoms
+        .iter()
+        .map(|a| match a.as_view() {
+            AtomView::Num(n) => n.get_coeff_view(),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+fn main() {
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    let base = Integer::from(Integer::u_pow_u(7, 50));
+    let mut naturals = vec![];
+    let mut larges = vec![];
+    for _ in 0..1000 {
+        let n = next();
+        naturals.push(Atom::new_num(((n >> 1) as i64, (n % 1000 + 1) as i64)));
+        let n = next();
+        larges.push(Atom::new_num(MultiPrecisionRational::from((
+            Integer::from(&base * n),
+            Integer::from(&base >> 30) + 1,
+        ))));
+    }
+
+    let naturals = views(&naturals);
+    let larges = views(&larges);
+
+    bench("natural vs large (cmp)", 1000, || {
+        for (n, l) in naturals.iter().zip(&larges) {
+            black_box(n.cmp(l));
+        }
+    });
+
+    bench("natural vs large (allocating)", 1000, || {
+        for (n, l) in naturals.iter().zip(&larges) {
+            if let (CoefficientView::Natural(n, d), CoefficientView::Large(l)) = (n, l) {
+                black_box(MultiPrecisionRational::from((*n, *d)).cmp(&l.to_rat()));
+            }
+        }
+    });
+}
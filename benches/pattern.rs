@@ -0,0 +1,37 @@
+//! Repeated replacements with a compiled pattern, compared to a pattern
+//! that is converted from an expression in every iteration.
+//!
+//! Run with `cargo bench --bench pattern`.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::bench;
+use symbolica::{id::Pattern, representations::Atom};
+
+fn main() {
+    let mut terms = vec![];
+    for i in 0..30 {
+        terms.push(format!("f({i})*g({i})*x^{i} + h({i},x)*y"));
+    }
+    let target = Atom::parse(&terms.join(" + ")).unwrap();
+
+    let lhs = Atom::parse("f(x_)*g(x_)*x^2").unwrap();
+    let rhs = Pattern::parse("k(x_)").unwrap();
+
+    bench("replace_all (pattern converted)", 10000, || {
+        let pat = lhs.as_view().into_pattern();
+        black_box(pat.replace_all(target.as_view(), &rhs, None, None));
+    });
+
+    let pat = lhs.as_view().into_pattern();
+    bench("replace_all (pattern reused)", 10000, || {
+        black_box(pat.replace_all(target.as_view(), &rhs, None, None));
+    });
+
+    let compiled = pat.compile();
+    bench("replace_all (pattern compiled)", 10000, || {
+        black_box(compiled.replace_all(target.as_view(), &rhs, None, None));
+    });
+}
@@ -0,0 +1,51 @@
+//! Equality of many unequal large rationals, compared to comparing the
+//! underlying multi-precision rationals directly.
+//!
+//! Run with `cargo bench --bench rational`.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::bench;
+use rug::{Integer, Rational as MultiPrecisionRational};
+use symbolica::domains::rational::Rational;
+
+fn main() {
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    // pairs that differ in sign, in size, or only in the last digits
+    let base = Integer::from(Integer::u_pow_u(5, 200));
+    let mut pairs = vec![];
+    for i in 0..1000 {
+        let n = Integer::from(&base * next());
+        let d: Integer = Integer::from(&base >> 100) + 1;
+        let a = MultiPrecisionRational::from((n.clone(), d.clone()));
+        let b = match i % 3 {
+            0 => -a.clone(),
+            1 => MultiPrecisionRational::from((n << 70, d)),
+            _ => MultiPrecisionRational::from((n + 1, d)),
+        };
+        pairs.push((Rational::from_large(a), Rational::from_large(b)));
+    }
+
+    bench("unequal large (eq)", 10000, || {
+        for (a, b) in &pairs {
+            black_box(a == b);
+        }
+    });
+
+    bench("unequal large (rug eq)", 10000, || {
+        for (a, b) in &pairs {
+            if let (Rational::Large(a), Rational::Large(b)) = (a, b) {
+                black_box(a == b);
+            }
+        }
+    });
+}
@@ -0,0 +1,41 @@
+//! Sorting large rationals with the exact comparison, compared to sorting
+//! them as `SortableRational`s that first compare an approximation.
+//!
+//! Run with `cargo bench --bench sort`.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::bench;
+use rug::{Integer, Rational as MultiPrecisionRational};
+use symbolica::domains::rational::{Rational, SortableRational};
+
+fn main() {
+    // deterministic pseudo-random rationals that do not fit in 64 bits
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let base = Integer::from(Integer::u_pow_u(3, 80));
+    let values: Vec<Rational> = (0..100000)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let num = Integer::from(&base * seed) + (seed >> 3);
+            let den = Integer::from(&base >> 20) + (seed & 0xffff) + 1;
+            Rational::from_large(MultiPrecisionRational::from((num, den)))
+        })
+        .collect();
+
+    bench("sort (exact)", 10, || {
+        let mut v = values.clone();
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        black_box(v);
+    });
+
+    bench("sort (SortableRational)", 10, || {
+        let mut v: Vec<SortableRational> =
+            values.iter().cloned().map(SortableRational::new).collect();
+        v.sort();
+        black_box(v);
+    });
+}
@@ -0,0 +1,70 @@
+//! Symbol lookups through the locked global state, compared to lookups
+//! through a thread-local handle that caches them, on one and on several threads.
+//!
+//! Run with `cargo bench --bench state`.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::bench;
+use symbolica::state::State;
+
+const THREADS: usize = 4;
+
+fn main() {
+    let names: Vec<String> = (0..100).map(|i| format!("bench_state_x{}", i)).collect();
+    for n in &names {
+        State::get_symbol(n);
+    }
+
+    bench("get_symbol (locked)", 1000, || {
+        for n in &names {
+            black_box(State::get_symbol(n));
+        }
+    });
+
+    let local = State::local();
+    bench("get_symbol (local)", 1000, || {
+        for n in &names {
+            black_box(local.get_symbol(n));
+        }
+    });
+
+    bench(
+        &format!("get_symbol (locked, {} threads)", THREADS),
+        10,
+        || {
+            std::thread::scope(|s| {
+                for _ in 0..THREADS {
+                    s.spawn(|| {
+                        for _ in 0..100 {
+                            for n in &names {
+                                black_box(State::get_symbol(n));
+                            }
+                        }
+                    });
+                }
+            });
+        },
+    );
+
+    bench(
+        &format!("get_symbol (local, {} threads)", THREADS),
+        10,
+        || {
+            std::thread::scope(|s| {
+                for _ in 0..THREADS {
+                    s.spawn(|| {
+                        let local = State::local();
+                        for _ in 0..100 {
+                            for n in &names {
+                                black_box(local.get_symbol(n));
+                            }
+                        }
+                    });
+                }
+            });
+        },
+    );
+}
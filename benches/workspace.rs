@@ -0,0 +1,46 @@
+//! The time and the number of allocations of normalization and pattern matching,
+//! with and without recycling intermediate atoms through the workspace.
+//!
+//! Run with `cargo bench --bench workspace`.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::bench;
+use symbolica::{id::Pattern, representations::Atom, state::Workspace};
+
+fn run(label: &str) {
+    let expr = Atom::parse("(x + y + f(x, 2*y))^4").unwrap();
+    bench(&format!("expand ({})", label), 100, || {
+        black_box(expr.expand());
+    });
+
+    let a = Atom::parse("x*f(y)*2").unwrap();
+    let b = Atom::parse("x^2*y*f(y)^-1*3").unwrap();
+    bench(&format!("multiply ({})", label), 10000, || {
+        black_box(&a * &b);
+    });
+
+    let target = Atom::parse("f(1,2)*f(3,4)*x + f(x,y)^2 + g(f(5,6))").unwrap();
+    let pat = Pattern::parse("f(x_,y_)").unwrap();
+    let rhs = Pattern::parse("f(y_,x_+1)").unwrap();
+    bench(&format!("replace_all ({})", label), 10000, || {
+        black_box(pat.replace_all(target.as_view(), &rhs, None, None));
+    });
+    bench(&format!("replace_first ({})", label), 10000, || {
+        black_box(pat.replace_first(target.as_view(), &rhs, None, None));
+    });
+}
+
+fn main() {
+    run("recycled");
+
+    let capacity = Workspace::get_local().with(|ws| {
+        let capacity = ws.get_buffer_capacity();
+        ws.set_buffer_capacity(0);
+        capacity
+    });
+    run("not recycled");
+    Workspace::get_local().with(|ws| ws.set_buffer_capacity(capacity));
+}
@@ -55,6 +55,7 @@ fn main() {
         square_brackets_for_function: false,
         num_exp_as_superscript: false,
         latex: false,
+        max_display_digits: None,
     };
 
     buffer.clear();
@@ -168,6 +168,7 @@ unsafe extern "C" fn simplify(
         square_brackets_for_function: false,
         num_exp_as_superscript: false,
         latex: false,
+        max_display_digits: None,
     };
 
     macro_rules! to_rational {
@@ -304,6 +305,7 @@ unsafe extern "C" fn simplify_factorized(
         square_brackets_for_function: false,
         num_exp_as_superscript: false,
         latex: false,
+        max_display_digits: None,
     };
 
     macro_rules! to_rational {
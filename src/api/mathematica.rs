@@ -95,6 +95,7 @@ fn simplify(input: String, prime: i64, explicit_rational_polynomial: bool) -> St
                             square_brackets_for_function: false,
                             num_exp_as_superscript: false,
                             latex: false,
+                            max_display_digits: None,
                         },
                         add_parentheses: false
                     }
@@ -131,6 +132,7 @@ fn simplify(input: String, prime: i64, explicit_rational_polynomial: bool) -> St
                                 square_brackets_for_function: false,
                                 num_exp_as_superscript: false,
                                 latex: false,
+                                max_display_digits: None,
                             },
                             add_parentheses: false
                         }
@@ -166,6 +168,7 @@ fn simplify(input: String, prime: i64, explicit_rational_polynomial: bool) -> St
                                 square_brackets_for_function: false,
                                 num_exp_as_superscript: false,
                                 latex: false,
+                                max_display_digits: None,
                             },
                             add_parentheses: false
                         }
@@ -20,6 +20,7 @@ use smallvec::SmallVec;
 use smartstring::{LazyCompact, SmartString};
 
 use crate::{
+    coefficient::Coefficient,
     domains::{
         finite_field::{ToFiniteField, Zp},
         float::Complex,
@@ -813,7 +814,8 @@ impl PythonPattern {
                 multiplication_operator,
                 square_brackets_for_function,
                 num_exp_as_superscript,
-                latex
+                latex,
+                max_display_digits: State::get_max_display_digits(),
             },)
         );
     }
@@ -1361,13 +1363,22 @@ impl PythonExpression {
         }
     }
 
+    /// The Euler–Mascheroni constant `γ`.
+    #[classattr]
+    #[pyo3(name = "EULER_GAMMA")]
+    pub fn euler_gamma() -> PythonExpression {
+        PythonExpression {
+            expr: Arc::new(Atom::new_var(State::EULER_GAMMA)),
+        }
+    }
+
     /// The mathematical constant `i`, where
     /// `i^2 = -1`.
     #[classattr]
     #[pyo3(name = "I")]
     pub fn i() -> PythonExpression {
         PythonExpression {
-            expr: Arc::new(Atom::new_var(State::I)),
+            expr: Arc::new(Atom::new_num(Coefficient::i())),
         }
     }
 
@@ -1467,7 +1478,8 @@ impl PythonExpression {
                     multiplication_operator,
                     square_brackets_for_function,
                     num_exp_as_superscript,
-                    latex
+                    latex,
+                    max_display_digits: State::get_max_display_digits(),
                 },
             )
         ))
@@ -3606,7 +3618,8 @@ macro_rules! generate_methods {
                                 multiplication_operator,
                                 square_brackets_for_function,
                                 num_exp_as_superscript,
-                                latex
+                                latex,
+                                max_display_digits: State::get_max_display_digits(),
                             },
                         )
                     ))
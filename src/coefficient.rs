@@ -1,6 +1,7 @@
 use std::{
     cmp::Ordering,
-    ops::{Add, Div, Mul},
+    hash::{Hash, Hasher},
+    ops::{Add, AddAssign, Div, Mul, MulAssign},
     sync::Arc,
 };
 
@@ -8,6 +9,7 @@ use ahash::HashMap;
 use rug::{
     integer::Order,
     ops::{NegAssign, Pow as RPow},
+    rational::MiniRational,
     Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational,
 };
 use smallvec::{smallvec, SmallVec};
@@ -16,15 +18,20 @@ use crate::{
     domains::{
         finite_field::{
             FiniteField, FiniteFieldCore, FiniteFieldElement, FiniteFieldWorkspace, ToFiniteField,
+            Zp64,
         },
-        integer::{Integer, IntegerRing, Z},
-        rational::{Rational, RationalField},
-        rational_polynomial::RationalPolynomial,
+        float::Float,
+        integer::{Integer, IntegerRing, SMALL_PRIMES, Z},
+        interval::Interval,
+        rational::{Rational, RationalField, Q},
+        rational_polynomial::{RationalPolynomial, RationalPolynomialField},
         EuclideanDomain, Field, Ring,
     },
-    poly::{polynomial::MultivariatePolynomial, Variable, INLINED_EXPONENTS},
-    representations::{Atom, AtomView},
-    state::{FiniteFieldIndex, State, Workspace},
+    poly::{
+        gcd::LARGE_U32_PRIMES, polynomial::MultivariatePolynomial, Variable, INLINED_EXPONENTS,
+    },
+    representations::{Atom, AtomView, PackedRationalNumberReader},
+    state::{FieldRemap, FiniteFieldIndex, State, Workspace},
 };
 
 pub trait ConvertToRing: Ring {
@@ -39,13 +46,98 @@ pub trait ConvertToRing: Ring {
 }
 
 /// A coefficient that can appear in a Symbolica expression.
-/// In most cases, this is a rational number but it can also be a finite field element or
-/// a rational polynomial.
+/// In most cases, this is a rational number but it can also be a finite field element,
+/// a rational polynomial, an arbitrary-precision float or a complex number.
+///
+/// Arithmetic between a float and a rational number yields a float.
+///
+/// Coefficients are totally ordered: real numbers are ordered by value, with a rational
+/// number before a float of the same value, followed by finite field elements and
+/// rational polynomials. Complex numbers are ordered by their real and imaginary part.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Coefficient {
     Rational(Rational),
     FiniteField(FiniteFieldElement<u64>, FiniteFieldIndex),
     RationalPolynomial(RationalPolynomial<IntegerRing, u16>),
+    Float(Float),
+    /// A complex number with a real and imaginary part that are rational numbers or floats,
+    /// and a non-zero imaginary part. Construct it with [`Coefficient::complex`].
+    Complex(Box<(Coefficient, Coefficient)>),
+}
+
+/// The rounding of the quotient in [`CoefficientView::div_round`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round towards zero.
+    Trunc,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards positive infinity.
+    Ceil,
+    /// Round to the nearest integer, with ties rounded away from zero.
+    Nearest,
+}
+
+/// Errors that can occur when combining coefficients from incompatible domains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoefficientError {
+    /// The operands are elements of finite fields with different primes.
+    DifferentFiniteFields { p1: u64, p2: u64 },
+    /// A finite field element is combined with a number that is not in a finite field.
+    MixedFiniteField,
+    /// The operation is not defined for the two kinds of coefficients.
+    Unsupported(&'static str, &'static str),
+    /// The denominator of a rational number is divisible by the prime of the finite field.
+    DivisionByZero { prime: u64 },
+    /// Zero is raised to a negative power.
+    ZeroToNegativePower,
+    /// The power, given as a string, is too large to compute.
+    PowerTooLarge(String),
+    /// The combination of base and exponent, described by the string, is not supported.
+    UnsupportedPower(String),
+    /// A modulus in the Chinese remainder theorem is not positive.
+    NonPositiveModulus(Integer),
+    /// A modulus in the Chinese remainder theorem is not coprime to the other moduli.
+    NonCoprimeModulus(Integer),
+    /// No rational number could be reconstructed from its image.
+    ReconstructionFailed(&'static str),
+}
+
+impl std::fmt::Display for CoefficientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoefficientError::DifferentFiniteFields { p1, p2 } => write!(
+                f,
+                "Cannot combine numbers from different finite fields: p1={}, p2={}",
+                p1, p2
+            ),
+            CoefficientError::MixedFiniteField => write!(
+                f,
+                "Cannot combine a finite field element with a non-finite number. Convert the other number first"
+            ),
+            CoefficientError::Unsupported(a, b) => {
+                write!(f, "Cannot combine a {} and a {}", a, b)
+            }
+            CoefficientError::DivisionByZero { prime } => write!(
+                f,
+                "Cannot convert to a finite field with prime {}: division by 0",
+                prime
+            ),
+            CoefficientError::ZeroToNegativePower => write!(
+                f,
+                "Division by zero: 0 cannot be raised to a negative power"
+            ),
+            CoefficientError::PowerTooLarge(e) => write!(f, "Power is too large: {}", e),
+            CoefficientError::UnsupportedPower(e) => write!(f, "Cannot compute the power {}", e),
+            CoefficientError::NonPositiveModulus(n) => write!(f, "Modulus {} is not positive", n),
+            CoefficientError::NonCoprimeModulus(n) => {
+                write!(f, "Modulus {} is not coprime to the other moduli", n)
+            }
+            CoefficientError::ReconstructionFailed(e) => {
+                write!(f, "Rational reconstruction failed: {}", e)
+            }
+        }
+    }
 }
 
 impl From<i64> for Coefficient {
@@ -91,6 +183,12 @@ impl From<MultiPrecisionRational> for Coefficient {
     }
 }
 
+impl From<Float> for Coefficient {
+    fn from(value: Float) -> Self {
+        Coefficient::Float(value)
+    }
+}
+
 impl From<Rational> for Coefficient {
     fn from(value: Rational) -> Self {
         Coefficient::Rational(value)
@@ -116,11 +214,569 @@ impl Coefficient {
         Coefficient::Rational(Rational::one())
     }
 
+    /// Create the complex number `re + im*i`, where `re` and `im` must be rational numbers
+    /// or floats. If the imaginary part is zero, the real part is returned.
+    pub fn complex(re: Coefficient, im: Coefficient) -> Coefficient {
+        for c in [&re, &im] {
+            if !matches!(c, Coefficient::Rational(_) | Coefficient::Float(_)) {
+                panic!("The parts of a complex number must be rational numbers or floats");
+            }
+        }
+
+        if im.is_zero() {
+            re
+        } else {
+            Coefficient::Complex(Box::new((re, im)))
+        }
+    }
+
+    /// The imaginary unit.
+    pub fn i() -> Coefficient {
+        Coefficient::complex(Coefficient::zero(), Coefficient::one())
+    }
+
+    /// Approximate the float `x` by the rational number closest to it whose denominator
+    /// is at most `max_denominator`. This lifts numerical results, for example from fits,
+    /// to exact coefficients.
+    pub fn from_f64_approx(x: f64, max_denominator: &Integer) -> Coefficient {
+        Coefficient::Rational(Rational::from_f64(x).truncate_denominator(max_denominator))
+    }
+
+    /// Compute the continued fraction `[a0; a1, ..., an]` of a rational number or float.
+    /// See [`Rational::continued_fraction`].
+    pub fn continued_fraction(&self) -> Result<Vec<Integer>, String> {
+        match self {
+            Coefficient::Rational(r) => Ok(r.continued_fraction()),
+            Coefficient::Float(f) => Ok(f.to_rational().continued_fraction()),
+            _ => Err(format!(
+                "Cannot compute the continued fraction of a {}",
+                self.kind()
+            )),
+        }
+    }
+
+    /// Compute `1/self` for a rational number, float or complex number.
+    fn inv(&self) -> Coefficient {
+        match self {
+            Coefficient::Rational(r) => {
+                if r.is_zero() {
+                    panic!("Division by zero");
+                }
+                Coefficient::Rational(r.inv())
+            }
+            Coefficient::Float(f) => Coefficient::Float(f.inv()),
+            Coefficient::Complex(c) => {
+                // 1/(a+bi) = (a-bi)/(a^2+b^2)
+                let (re, im) = (&c.0, &c.1);
+                let norm = (re.clone() * re.clone() + im.clone() * im.clone()).inv();
+                Coefficient::complex(
+                    re.clone() * norm.clone(),
+                    im.clone() * norm * Coefficient::from(-1),
+                )
+            }
+            _ => panic!("Cannot invert {:?}", self),
+        }
+    }
+
+    /// For a rational number `r`, write `r^(1/d) = c * s^(1/d)`, where `c` is a positive rational
+    /// and `s` contains no perfect `d`-th powers, and return `(c, s)`. Returns `None` for other
+    /// coefficients. Factors are extracted by trial division by small primes, after which the
+    /// remaining cofactor is only checked for being a perfect power itself.
+    pub(crate) fn extract_root(&self, d: u32) -> Option<(Coefficient, Coefficient)> {
+        let r = match self {
+            Coefficient::Rational(Rational::Natural(n, den)) => {
+                MultiPrecisionRational::from((*n, *den))
+            }
+            Coefficient::Rational(Rational::Large(r)) => r.clone(),
+            _ => return None,
+        };
+
+        let (num, den) = r.into_numer_denom();
+        let is_negative = num.cmp0() == Ordering::Less;
+        let (num_out, mut num_in) = split_integer_root(num.abs(), d);
+        let (den_out, den_in) = split_integer_root(den, d);
+
+        if is_negative {
+            num_in = -num_in;
+        }
+
+        Some((
+            MultiPrecisionRational::from((num_out, den_out)).into(),
+            MultiPrecisionRational::from((num_in, den_in)).into(),
+        ))
+    }
+
+    /// The zero of the finite field `field`.
+    pub fn finite_zero(field: FiniteFieldIndex) -> Coefficient {
+        Coefficient::FiniteField(State::get_finite_field(field).zero(), field)
+    }
+
+    /// Create the element `value` of the finite field `field`, reduced modulo the
+    /// prime. An error is returned if the field does not exist. Prefer this over
+    /// constructing [`Coefficient::FiniteField`] directly, as the latter expects the
+    /// element to be in the internal representation of the field.
+    pub fn finite_field(value: u64, field: FiniteFieldIndex) -> Result<Coefficient, String> {
+        let f = State::try_get_finite_field(field)
+            .ok_or_else(|| format!("Finite field with index {} does not exist", field.0))?;
+        Ok(Coefficient::FiniteField(
+            f.to_element(value % f.get_prime()),
+            field,
+        ))
+    }
+
+    /// The one of the finite field `field`.
+    pub fn finite_one(field: FiniteFieldIndex) -> Coefficient {
+        Coefficient::FiniteField(State::get_finite_field(field).one(), field)
+    }
+
     pub fn is_zero(&self) -> bool {
         match self {
             Coefficient::Rational(r) => r.is_zero(),
             Coefficient::FiniteField(num, _field) => num.0 == 0,
             Coefficient::RationalPolynomial(r) => r.numerator.is_zero(),
+            Coefficient::Float(f) => f.is_zero(),
+            Coefficient::Complex(_) => false,
+        }
+    }
+
+    pub fn is_one(&self) -> bool {
+        match self {
+            Coefficient::Rational(r) => r.is_one(),
+            Coefficient::FiniteField(num, field) => State::get_finite_field(*field).is_one(num),
+            Coefficient::RationalPolynomial(r) => r.numerator.is_one() && r.denominator.is_one(),
+            Coefficient::Float(f) => f.is_one(),
+            Coefficient::Complex(_) => false,
+        }
+    }
+
+    /// Convert the coefficient to the tightest interval of `f64`s that contains it.
+    ///
+    /// Finite field elements, rational polynomials and complex numbers
+    /// have no interval equivalent and will cause a panic.
+    pub fn to_interval(&self) -> Interval {
+        match self {
+            Coefficient::Rational(r) => r.into(),
+            Coefficient::Float(f) => f.into(),
+            Coefficient::FiniteField(_, _) => {
+                panic!("Cannot convert finite field to interval")
+            }
+            Coefficient::RationalPolynomial(_) => {
+                panic!("Cannot convert rational polynomial to interval")
+            }
+            Coefficient::Complex(_) => panic!("Cannot convert complex number to interval"),
+        }
+    }
+
+    /// If the coefficient is a positive integer of the form `2^k`, return `k`.
+    pub fn is_power_of_two(&self) -> Option<u32> {
+        match self {
+            Coefficient::Rational(Rational::Natural(n, 1)) => {
+                if *n > 0 && (*n as u64).is_power_of_two() {
+                    Some(n.trailing_zeros())
+                } else {
+                    None
+                }
+            }
+            Coefficient::Rational(Rational::Large(r)) => {
+                if r.is_integer() && *r.numer() > 0 && r.numer().is_power_of_two() {
+                    Some(r.numer().significant_bits() - 1)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// If the coefficient is a positive integer of the form `10^k`, return `k`.
+    pub fn is_power_of_ten(&self) -> Option<u32> {
+        match self {
+            Coefficient::Rational(Rational::Natural(n, 1)) => {
+                if *n <= 0 {
+                    return None;
+                }
+
+                let mut n = *n;
+                let mut k = 0;
+                while n % 10 == 0 {
+                    n /= 10;
+                    k += 1;
+                }
+
+                if n == 1 {
+                    Some(k)
+                } else {
+                    None
+                }
+            }
+            Coefficient::Rational(Rational::Large(r)) => {
+                if !r.is_integer() || *r.numer() <= 0 {
+                    return None;
+                }
+
+                let (rest, k) = r
+                    .numer()
+                    .clone()
+                    .remove_factor(&MultiPrecisionInteger::from(10));
+                if rest == 1 {
+                    Some(k)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// If the coefficient is an integer that does not fit in an `i64`, return the limbs
+    /// of its absolute value, ordered from least to most significant. The sign can
+    /// be obtained separately, e.g. with [`Rational::is_negative`].
+    pub fn large_limbs(&self) -> Option<&[u64]> {
+        match self {
+            Coefficient::Rational(Rational::Large(r)) if r.is_integer() => {
+                Some(r.numer().as_limbs())
+            }
+            _ => None,
+        }
+    }
+
+    /// Create an integer coefficient from the limbs of its absolute value, ordered
+    /// from least to most significant, and its sign. This is the inverse of
+    /// [`Coefficient::large_limbs`].
+    pub fn from_limbs(limbs: &[u64], negative: bool) -> Coefficient {
+        let mut n = MultiPrecisionInteger::from_digits(limbs, Order::Lsf);
+        if negative {
+            n.neg_assign();
+        }
+        n.into()
+    }
+
+    /// Compute the linear combination `sum_i coeffs[i] * values[i]`.
+    /// Rational terms are accumulated in place, without intermediate coefficients.
+    pub fn weighted_sum(
+        coeffs: &[CoefficientView<'_>],
+        values: &[CoefficientView<'_>],
+    ) -> Result<Coefficient, String> {
+        if coeffs.len() != values.len() {
+            return Err(format!(
+                "Cannot compute a weighted sum of {} coefficients and {} values",
+                coeffs.len(),
+                values.len()
+            ));
+        }
+
+        let mut acc = Coefficient::zero();
+        for (c, v) in coeffs.iter().zip(values) {
+            match (&mut acc, c, v) {
+                (
+                    Coefficient::Rational(r),
+                    CoefficientView::Natural(_, _) | CoefficientView::Large(_),
+                    CoefficientView::Natural(_, _) | CoefficientView::Large(_),
+                ) => {
+                    Q.add_mul_assign(
+                        r,
+                        &Q.element_from_coefficient_view(*c),
+                        &Q.element_from_coefficient_view(*v),
+                    );
+                }
+                _ => {
+                    acc = acc + (*c * *v);
+                }
+            }
+        }
+
+        Ok(acc)
+    }
+
+    /// Compute the greatest common divisor of the rational numbers `nums`, where
+    /// `gcd(a/b, c/d) = gcd(a, c) / lcm(b, d)`. The gcd of an empty list is 0.
+    pub fn gcd_all(nums: &[CoefficientView<'_>]) -> Coefficient {
+        let all_integer = nums.iter().all(|n| n.is_integer());
+
+        let mut acc = Q.zero();
+        for n in nums {
+            acc = Q.gcd(&acc, &Q.element_from_coefficient_view(*n));
+
+            // the gcd of integers cannot decrease further
+            if all_integer && acc.is_one() {
+                break;
+            }
+        }
+
+        Coefficient::Rational(acc)
+    }
+
+    /// Compute the least common multiple of the rational numbers `nums`, where
+    /// `lcm(a/b, c/d) = lcm(a, c) / gcd(b, d)`. The lcm of an empty list is 1.
+    pub fn lcm_all(nums: &[CoefficientView<'_>]) -> Coefficient {
+        let mut acc = Q.one();
+        for n in nums {
+            let n = Q.element_from_coefficient_view(*n);
+            if n.is_zero() {
+                return Coefficient::zero();
+            }
+
+            let g = Q.gcd(&acc, &n);
+            acc = Q.mul(&Q.div(&acc, &g), &n).abs();
+        }
+
+        Coefficient::Rational(acc)
+    }
+
+    /// Map the coefficients `nums` into the finite field `field`. The inverse of every
+    /// distinct denominator modulo the prime is only computed once. An error is returned
+    /// if a denominator is divisible by the prime or if a coefficient is not rational
+    /// and not an element of `field`.
+    pub fn reduce_array_mod_p(
+        nums: &[Coefficient],
+        field: FiniteFieldIndex,
+    ) -> Result<Vec<FiniteFieldElement<u64>>, String> {
+        let f = State::get_finite_field(field);
+        let mut inverses: HashMap<u64, FiniteFieldElement<u64>> = HashMap::default();
+
+        nums.iter()
+            .map(|n| match n {
+                Coefficient::Rational(r) => {
+                    let num = r.numerator().to_finite_field(f);
+                    if r.is_integer() {
+                        return Ok(num);
+                    }
+
+                    let den = r.denominator().to_finite_field(f);
+                    let key = f.from_element(&den);
+                    if key == 0 {
+                        return Err(format!(
+                            "Cannot convert {} to a finite field with prime {}: division by 0",
+                            r,
+                            f.get_prime()
+                        ));
+                    }
+
+                    let inv = inverses.entry(key).or_insert_with(|| f.inv(&den));
+                    Ok(f.mul(&num, inv))
+                }
+                Coefficient::FiniteField(e, i) if *i == field => Ok(*e),
+                Coefficient::FiniteField(_, _) => {
+                    Err("Cannot convert an element of a different finite field".into())
+                }
+                Coefficient::RationalPolynomial(_) => {
+                    Err("Cannot convert a rational polynomial to a finite field".into())
+                }
+                Coefficient::Float(_) => Err("Cannot convert a float to a finite field".into()),
+                Coefficient::Complex(_) => {
+                    Err("Cannot convert a complex number to a finite field".into())
+                }
+            })
+            .collect()
+    }
+
+    /// Compute the product of `nums`. The product of rational numbers is accumulated
+    /// in machine-size integers until it overflows, after which a single multi-precision
+    /// accumulator is updated in place. The product of an empty list is 1.
+    pub fn product_all(nums: &[CoefficientView<'_>]) -> Coefficient {
+        if nums
+            .iter()
+            .any(|n| matches!(n, CoefficientView::Natural(0, _)))
+        {
+            return Coefficient::zero();
+        }
+
+        let (mut num, mut den) = (1i64, 1i64);
+        let mut large: Option<MultiPrecisionRational> = None;
+        let mut rest: Option<Coefficient> = None;
+
+        for n in nums {
+            match n {
+                &CoefficientView::Natural(n, d) => {
+                    if let Some(l) = &mut large {
+                        *l *= &*MiniRational::from((n, d)).borrow();
+                    } else if let (Some(nn), Some(dd)) = (num.checked_mul(n), den.checked_mul(d)) {
+                        (num, den) = (nn, dd);
+                    } else {
+                        let mut l = MultiPrecisionRational::from((num, den));
+                        l *= &*MiniRational::from((n, d)).borrow();
+                        large = Some(l);
+                    }
+                }
+                CoefficientView::Large(r) => {
+                    *large.get_or_insert_with(|| MultiPrecisionRational::from((num, den))) *=
+                        &r.to_rat();
+                }
+                _ => {
+                    rest = Some(match rest {
+                        Some(r) => r * n.to_owned(),
+                        None => n.to_owned(),
+                    });
+                }
+            }
+        }
+
+        let res = match large {
+            Some(l) => l.into(),
+            None => Coefficient::from((num, den)),
+        };
+
+        match rest {
+            Some(r) => res * r,
+            None => res,
+        }
+    }
+
+    /// Find the `x` in the range `[0, m)`, with `m` the product of all moduli,
+    /// such that `x ≡ r mod n` for every pair `(r, n)` in `residues`.
+    /// The moduli must be positive and pairwise coprime.
+    pub fn chinese_remainder(
+        residues: &[(Integer, Integer)],
+    ) -> Result<(Integer, Integer), CoefficientError> {
+        let mut x = Integer::zero();
+        let mut m = Integer::one();
+
+        for (r, n) in residues {
+            if n <= &Integer::zero() {
+                return Err(CoefficientError::NonPositiveModulus(n.clone()));
+            }
+            if !Z.gcd(&m, n).is_one() {
+                return Err(CoefficientError::NonCoprimeModulus(n.clone()));
+            }
+
+            let r = Z.quot_rem(r, n).1;
+            x = Integer::chinese_remainder(x, r, m.clone(), n.clone());
+            m = &m * n;
+            if x.is_negative() {
+                x += &m;
+            }
+        }
+
+        Ok((x, m))
+    }
+
+    /// Reconstruct a rational number `q` such that `q ≡ image mod modulus`,
+    /// using maximal quotient rational reconstruction. The modulus should be
+    /// sufficiently large compared to the numerator and denominator of `q`.
+    pub fn rational_reconstruct(
+        image: &Integer,
+        modulus: &Integer,
+    ) -> Result<Coefficient, CoefficientError> {
+        Rational::maximal_quotient_reconstruction(image, modulus, None)
+            .map(Coefficient::Rational)
+            .map_err(CoefficientError::ReconstructionFailed)
+    }
+
+    /// Convert the coefficient to an element of the finite field `field`.
+    /// Elements of `field` are returned as is.
+    pub fn to_finite_field(
+        &self,
+        field: FiniteFieldIndex,
+    ) -> Result<Coefficient, CoefficientError> {
+        let f = State::get_finite_field(field);
+        match self {
+            Coefficient::Rational(r) => {
+                let den = r.denominator().to_finite_field(f);
+                if FiniteField::<u64>::is_zero(&den) {
+                    return Err(CoefficientError::DivisionByZero {
+                        prime: f.get_prime(),
+                    });
+                }
+                let num = r.numerator().to_finite_field(f);
+                Ok(Coefficient::FiniteField(f.div(&num, &den), field))
+            }
+            Coefficient::FiniteField(e, i) if *i == field => {
+                Ok(Coefficient::FiniteField(*e, field))
+            }
+            Coefficient::FiniteField(_, i) => Err(CoefficientError::DifferentFiniteFields {
+                p1: State::get_finite_field(*i).get_prime(),
+                p2: f.get_prime(),
+            }),
+            _ => Err(CoefficientError::Unsupported(
+                self.kind(),
+                "finite field element",
+            )),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Coefficient::Rational(_) => "rational number",
+            Coefficient::FiniteField(_, _) => "finite field element",
+            Coefficient::RationalPolynomial(_) => "rational polynomial",
+            Coefficient::Float(_) => "float",
+            Coefficient::Complex(_) => "complex number",
+        }
+    }
+
+    /// The class of the coefficient in the total order, which orders
+    /// real and complex numbers before finite field elements and rational polynomials.
+    fn order_class(&self) -> u8 {
+        match self {
+            Coefficient::Rational(_) | Coefficient::Float(_) | Coefficient::Complex(_) => 0,
+            Coefficient::FiniteField(_, _) => 1,
+            Coefficient::RationalPolynomial(_) => 2,
+        }
+    }
+}
+
+impl Hash for Coefficient {
+    /// Hash the coefficient, consistent with the hash of its [`CoefficientView`].
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Coefficient::Rational(r) => {
+                0u8.hash(state);
+                r.hash(state);
+            }
+            Coefficient::FiniteField(n, fi) => {
+                1u8.hash(state);
+                n.hash(state);
+                fi.hash(state);
+            }
+            Coefficient::RationalPolynomial(r) => {
+                2u8.hash(state);
+                r.hash(state);
+            }
+            Coefficient::Float(f) => {
+                3u8.hash(state);
+                f.hash(state);
+            }
+            Coefficient::Complex(c) => {
+                4u8.hash(state);
+                c.0.hash(state);
+                c.1.hash(state);
+            }
+        }
+    }
+}
+
+impl PartialOrd for Coefficient {
+    fn partial_cmp(&self, other: &Coefficient) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Coefficient {
+    fn cmp(&self, other: &Coefficient) -> Ordering {
+        match (self, other) {
+            (Coefficient::Rational(r1), Coefficient::Rational(r2)) => r1.partial_cmp(r2).unwrap(),
+            (Coefficient::FiniteField(n1, f1), Coefficient::FiniteField(n2, f2)) => {
+                f1.cmp(f2).then(n1.0.cmp(&n2.0))
+            }
+            (Coefficient::RationalPolynomial(r1), Coefficient::RationalPolynomial(r2)) => {
+                r1.partial_cmp(r2).unwrap()
+            }
+            (Coefficient::Float(f1), Coefficient::Float(f2)) => f1.cmp(f2),
+            (Coefficient::Float(f), Coefficient::Rational(r)) => f
+                .to_rational()
+                .partial_cmp(r)
+                .unwrap()
+                .then(Ordering::Greater),
+            (Coefficient::Rational(r), Coefficient::Float(f)) => r
+                .partial_cmp(&f.to_rational())
+                .unwrap()
+                .then(Ordering::Less),
+            (Coefficient::Complex(c1), Coefficient::Complex(c2)) => {
+                c1.0.cmp(&c2.0).then_with(|| c1.1.cmp(&c2.1))
+            }
+            (Coefficient::Complex(c), x) => c.0.cmp(x).then_with(|| c.1.cmp(&Coefficient::zero())),
+            (x, Coefficient::Complex(c)) => x.cmp(&c.0).then_with(|| Coefficient::zero().cmp(&c.1)),
+            _ => self.order_class().cmp(&other.order_class()),
         }
     }
 }
@@ -131,7 +787,14 @@ impl Add for Coefficient {
     fn add(self, rhs: Coefficient) -> Coefficient {
         match (self, rhs) {
             (Coefficient::Rational(r1), Coefficient::Rational(r2)) => {
-                Coefficient::Rational(r1 + r2)
+                // adding zero keeps the other operand and its allocation
+                if r2.is_zero() {
+                    Coefficient::Rational(r1)
+                } else if r1.is_zero() {
+                    Coefficient::Rational(r2)
+                } else {
+                    Coefficient::Rational(r1 + r2)
+                }
             }
             (Coefficient::FiniteField(n1, i1), Coefficient::FiniteField(n2, i2)) => {
                 if i1 != i2 {
@@ -150,6 +813,26 @@ impl Add for Coefficient {
             (_, Coefficient::FiniteField(_, _)) => {
                 panic!("Cannot add finite field to non-finite number. Convert other number first?");
             }
+            (Coefficient::Complex(c1), Coefficient::Complex(c2)) => {
+                let (c1, c2) = (*c1, *c2);
+                Coefficient::complex(c1.0 + c2.0, c1.1 + c2.1)
+            }
+            (Coefficient::Complex(c), x @ (Coefficient::Rational(_) | Coefficient::Float(_)))
+            | (x @ (Coefficient::Rational(_) | Coefficient::Float(_)), Coefficient::Complex(c)) => {
+                let (re, im) = *c;
+                Coefficient::complex(re + x, im)
+            }
+            (Coefficient::Complex(_), _) | (_, Coefficient::Complex(_)) => {
+                panic!("Cannot add a complex number to a rational polynomial")
+            }
+            (Coefficient::Float(f1), Coefficient::Float(f2)) => Coefficient::Float(f1.add(&f2)),
+            (Coefficient::Float(f), Coefficient::Rational(r))
+            | (Coefficient::Rational(r), Coefficient::Float(f)) => {
+                Coefficient::Float(f.add(&Float::from_rational(&r, f.prec())))
+            }
+            (Coefficient::Float(_), _) | (_, Coefficient::Float(_)) => {
+                panic!("Cannot add a float to a rational polynomial")
+            }
             (Coefficient::Rational(r), Coefficient::RationalPolynomial(rp))
             | (Coefficient::RationalPolynomial(rp), Coefficient::Rational(r)) => {
                 let r2 = RationalPolynomial {
@@ -200,6 +883,30 @@ impl Mul for Coefficient {
             (_, Coefficient::FiniteField(_, _)) => {
                 panic!("Cannot multiply finite field to non-finite number. Convert other number first?");
             }
+            (Coefficient::Complex(c1), Coefficient::Complex(c2)) => {
+                // (a+bi)(c+di) = ac-bd + (ad+bc)i
+                let ((a, b), (c, d)) = (*c1, *c2);
+                Coefficient::complex(
+                    a.clone() * c.clone() + b.clone() * d.clone() * Coefficient::from(-1),
+                    a * d + b * c,
+                )
+            }
+            (Coefficient::Complex(c), x @ (Coefficient::Rational(_) | Coefficient::Float(_)))
+            | (x @ (Coefficient::Rational(_) | Coefficient::Float(_)), Coefficient::Complex(c)) => {
+                let (re, im) = *c;
+                Coefficient::complex(re * x.clone(), im * x)
+            }
+            (Coefficient::Complex(_), _) | (_, Coefficient::Complex(_)) => {
+                panic!("Cannot multiply a complex number by a rational polynomial")
+            }
+            (Coefficient::Float(f1), Coefficient::Float(f2)) => Coefficient::Float(f1.mul(&f2)),
+            (Coefficient::Float(f), Coefficient::Rational(r))
+            | (Coefficient::Rational(r), Coefficient::Float(f)) => {
+                Coefficient::Float(f.mul(&Float::from_rational(&r, f.prec())))
+            }
+            (Coefficient::Float(_), _) | (_, Coefficient::Float(_)) => {
+                panic!("Cannot multiply a float by a rational polynomial")
+            }
             (Coefficient::Rational(r), Coefficient::RationalPolynomial(mut rp))
             | (Coefficient::RationalPolynomial(mut rp), Coefficient::Rational(r)) => {
                 let gcd1 = Z.gcd(&r.numerator(), &rp.denominator.content());
@@ -231,6 +938,65 @@ impl Mul for Coefficient {
     }
 }
 
+impl AddAssign<CoefficientView<'_>> for Coefficient {
+    /// Add `other` to `self` in place. A multi-precision rational in `self`
+    /// is updated without reallocation if the other coefficient is rational.
+    fn add_assign(&mut self, other: CoefficientView<'_>) {
+        if let Coefficient::Rational(Rational::Large(r)) = self {
+            match other {
+                CoefficientView::Natural(n, d) => {
+                    *r += &*MiniRational::from((n, d)).borrow();
+                }
+                CoefficientView::Large(l) => *r += l.to_rat(),
+                _ => {
+                    *self = std::mem::take(self) + other.to_owned();
+                    return;
+                }
+            }
+
+            self.downcast_large();
+            return;
+        }
+
+        *self = std::mem::take(self) + other.to_owned();
+    }
+}
+
+impl MulAssign<CoefficientView<'_>> for Coefficient {
+    /// Multiply `self` by `other` in place. A multi-precision rational in `self`
+    /// is updated without reallocation if the other coefficient is rational.
+    fn mul_assign(&mut self, other: CoefficientView<'_>) {
+        if let Coefficient::Rational(Rational::Large(r)) = self {
+            match other {
+                CoefficientView::Natural(n, d) => {
+                    *r *= &*MiniRational::from((n, d)).borrow();
+                }
+                CoefficientView::Large(l) => *r *= l.to_rat(),
+                _ => {
+                    *self = std::mem::take(self) * other.to_owned();
+                    return;
+                }
+            }
+
+            self.downcast_large();
+            return;
+        }
+
+        *self = std::mem::take(self) * other.to_owned();
+    }
+}
+
+impl Coefficient {
+    /// Convert a multi-precision rational that fits in machine-size integers to a natural one.
+    fn downcast_large(&mut self) {
+        if let Coefficient::Rational(Rational::Large(r)) = self {
+            if let (Some(n), Some(d)) = (r.numer().to_i64(), r.denom().to_i64()) {
+                *self = Coefficient::Rational(Rational::Natural(n, d));
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SerializedRational<'a> {
     pub(crate) is_negative: bool,
@@ -252,31 +1018,100 @@ impl<'a> SerializedRational<'a> {
 
         MultiPrecisionRational::from((num, den))
     }
-}
 
-/// A view of a coefficient that keeps GMP rationals serialized.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum CoefficientView<'a> {
-    Natural(i64, i64),
-    Large(SerializedRational<'a>),
-    FiniteField(FiniteFieldElement<u64>, FiniteFieldIndex),
-    RationalPolynomial(&'a RationalPolynomial<IntegerRing, u16>),
-}
+    /// Get the numerator and denominator if they fit in 128 bits, without allocating.
+    pub fn to_i128(&self) -> Option<(i128, i128)> {
+        if self.num_digits.len() > 16 || self.den_digits.len() > 16 {
+            return None;
+        }
 
-impl ConvertToRing for RationalField {
-    #[inline]
-    fn element_from_integer(&self, number: Integer) -> Self::Element {
-        number.into()
+        let read = |digits: &[u8]| {
+            let mut bytes = [0; 16];
+            bytes[..digits.len()].copy_from_slice(digits);
+            i128::try_from(u128::from_le_bytes(bytes)).ok()
+        };
+
+        let num = read(self.num_digits)?;
+        let den = read(self.den_digits)?;
+        Some((if self.is_negative { -num } else { num }, den))
     }
+}
 
-    #[inline]
-    fn element_from_coefficient(&self, number: Coefficient) -> Self::Element {
-        match number {
+/// A float `mantissa * 2^exp` whose mantissa is kept serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializedFloat<'a> {
+    pub(crate) is_negative: bool,
+    pub(crate) prec: u32,
+    pub(crate) exp: i64,
+    pub(crate) digits: &'a [u8],
+}
+
+impl<'a> SerializedFloat<'a> {
+    pub fn is_negative(&self) -> bool {
+        self.is_negative
+    }
+
+    pub fn to_float(&self) -> Float {
+        let mut mantissa = MultiPrecisionInteger::from_digits(self.digits, Order::Lsf);
+        if self.is_negative {
+            mantissa.neg_assign();
+        }
+
+        Float::from_parts(mantissa, self.exp, self.prec)
+    }
+}
+
+/// A complex number whose real and imaginary part are kept serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializedComplex<'a> {
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> SerializedComplex<'a> {
+    /// Get the real part.
+    pub fn re(&self) -> CoefficientView<'a> {
+        self.data.get_coeff_view().0
+    }
+
+    /// Get the imaginary part.
+    pub fn im(&self) -> CoefficientView<'a> {
+        self.data.skip_rational().get_coeff_view().0
+    }
+
+    /// Check if the number is `𝑖` or `-𝑖`.
+    pub fn is_imaginary_unit(&self) -> bool {
+        let im = self.im();
+        self.re().is_zero() && (im.is_one() || im.is_minus_one())
+    }
+}
+
+/// A view of a coefficient that keeps GMP rationals serialized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CoefficientView<'a> {
+    Natural(i64, i64),
+    Large(SerializedRational<'a>),
+    FiniteField(FiniteFieldElement<u64>, FiniteFieldIndex),
+    RationalPolynomial(&'a RationalPolynomial<IntegerRing, u16>),
+    Float(SerializedFloat<'a>),
+    Complex(SerializedComplex<'a>),
+}
+
+impl ConvertToRing for RationalField {
+    #[inline]
+    fn element_from_integer(&self, number: Integer) -> Self::Element {
+        number.into()
+    }
+
+    #[inline]
+    fn element_from_coefficient(&self, number: Coefficient) -> Self::Element {
+        match number {
             Coefficient::Rational(r) => r,
             Coefficient::FiniteField(_, _) => panic!("Cannot convert finite field to rational"),
             Coefficient::RationalPolynomial(_) => {
                 panic!("Cannot convert rational polynomial to rational")
             }
+            Coefficient::Float(_) => panic!("Cannot convert float to rational"),
+            Coefficient::Complex(_) => panic!("Cannot convert complex number to rational"),
         }
     }
 
@@ -291,6 +1126,8 @@ impl ConvertToRing for RationalField {
             CoefficientView::RationalPolynomial(_) => {
                 panic!("Cannot convert rational polynomial to rational")
             }
+            CoefficientView::Float(_) => panic!("Cannot convert float to rational"),
+            CoefficientView::Complex(_) => panic!("Cannot convert complex number to rational"),
         }
     }
 }
@@ -312,6 +1149,8 @@ impl ConvertToRing for IntegerRing {
             Coefficient::RationalPolynomial(_) => {
                 panic!("Cannot convert rational polynomial to rational")
             }
+            Coefficient::Float(_) => panic!("Cannot convert float to integer"),
+            Coefficient::Complex(_) => panic!("Cannot convert complex number to integer"),
         }
     }
 
@@ -333,6 +1172,8 @@ impl ConvertToRing for IntegerRing {
             CoefficientView::RationalPolynomial(_) => {
                 panic!("Cannot convert rational polynomial to integer")
             }
+            CoefficientView::Float(_) => panic!("Cannot convert float to integer"),
+            CoefficientView::Complex(_) => panic!("Cannot convert complex number to integer"),
         }
     }
 }
@@ -357,10 +1198,14 @@ where
                 &r.numerator().to_finite_field(self),
                 &r.denominator().to_finite_field(self),
             ),
-            Coefficient::FiniteField(_, _) => panic!("Cannot convert finite field to other one"),
+            Coefficient::FiniteField(n, fi) => {
+                self.element_from_coefficient_view(CoefficientView::FiniteField(n, fi))
+            }
             Coefficient::RationalPolynomial(_) => {
                 panic!("Cannot convert rational polynomial to finite field")
             }
+            Coefficient::Float(_) => panic!("Cannot convert float to finite field"),
+            Coefficient::Complex(_) => panic!("Cannot convert complex number to finite field"),
         }
     }
 
@@ -381,17 +1226,141 @@ where
                     &Integer::Large(d).to_finite_field(self),
                 )
             }
-            CoefficientView::FiniteField(_, _) => {
-                panic!("Cannot convert finite field to other one")
+            CoefficientView::FiniteField(n, fi) => {
+                // an element of the same field is copied, other fields cannot be converted
+                let f = State::get_finite_field(fi);
+                if f.get_prime() != self.get_prime().to_u64() {
+                    panic!("Cannot convert finite field to other one")
+                }
+
+                Integer::from(f.from_element(&n)).to_finite_field(self)
             }
             CoefficientView::RationalPolynomial(_) => {
                 panic!("Cannot convert rational polynomial to finite field")
             }
+            CoefficientView::Float(_) => panic!("Cannot convert float to finite field"),
+            CoefficientView::Complex(_) => {
+                panic!("Cannot convert complex number to finite field")
+            }
+        }
+    }
+}
+
+impl ConvertToRing for RationalPolynomialField<IntegerRing, u16> {
+    #[inline]
+    fn element_from_integer(&self, number: Integer) -> Self::Element {
+        let num = MultivariatePolynomial::new(&Z, None, self.get_variables().clone());
+        RationalPolynomial {
+            denominator: num.one(),
+            numerator: num.constant(number),
+        }
+    }
+
+    #[inline]
+    fn element_from_coefficient(&self, number: Coefficient) -> Self::Element {
+        match number {
+            Coefficient::Rational(r) => {
+                let num = MultivariatePolynomial::new(&Z, None, self.get_variables().clone());
+                RationalPolynomial {
+                    denominator: num.constant(r.denominator()),
+                    numerator: num.constant(r.numerator()),
+                }
+            }
+            Coefficient::RationalPolynomial(r) => {
+                self.element_from_coefficient_view(CoefficientView::RationalPolynomial(&r))
+            }
+            Coefficient::FiniteField(_, _) => {
+                panic!("Cannot convert finite field to rational polynomial")
+            }
+            Coefficient::Float(_) => panic!("Cannot convert float to rational polynomial"),
+            Coefficient::Complex(_) => {
+                panic!("Cannot convert complex number to rational polynomial")
+            }
+        }
+    }
+
+    #[inline]
+    fn element_from_coefficient_view(&self, number: CoefficientView<'_>) -> Self::Element {
+        match number {
+            CoefficientView::Natural(_, _) | CoefficientView::Large(_) => {
+                self.element_from_coefficient(number.to_owned())
+            }
+            CoefficientView::RationalPolynomial(r) => extend_variables(r, self.get_variables())
+                .unwrap_or_else(|| {
+                    panic!("Cannot convert rational polynomial with variables outside of the field")
+                }),
+            CoefficientView::FiniteField(_, _) => {
+                panic!("Cannot convert finite field to rational polynomial")
+            }
+            CoefficientView::Float(_) => panic!("Cannot convert float to rational polynomial"),
+            CoefficientView::Complex(_) => {
+                panic!("Cannot convert complex number to rational polynomial")
+            }
         }
     }
 }
 
 impl CoefficientView<'_> {
+    /// The class of the coefficient in the total order, see [`Coefficient`].
+    fn order_class(&self) -> u8 {
+        match self {
+            CoefficientView::Natural(_, _)
+            | CoefficientView::Large(_)
+            | CoefficientView::Float(_)
+            | CoefficientView::Complex(_) => 0,
+            CoefficientView::FiniteField(_, _) => 1,
+            CoefficientView::RationalPolynomial(_) => 2,
+        }
+    }
+
+    /// Add two coefficients, returning an error instead of panicking when their
+    /// domains are incompatible, for example for finite fields with different primes.
+    pub fn try_add(&self, other: &CoefficientView<'_>) -> Result<Coefficient, CoefficientError> {
+        self.check_compatible(other)?;
+        Ok(*self + *other)
+    }
+
+    /// Multiply two coefficients, returning an error instead of panicking when their
+    /// domains are incompatible, for example for finite fields with different primes.
+    pub fn try_mul(&self, other: &CoefficientView<'_>) -> Result<Coefficient, CoefficientError> {
+        self.check_compatible(other)?;
+        Ok(*self * *other)
+    }
+
+    fn check_compatible(&self, other: &CoefficientView<'_>) -> Result<(), CoefficientError> {
+        match (self, other) {
+            (CoefficientView::FiniteField(_, i1), CoefficientView::FiniteField(_, i2)) => {
+                if i1 != i2 {
+                    Err(CoefficientError::DifferentFiniteFields {
+                        p1: State::get_finite_field(*i1).get_prime(),
+                        p2: State::get_finite_field(*i2).get_prime(),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+            (CoefficientView::FiniteField(_, _), _) | (_, CoefficientView::FiniteField(_, _)) => {
+                Err(CoefficientError::MixedFiniteField)
+            }
+            (
+                CoefficientView::RationalPolynomial(_),
+                x @ (CoefficientView::Float(_) | CoefficientView::Complex(_)),
+            )
+            | (
+                x @ (CoefficientView::Float(_) | CoefficientView::Complex(_)),
+                CoefficientView::RationalPolynomial(_),
+            ) => Err(CoefficientError::Unsupported(
+                if matches!(x, CoefficientView::Float(_)) {
+                    "float"
+                } else {
+                    "complex number"
+                },
+                "rational polynomial",
+            )),
+            _ => Ok(()),
+        }
+    }
+
     pub fn normalize(&self) -> Coefficient {
         match self {
             CoefficientView::Natural(num, den) => match Rational::new(*num, *den) {
@@ -400,7 +1369,9 @@ impl CoefficientView<'_> {
             },
             CoefficientView::Large(_)
             | CoefficientView::FiniteField(_, _)
-            | CoefficientView::RationalPolynomial(_) => self.to_owned(),
+            | CoefficientView::RationalPolynomial(_)
+            | CoefficientView::Float(_)
+            | CoefficientView::Complex(_) => self.to_owned(),
         }
     }
 
@@ -410,207 +1381,805 @@ impl CoefficientView<'_> {
             CoefficientView::Large(r) => Coefficient::Rational(r.to_rat().into()),
             CoefficientView::FiniteField(num, field) => Coefficient::FiniteField(*num, *field),
             CoefficientView::RationalPolynomial(p) => Coefficient::RationalPolynomial((*p).clone()),
+            CoefficientView::Float(f) => Coefficient::Float(f.to_float()),
+            CoefficientView::Complex(c) => {
+                Coefficient::complex(c.re().to_owned(), c.im().to_owned())
+            }
         }
     }
 
+    /// Raise the coefficient to the power `other`, returning the new base and the
+    /// remaining exponent, for example `4^(3/2) = (64, 1/2)`.
+    ///
+    /// Following the convention used for general expressions, `0^0 = 1`. A zero
+    /// base with a positive exponent yields `0` and with a negative exponent it panics.
+    ///
+    /// Panics if the power cannot be computed. Use [`CoefficientView::try_pow`] to get an error instead.
     pub fn pow(&self, other: &CoefficientView<'_>) -> (Coefficient, Coefficient) {
+        self.try_pow(other).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Raise the coefficient to the power `other`, returning the new base and the
+    /// remaining exponent. An error is returned if zero is raised to a negative power,
+    /// if the power is too large to compute or if the combination of base and exponent
+    /// is not supported, for example a rational raised to a finite field element.
+    pub fn try_pow(
+        &self,
+        other: &CoefficientView<'_>,
+    ) -> Result<(Coefficient, Coefficient), CoefficientError> {
         // TODO: normalize 4^1/3 to 2^(2/3)?
         match (self, other) {
+            (&CoefficientView::Natural(0, _), &CoefficientView::Natural(n2, _)) => {
+                match n2.cmp(&0) {
+                    Ordering::Equal => Ok((Coefficient::one(), Coefficient::one())),
+                    Ordering::Greater => Ok((Coefficient::zero(), Coefficient::one())),
+                    Ordering::Less => Err(CoefficientError::ZeroToNegativePower),
+                }
+            }
             (&CoefficientView::Natural(mut n1, mut d1), &CoefficientView::Natural(mut n2, d2)) => {
-                if n2 < 0 {
-                    if n1 == 0 {
-                        panic!("Division by 0");
-                    }
+                if n2 == 0 {
+                    return Ok((Coefficient::one(), Coefficient::one()));
+                }
 
+                if n2 < 0 {
                     n2 = n2.saturating_abs();
                     (n1, d1) = (d1, n1);
                 }
 
+                if n1.abs() == 1 && d1.abs() == 1 {
+                    // the exponent may be too large to compute the power directly,
+                    // and the sign may be in the denominator after inversion
+                    let sign = if n1 * d1 < 0 && n2 % 2 == 1 { -1 } else { 1 };
+                    return Ok((sign.into(), (1, d2).into()));
+                }
+
                 if n2 <= u32::MAX as i64 {
                     if let Some(pn) = n1.checked_pow(n2 as u32) {
                         if let Some(pd) = d1.checked_pow(n2 as u32) {
                             // TODO: simplify 4^(1/2)
-                            return ((pn, pd).into(), (1, d2).into());
+                            return Ok(((pn, pd).into(), (1, d2).into()));
                         }
                     }
 
-                    (
+                    Ok((
                         MultiPrecisionRational::from((n1, d1)).pow(n2 as u32).into(),
                         (1, d2).into(),
-                    )
+                    ))
                 } else {
-                    panic!("Power is too large: {}", n2);
+                    Err(CoefficientError::PowerTooLarge(n2.to_string()))
                 }
             }
             (&CoefficientView::RationalPolynomial(r), &CoefficientView::Natural(n2, d2)) => {
                 if n2.unsigned_abs() > u32::MAX as u64 {
-                    panic!("Power is too large: {}", n2);
+                    return Err(CoefficientError::PowerTooLarge(n2.to_string()));
                 }
 
                 if n2 < 0 {
                     let r = r.clone().inv();
-                    (
+                    Ok((
                         Coefficient::RationalPolynomial(r.pow(n2.unsigned_abs())),
                         (1, d2).into(),
-                    )
+                    ))
                 } else {
-                    (
+                    Ok((
                         Coefficient::RationalPolynomial(r.pow(n2 as u64)),
                         (1, d2).into(),
-                    )
+                    ))
                 }
             }
             (&CoefficientView::Large(r), &CoefficientView::Natural(n2, d2)) => {
                 if n2.unsigned_abs() > u32::MAX as u64 {
-                    panic!("Power is too large: {}", n2);
+                    return Err(CoefficientError::PowerTooLarge(n2.to_string()));
                 }
 
                 if n2 < 0 {
                     let r = r.to_rat().clone().recip();
-                    (r.pow(n2.unsigned_abs() as u32).into(), (1, d2).into())
+                    Ok((r.pow(n2.unsigned_abs() as u32).into(), (1, d2).into()))
                 } else {
-                    (r.to_rat().pow(n2 as u32).into(), (1, d2).into())
+                    Ok((r.to_rat().pow(n2 as u32).into(), (1, d2).into()))
                 }
             }
-            _ => {
-                unimplemented!(
-                    "Power of configuration {:?}^{:?} is not implemented",
-                    self,
-                    other
-                );
-            }
-        }
-    }
-
-    pub fn is_integer(&self) -> bool {
-        match self {
-            CoefficientView::Natural(_, d) => *d == 1,
-            CoefficientView::Large(r) => r.to_rat().is_integer(),
-            CoefficientView::FiniteField(_, _) => true,
-            CoefficientView::RationalPolynomial(_) => false,
-        }
-    }
-}
+            (&CoefficientView::Complex(c), &CoefficientView::Natural(mut n2, d2)) => {
+                if c.is_imaginary_unit() {
+                    // 𝑖^4 = 1
+                    n2 = n2.rem_euclid(4);
+                }
 
-impl PartialOrd for CoefficientView<'_> {
-    fn partial_cmp(&self, other: &CoefficientView) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
+                if n2.unsigned_abs() > u32::MAX as u64 {
+                    return Err(CoefficientError::PowerTooLarge(n2.to_string()));
+                }
 
-impl Ord for CoefficientView<'_> {
-    fn cmp(&self, other: &CoefficientView) -> Ordering {
-        match (self, other) {
-            (&CoefficientView::Natural(n1, d1), &CoefficientView::Natural(n2, d2)) => {
-                // TODO: improve
-                if n1 < 0 && n2 > 0 {
-                    return Ordering::Less;
+                let mut base =
+                    Coefficient::Complex(Box::new((c.re().to_owned(), c.im().to_owned())));
+                if n2 < 0 {
+                    base = base.inv();
                 }
-                if n1 > 0 && n2 < 0 {
-                    return Ordering::Greater;
+
+                let mut e = n2.unsigned_abs();
+                let mut res = Coefficient::one();
+                while e > 0 {
+                    if e & 1 == 1 {
+                        res = res * base.clone();
+                    }
+                    e >>= 1;
+                    if e > 0 {
+                        base = base.clone() * base;
+                    }
                 }
 
-                match n1.checked_mul(d2) {
-                    Some(a1) => match n2.checked_mul(d1) {
-                        Some(a2) => a1.cmp(&a2),
-                        None => MultiPrecisionInteger::from(a1).cmp(
-                            &(MultiPrecisionInteger::from(n2) * MultiPrecisionInteger::from(d1)),
-                        ),
-                    },
-                    None => (MultiPrecisionInteger::from(n1) * MultiPrecisionInteger::from(d2))
-                        .cmp(&(MultiPrecisionInteger::from(n2) * MultiPrecisionInteger::from(d1))),
+                Ok((res, (1, d2).into()))
+            }
+            (&CoefficientView::Float(f), &CoefficientView::Natural(n2, d2)) => {
+                if d2 > u32::MAX as i64 {
+                    return Err(CoefficientError::PowerTooLarge(format!("{}/{}", n2, d2)));
                 }
+
+                Ok((
+                    Coefficient::Float(f.to_float().pow_rational(n2, d2 as u32)),
+                    Coefficient::one(),
+                ))
             }
-            (CoefficientView::Large(n1), CoefficientView::Large(n2)) => {
-                n1.to_rat().cmp(&n2.to_rat())
+            (&CoefficientView::Float(f), &CoefficientView::Float(e)) => Ok((
+                Coefficient::Float(f.to_float().powi(float_to_integer_exp(&e.to_float())?)),
+                Coefficient::one(),
+            )),
+            (
+                CoefficientView::Natural(..) | CoefficientView::Large(_),
+                &CoefficientView::Float(e),
+            ) => {
+                // the power of the rational is exact, so that only the result is rounded
+                let e = e.to_float();
+                let (b, _) =
+                    self.try_pow(&CoefficientView::Natural(float_to_integer_exp(&e)?, 1))?;
+                Ok((
+                    Coefficient::Float(Float::from_rational(
+                        &Q.element_from_coefficient(b),
+                        e.prec(),
+                    )),
+                    Coefficient::one(),
+                ))
             }
-            (CoefficientView::FiniteField(n1, _), CoefficientView::FiniteField(n2, _)) => {
-                n1.0.cmp(&n2.0)
+            (&CoefficientView::FiniteField(n1, fi), &CoefficientView::Natural(n2, d2)) => {
+                let f = State::get_finite_field(fi);
+                let b = if n2 < 0 { f.inv(&n1) } else { n1 };
+                Ok((
+                    Coefficient::FiniteField(f.pow(&b, n2.unsigned_abs()), fi),
+                    (1, d2).into(),
+                ))
             }
-            (&CoefficientView::Natural(n1, d1), CoefficientView::Large(n2)) => {
-                MultiPrecisionRational::from((n1, d1)).cmp(&n2.to_rat())
+            (&CoefficientView::Complex(c), &CoefficientView::Large(e)) => {
+                let e = e.to_rat();
+                if c.is_imaginary_unit() && e.is_integer() {
+                    self.try_pow(&CoefficientView::Natural(e.numer().mod_u(4) as i64, 1))
+                } else {
+                    Err(CoefficientError::PowerTooLarge(e.to_string()))
+                }
             }
-            (CoefficientView::Large(n1), &CoefficientView::Natural(n2, d2)) => {
-                n1.to_rat().cmp(&MultiPrecisionRational::from((n2, d2)))
+            (&CoefficientView::FiniteField(n1, fi), &CoefficientView::Large(e)) => {
+                let e = e.to_rat();
+                if !e.is_integer() {
+                    return Err(CoefficientError::UnsupportedPower(format!(
+                        "of a finite field element to the fractional power {}",
+                        e
+                    )));
+                }
+
+                let f = State::get_finite_field(fi);
+                if Zp64::is_zero(&n1) {
+                    if e.cmp0() == Ordering::Less {
+                        return Err(CoefficientError::ZeroToNegativePower);
+                    }
+                    return Ok((self.to_owned(), Coefficient::one()));
+                }
+
+                // the multiplicative group has order p-1
+                let order = MultiPrecisionInteger::from(f.get_prime() - 1);
+                let e = e.numer().clone().modulo(&order).to_u64().unwrap();
+                Ok((
+                    Coefficient::FiniteField(f.pow(&n1, e), fi),
+                    Coefficient::one(),
+                ))
             }
-            _ => unreachable!(),
-        }
-    }
-}
+            (
+                CoefficientView::Natural(..) | CoefficientView::Large(_),
+                &CoefficientView::Large(e),
+            ) => {
+                let e = e.to_rat();
+                if self.is_one() {
+                    return Ok((Coefficient::one(), Coefficient::one()));
+                }
 
-impl Add<CoefficientView<'_>> for CoefficientView<'_> {
-    type Output = Coefficient;
+                if self.is_zero() {
+                    if e.cmp0() == Ordering::Less {
+                        return Err(CoefficientError::ZeroToNegativePower);
+                    }
+                    return Ok((Coefficient::zero(), Coefficient::one()));
+                }
 
-    fn add(self, other: CoefficientView<'_>) -> Coefficient {
-        match (self, other) {
-            (CoefficientView::Natural(n1, d1), CoefficientView::Natural(n2, d2)) => {
-                Coefficient::Rational(Rational::Natural(n1, d1) + &Rational::Natural(n2, d2))
+                if self.is_minus_one() && e.is_integer() {
+                    return if e.numer().is_even() {
+                        Ok((Coefficient::one(), Coefficient::one()))
+                    } else {
+                        Ok((Coefficient::from(-1), Coefficient::one()))
+                    };
+                }
+
+                Err(CoefficientError::PowerTooLarge(e.to_string()))
             }
-            (CoefficientView::Natural(n1, d1), CoefficientView::Large(r2))
-            | (CoefficientView::Large(r2), CoefficientView::Natural(n1, d1)) => {
-                Coefficient::Rational(Rational::Natural(n1, d1) + &Rational::Large(r2.to_rat()))
+            _ => Err(CoefficientError::UnsupportedPower(format!(
+                "{:?}^{:?}",
+                self, other
+            ))),
+        }
+    }
+
+    /// Convert the coefficient to the nearest floating point number.
+    ///
+    /// Finite field elements and rational polynomials have no floating point
+    /// equivalent and will cause a panic.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            CoefficientView::Natural(n, d) => *n as f64 / *d as f64,
+            CoefficientView::Large(r) => r.to_rat().to_f64(),
+            CoefficientView::FiniteField(_, _) => {
+                panic!("Cannot convert finite field to float")
             }
-            (CoefficientView::Large(r1), CoefficientView::Large(r2)) => {
-                (r1.to_rat() + r2.to_rat()).into()
+            CoefficientView::RationalPolynomial(_) => {
+                panic!("Cannot convert rational polynomial to float")
             }
-            (CoefficientView::FiniteField(n1, i1), CoefficientView::FiniteField(n2, i2)) => {
-                if i1 != i2 {
-                    panic!(
-                        "Cannot add numbers from different finite fields: p1={}, p2={}",
-                        State::get_finite_field(i1).get_prime(),
-                        State::get_finite_field(i2).get_prime()
-                    );
-                }
-                let f = State::get_finite_field(i1);
-                Coefficient::FiniteField(f.add(&n1, &n2), i1)
+            CoefficientView::Float(f) => f.to_float().to_f64(),
+            CoefficientView::Complex(_) => panic!("Cannot convert complex number to float"),
+        }
+    }
+
+    /// Convert the coefficient to the tightest interval of `f64`s that contains it.
+    ///
+    /// Finite field elements, rational polynomials and complex numbers
+    /// have no interval equivalent and will cause a panic.
+    pub fn to_interval(&self) -> Interval {
+        match self {
+            CoefficientView::Natural(n, d) => (&Rational::Natural(*n, *d)).into(),
+            CoefficientView::Large(r) => (&Rational::from_large(r.to_rat())).into(),
+            CoefficientView::FiniteField(_, _) => {
+                panic!("Cannot convert finite field to interval")
             }
-            (CoefficientView::FiniteField(_, _), _) => {
-                panic!("Cannot add finite field to non-finite number. Convert other number first?");
+            CoefficientView::RationalPolynomial(_) => {
+                panic!("Cannot convert rational polynomial to interval")
             }
-            (_, CoefficientView::FiniteField(_, _)) => {
-                panic!("Cannot add finite field to non-finite number. Convert other number first?");
+            CoefficientView::Float(f) => (&f.to_float()).into(),
+            CoefficientView::Complex(_) => panic!("Cannot convert complex number to interval"),
+        }
+    }
+
+    /// Write the rational coefficient as a decimal number with exactly `scale`
+    /// fractional digits, rounding the last digit according to `mode`.
+    /// For example, `2/3` with scale 2 is `0.67` for [`RoundingMode::Nearest`]
+    /// and `0.66` for [`RoundingMode::Trunc`].
+    ///
+    /// Finite field elements and rational polynomials will cause a panic.
+    pub fn to_fixed(&self, scale: u32, mode: RoundingMode) -> String {
+        let r = match self {
+            CoefficientView::Natural(n, d) => MultiPrecisionRational::from((*n, *d)),
+            CoefficientView::Large(r) => r.to_rat(),
+            CoefficientView::FiniteField(_, _) => {
+                panic!("Cannot convert finite field to decimal")
             }
-            (CoefficientView::Natural(n, d), CoefficientView::RationalPolynomial(p))
-            | (CoefficientView::RationalPolynomial(p), CoefficientView::Natural(n, d)) => {
-                let r = (*p).clone();
-                let r2 = RationalPolynomial {
-                    numerator: p.numerator.constant(Integer::Natural(n)),
-                    denominator: p.denominator.constant(Integer::Natural(d)),
-                };
-                Coefficient::RationalPolynomial(&r + &r2)
+            CoefficientView::RationalPolynomial(_) => {
+                panic!("Cannot convert rational polynomial to decimal")
             }
-            (CoefficientView::Large(l), CoefficientView::RationalPolynomial(p))
-            | (CoefficientView::RationalPolynomial(p), CoefficientView::Large(l)) => {
-                let r = (*p).clone();
-                let (n, d) = l.to_rat().into_numer_denom();
-                let r2 = RationalPolynomial {
-                    numerator: p.numerator.constant(Integer::from_large(n)),
-                    denominator: p.denominator.constant(Integer::from_large(d)),
-                };
-                Coefficient::RationalPolynomial(&r + &r2)
+            CoefficientView::Float(f) => match f.to_float().to_rational() {
+                Rational::Natural(n, d) => MultiPrecisionRational::from((n, d)),
+                Rational::Large(r) => r,
+            },
+            CoefficientView::Complex(_) => {
+                panic!("Cannot convert complex number to decimal")
             }
-            (CoefficientView::RationalPolynomial(p1), CoefficientView::RationalPolynomial(p2)) => {
-                let r = if p1.get_variables() != p2.get_variables() {
-                    let mut p1 = (*p1).clone();
-                    let mut p2 = (*p2).clone();
-                    p1.unify_variables(&mut p2);
-                    &p1 + &p2
+        };
+
+        let scaled = r * MultiPrecisionInteger::from(MultiPrecisionInteger::u_pow_u(10, scale));
+        let (num, den) = scaled.into_numer_denom();
+        let q = match mode {
+            RoundingMode::Trunc => num.div_rem(den).0,
+            RoundingMode::Floor => num.div_rem_floor(den).0,
+            RoundingMode::Ceil => num.div_rem_ceil(den).0,
+            RoundingMode::Nearest => num.div_rem_round(den).0,
+        };
+
+        let mut digits = MultiPrecisionInteger::from(q.abs_ref()).to_string();
+        if digits.len() <= scale as usize {
+            digits.insert_str(0, &"0".repeat(scale as usize + 1 - digits.len()));
+        }
+        if scale > 0 {
+            digits.insert(digits.len() - scale as usize, '.');
+        }
+        if q < 0 {
+            digits.insert(0, '-');
+        }
+        digits
+    }
+
+    /// Check if `self` and `other` are approximately equal, i.e., if their relative
+    /// difference is at most `rel_tol` when converted to `f64`. Exactly equal
+    /// coefficients are detected without a conversion.
+    ///
+    /// This comparison is meant for numerical workflows only, as it is not transitive.
+    /// Finite field elements and rational polynomials are compared exactly.
+    pub fn approx_eq(&self, other: &CoefficientView<'_>, rel_tol: f64) -> bool {
+        // equal numbers outside of the range of `f64` would otherwise compare `inf - inf`
+        if self == other {
+            return true;
+        }
+
+        match (self, other) {
+            (
+                CoefficientView::Natural(..)
+                | CoefficientView::Large(_)
+                | CoefficientView::Float(_),
+                CoefficientView::Natural(..)
+                | CoefficientView::Large(_)
+                | CoefficientView::Float(_),
+            ) => {}
+            _ => return self == other,
+        }
+
+        let (a, b) = (self.to_f64(), other.to_f64());
+        (a - b).abs() <= rel_tol * a.abs().max(b.abs())
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match self {
+            CoefficientView::Natural(_, d) => *d == 1,
+            CoefficientView::Large(r) => r.to_rat().is_integer(),
+            CoefficientView::FiniteField(_, _) => true,
+            CoefficientView::RationalPolynomial(_) => false,
+            CoefficientView::Float(f) => f.to_float().is_integer(),
+            CoefficientView::Complex(_) => false,
+        }
+    }
+
+    /// Divide the integer `self` by the integer `other`, rounding the quotient
+    /// according to `mode`. For example, `-7` divided by `2` gives `-3` for
+    /// [`RoundingMode::Trunc`] and [`RoundingMode::Ceil`], and `-4` for
+    /// [`RoundingMode::Floor`] and [`RoundingMode::Nearest`].
+    ///
+    /// Panics if either coefficient is not an integer or if `other` is zero.
+    pub fn div_round(&self, other: &CoefficientView, mode: RoundingMode) -> Coefficient {
+        if !self.is_rational_integer() || !other.is_rational_integer() {
+            panic!("Cannot perform an integer division of non-integer coefficients");
+        }
+
+        if let CoefficientView::Natural(0, _) = other {
+            panic!("Division by zero");
+        }
+
+        if let (&CoefficientView::Natural(n1, _), &CoefficientView::Natural(n2, _)) = (self, other)
+        {
+            if let (Some(q), Some(r)) = (n1.checked_div(n2), n1.checked_rem(n2)) {
+                let away = if r == 0 {
+                    false
                 } else {
-                    p1 + p2
+                    let same_sign = (r < 0) == (n2 < 0);
+                    match mode {
+                        RoundingMode::Trunc => false,
+                        RoundingMode::Floor => !same_sign,
+                        RoundingMode::Ceil => same_sign,
+                        RoundingMode::Nearest => 2 * r.unsigned_abs() >= n2.unsigned_abs(),
+                    }
                 };
 
-                if r.is_constant() {
-                    (r.numerator.lcoeff(), r.denominator.lcoeff()).into()
+                // a non-zero remainder implies |n2| > 1, so this cannot overflow
+                return if !away {
+                    Coefficient::from(q)
+                } else if (n1 < 0) == (n2 < 0) {
+                    Coefficient::from(q + 1)
                 } else {
-                    Coefficient::RationalPolynomial(r)
-                }
+                    Coefficient::from(q - 1)
+                };
             }
         }
+
+        let to_int = |c: &CoefficientView| match c {
+            CoefficientView::Natural(n, _) => MultiPrecisionInteger::from(*n),
+            CoefficientView::Large(r) => r.to_rat().numer().clone(),
+            _ => unreachable!(),
+        };
+
+        let (n1, n2) = (to_int(self), to_int(other));
+        let q = match mode {
+            RoundingMode::Trunc => n1.div_rem(n2).0,
+            RoundingMode::Floor => n1.div_rem_floor(n2).0,
+            RoundingMode::Ceil => n1.div_rem_ceil(n2).0,
+            RoundingMode::Nearest => n1.div_rem_round(n2).0,
+        };
+        q.into()
     }
-}
 
-impl Mul for CoefficientView<'_> {
-    type Output = Coefficient;
+    pub fn is_zero(&self) -> bool {
+        match self {
+            CoefficientView::Natural(n, _) => *n == 0,
+            CoefficientView::Float(f) => f.to_float().is_zero(),
+            _ => self.to_owned().is_zero(),
+        }
+    }
+
+    pub fn is_one(&self) -> bool {
+        matches!(self, CoefficientView::Natural(1, 1)) || self.to_owned().is_one()
+    }
+
+    pub fn is_minus_one(&self) -> bool {
+        matches!(self, CoefficientView::Natural(-1, 1))
+    }
+
+    /// Returns `true` iff the rational number or float is negative.
+    pub fn is_negative(&self) -> bool {
+        match self {
+            CoefficientView::Natural(n, _) => *n < 0,
+            CoefficientView::Large(r) => r.is_negative(),
+            CoefficientView::Float(f) => f.is_negative(),
+            _ => false,
+        }
+    }
+
+    /// Write a rational number or float, or its absolute value if `abs` is set.
+    pub(crate) fn to_real_string(self, abs: bool) -> String {
+        match self {
+            CoefficientView::Natural(..) | CoefficientView::Large(_) => {
+                let r = Q.element_from_coefficient_view(self);
+                if abs {
+                    r.abs().to_string()
+                } else {
+                    r.to_string()
+                }
+            }
+            CoefficientView::Float(f) => {
+                let f = f.to_float();
+                if abs {
+                    f.abs().to_string()
+                } else {
+                    f.to_string()
+                }
+            }
+            _ => panic!("{:?} is not a real number", self),
+        }
+    }
+
+    /// Returns `true` iff the finite field element is a square in its field.
+    /// Zero is considered a quadratic residue. This can be used in pattern
+    /// restrictions on numbers in a finite field.
+    ///
+    /// Returns `None` if the coefficient is not a finite field element.
+    pub fn is_quadratic_residue(&self) -> Option<bool> {
+        match self {
+            CoefficientView::FiniteField(e, f) => {
+                Some(State::get_finite_field(*f).is_quadratic_residue(e))
+            }
+            _ => None,
+        }
+    }
+
+    /// Compute the distance `|self - other|` of two rational numbers.
+    ///
+    /// Finite field elements have no absolute value, so a panic occurs when
+    /// either coefficient is not rational.
+    pub fn abs_sub(&self, other: &CoefficientView) -> Coefficient {
+        match (self, other) {
+            (&CoefficientView::Natural(n1, d1), &CoefficientView::Natural(n2, d2)) => {
+                if let (Some(a), Some(b), Some(d)) =
+                    (n1.checked_mul(d2), n2.checked_mul(d1), d1.checked_mul(d2))
+                {
+                    if let Some(n) = a.checked_sub(b).and_then(|n| n.checked_abs()) {
+                        return Coefficient::from((n, d));
+                    }
+                }
+            }
+            (
+                CoefficientView::Natural(..) | CoefficientView::Large(_),
+                CoefficientView::Natural(..) | CoefficientView::Large(_),
+            ) => {}
+            _ => panic!("Cannot compute the distance of non-rational coefficients"),
+        }
+
+        let diff = Q.sub(
+            &Q.element_from_coefficient_view(*self),
+            &Q.element_from_coefficient_view(*other),
+        );
+        Coefficient::Rational(diff.abs())
+    }
+
+    fn is_rational_integer(&self) -> bool {
+        match self {
+            CoefficientView::Natural(_, d) => *d == 1,
+            CoefficientView::Large(r) => r.to_rat().is_integer(),
+            _ => false,
+        }
+    }
+
+    /// Compare two coefficients, returning `None` if they are not comparable:
+    /// a finite field element has no canonical ordering with respect to a rational
+    /// number or to an element of a different field, and rational polynomials are not ordered.
+    /// Floats and rational numbers are compared by value.
+    pub fn try_cmp(&self, other: &CoefficientView) -> Option<Ordering> {
+        match (self, other) {
+            (
+                CoefficientView::Natural(..)
+                | CoefficientView::Large(_)
+                | CoefficientView::Float(_),
+                CoefficientView::Natural(..)
+                | CoefficientView::Large(_)
+                | CoefficientView::Float(_),
+            ) => Some(self.cmp(other)),
+            (CoefficientView::FiniteField(_, f1), CoefficientView::FiniteField(_, f2))
+                if f1 == f2 =>
+            {
+                Some(self.cmp(other))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Hash for CoefficientView<'_> {
+    /// Hash the view, consistent with the hash of the owned [`Coefficient`].
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            CoefficientView::Natural(n, d) => {
+                0u8.hash(state);
+                Rational::Natural(*n, *d).hash(state);
+            }
+            CoefficientView::Large(r) => {
+                0u8.hash(state);
+                Rational::from_large(r.to_rat()).hash(state);
+            }
+            CoefficientView::FiniteField(n, fi) => {
+                1u8.hash(state);
+                n.hash(state);
+                fi.hash(state);
+            }
+            CoefficientView::RationalPolynomial(r) => {
+                2u8.hash(state);
+                r.hash(state);
+            }
+            CoefficientView::Float(f) => {
+                3u8.hash(state);
+                f.to_float().hash(state);
+            }
+            CoefficientView::Complex(c) => {
+                4u8.hash(state);
+                c.re().hash(state);
+                c.im().hash(state);
+            }
+        }
+    }
+}
+
+impl PartialOrd for CoefficientView<'_> {
+    fn partial_cmp(&self, other: &CoefficientView) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CoefficientView<'_> {
+    fn cmp(&self, other: &CoefficientView) -> Ordering {
+        match (self, other) {
+            (&CoefficientView::Natural(n1, d1), &CoefficientView::Natural(n2, d2)) => {
+                // the cross products of 64-bit fractions always fit in 128 bits
+                Rational::cmp_i128(n1 as i128, d1 as i128, n2 as i128, d2 as i128).unwrap()
+            }
+            (CoefficientView::Large(n1), CoefficientView::Large(n2)) => {
+                match (n1.to_i128(), n2.to_i128()) {
+                    (Some((a1, b1)), Some((a2, b2))) => Rational::cmp_i128(a1, b1, a2, b2),
+                    _ => None,
+                }
+                .unwrap_or_else(|| n1.to_rat().cmp(&n2.to_rat()))
+            }
+            (CoefficientView::FiniteField(n1, f1), CoefficientView::FiniteField(n2, f2)) => {
+                f1.cmp(f2).then(n1.0.cmp(&n2.0))
+            }
+            (&CoefficientView::Natural(n1, d1), CoefficientView::Large(n2)) => n2
+                .to_i128()
+                .and_then(|(a2, b2)| Rational::cmp_i128(n1 as i128, d1 as i128, a2, b2))
+                .unwrap_or_else(|| cmp_natural_large(n1, d1, &n2.to_rat())),
+            (CoefficientView::Large(n1), &CoefficientView::Natural(n2, d2)) => n1
+                .to_i128()
+                .and_then(|(a1, b1)| Rational::cmp_i128(a1, b1, n2 as i128, d2 as i128))
+                .unwrap_or_else(|| cmp_natural_large(n2, d2, &n1.to_rat()).reverse()),
+            (CoefficientView::Float(f1), CoefficientView::Float(f2)) => f1
+                .to_float()
+                .cmp(&f2.to_float())
+                .then(f1.prec.cmp(&f2.prec)),
+            (
+                CoefficientView::Float(f),
+                r @ (CoefficientView::Natural(..) | CoefficientView::Large(_)),
+            ) => f
+                .to_float()
+                .to_rational()
+                .partial_cmp(&Q.element_from_coefficient_view(*r))
+                .unwrap()
+                .then(Ordering::Greater),
+            (
+                r @ (CoefficientView::Natural(..) | CoefficientView::Large(_)),
+                CoefficientView::Float(f),
+            ) => Q
+                .element_from_coefficient_view(*r)
+                .partial_cmp(&f.to_float().to_rational())
+                .unwrap()
+                .then(Ordering::Less),
+            (CoefficientView::RationalPolynomial(r1), CoefficientView::RationalPolynomial(r2)) => {
+                r1.partial_cmp(r2).unwrap()
+            }
+            // complex numbers are ordered lexicographically by their real and imaginary part
+            (CoefficientView::Complex(c1), CoefficientView::Complex(c2)) => {
+                c1.re().cmp(&c2.re()).then_with(|| c1.im().cmp(&c2.im()))
+            }
+            (CoefficientView::Complex(c), x) => c
+                .re()
+                .cmp(x)
+                .then_with(|| c.im().cmp(&CoefficientView::Natural(0, 1))),
+            (x, CoefficientView::Complex(c)) => x
+                .cmp(&c.re())
+                .then_with(|| CoefficientView::Natural(0, 1).cmp(&c.im())),
+            _ => self.order_class().cmp(&other.order_class()),
+        }
+    }
+}
+
+/// Convert a float that is used as an exponent to an integer.
+fn float_to_integer_exp(e: &Float) -> Result<i64, CoefficientError> {
+    match e.to_rational() {
+        Rational::Natural(n, 1) => Ok(n),
+        _ => Err(CoefficientError::UnsupportedPower(format!(
+            "to the float {}: only integer powers are supported",
+            e
+        ))),
+    }
+}
+
+/// Write the positive integer `n` as `c^d * s`, where `s` contains no perfect `d`-th power
+/// of a small prime, and return `(c, s)`. A remaining cofactor without small prime factors
+/// is checked for being a perfect power.
+fn split_integer_root(
+    mut n: MultiPrecisionInteger,
+    d: u32,
+) -> (MultiPrecisionInteger, MultiPrecisionInteger) {
+    let mut outside = MultiPrecisionInteger::from(1);
+    let mut inside = MultiPrecisionInteger::from(1);
+
+    for p in SMALL_PRIMES {
+        if n == 1 {
+            break;
+        }
+
+        let mut k = 0;
+        while n.is_divisible_u(p as u32) {
+            n /= p as u32;
+            k += 1;
+        }
+
+        if k > 0 {
+            outside *= MultiPrecisionInteger::from(p).pow(k / d);
+            inside *= MultiPrecisionInteger::from(p).pow(k % d);
+        }
+    }
+
+    if n > 1 {
+        // all prime factors are larger than the largest small prime, which bounds the power
+        let max_pow = n.significant_bits() / (SMALL_PRIMES[SMALL_PRIMES.len() - 1] as u32).ilog2();
+        for k in (d..=max_pow).rev() {
+            let (root, rem) = n.clone().root_rem(MultiPrecisionInteger::new(), k);
+            if rem == 0 {
+                outside *= root.clone().pow(k / d);
+                n = root.pow(k % d);
+                break;
+            }
+        }
+    }
+
+    inside *= n;
+    (outside, inside)
+}
+
+/// Compare the fraction `n/d` to `r`, without allocating a multi-precision rational for `n/d`.
+#[inline]
+fn cmp_natural_large(n: i64, d: i64, r: &MultiPrecisionRational) -> Ordering {
+    (*MiniRational::from((n, d)).borrow()).cmp(r)
+}
+
+impl Add<CoefficientView<'_>> for CoefficientView<'_> {
+    type Output = Coefficient;
+
+    fn add(self, other: CoefficientView<'_>) -> Coefficient {
+        match (self, other) {
+            (
+                CoefficientView::Natural(0, _),
+                x @ (CoefficientView::Natural(..) | CoefficientView::Large(_)),
+            )
+            | (
+                x @ (CoefficientView::Natural(..) | CoefficientView::Large(_)),
+                CoefficientView::Natural(0, _),
+            ) => x.to_owned(),
+            (CoefficientView::Natural(n1, d1), CoefficientView::Natural(n2, d2)) => {
+                Coefficient::Rational(Rational::Natural(n1, d1) + &Rational::Natural(n2, d2))
+            }
+            (CoefficientView::Natural(n1, d1), CoefficientView::Large(r2))
+            | (CoefficientView::Large(r2), CoefficientView::Natural(n1, d1)) => {
+                Coefficient::Rational(
+                    r2.to_i128()
+                        .and_then(|(n2, d2)| Rational::add_i128(n1 as i128, d1 as i128, n2, d2))
+                        .unwrap_or_else(|| {
+                            Rational::Natural(n1, d1) + &Rational::Large(r2.to_rat())
+                        }),
+                )
+            }
+            (CoefficientView::Large(r1), CoefficientView::Large(r2)) => {
+                match (r1.to_i128(), r2.to_i128()) {
+                    (Some((n1, d1)), Some((n2, d2))) => Rational::add_i128(n1, d1, n2, d2),
+                    _ => None,
+                }
+                .map(Coefficient::Rational)
+                .unwrap_or_else(|| (r1.to_rat() + r2.to_rat()).into())
+            }
+            (CoefficientView::FiniteField(n1, i1), CoefficientView::FiniteField(n2, i2)) => {
+                if i1 != i2 {
+                    panic!(
+                        "Cannot add numbers from different finite fields: p1={}, p2={}",
+                        State::get_finite_field(i1).get_prime(),
+                        State::get_finite_field(i2).get_prime()
+                    );
+                }
+                let f = State::get_finite_field(i1);
+                Coefficient::FiniteField(f.add(&n1, &n2), i1)
+            }
+            (CoefficientView::FiniteField(_, _), _) => {
+                panic!("Cannot add finite field to non-finite number. Convert other number first?");
+            }
+            (_, CoefficientView::FiniteField(_, _)) => {
+                panic!("Cannot add finite field to non-finite number. Convert other number first?");
+            }
+            (CoefficientView::Float(_) | CoefficientView::Complex(_), _)
+            | (_, CoefficientView::Float(_) | CoefficientView::Complex(_)) => {
+                self.to_owned() + other.to_owned()
+            }
+            (CoefficientView::Natural(n, d), CoefficientView::RationalPolynomial(p))
+            | (CoefficientView::RationalPolynomial(p), CoefficientView::Natural(n, d)) => {
+                let r = (*p).clone();
+                let r2 = RationalPolynomial {
+                    numerator: p.numerator.constant(Integer::Natural(n)),
+                    denominator: p.denominator.constant(Integer::Natural(d)),
+                };
+                Coefficient::RationalPolynomial(&r + &r2)
+            }
+            (CoefficientView::Large(l), CoefficientView::RationalPolynomial(p))
+            | (CoefficientView::RationalPolynomial(p), CoefficientView::Large(l)) => {
+                let r = (*p).clone();
+                let (n, d) = l.to_rat().into_numer_denom();
+                let r2 = RationalPolynomial {
+                    numerator: p.numerator.constant(Integer::from_large(n)),
+                    denominator: p.denominator.constant(Integer::from_large(d)),
+                };
+                Coefficient::RationalPolynomial(&r + &r2)
+            }
+            (CoefficientView::RationalPolynomial(p1), CoefficientView::RationalPolynomial(p2)) => {
+                let r = if p1.get_variables() != p2.get_variables() {
+                    let mut p1 = (*p1).clone();
+                    let mut p2 = (*p2).clone();
+                    p1.unify_variables(&mut p2);
+                    &p1 + &p2
+                } else {
+                    p1 + p2
+                };
+
+                if r.is_constant() {
+                    (r.numerator.lcoeff(), r.denominator.lcoeff()).into()
+                } else {
+                    Coefficient::RationalPolynomial(r)
+                }
+            }
+        }
+    }
+}
+
+impl Mul for CoefficientView<'_> {
+    type Output = Coefficient;
 
     fn mul(self, other: CoefficientView<'_>) -> Coefficient {
         match (self, other) {
@@ -619,10 +2188,21 @@ impl Mul for CoefficientView<'_> {
             }
             (CoefficientView::Natural(n1, d1), CoefficientView::Large(r2))
             | (CoefficientView::Large(r2), CoefficientView::Natural(n1, d1)) => {
-                Coefficient::Rational(Rational::Natural(n1, d1) * &Rational::Large(r2.to_rat()))
+                Coefficient::Rational(
+                    r2.to_i128()
+                        .and_then(|(n2, d2)| Rational::mul_i128(n1 as i128, d1 as i128, n2, d2))
+                        .unwrap_or_else(|| {
+                            Rational::Natural(n1, d1) * &Rational::Large(r2.to_rat())
+                        }),
+                )
             }
             (CoefficientView::Large(r1), CoefficientView::Large(r2)) => {
-                (r1.to_rat() * r2.to_rat()).into()
+                match (r1.to_i128(), r2.to_i128()) {
+                    (Some((n1, d1)), Some((n2, d2))) => Rational::mul_i128(n1, d1, n2, d2),
+                    _ => None,
+                }
+                .map(Coefficient::Rational)
+                .unwrap_or_else(|| (r1.to_rat() * r2.to_rat()).into())
             }
             (CoefficientView::FiniteField(n1, i1), CoefficientView::FiniteField(n2, i2)) => {
                 if i1 != i2 {
@@ -641,6 +2221,10 @@ impl Mul for CoefficientView<'_> {
             (_, CoefficientView::FiniteField(_, _)) => {
                 panic!("Cannot multiply finite field to non-finite number. Convert other number first?");
             }
+            (CoefficientView::Float(_) | CoefficientView::Complex(_), _)
+            | (_, CoefficientView::Float(_) | CoefficientView::Complex(_)) => {
+                self.to_owned() * other.to_owned()
+            }
             (CoefficientView::Natural(n, d), CoefficientView::RationalPolynomial(p))
             | (CoefficientView::RationalPolynomial(p), CoefficientView::Natural(n, d)) => {
                 let mut r = (*p).clone();
@@ -684,6 +2268,99 @@ impl Mul for CoefficientView<'_> {
     }
 }
 
+impl Div for CoefficientView<'_> {
+    type Output = Coefficient;
+
+    fn div(self, other: CoefficientView<'_>) -> Coefficient {
+        match (self, other) {
+            (CoefficientView::Natural(n1, d1), CoefficientView::Natural(n2, d2)) => {
+                Coefficient::Rational(Rational::Natural(n1, d1) / &Rational::Natural(n2, d2))
+            }
+            (CoefficientView::Natural(n1, d1), CoefficientView::Large(r2)) => {
+                Coefficient::Rational(Rational::Natural(n1, d1) / &Rational::Large(r2.to_rat()))
+            }
+            (CoefficientView::Large(r1), CoefficientView::Natural(n2, d2)) => {
+                Coefficient::Rational(Rational::Large(r1.to_rat()) / &Rational::Natural(n2, d2))
+            }
+            (CoefficientView::Large(r1), CoefficientView::Large(r2)) => {
+                Coefficient::Rational(Rational::Large(r1.to_rat()) / &Rational::Large(r2.to_rat()))
+            }
+            (CoefficientView::FiniteField(n1, i1), CoefficientView::FiniteField(n2, i2)) => {
+                if i1 != i2 {
+                    panic!(
+                        "Cannot divide numbers from different finite fields: p1={}, p2={}",
+                        State::get_finite_field(i1).get_prime(),
+                        State::get_finite_field(i2).get_prime()
+                    );
+                }
+                let f = State::get_finite_field(i1);
+                if n2.0 == 0 {
+                    panic!(
+                        "Division by zero in finite field with prime {}",
+                        f.get_prime()
+                    );
+                }
+                Coefficient::FiniteField(f.div(&n1, &n2), i1)
+            }
+            (CoefficientView::FiniteField(_, _), _) => {
+                panic!(
+                    "Cannot divide finite field by non-finite number. Convert other number first?"
+                );
+            }
+            (_, CoefficientView::FiniteField(_, _)) => {
+                panic!(
+                    "Cannot divide non-finite number by finite field. Convert other number first?"
+                );
+            }
+            (
+                CoefficientView::Float(_) | CoefficientView::Complex(_),
+                CoefficientView::RationalPolynomial(_),
+            )
+            | (
+                CoefficientView::RationalPolynomial(_),
+                CoefficientView::Float(_) | CoefficientView::Complex(_),
+            ) => {
+                panic!("Cannot divide a float or complex number and a rational polynomial");
+            }
+            (CoefficientView::Complex(_), _) | (_, CoefficientView::Complex(_)) => {
+                self.to_owned() * other.to_owned().inv()
+            }
+            (CoefficientView::Float(f), x) => {
+                let f = f.to_float();
+                let x = match x {
+                    CoefficientView::Float(x) => x.to_float(),
+                    _ => Float::from_rational(&Q.element_from_coefficient_view(x), f.prec()),
+                };
+                Coefficient::Float(f.div(&x))
+            }
+            (x, CoefficientView::Float(f)) => {
+                let f = f.to_float();
+                let x = Float::from_rational(&Q.element_from_coefficient_view(x), f.prec());
+                Coefficient::Float(x.div(&f))
+            }
+            (CoefficientView::RationalPolynomial(p1), CoefficientView::RationalPolynomial(p2)) => {
+                let p2_inv = (*p2).clone().inv();
+                CoefficientView::RationalPolynomial(p1)
+                    * CoefficientView::RationalPolynomial(&p2_inv)
+            }
+            (CoefficientView::RationalPolynomial(p), x) => {
+                if let Coefficient::Rational(r) = x.to_owned() {
+                    if r.is_zero() {
+                        panic!("Division by zero: cannot divide a rational polynomial by 0");
+                    }
+
+                    Coefficient::RationalPolynomial((*p).clone()) * Coefficient::Rational(r.inv())
+                } else {
+                    unreachable!()
+                }
+            }
+            (x, CoefficientView::RationalPolynomial(p)) => {
+                x.to_owned() * Coefficient::RationalPolynomial((*p).clone().inv())
+            }
+        }
+    }
+}
+
 impl Add<i64> for CoefficientView<'_> {
     type Output = Coefficient;
 
@@ -705,11 +2382,52 @@ impl Add<i64> for CoefficientView<'_> {
 
                 Coefficient::RationalPolynomial(p + &a)
             }
+            CoefficientView::Float(f) => {
+                let f = f.to_float();
+                Coefficient::Float(f.add(&Float::from_rational(&other.into(), f.prec())))
+            }
+            CoefficientView::Complex(_) => self.to_owned() + Coefficient::from(other),
         }
     }
 }
 
+/// Rewrite `r` in terms of the variables `vars`, if they contain all variables of `r`.
+fn extend_variables(
+    r: &RationalPolynomial<IntegerRing, u16>,
+    vars: &Arc<Vec<Variable>>,
+) -> Option<RationalPolynomial<IntegerRing, u16>> {
+    let old_var_map = r.get_variables();
+    if old_var_map == vars {
+        return Some(r.clone());
+    }
+
+    if !old_var_map.iter().all(|x| vars.contains(x)) {
+        return None;
+    }
+
+    let order: SmallVec<[Option<usize>; INLINED_EXPONENTS]> = vars
+        .iter()
+        .map(|x| old_var_map.iter().position(|xx| xx == x))
+        .collect();
+
+    let mut r = r.clone();
+    r.numerator = r.numerator.rearrange_with_growth(&order);
+    r.denominator = r.denominator.rearrange_with_growth(&order);
+    r.numerator.variables = vars.clone();
+    r.denominator.variables = r.numerator.variables.clone();
+    Some(r)
+}
+
 impl Atom {
+    /// Move all occurrences of the variables `vars` outside of functions into the coefficient,
+    /// which becomes a rational polynomial in `vars`. Sums and products of such coefficients
+    /// are normalized by cancelling the gcd of numerator and denominator, so that the
+    /// parameters are never expanded into separate terms.
+    ///
+    /// The packed atom stores a rational polynomial coefficient by reference. As a result,
+    /// atoms are only equal if their rational polynomial coefficients are the same object,
+    /// for example when one atom is a copy of the other. To compare by value, compare the
+    /// [`CoefficientView`]s or convert to polynomials.
     pub fn set_coefficient_ring(&self, vars: &Arc<Vec<Variable>>) -> Atom {
         self.as_view().set_coefficient_ring(vars)
     }
@@ -733,20 +2451,9 @@ impl<'a> AtomView<'a> {
         match self {
             AtomView::Num(n) => {
                 if let CoefficientView::RationalPolynomial(r) = n.get_coeff_view() {
-                    let old_var_map = r.get_variables();
-                    if old_var_map != vars {
-                        if old_var_map.iter().all(|x| vars.contains(x)) {
+                    if r.get_variables() != vars {
+                        if let Some(r) = extend_variables(r, vars) {
                             // upgrade the polynomial if no variables got removed
-                            let mut r = r.clone();
-                            let order: SmallVec<[Option<usize>; INLINED_EXPONENTS]> = vars
-                                .iter()
-                                .map(|x| old_var_map.iter().position(|xx| xx == x))
-                                .collect();
-
-                            r.numerator = r.numerator.rearrange_with_growth(&order);
-                            r.denominator = r.denominator.rearrange_with_growth(&order);
-                            r.numerator.variables = vars.clone();
-                            r.denominator.variables = r.numerator.variables.clone();
                             out.to_num(Coefficient::RationalPolynomial(r));
                             true
                         } else {
@@ -887,3 +2594,1395 @@ impl<'a> AtomView<'a> {
         }
     }
 }
+
+impl Atom {
+    /// Map all rational coefficients, including those inside functions, into the
+    /// finite field `field`. Exponents are left untouched.
+    ///
+    /// Panics if a denominator is divisible by the prime of the field.
+    pub fn to_finite_field(&self, field: FiniteFieldIndex) -> Atom {
+        self.as_view().to_finite_field(field)
+    }
+
+    /// Reconstruct the rational coefficients of `f` applied to this expression from its
+    /// images over finite fields. See [`AtomView::reconstruct_from_finite_fields`].
+    pub fn reconstruct_from_finite_fields<F: Fn(AtomView<'_>, FiniteFieldIndex) -> Atom>(
+        &self,
+        f: F,
+    ) -> Result<Atom, String> {
+        self.as_view().reconstruct_from_finite_fields(f)
+    }
+
+    /// Update the field indices of all finite field elements using `remap`,
+    /// which is obtained from [`State::load_fields`].
+    ///
+    /// Panics if an element belongs to a field that is not in the map.
+    pub fn remap_fields(&self, remap: &FieldRemap) -> Atom {
+        self.as_view().remap_fields(remap)
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Map all rational coefficients, including those inside functions, into the
+    /// finite field `field`. Exponents are left untouched.
+    ///
+    /// Panics if a denominator is divisible by the prime of the field.
+    pub fn to_finite_field(&self, field: FiniteFieldIndex) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.to_finite_field_with_ws_into(field, ws, &mut out);
+            out.into_inner()
+        })
+    }
+
+    /// Map all rational coefficients into the finite field `field`, like
+    /// [`AtomView::to_finite_field`], but return an error instead of panicking
+    /// if a denominator is divisible by the prime of the field.
+    pub fn try_to_finite_field(&self, field: FiniteFieldIndex) -> Result<Atom, CoefficientError> {
+        self.check_finite_field_denominators(field)?;
+        Ok(self.to_finite_field(field))
+    }
+
+    fn check_finite_field_denominators(
+        &self,
+        field: FiniteFieldIndex,
+    ) -> Result<(), CoefficientError> {
+        match self {
+            AtomView::Num(n) => match n.get_coeff_view() {
+                c @ (CoefficientView::Natural(_, _) | CoefficientView::Large(_)) => {
+                    c.to_owned().to_finite_field(field).map(|_| ())
+                }
+                _ => Ok(()),
+            },
+            AtomView::Var(_) => Ok(()),
+            // exponents are not mapped
+            AtomView::Pow(p) => p.get_base().check_finite_field_denominators(field),
+            AtomView::Fun(f) => f
+                .iter()
+                .try_for_each(|a| a.check_finite_field_denominators(field)),
+            AtomView::Mul(m) => m
+                .iter()
+                .try_for_each(|a| a.check_finite_field_denominators(field)),
+            AtomView::Add(a) => a
+                .iter()
+                .try_for_each(|a| a.check_finite_field_denominators(field)),
+        }
+    }
+
+    /// Reconstruct the rational coefficients of `f` applied to this expression from its
+    /// images over finite fields. For every prime, the expression is mapped into the prime field,
+    /// `f` is applied and the coefficients of the expanded result are combined with the images
+    /// of the previous primes using the Chinese remainder theorem. The procedure stops when the
+    /// rational reconstruction yields the same expression for two consecutive primes.
+    ///
+    /// For example, with `f` the identity, `x/3+5/7*y` is recovered from its images.
+    ///
+    /// An error is returned if the result of `f` has a coefficient that is not in the prime field,
+    /// or if the reconstruction does not stabilize before the primes run out.
+    pub fn reconstruct_from_finite_fields<F: Fn(AtomView<'_>, FiniteFieldIndex) -> Atom>(
+        &self,
+        f: F,
+    ) -> Result<Atom, String> {
+        let mut images: HashMap<Vec<(Atom, i64)>, Integer> = HashMap::default();
+        let mut modulus = Integer::one();
+        let mut last_guess = None;
+
+        for &p in &LARGE_U32_PRIMES {
+            let field = Zp64::new(p as u64);
+            let fi = State::get_or_insert_finite_field(field.clone());
+            let p = Integer::from(p as i64);
+
+            let mut residues: HashMap<Vec<(Atom, i64)>, Integer> = HashMap::default();
+            for (c, m) in f(self.to_finite_field(fi).as_view(), fi).to_sum_of_products() {
+                let r = match c {
+                    Coefficient::FiniteField(e, i) if i == fi => {
+                        Integer::from(field.from_element(&e) as i64)
+                    }
+                    Coefficient::Rational(r) if r.is_integer() => Z.quot_rem(&r.numerator(), &p).1,
+                    c => {
+                        return Err(format!(
+                            "Coefficient {:?} is not an element of the field with prime {}",
+                            c, p
+                        ))
+                    }
+                };
+                residues.insert(m, r);
+            }
+
+            for m in residues.keys() {
+                images.entry(m.clone()).or_insert_with(Integer::zero);
+            }
+
+            let mut guess = Atom::new_num(0);
+            let mut failed = false;
+            for (m, x) in &mut images {
+                let r = residues.remove(m).unwrap_or_else(Integer::zero);
+                (*x, _) =
+                    Coefficient::chinese_remainder(&[(x.clone(), modulus.clone()), (r, p.clone())])
+                        .map_err(|e| e.to_string())?;
+
+                if failed {
+                    continue;
+                }
+
+                let Ok(c) = Coefficient::rational_reconstruct(x, &(&modulus * &p)) else {
+                    failed = true;
+                    continue;
+                };
+
+                let mut term = Atom::new_num(c);
+                for (b, e) in m {
+                    term = term * &b.npow(*e);
+                }
+                guess = guess + &term;
+            }
+
+            modulus = &modulus * &p;
+
+            if failed {
+                last_guess = None;
+                continue;
+            }
+
+            if last_guess.as_ref() == Some(&guess) {
+                return Ok(guess);
+            }
+            last_guess = Some(guess);
+        }
+
+        Err("Rational reconstruction did not stabilize before running out of primes".into())
+    }
+
+    /// Update the field indices of all finite field elements using `remap`,
+    /// which is obtained from [`State::load_fields`].
+    ///
+    /// Panics if an element belongs to a field that is not in the map.
+    pub fn remap_fields(&self, remap: &FieldRemap) -> Atom {
+        if remap.is_identity() {
+            return self.to_owned();
+        }
+
+        self.map_subterms(
+            &|a| matches!(a, AtomView::Num(n) if matches!(n.get_coeff_view(), CoefficientView::FiniteField(..))),
+            &|a| {
+                let AtomView::Num(n) = a else { unreachable!() };
+                let CoefficientView::FiniteField(e, fi) = n.get_coeff_view() else {
+                    unreachable!()
+                };
+
+                let new_fi = remap
+                    .get(fi)
+                    .unwrap_or_else(|| panic!("Finite field {} is missing from the map", fi.0));
+                Atom::new_num(Coefficient::FiniteField(e, new_fi))
+            },
+        )
+    }
+
+    /// Get the finite field of the first finite field coefficient in the expression, if any.
+    pub fn get_finite_field(&self) -> Option<FiniteFieldIndex> {
+        match self {
+            AtomView::Num(n) => {
+                if let CoefficientView::FiniteField(_, field) = n.get_coeff_view() {
+                    Some(field)
+                } else {
+                    None
+                }
+            }
+            AtomView::Var(_) => None,
+            AtomView::Fun(f) => f.iter().find_map(|a| a.get_finite_field()),
+            AtomView::Pow(p) => p.get_base().get_finite_field(),
+            AtomView::Mul(m) => m.iter().find_map(|a| a.get_finite_field()),
+            AtomView::Add(a) => a.iter().find_map(|a| a.get_finite_field()),
+        }
+    }
+
+    /// Map all rational coefficients into the finite field `field`, returning `true` iff
+    /// the expression changed.
+    pub fn to_finite_field_with_ws_into(
+        &self,
+        field: FiniteFieldIndex,
+        workspace: &Workspace,
+        out: &mut Atom,
+    ) -> bool {
+        match self {
+            AtomView::Num(n) => match n.get_coeff_view() {
+                CoefficientView::Natural(_, _) | CoefficientView::Large(_) => {
+                    let f = State::get_finite_field(field);
+                    let r = n.get_coeff_view().to_owned();
+                    if let Coefficient::Rational(r) = &r {
+                        if FiniteField::<u64>::is_zero(&r.denominator().to_finite_field(f)) {
+                            panic!(
+                                "Cannot convert {} to a finite field with prime {}: division by 0",
+                                r,
+                                f.get_prime()
+                            );
+                        }
+                    }
+
+                    out.to_num(Coefficient::FiniteField(
+                        f.element_from_coefficient(r),
+                        field,
+                    ));
+                    true
+                }
+                CoefficientView::FiniteField(_, _)
+                | CoefficientView::RationalPolynomial(_)
+                | CoefficientView::Float(_)
+                | CoefficientView::Complex(_) => {
+                    out.set_from_view(self);
+                    false
+                }
+            },
+            AtomView::Var(_) => {
+                out.set_from_view(self);
+                false
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+
+                let mut nb = workspace.new_atom();
+                if base.to_finite_field_with_ws_into(field, workspace, &mut nb) {
+                    let mut o = workspace.new_atom();
+                    o.to_pow(nb.as_view(), exp);
+
+                    o.as_view().normalize(workspace, out);
+                    true
+                } else {
+                    out.set_from_view(self);
+                    false
+                }
+            }
+            AtomView::Mul(m) => {
+                let mut o = workspace.new_atom();
+                let mul = o.to_mul();
+
+                let mut changed = false;
+
+                let mut arg_o = workspace.new_atom();
+                for arg in m.iter() {
+                    changed |= arg.to_finite_field_with_ws_into(field, workspace, &mut arg_o);
+                    mul.extend(arg_o.as_view());
+                }
+
+                mul.set_normalized(!changed);
+
+                if !changed {
+                    std::mem::swap(out, &mut o);
+                    false
+                } else {
+                    o.as_view().normalize(workspace, out);
+                    true
+                }
+            }
+            AtomView::Add(a) => {
+                let mut o = workspace.new_atom();
+                let add = o.to_add();
+
+                let mut changed = false;
+
+                let mut arg_o = workspace.new_atom();
+                for arg in a.iter() {
+                    changed |= arg.to_finite_field_with_ws_into(field, workspace, &mut arg_o);
+                    add.extend(arg_o.as_view());
+                }
+
+                add.set_normalized(!changed);
+
+                if !changed {
+                    std::mem::swap(out, &mut o);
+                    false
+                } else {
+                    o.as_view().normalize(workspace, out);
+                    true
+                }
+            }
+            AtomView::Fun(f) => {
+                let mut o = workspace.new_atom();
+                let fun = o.to_fun(f.get_symbol());
+
+                let mut changed = false;
+
+                let mut arg_o = workspace.new_atom();
+                for arg in f.iter() {
+                    changed |= arg.to_finite_field_with_ws_into(field, workspace, &mut arg_o);
+                    fun.add_arg(arg_o.as_view());
+                }
+
+                fun.set_normalized(!changed);
+
+                if !changed {
+                    std::mem::swap(out, &mut o);
+                    false
+                } else {
+                    o.as_view().normalize(workspace, out);
+                    true
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cmp::Ordering,
+        collections::{hash_map::DefaultHasher, BTreeSet},
+        hash::{Hash, Hasher},
+        sync::Arc,
+    };
+
+    use ahash::HashSet;
+
+    use rug::{ops::Pow, Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational};
+
+    use crate::representations::{Atom, AtomView, FunctionBuilder};
+
+    use crate::{
+        domains::{
+            finite_field::{FiniteFieldCore, Zp, Zp64},
+            integer::{Integer, Z},
+            rational::{Rational, Q},
+            rational_polynomial::RationalPolynomialField,
+            EuclideanDomain, Ring,
+        },
+        id::TraversalOrder,
+        state::{FiniteFieldIndex, State},
+    };
+
+    use super::{
+        Coefficient, CoefficientError, CoefficientView, ConvertToRing, Float, RoundingMode,
+    };
+
+    #[test]
+    fn cmp_natural_large() {
+        let big = MultiPrecisionRational::from((i64::MAX, 2)) * MultiPrecisionRational::from(3);
+        for l in [big.clone(), -big.clone(), big.clone().recip(), -big.recip()] {
+            for (n, d) in [
+                (0, 1),
+                (1, 1),
+                (-1, 1),
+                (i64::MAX, 1),
+                (i64::MIN, 1),
+                (-3, 7),
+                (5, i64::MAX),
+            ] {
+                assert_eq!(
+                    super::cmp_natural_large(n, d, &l),
+                    MultiPrecisionRational::from((n, d)).cmp(&l)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn zero_one() {
+        assert!(Coefficient::zero().is_zero());
+        assert!(Coefficient::one().is_one());
+        assert!(!Coefficient::zero().is_one());
+        assert_eq!(
+            CoefficientView::Natural(0, 5).normalize(),
+            Coefficient::zero()
+        );
+        assert_eq!(
+            CoefficientView::Natural(3, 3).normalize(),
+            Coefficient::one()
+        );
+
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        assert!(Coefficient::finite_zero(field).is_zero());
+        assert!(Coefficient::finite_one(field).is_one());
+        assert_eq!(
+            Coefficient::finite_one(field),
+            Coefficient::FiniteField(State::get_finite_field(field).to_element(8), field)
+        );
+    }
+
+    #[test]
+    fn convert_to_ring() {
+        fn lift<R: ConvertToRing>(ring: &R) -> R::Element {
+            ring.add(
+                &ring.element_from_coefficient_view(CoefficientView::Natural(3, 2)),
+                &ring.element_from_coefficient(Coefficient::from(1)),
+            )
+        }
+
+        assert_eq!(lift(&Q), Rational::new(5, 2));
+
+        // 5/2 = 5 * 4 = 6 mod 7
+        let field = Zp::new(7);
+        assert_eq!(lift(&field), field.to_element(6));
+    }
+
+    #[test]
+    fn limbs() {
+        let n = MultiPrecisionInteger::from(-3).pow(100u32);
+        let c = Coefficient::from(n.clone());
+        let limbs = c.large_limbs().unwrap();
+        assert_eq!(limbs, n.as_limbs());
+        assert_eq!(Coefficient::from_limbs(limbs, false), c);
+
+        let n = -MultiPrecisionInteger::from(7).pow(50u32);
+        let c = Coefficient::from(n.clone());
+        assert_eq!(Coefficient::from_limbs(c.large_limbs().unwrap(), true), c);
+
+        assert_eq!(Coefficient::from(5).large_limbs(), None);
+        assert_eq!(Coefficient::from_limbs(&[5], true), Coefficient::from(-5));
+    }
+
+    #[test]
+    fn gcd_lcm_all() {
+        let n = |x| CoefficientView::Natural(x, 1);
+        assert_eq!(
+            Coefficient::gcd_all(&[n(6), n(10), n(15)]),
+            Coefficient::one()
+        );
+        assert_eq!(
+            Coefficient::gcd_all(&[n(12), n(-18), n(24)]),
+            Coefficient::from(6)
+        );
+        assert_eq!(
+            Coefficient::gcd_all(&[
+                CoefficientView::Natural(1, 2),
+                CoefficientView::Natural(1, 3)
+            ]),
+            Coefficient::from((1, 6))
+        );
+        assert_eq!(
+            Coefficient::gcd_all(&[n(1), CoefficientView::Natural(1, 3)]),
+            Coefficient::from((1, 3))
+        );
+        assert_eq!(Coefficient::gcd_all(&[]), Coefficient::zero());
+
+        assert_eq!(
+            Coefficient::lcm_all(&[n(4), n(-6), n(10)]),
+            Coefficient::from(60)
+        );
+        assert_eq!(
+            Coefficient::lcm_all(&[
+                CoefficientView::Natural(2, 3),
+                CoefficientView::Natural(3, 4)
+            ]),
+            Coefficient::from(6)
+        );
+        assert_eq!(
+            Coefficient::lcm_all(&[n(i64::MAX), n(i64::MAX - 1)]),
+            Coefficient::from(
+                MultiPrecisionInteger::from(i64::MAX) * MultiPrecisionInteger::from(i64::MAX - 1)
+            )
+        );
+        assert_eq!(Coefficient::lcm_all(&[n(3), n(0)]), Coefficient::zero());
+    }
+
+    #[test]
+    fn weighted_sum() {
+        let r = Coefficient::weighted_sum(
+            &[
+                CoefficientView::Natural(1, 2),
+                CoefficientView::Natural(1, 3),
+            ],
+            &[
+                CoefficientView::Natural(6, 1),
+                CoefficientView::Natural(9, 1),
+            ],
+        )
+        .unwrap();
+        assert_eq!(r, Coefficient::from(6));
+
+        let r = Coefficient::weighted_sum(
+            &[
+                CoefficientView::Natural(i64::MAX, 1),
+                CoefficientView::Natural(1, 1),
+            ],
+            &[
+                CoefficientView::Natural(i64::MAX, 1),
+                CoefficientView::Natural(-1, 1),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            r,
+            Coefficient::from(MultiPrecisionInteger::from(i64::MAX).pow(2) - 1)
+        );
+
+        assert!(Coefficient::weighted_sum(&[CoefficientView::Natural(1, 1)], &[]).is_err());
+    }
+
+    #[test]
+    fn div() {
+        assert_eq!(
+            CoefficientView::Natural(3, 4) / CoefficientView::Natural(1, 2),
+            Coefficient::from((3, 2))
+        );
+        assert_eq!(
+            CoefficientView::Natural(i64::MAX, 1) / CoefficientView::Natural(1, 3),
+            Coefficient::from(MultiPrecisionInteger::from(i64::MAX) * 3)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn div_by_zero() {
+        let _ = CoefficientView::Natural(3, 4) / CoefficientView::Natural(0, 1);
+    }
+
+    #[test]
+    fn pow_zero() {
+        let zero = CoefficientView::Natural(0, 1);
+        assert_eq!(
+            zero.pow(&CoefficientView::Natural(0, 1)),
+            (Coefficient::from(1), Coefficient::from(1))
+        );
+        assert_eq!(
+            zero.pow(&CoefficientView::Natural(3, 1)),
+            (Coefficient::from(0), Coefficient::from(1))
+        );
+        assert_eq!(
+            zero.pow(&CoefficientView::Natural(1, 2)),
+            (Coefficient::from(0), Coefficient::from(1))
+        );
+        assert_eq!(
+            CoefficientView::Natural(0, 5).pow(&CoefficientView::Natural(3, 1)),
+            (Coefficient::from(0), Coefficient::from(1))
+        );
+        assert_eq!(
+            CoefficientView::Natural(3, 5).pow(&CoefficientView::Natural(0, 1)),
+            (Coefficient::from(1), Coefficient::from(1))
+        );
+
+        assert_eq!(Atom::parse("0^0").unwrap(), Atom::new_num(1));
+        assert_eq!(Atom::parse("0^(1/2)").unwrap(), Atom::new_num(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn pow_zero_negative() {
+        CoefficientView::Natural(0, 1).pow(&CoefficientView::Natural(-2, 1));
+    }
+
+    #[test]
+    fn pow_all() {
+        let big = Atom::parse("2^70").unwrap();
+        let AtomView::Num(big_n) = big.as_view() else {
+            unreachable!()
+        };
+        let big = big_n.get_coeff_view();
+
+        assert_eq!(
+            big.pow(&CoefficientView::Natural(-2, 3)),
+            (
+                Coefficient::from(MultiPrecisionRational::from((
+                    1,
+                    MultiPrecisionInteger::from(2).pow(140u32)
+                ))),
+                Coefficient::from((1, 3))
+            )
+        );
+
+        let fi = State::get_or_insert_finite_field(Zp64::new(7));
+        let f = State::get_finite_field(fi);
+        let three = CoefficientView::FiniteField(f.to_element(3), fi);
+        assert_eq!(
+            three.pow(&CoefficientView::Natural(5, 1)).0,
+            Coefficient::FiniteField(f.to_element(5), fi)
+        );
+        assert_eq!(
+            three.pow(&CoefficientView::Natural(-1, 1)).0,
+            Coefficient::FiniteField(f.to_element(5), fi)
+        );
+        // 2^70 = 4 mod 6
+        assert_eq!(
+            three.pow(&big).0,
+            Coefficient::FiniteField(f.to_element(4), fi)
+        );
+
+        assert_eq!(
+            CoefficientView::Natural(-1, 1).pow(&big).0,
+            Coefficient::one()
+        );
+        assert!(matches!(
+            CoefficientView::Natural(2, 1).try_pow(&big),
+            Err(CoefficientError::PowerTooLarge(_))
+        ));
+        assert!(matches!(
+            CoefficientView::Natural(2, 1).try_pow(&three),
+            Err(CoefficientError::UnsupportedPower(_))
+        ));
+        let neg_big = Atom::parse("-(2^70)").unwrap();
+        let AtomView::Num(neg_big) = neg_big.as_view() else {
+            unreachable!()
+        };
+        for e in [CoefficientView::Natural(-1, 1), neg_big.get_coeff_view()] {
+            assert_eq!(
+                CoefficientView::Natural(0, 1).try_pow(&e),
+                Err(CoefficientError::ZeroToNegativePower)
+            );
+        }
+
+        // powers that cannot be computed stay unevaluated
+        let a = Atom::parse("0^(-1)").unwrap();
+        assert!(matches!(a.as_view(), AtomView::Pow(_)));
+        let a = Atom::parse("2^(2^70)").unwrap();
+        assert!(matches!(a.as_view(), AtomView::Pow(_)));
+        assert_eq!(a.to_string(), "2^1180591620717411303424");
+    }
+
+    #[test]
+    fn power_of_two_and_ten() {
+        assert_eq!(Coefficient::from(1024).is_power_of_two(), Some(10));
+        assert_eq!(Coefficient::from(1).is_power_of_two(), Some(0));
+        assert_eq!(Coefficient::from(1023).is_power_of_two(), None);
+        assert_eq!(Coefficient::from(-1024).is_power_of_two(), None);
+        assert_eq!(Coefficient::from((1, 1024)).is_power_of_two(), None);
+        assert_eq!(Coefficient::from(1000).is_power_of_ten(), Some(3));
+        assert_eq!(Coefficient::from(1024).is_power_of_ten(), None);
+        assert_eq!(Coefficient::from(1023).is_power_of_ten(), None);
+
+        let large = Coefficient::from(MultiPrecisionInteger::from(1) << 200);
+        assert_eq!(large.is_power_of_two(), Some(200));
+        assert_eq!(large.is_power_of_ten(), None);
+
+        let large = Coefficient::from(MultiPrecisionInteger::from(10).pow(30));
+        assert_eq!(large.is_power_of_ten(), Some(30));
+        assert_eq!(large.is_power_of_two(), None);
+    }
+
+    #[test]
+    fn approx_eq() {
+        let a = CoefficientView::Natural(1, 3);
+        let b = CoefficientView::Natural(333333, 1000000);
+        assert!(a.approx_eq(&b, 1e-5));
+        assert!(!a.approx_eq(&b, 1e-10));
+        assert!(a.approx_eq(&a, 0.));
+
+        let large = MultiPrecisionRational::from((1, 3)) + MultiPrecisionRational::from(i64::MAX);
+        let large = Atom::new_num(Coefficient::from(large));
+        let large_shifted =
+            MultiPrecisionRational::from((1, 2)) + MultiPrecisionRational::from(i64::MAX);
+        let large_shifted = Atom::new_num(Coefficient::from(large_shifted));
+        if let (AtomView::Num(l1), AtomView::Num(l2)) = (large.as_view(), large_shifted.as_view()) {
+            assert!(l1.get_coeff_view().approx_eq(&l2.get_coeff_view(), 1e-10));
+            assert!(!l1.get_coeff_view().approx_eq(&b, 1e-10));
+        } else {
+            unreachable!()
+        }
+
+        // equal numbers beyond the range of f64
+        let huge = Atom::new_num(MultiPrecisionInteger::from(10).pow(400u32));
+        let huge2 = Atom::new_num(MultiPrecisionInteger::from(10).pow(400u32));
+        if let (AtomView::Num(h1), AtomView::Num(h2)) = (huge.as_view(), huge2.as_view()) {
+            assert!(h1.get_coeff_view().approx_eq(&h2.get_coeff_view(), 1e-10));
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn try_cmp() {
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        let field2 = State::get_or_insert_finite_field(Zp64::new(11));
+        let ff = State::get_finite_field(field);
+
+        let atoms = [
+            Atom::new_num(3),
+            Atom::new_num(MultiPrecisionRational::from(i64::MAX) * MultiPrecisionRational::from(2)),
+            Atom::new_num(Coefficient::FiniteField(ff.to_element(5), field)),
+            Atom::new_num(Coefficient::FiniteField(ff.to_element(2), field)),
+            Atom::new_num(Coefficient::finite_one(field2)),
+        ];
+        let v: Vec<_> = atoms
+            .iter()
+            .map(|a| match a.as_view() {
+                AtomView::Num(n) => n.get_coeff_view(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(v[0].try_cmp(&v[1]), Some(Ordering::Less));
+        assert_eq!(v[1].try_cmp(&v[0]), Some(Ordering::Greater));
+        assert_eq!(v[0].try_cmp(&v[0]), Some(Ordering::Equal));
+        assert_eq!(v[1].try_cmp(&v[1]), Some(Ordering::Equal));
+        assert!(v[2].try_cmp(&v[3]).is_some());
+
+        assert_eq!(v[0].try_cmp(&v[2]), None);
+        assert_eq!(v[2].try_cmp(&v[1]), None);
+        assert_eq!(v[3].try_cmp(&v[4]), None);
+    }
+
+    #[test]
+    fn remap_fields() {
+        let f7 = State::get_or_insert_finite_field(Zp64::new(7));
+        let f11 = State::get_or_insert_finite_field(Zp64::new(11));
+
+        // fields exported from another state in which 11 and 7 have other indices
+        let remap = State::load_fields(&[(11, 0), (7, 5)]).unwrap();
+        assert_eq!(remap.get(FiniteFieldIndex(0)), Some(f11));
+        assert_eq!(remap.get(FiniteFieldIndex(5)), Some(f7));
+        assert_eq!(remap.get(FiniteFieldIndex(1)), None);
+
+        let z7 = State::get_finite_field(f7);
+        let z11 = State::get_finite_field(f11);
+        let imported = FunctionBuilder::new(State::get_symbol("rmf_f"))
+            .add_arg(&Atom::new_num(Coefficient::FiniteField(
+                z7.to_element(3),
+                FiniteFieldIndex(5),
+            )))
+            .add_arg(&Atom::new_num(Coefficient::FiniteField(
+                z11.to_element(4),
+                FiniteFieldIndex(0),
+            )))
+            .finish();
+
+        let r = imported.remap_fields(&remap);
+        let expected = FunctionBuilder::new(State::get_symbol("rmf_f"))
+            .add_arg(&Atom::new_num(3).to_finite_field(f7))
+            .add_arg(&Atom::new_num(4).to_finite_field(f11))
+            .finish();
+        assert_eq!(r, expected);
+
+        let dump = State::dump_fields();
+        assert!(dump.contains(&(7, f7.0)));
+        assert!(State::load_fields(&dump).unwrap().is_identity());
+
+        // invalid moduli are rejected
+        assert!(State::load_fields(&[(4, 0)]).is_err());
+        assert!(State::load_fields(&[(7, 0), (1, 1)]).is_err());
+    }
+
+    #[test]
+    fn assign_ops() {
+        let big: MultiPrecisionInteger = MultiPrecisionInteger::from(i64::MAX) * 5;
+        let atoms = [
+            Atom::new_num((i64::MAX / 30, 7)),
+            Atom::new_num((-3i64, 4i64)),
+            Atom::new_num(big.clone()),
+            Atom::new_num(-big),
+            Atom::new_num(i64::MAX),
+            Atom::new_num(-i64::MAX),
+        ];
+        let v: Vec<_> = atoms
+            .iter()
+            .map(|a| match a.as_view() {
+                AtomView::Num(n) => n.get_coeff_view(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        // the sequence goes from natural to large and back
+        let mut acc = Coefficient::from(1);
+        let mut reference = Coefficient::from(1);
+        for i in [0, 4, 4, 1, 2, 3, 5, 3, 5, 0, 1, 2] {
+            acc += v[i];
+            reference = reference + v[i].to_owned();
+            assert_eq!(acc, reference);
+        }
+        assert!(matches!(acc, Coefficient::Rational(Rational::Natural(..))));
+
+        let mut acc = Coefficient::from(1);
+        let mut reference = Coefficient::from(1);
+        for i in [0, 1, 4, 0, 2, 1, 3, 5] {
+            acc *= v[i];
+            reference = reference * v[i].to_owned();
+            assert_eq!(acc, reference);
+        }
+
+        let zero = Atom::new_num(0);
+        let AtomView::Num(n) = zero.as_view() else {
+            unreachable!()
+        };
+        acc *= n.get_coeff_view();
+        assert_eq!(acc, Coefficient::Rational(Rational::Natural(0, 1)));
+    }
+
+    #[test]
+    fn add_zero() {
+        let atoms = [
+            Atom::new_num((3i64, 4i64)),
+            Atom::new_num(0),
+            Atom::new_num(MultiPrecisionInteger::from(i64::MAX) * 3),
+        ];
+        let v: Vec<_> = atoms
+            .iter()
+            .map(|a| match a.as_view() {
+                AtomView::Num(n) => n.get_coeff_view(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(v[0] + v[1], Coefficient::Rational(Rational::Natural(3, 4)));
+        assert_eq!(v[1] + v[0], Coefficient::Rational(Rational::Natural(3, 4)));
+        assert_eq!(v[2] + v[1], v[2].to_owned());
+        assert_eq!(v[1] + v[1], Coefficient::zero());
+
+        let large = v[2].to_owned();
+        let Coefficient::Rational(Rational::Large(r)) = &large else {
+            panic!("Expected a large rational");
+        };
+        let limbs = r.numer().as_limbs().as_ptr();
+
+        let sum = Coefficient::zero() + large.clone();
+        assert_eq!(sum, large);
+
+        let sum = large + Coefficient::zero();
+        let Coefficient::Rational(Rational::Large(r)) = &sum else {
+            panic!("Expected a large rational");
+        };
+        assert_eq!(r.numer().as_limbs().as_ptr(), limbs);
+    }
+
+    #[test]
+    fn to_fixed() {
+        let atoms = [
+            Atom::new_num((1i64, 3i64)),
+            Atom::new_num((2i64, 3i64)),
+            Atom::new_num((-2i64, 3i64)),
+            Atom::new_num((-1i64, 1000i64)),
+            Atom::new_num(MultiPrecisionInteger::from(i64::MAX) * 10 + 5),
+        ];
+        let v: Vec<_> = atoms
+            .iter()
+            .map(|a| match a.as_view() {
+                AtomView::Num(n) => n.get_coeff_view(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(v[0].to_fixed(4, RoundingMode::Trunc), "0.3333");
+        assert_eq!(v[0].to_fixed(4, RoundingMode::Ceil), "0.3334");
+        assert_eq!(v[1].to_fixed(2, RoundingMode::Nearest), "0.67");
+        assert_eq!(v[1].to_fixed(2, RoundingMode::Trunc), "0.66");
+        assert_eq!(v[2].to_fixed(2, RoundingMode::Nearest), "-0.67");
+        assert_eq!(v[2].to_fixed(2, RoundingMode::Floor), "-0.67");
+        assert_eq!(v[2].to_fixed(2, RoundingMode::Ceil), "-0.66");
+        assert_eq!(v[2].to_fixed(0, RoundingMode::Nearest), "-1");
+        assert_eq!(v[3].to_fixed(2, RoundingMode::Trunc), "0.00");
+        assert_eq!(v[3].to_fixed(2, RoundingMode::Floor), "-0.01");
+        assert_eq!(
+            v[4].to_fixed(1, RoundingMode::Trunc),
+            "92233720368547758075.0"
+        );
+        assert_eq!(
+            v[4].to_fixed(0, RoundingMode::Nearest),
+            "92233720368547758075"
+        );
+    }
+
+    #[test]
+    fn div_round() {
+        let atoms = [
+            Atom::new_num(7),
+            Atom::new_num(-7),
+            Atom::new_num(2),
+            Atom::new_num(MultiPrecisionInteger::from(i64::MAX) * 2 + 1),
+        ];
+        let v: Vec<_> = atoms
+            .iter()
+            .map(|a| match a.as_view() {
+                AtomView::Num(n) => n.get_coeff_view(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        for (a, modes) in [(0, [3, 3, 4, 4]), (1, [-3, -4, -3, -4])] {
+            for (mode, r) in [
+                RoundingMode::Trunc,
+                RoundingMode::Floor,
+                RoundingMode::Ceil,
+                RoundingMode::Nearest,
+            ]
+            .into_iter()
+            .zip(modes)
+            {
+                assert_eq!(v[a].div_round(&v[2], mode), Coefficient::from(r));
+            }
+        }
+
+        // (2^64 - 1) / 2 = 2^63 - 1/2
+        let half = MultiPrecisionInteger::from(i64::MAX);
+        assert_eq!(
+            v[3].div_round(&v[2], RoundingMode::Floor),
+            Coefficient::from(half.clone())
+        );
+        assert_eq!(
+            v[3].div_round(&v[2], RoundingMode::Nearest),
+            Coefficient::from(half + 1)
+        );
+        assert_eq!(
+            v[2].div_round(&v[3], RoundingMode::Ceil),
+            Coefficient::from(1)
+        );
+    }
+
+    #[test]
+    fn product_all() {
+        let f = |n: i64| -> Vec<CoefficientView> {
+            (1..=n).map(|i| CoefficientView::Natural(i, 1)).collect()
+        };
+
+        assert_eq!(Coefficient::product_all(&f(10)), Coefficient::from(3628800));
+        assert_eq!(
+            Coefficient::product_all(&f(25)),
+            Coefficient::from(MultiPrecisionInteger::from(
+                MultiPrecisionInteger::factorial(25)
+            ))
+        );
+
+        let r = Coefficient::product_all(&[
+            CoefficientView::Natural(i64::MAX, 3),
+            CoefficientView::Natural(3, i64::MAX),
+            CoefficientView::Natural(-1, 2),
+        ]);
+        assert_eq!(r, Coefficient::from((-1i64, 2i64)));
+
+        let mut nums = f(25);
+        nums.push(CoefficientView::Natural(0, 1));
+        assert!(Coefficient::product_all(&nums).is_zero());
+        assert_eq!(Coefficient::product_all(&[]), Coefficient::one());
+    }
+
+    #[test]
+    fn abs_sub() {
+        let (a, b) = (
+            CoefficientView::Natural(3, 1),
+            CoefficientView::Natural(5, 1),
+        );
+        assert_eq!(a.abs_sub(&b), Coefficient::from(2));
+        assert_eq!(b.abs_sub(&a), Coefficient::from(2));
+
+        let (a, b) = (
+            CoefficientView::Natural(1, 3),
+            CoefficientView::Natural(1, 2),
+        );
+        assert_eq!(a.abs_sub(&b), Coefficient::from((1i64, 6i64)));
+
+        let (a, b) = (
+            CoefficientView::Natural(i64::MIN, 1),
+            CoefficientView::Natural(i64::MAX, 1),
+        );
+        assert_eq!(
+            a.abs_sub(&b),
+            Coefficient::from(MultiPrecisionInteger::from(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn reduce_array_mod_p() {
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        let f = State::get_finite_field(field);
+
+        let nums: Vec<Coefficient> = vec![
+            (1i64, 3i64).into(),
+            (2i64, 3i64).into(),
+            (4i64, 3i64).into(),
+            (-5i64, 1i64).into(),
+            (MultiPrecisionRational::from(i64::MAX) * MultiPrecisionRational::from((1, 3))).into(),
+        ];
+
+        let r = Coefficient::reduce_array_mod_p(&nums, field).unwrap();
+        // 3^-1 = 5 mod 7
+        assert_eq!(
+            r[..4].iter().map(|e| f.from_element(e)).collect::<Vec<_>>(),
+            vec![5, 3, 6, 2]
+        );
+
+        for (n, e) in nums.iter().zip(&r) {
+            let a = Atom::new_num(n.clone()).to_finite_field(field);
+            assert_eq!(a, Atom::new_num(Coefficient::FiniteField(*e, field)));
+        }
+
+        let nums: Vec<Coefficient> = vec![(1i64, 3i64).into(), (1i64, 14i64).into()];
+        assert!(Coefficient::reduce_array_mod_p(&nums, field).is_err());
+    }
+
+    #[test]
+    fn finite_field() {
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        let f = State::get_finite_field(field);
+
+        let a = Coefficient::finite_field(23, field).unwrap();
+        assert_eq!(a, Coefficient::finite_field(2, field).unwrap());
+        assert!(Coefficient::finite_field(1, FiniteFieldIndex(usize::MAX)).is_err());
+
+        let b = Coefficient::finite_field(4, field).unwrap();
+        match a * b {
+            Coefficient::FiniteField(e, i) => {
+                assert_eq!(i, field);
+                assert_eq!(f.from_element(&e), 1);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn quadratic_residue() {
+        let field = State::get_or_insert_finite_field(Zp64::new(11));
+        let f = State::get_finite_field(field);
+
+        // the squares modulo 11 are 1, 3, 4, 5 and 9
+        let residues: Vec<_> = (1..11)
+            .filter(|i| f.is_quadratic_residue(&f.to_element(*i)))
+            .collect();
+        assert_eq!(residues, vec![1, 3, 4, 5, 9]);
+        assert!(f.is_quadratic_residue(&f.to_element(0)));
+
+        let a = Atom::new_num(Coefficient::finite_field(5, field).unwrap());
+        let b = Atom::new_num(Coefficient::finite_field(6, field).unwrap());
+        for (a, r) in [(a, Some(true)), (b, Some(false)), (Atom::new_num(3), None)] {
+            if let AtomView::Num(n) = a.as_view() {
+                assert_eq!(n.get_coeff_view().is_quadratic_residue(), r);
+            }
+        }
+    }
+
+    #[test]
+    fn float() {
+        let f = |x: f64| Coefficient::Float(Float::from_f64(x, 100));
+        let view = |a: &Atom| match a.as_view() {
+            AtomView::Num(n) => n.get_coeff_view().to_owned(),
+            _ => unreachable!(),
+        };
+
+        // packed round trip
+        for x in [0., 1.5, -2.25, 1e-300, -3e200] {
+            let a = Atom::new_num(f(x));
+            assert_eq!(view(&a), f(x));
+        }
+
+        assert_eq!(f(1.5) + f(2.25), f(3.75));
+        assert_eq!(f(1.5) + Coefficient::from((1, 4)), f(1.75));
+        assert_eq!(f(1.5) * Coefficient::from((2, 3)), f(1.));
+        assert_eq!(Coefficient::from(3) * f(0.5), f(1.5));
+
+        let a = Atom::new_num(f(1.5));
+        let b = Atom::new_num((1, 3));
+        let c = Atom::new_num(f(2.));
+        let (AtomView::Num(a), AtomView::Num(b), AtomView::Num(c)) =
+            (a.as_view(), b.as_view(), c.as_view())
+        else {
+            unreachable!()
+        };
+        let (a, b, c) = (a.get_coeff_view(), b.get_coeff_view(), c.get_coeff_view());
+
+        assert!(a > b);
+        assert!(b < a);
+        assert!(a < c);
+        assert_eq!(a.try_cmp(&b), Some(Ordering::Greater));
+
+        assert_eq!(a.pow(&CoefficientView::Natural(2, 1)).0, f(2.25));
+        assert_eq!(c.pow(&CoefficientView::Natural(-3, 1)).0, f(0.125));
+        assert_eq!(
+            b.pow(&c).0,
+            Coefficient::Float(Float::from_rational(&(1, 9).into(), 100))
+        );
+
+        // the square root is correct to all 100 bits
+        let Coefficient::Float(r) = c.pow(&CoefficientView::Natural(1, 2)).0 else {
+            unreachable!()
+        };
+        let sq = r.mul(&r);
+        assert!(sq.sub(&Float::from_f64(2., 100)).abs().to_f64() < 2f64.powi(-98));
+
+        assert_eq!(
+            (b / c),
+            Coefficient::Float(Float::from_rational(&(1, 6).into(), 100))
+        );
+        assert_eq!(Float::from_f64(0.1, 53).to_f64(), 0.1);
+        assert_eq!(
+            Float::from_rational(&(1, 3).into(), 10).to_string(),
+            "0.3335"
+        );
+        assert_eq!(
+            Atom::new_num(f(-2.5)).to_string(),
+            "-2.500000000000000000000000000000"
+        );
+
+        let x = Atom::parse("fl_x").unwrap();
+        let e = Atom::new_num(Coefficient::Float(Float::from_f64(0.5, 10))) * &x
+            + &Atom::parse("2*fl_x+1/2").unwrap()
+            + &Atom::new_num(Coefficient::Float(Float::from_f64(0.25, 10)));
+        assert_eq!(e.to_string(), "2.500*fl_x+0.7500");
+    }
+
+    #[test]
+    fn complex() {
+        let c = |re: i64, im: i64| Coefficient::complex(re.into(), im.into());
+
+        let a = c(2, 3);
+        let b = c(1, -1);
+        assert_eq!(a.clone() * b.clone(), c(5, 1));
+        assert_eq!(a.clone() + b.clone(), c(3, 2));
+        assert_eq!(Coefficient::i() * Coefficient::i(), Coefficient::from(-1));
+        assert_eq!(c(4, 0), Coefficient::from(4));
+
+        let q = Atom::new_num(a.clone()) / &Atom::new_num(b.clone());
+        assert_eq!(
+            q,
+            Atom::new_num(Coefficient::complex((-1, 2).into(), (5, 2).into()))
+        );
+
+        let packed = Atom::new_num(a.clone());
+        let AtomView::Num(n) = packed.as_view() else {
+            unreachable!()
+        };
+        assert_eq!(n.get_coeff_view().to_owned(), a);
+
+        let pb = Atom::new_num(b);
+        let AtomView::Num(n) = pb.as_view() else {
+            unreachable!()
+        };
+        assert_eq!(
+            n.get_coeff_view().pow(&CoefficientView::Natural(-2, 1)).0,
+            Coefficient::complex(0.into(), (1, 2).into())
+        );
+
+        assert_eq!(packed.to_string(), "(2+3𝑖)");
+        assert_eq!(Atom::new_num(c(0, -1)).to_string(), "-𝑖");
+
+        let e = Atom::parse("(2+3*𝑖)*cplx_x + 𝑖*𝑖*cplx_x - 𝑖*cplx_x").unwrap();
+        assert_eq!(e.to_string(), "(1+2𝑖)*cplx_x");
+    }
+
+    #[test]
+    fn reconstruct() {
+        let (x, m) = Coefficient::chinese_remainder(&[
+            (2.into(), 3.into()),
+            (3.into(), 5.into()),
+            ((-5).into(), 7.into()),
+        ])
+        .unwrap();
+        assert_eq!((x, m), (23.into(), 105.into()));
+        assert_eq!(
+            Coefficient::chinese_remainder(&[(1.into(), 4.into()), (1.into(), 6.into())]),
+            Err(CoefficientError::NonCoprimeModulus(6.into()))
+        );
+        assert_eq!(
+            Coefficient::chinese_remainder(&[(1.into(), 0.into())]),
+            Err(CoefficientError::NonPositiveModulus(0.into()))
+        );
+
+        let p = Integer::from(2305843009213693951i64);
+        let image = Z
+            .quot_rem(
+                &(&Integer::from(-4) * &Integer::from(7).mod_inverse(&p)),
+                &p,
+            )
+            .1;
+        assert_eq!(
+            Coefficient::rational_reconstruct(&image, &p).unwrap(),
+            Coefficient::from((-4, 7))
+        );
+
+        let e = Atom::parse("rr_x/3+5/7*rr_y+123456789/987654321*rr_x*rr_y").unwrap();
+        let g = Atom::parse("rr_x-2/5").unwrap();
+        let r = e
+            .reconstruct_from_finite_fields(|a, fi| {
+                (a.to_owned() * &g.to_finite_field(fi)).expand()
+            })
+            .unwrap();
+        assert_eq!(r, (e * &g).expand());
+    }
+
+    #[test]
+    fn try_arithmetic() {
+        let f1 = State::get_or_insert_finite_field(Zp64::new(7));
+        let f2 = State::get_or_insert_finite_field(Zp64::new(11));
+
+        let a = Atom::new_num(Coefficient::finite_field(3, f1).unwrap());
+        let b = Atom::new_num(Coefficient::finite_field(5, f2).unwrap());
+        let c = Atom::new_num((1, 2));
+        let (AtomView::Num(a), AtomView::Num(b), AtomView::Num(c)) =
+            (a.as_view(), b.as_view(), c.as_view())
+        else {
+            unreachable!()
+        };
+        let (a, b, c) = (a.get_coeff_view(), b.get_coeff_view(), c.get_coeff_view());
+
+        assert_eq!(
+            a.try_add(&b),
+            Err(CoefficientError::DifferentFiniteFields { p1: 7, p2: 11 })
+        );
+        assert_eq!(a.try_mul(&c), Err(CoefficientError::MixedFiniteField));
+        assert_eq!(
+            a.try_mul(&a).unwrap(),
+            Coefficient::finite_field(2, f1).unwrap()
+        );
+        assert_eq!(c.try_add(&c).unwrap(), Coefficient::one());
+
+        let h = Coefficient::from((1, 2)).to_finite_field(f1).unwrap();
+        assert_eq!(h, Coefficient::finite_field(4, f1).unwrap());
+        let ha = Atom::new_num(h.clone());
+        let AtomView::Num(hn) = ha.as_view() else {
+            unreachable!()
+        };
+        assert_eq!(
+            a.try_add(&hn.get_coeff_view()).unwrap(),
+            Coefficient::finite_field(0, f1).unwrap()
+        );
+        assert_eq!(
+            Coefficient::from((1, 7)).to_finite_field(f1),
+            Err(CoefficientError::DivisionByZero { prime: 7 })
+        );
+        assert!(h.to_finite_field(f2).is_err());
+    }
+
+    #[test]
+    fn packed_i128() {
+        let a = MultiPrecisionRational::from((i64::MAX as i128 * 7, 3));
+        let b = MultiPrecisionRational::from((-(i64::MAX as i128) * 5, 11));
+        let (x, y) = (Atom::new_num(a.clone()), Atom::new_num(b.clone()));
+        let (AtomView::Num(x), AtomView::Num(y)) = (x.as_view(), y.as_view()) else {
+            unreachable!()
+        };
+        let (x, y) = (x.get_coeff_view(), y.get_coeff_view());
+
+        let CoefficientView::Large(l) = x else {
+            unreachable!()
+        };
+        assert_eq!(l.to_i128(), Some((i64::MAX as i128 * 7, 3)));
+
+        assert_eq!(x + y, Coefficient::from(a.clone() + &b));
+        assert_eq!(x * y, Coefficient::from(a.clone() * &b));
+        assert_eq!(x.cmp(&y), Ordering::Greater);
+        assert_eq!(
+            CoefficientView::Natural(i64::MAX, 1).cmp(&x),
+            Ordering::Less
+        );
+        assert_eq!(
+            x + CoefficientView::Natural(-i64::MAX, 3),
+            Coefficient::from(MultiPrecisionRational::from(i64::MAX as i128 * 2))
+        );
+    }
+
+    #[test]
+    fn rational_function_coefficients() {
+        let vars = Arc::new(vec![State::get_symbol("rfc_t").into()]);
+        let a = Atom::parse("(rfc_t^2-1)/(rfc_t-1)*rfc_x + rfc_t*rfc_x + rfc_x^2/(rfc_t+1)")
+            .unwrap()
+            .set_coefficient_ring(&vars);
+        let b = Atom::parse("(2*rfc_t+1)*rfc_x + 1/(rfc_t+1)*rfc_x^2")
+            .unwrap()
+            .set_coefficient_ring(&vars);
+        // packed rational polynomial coefficients are compared by identity, so atoms
+        // with equal coefficients that were created separately are not equal
+        assert_ne!(a, b);
+        assert_eq!(a, a.clone());
+        assert_eq!(a.to_string(), b.to_string());
+
+        // coefficient views compare by value
+        let coeffs = |a: &Atom| {
+            a.as_view()
+                .iter_subexpressions(TraversalOrder::PreOrder)
+                .filter_map(|x| match x {
+                    AtomView::Num(n) => Some(n.get_coeff_view().to_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(coeffs(&a), coeffs(&b));
+
+        let field = RationalPolynomialField::new(Z, vars.clone());
+        let p = a.to_polynomial::<_, u8>(&field, None);
+        assert_eq!(p.nterms(), 2);
+
+        let p2 = &p * &p;
+        let r = Atom::parse(
+            "(4*rfc_t^2+4*rfc_t+1)*rfc_x^2 + (4*rfc_t+2)/(rfc_t+1)*rfc_x^3 + rfc_x^4/(rfc_t+1)^2",
+        )
+        .unwrap()
+        .set_coefficient_ring(&vars)
+        .to_polynomial::<_, u8>(&field, p.variables.clone().into());
+        assert_eq!(p2, r);
+        assert_eq!(
+            field.element_from_coefficient(Coefficient::from(3)),
+            field.nth(3)
+        );
+    }
+
+    #[test]
+    fn hash_and_order() {
+        let f = State::get_or_insert_finite_field(Zp64::new(13));
+        let large = Coefficient::from(Integer::from(i64::MAX) * &Integer::from(5));
+        let coeffs = [
+            Coefficient::FiniteField(Zp64::new(13).to_element(4), f),
+            large.clone(),
+            Coefficient::Float(Float::from_rational(&(1, 2).into(), 53)),
+            Coefficient::from((1, 2)),
+            Coefficient::complex(Coefficient::from((1, 2)), Coefficient::from(-1)),
+            Coefficient::from(-3),
+        ];
+
+        let sorted: BTreeSet<_> = coeffs.iter().cloned().collect();
+        assert_eq!(
+            sorted.into_iter().collect::<Vec<_>>(),
+            vec![
+                coeffs[5].clone(),
+                coeffs[4].clone(),
+                coeffs[3].clone(),
+                coeffs[2].clone(),
+                coeffs[1].clone(),
+                coeffs[0].clone(),
+            ]
+        );
+
+        let hash = |x: &dyn Fn(&mut DefaultHasher)| {
+            let mut h = DefaultHasher::new();
+            x(&mut h);
+            h.finish()
+        };
+
+        let mut set = HashSet::default();
+        for c in &coeffs {
+            let a = Atom::new_num(c.clone());
+            let AtomView::Num(n) = a.as_view() else {
+                unreachable!()
+            };
+            let v = n.get_coeff_view();
+            assert_eq!(hash(&|h| c.hash(h)), hash(&|h| v.hash(h)));
+            assert_eq!(v.cmp(&v), Ordering::Equal);
+            set.insert(c.clone());
+            set.insert(v.to_owned());
+        }
+        assert_eq!(set.len(), coeffs.len());
+
+        // views of different kinds are ordered without panicking
+        let (a, b) = (Atom::new_num(large), Atom::new_num(coeffs[0].clone()));
+        let (AtomView::Num(a), AtomView::Num(b)) = (a.as_view(), b.as_view()) else {
+            unreachable!()
+        };
+        assert_eq!(a.get_coeff_view().cmp(&b.get_coeff_view()), Ordering::Less);
+    }
+
+    #[test]
+    fn rational_approximation() {
+        let max_den = Integer::from(1000);
+        assert_eq!(
+            Coefficient::from_f64_approx(std::f64::consts::PI, &max_den),
+            Coefficient::from((355, 113))
+        );
+        assert_eq!(
+            Coefficient::from_f64_approx(-0.33333333338, &max_den),
+            Coefficient::from((-1, 3))
+        );
+        assert_eq!(
+            Coefficient::from_f64_approx(0.5, &max_den),
+            Coefficient::from((1, 2))
+        );
+
+        let cf = Coefficient::from((-355, 113)).continued_fraction().unwrap();
+        assert_eq!(cf, [-4, 1, 6, 16].map(Integer::from));
+        assert_eq!(
+            Rational::from_continued_fraction(&cf),
+            Rational::new(-355, 113)
+        );
+
+        let f = Coefficient::Float(Float::from_f64(0.375, 53));
+        assert_eq!(
+            f.continued_fraction().unwrap(),
+            [0, 2, 1, 2].map(Integer::from)
+        );
+        assert!(
+            Coefficient::finite_one(State::get_or_insert_finite_field(Zp64::new(5)))
+                .continued_fraction()
+                .is_err()
+        );
+    }
+}
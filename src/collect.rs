@@ -1,6 +1,7 @@
 use ahash::HashMap;
 
 use crate::{
+    coefficient::{Coefficient, CoefficientView},
     representations::{Add, Atom, AtomView, Symbol},
     state::Workspace,
 };
@@ -46,6 +47,35 @@ impl Atom {
     pub fn coefficient_list(&self, x: Symbol) -> (Vec<(AtomView<'_>, Atom)>, Atom) {
         Workspace::get_local().with(|ws| self.as_view().coefficient_list_with_ws(x, ws))
     }
+
+    /// Write the expression as a polynomial in the variable `x` in Horner form, e.g.
+    ///
+    /// ```math
+    /// 3*x^2 + 2*x + 1 = (3*x + 2)*x + 1
+    /// ```
+    ///
+    /// The coefficients of the powers of `x` may be arbitrary expressions.
+    pub fn to_horner(&self, x: Symbol) -> Atom {
+        self.as_view().to_horner(x)
+    }
+
+    /// Get the numerical coefficients of the univariate polynomial in `x`, from
+    /// degree 0 up to the highest degree. An error is returned if the expression
+    /// is not a polynomial in `x` with numerical coefficients.
+    pub fn dense_coeffs(&self, x: Symbol) -> Result<Vec<Coefficient>, String> {
+        self.as_view().dense_coeffs(x)
+    }
+
+    /// Get the number of top-level terms. See [`AtomView::term_count`].
+    pub fn term_count(&self) -> usize {
+        self.as_view().term_count()
+    }
+
+    /// Estimate how sparse the expression is as a polynomial in `vars`.
+    /// See [`AtomView::sparsity`].
+    pub fn sparsity(&self, vars: &[Symbol]) -> f64 {
+        self.as_view().sparsity(vars)
+    }
 }
 
 impl<'a> AtomView<'a> {
@@ -157,6 +187,192 @@ impl<'a> AtomView<'a> {
         add_h.as_view().normalize(workspace, out);
     }
 
+    /// Write the expression as a polynomial in the variable `x` in Horner form, e.g.
+    ///
+    /// ```math
+    /// 3*x^2 + 2*x + 1 = (3*x + 2)*x + 1
+    /// ```
+    ///
+    /// The coefficients of the powers of `x` may be arbitrary expressions.
+    pub fn to_horner(&self, x: Symbol) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.to_horner_with_ws_into(x, ws, &mut out);
+            out.into_inner()
+        })
+    }
+
+    /// Write the expression as a polynomial in the variable `x` in Horner form.
+    /// Terms with non-integer or negative powers of `x` are treated as part of
+    /// the constant coefficient.
+    pub fn to_horner_with_ws_into(&self, x: Symbol, workspace: &Workspace, out: &mut Atom) {
+        let mut expanded = workspace.new_atom();
+        self.expand_with_ws_into(workspace, &mut expanded);
+
+        let (list, rest) = expanded.as_view().coefficient_list_with_ws(x, workspace);
+
+        let mut coeffs: Vec<Atom> = vec![rest];
+        for (key, coeff) in list {
+            let degree = match key {
+                AtomView::Var(_) => Some(1),
+                AtomView::Pow(p) => match p.get_base_exp() {
+                    (AtomView::Var(_), AtomView::Num(n)) => match n.get_coeff_view() {
+                        CoefficientView::Natural(k, 1) if k > 0 => Some(k as usize),
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            let (degree, coeff) = match degree {
+                Some(d) => (d, coeff),
+                None => {
+                    let mut term = workspace.new_atom();
+                    key.mul_with_ws_into(workspace, coeff.as_view(), &mut term);
+                    (0, term.into_inner())
+                }
+            };
+
+            if coeffs.len() <= degree {
+                coeffs.resize(degree + 1, Atom::new_num(0));
+            }
+
+            let mut sum = workspace.new_atom();
+            coeffs[degree]
+                .as_view()
+                .add_with_ws_into(workspace, coeff.as_view(), &mut sum);
+            std::mem::swap(&mut coeffs[degree], &mut sum);
+        }
+
+        let var = workspace.new_var(x);
+        let mut tmp = workspace.new_atom();
+        out.set_from_view(&coeffs.pop().unwrap().as_view());
+        for c in coeffs.iter().rev() {
+            out.as_view()
+                .mul_with_ws_into(workspace, var.as_view(), &mut tmp);
+
+            if matches!(c.as_view(), AtomView::Num(n) if n.is_zero()) {
+                std::mem::swap(out, &mut tmp);
+            } else {
+                tmp.as_view().add_with_ws_into(workspace, c.as_view(), out);
+            }
+        }
+    }
+
+    /// Get the numerical coefficients of the univariate polynomial in `x`, from
+    /// degree 0 up to the highest degree. An error is returned if the expression
+    /// is not a polynomial in `x` with numerical coefficients.
+    pub fn dense_coeffs(&self, x: Symbol) -> Result<Vec<Coefficient>, String> {
+        let expanded = self.expand();
+
+        let mut coeffs: Vec<Coefficient> = vec![];
+        let mut add_term = |term: AtomView| -> Result<(), String> {
+            let mut degree = 0;
+            let mut coeff = Coefficient::one();
+
+            let factors: Vec<_> = match term {
+                AtomView::Mul(m) => m.iter().collect(),
+                _ => vec![term],
+            };
+
+            for f in factors {
+                match f {
+                    AtomView::Num(n) => coeff = coeff * n.get_coeff_view().to_owned(),
+                    AtomView::Var(v) if v.get_symbol() == x => degree += 1,
+                    AtomView::Pow(p) if p.get_base() == Atom::new_var(x).as_view() => {
+                        match p.get_exp() {
+                            AtomView::Num(n) => match n.get_coeff_view() {
+                                CoefficientView::Natural(k, 1) if k > 0 => degree += k as usize,
+                                _ => Err(format!(
+                                    "Non-positive or non-integer power of {} in {}",
+                                    x, term
+                                ))?,
+                            },
+                            _ => Err(format!("Symbolic power of {} in {}", x, term))?,
+                        }
+                    }
+                    _ if f.contains_symbol(x) => {
+                        Err(format!("{} appears in a non-polynomial way in {}", x, term))?
+                    }
+                    _ => Err(format!("Non-numerical coefficient in {}", term))?,
+                }
+            }
+
+            if coeffs.len() <= degree {
+                coeffs.resize(degree + 1, Coefficient::zero());
+            }
+            coeffs[degree] = coeffs[degree].clone() + coeff;
+            Ok(())
+        };
+
+        match expanded.as_view() {
+            AtomView::Add(a) => {
+                for t in a.iter() {
+                    add_term(t)?;
+                }
+            }
+            AtomView::Num(n) if n.is_zero() => {}
+            t => add_term(t)?,
+        }
+
+        Ok(coeffs)
+    }
+
+    /// Get the number of top-level terms, which is 1 if the expression is not a sum.
+    /// The expression is not expanded.
+    pub fn term_count(&self) -> usize {
+        match self {
+            AtomView::Add(a) => a.get_nargs(),
+            _ => 1,
+        }
+    }
+
+    /// Estimate how sparse the expression is as a polynomial in `vars`, as the
+    /// number of terms divided by the number of terms `(d+1)^n` of a dense polynomial
+    /// in `n` variables, where `d` is the highest power of any of the `vars`.
+    /// A value close to 1 means the expression is dense.
+    ///
+    /// The expression is not expanded and only the positive integer powers of `vars`
+    /// that appear directly in a term are considered.
+    pub fn sparsity(&self, vars: &[Symbol]) -> f64 {
+        let term_degree = |term: AtomView| -> i64 {
+            let factors: Vec<_> = match term {
+                AtomView::Mul(m) => m.iter().collect(),
+                _ => vec![term],
+            };
+
+            let mut max_degree = 0;
+            for v in vars {
+                let mut degree = 0;
+                for f in &factors {
+                    match f {
+                        AtomView::Var(x) if x.get_symbol() == *v => degree += 1,
+                        AtomView::Pow(p) => {
+                            if let (AtomView::Var(x), AtomView::Num(n)) = p.get_base_exp() {
+                                if let CoefficientView::Natural(k, 1) = n.get_coeff_view() {
+                                    if x.get_symbol() == *v && k > 0 {
+                                        degree += k;
+                                    }
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                max_degree = max_degree.max(degree);
+            }
+            max_degree
+        };
+
+        let max_degree = match self {
+            AtomView::Add(a) => a.iter().map(term_degree).max().unwrap_or(0),
+            _ => term_degree(*self),
+        };
+
+        self.term_count() as f64 / ((max_degree + 1) as f64).powi(vars.len() as i32)
+    }
+
     /// Collect terms involving the same power of `x`, where `x` is a variable or function name.
     /// Return the list of key-coefficient pairs and the remainder that matched no key.
     pub fn coefficient_list(&self, x: Symbol) -> (Vec<(AtomView<'a>, Atom)>, Atom) {
@@ -269,3 +485,86 @@ impl<'a> AtomView<'a> {
         std::mem::swap(rest, &mut new_atom);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMap;
+
+    use crate::{coefficient::Coefficient, representations::Atom, state::State};
+
+    #[test]
+    fn horner() {
+        let x = State::get_symbol("hor_x");
+        let a = Atom::parse("3*hor_x^2 + 2*hor_x + 1").unwrap();
+        let h = a.to_horner(x);
+        assert_eq!(h, Atom::parse("(3*hor_x + 2)*hor_x + 1").unwrap());
+
+        let b = Atom::parse("hor_y*hor_x^3 + hor_x + hor_y + 5").unwrap();
+        let h = b.to_horner(x);
+        assert_eq!(
+            h.to_string(),
+            Atom::parse("(hor_y*hor_x^2 + 1)*hor_x + hor_y + 5")
+                .unwrap()
+                .to_string()
+        );
+
+        let x_atom = Atom::new_var(x);
+        let y_atom = Atom::new_var(State::get_symbol("hor_y"));
+        for v in [-2., 0.5, 3.] {
+            let mut const_map = HashMap::default();
+            const_map.insert(x_atom.as_view(), v);
+            const_map.insert(y_atom.as_view(), 1.5);
+
+            for (e, h) in [(&a, a.to_horner(x)), (&b, b.to_horner(x))] {
+                let r1: f64 = e.evaluate(&const_map, &HashMap::default(), &mut HashMap::default());
+                let r2: f64 = h.evaluate(&const_map, &HashMap::default(), &mut HashMap::default());
+                assert!((r1 - r2).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn dense_coeffs() {
+        let x = State::get_symbol("dc_x");
+
+        let a = Atom::parse("3*dc_x^2 + 1").unwrap();
+        assert_eq!(
+            a.dense_coeffs(x).unwrap(),
+            vec![1.into(), 0.into(), 3.into()] as Vec<Coefficient>
+        );
+
+        let a = Atom::parse("dc_x^3").unwrap();
+        assert_eq!(
+            a.dense_coeffs(x).unwrap(),
+            vec![0.into(), 0.into(), 0.into(), 1.into()] as Vec<Coefficient>
+        );
+
+        let a = Atom::parse("(dc_x+1)^2 - 1/2*dc_x").unwrap();
+        assert_eq!(
+            a.dense_coeffs(x).unwrap(),
+            vec![1.into(), (3i64, 2i64).into(), 1.into()] as Vec<Coefficient>
+        );
+
+        assert!(Atom::parse("dc_x^-1").unwrap().dense_coeffs(x).is_err());
+        assert!(Atom::parse("dc_x^(1/2)").unwrap().dense_coeffs(x).is_err());
+        assert!(Atom::parse("dc_f(dc_x)").unwrap().dense_coeffs(x).is_err());
+        assert!(Atom::parse("dc_y*dc_x").unwrap().dense_coeffs(x).is_err());
+        assert!(Atom::new_num(0).dense_coeffs(x).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sparsity() {
+        assert_eq!(Atom::parse("sp_a + sp_b + sp_c").unwrap().term_count(), 3);
+        assert_eq!(Atom::parse("sp_x^5").unwrap().term_count(), 1);
+        assert_eq!(Atom::parse("(sp_x+1)^5").unwrap().term_count(), 1);
+
+        let (x, y) = (State::get_symbol("sp_x"), State::get_symbol("sp_y"));
+
+        // 3 terms out of 11^2 possible ones
+        let a = Atom::parse("sp_x^10 + sp_y^10 + sp_x*sp_y").unwrap();
+        assert_eq!(a.sparsity(&[x, y]), 3. / 121.);
+
+        let a = Atom::parse("1 + sp_x + sp_y + sp_x*sp_y").unwrap();
+        assert_eq!(a.sparsity(&[x, y]), 1.);
+    }
+}
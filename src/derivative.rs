@@ -1,9 +1,9 @@
 use std::ops::DerefMut;
 
 use crate::{
-    coefficient::Coefficient,
+    coefficient::{Coefficient, CoefficientView},
     domains::integer::Integer,
-    representations::{Atom, AtomView, Symbol},
+    representations::{Atom, AtomView, FunctionBuilder, Symbol},
     state::{State, Workspace},
 };
 
@@ -20,6 +20,19 @@ impl Atom {
         self.as_view().derivative_into(x, out)
     }
 
+    /// Take a derivative of the expression with respect to the function `f`,
+    /// treating every occurrence of `f(...)` as the variable. Functions that
+    /// contain `f` in their arguments yield `der` placeholders.
+    pub fn derivative_wrt_function(&self, f: Symbol) -> Atom {
+        self.as_view().derivative_wrt_function(f)
+    }
+
+    /// Rewrite `der` placeholders into concrete derivatives, using the partial derivatives
+    /// registered with [`State::set_derivative_rule`]. Nested `der` functions are combined.
+    pub fn resolve_derivatives(&self) -> Atom {
+        self.as_view().resolve_derivatives()
+    }
+
     /// Taylor expand in `x` around `expansion_point` to depth `depth`.
     pub fn taylor_series(&self, x: Symbol, expansion_point: AtomView, depth: u32) -> Atom {
         self.as_view().taylor_series(x, expansion_point, depth)
@@ -37,6 +50,14 @@ impl Atom {
         self.as_view()
             .taylor_series_into(x, expansion_point, depth, out)
     }
+
+    /// Compute an antiderivative of the expression with respect to `x`, without
+    /// integration constant. Supported are polynomials in `x`, including `1/x`, and
+    /// `sin`, `cos` and `exp` of an argument that is linear in `x`.
+    /// Returns `None` if the expression is outside of the supported set.
+    pub fn integrate(&self, x: Symbol) -> Option<Atom> {
+        self.as_view().integrate(x)
+    }
 }
 
 impl<'a> AtomView<'a> {
@@ -49,6 +70,17 @@ impl<'a> AtomView<'a> {
         })
     }
 
+    /// Take a derivative of the expression with respect to the function `f`,
+    /// treating every occurrence of `f(...)` as the variable. Functions that
+    /// contain `f` in their arguments yield `der` placeholders.
+    pub fn derivative_wrt_function(&self, f: Symbol) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.derivative_wrt_function_with_ws_into(f, ws, &mut out);
+            out.into_inner()
+        })
+    }
+
     /// Take a derivative of the expression with respect to `x` and
     /// write the result in `out`.
     /// Returns `true` if the derivative is non-zero.
@@ -64,6 +96,29 @@ impl<'a> AtomView<'a> {
         x: Symbol,
         workspace: &Workspace,
         out: &mut Atom,
+    ) -> bool {
+        self.derivative_impl(x, false, workspace, out)
+    }
+
+    /// Take a derivative of the expression with respect to the function `f`,
+    /// treating every occurrence of `f(...)` as the variable, and
+    /// write the result in `out`.
+    /// Returns `true` if the derivative is non-zero.
+    pub fn derivative_wrt_function_with_ws_into(
+        &self,
+        f: Symbol,
+        workspace: &Workspace,
+        out: &mut Atom,
+    ) -> bool {
+        self.derivative_impl(f, true, workspace, out)
+    }
+
+    fn derivative_impl(
+        &self,
+        x: Symbol,
+        wrt_function: bool,
+        workspace: &Workspace,
+        out: &mut Atom,
     ) -> bool {
         match self {
             AtomView::Num(_) => {
@@ -71,7 +126,7 @@ impl<'a> AtomView<'a> {
                 false
             }
             AtomView::Var(v) => {
-                if v.get_symbol() == x {
+                if !wrt_function && v.get_symbol() == x {
                     out.to_num(1.into());
                     true
                 } else {
@@ -80,29 +135,34 @@ impl<'a> AtomView<'a> {
                     false
                 }
             }
+            AtomView::Fun(f_orig) if wrt_function && f_orig.get_symbol() == x => {
+                out.to_num(1.into());
+                true
+            }
             AtomView::Fun(f_orig) => {
                 // detect if the function to derive is the derivative function itself
                 // if so, derive the last argument of the derivative function and set
                 // a flag to later accumulate previous derivatives
-                let (to_derive, f, is_der) = if f_orig.get_symbol() == State::DERIVATIVE {
-                    let to_derive = f_orig.iter().last().unwrap();
-                    (
-                        to_derive,
-                        match to_derive {
-                            AtomView::Fun(f) => f,
-                            _ => panic!("Last argument of der function must be a function"),
-                        },
-                        true,
-                    )
-                } else {
-                    (*self, *f_orig, false)
-                };
+                let (to_derive, f, is_der) =
+                    if f_orig.get_symbol() == State::DERIVATIVE && f_orig.get_nargs() > 0 {
+                        let to_derive = f_orig.iter().last().unwrap();
+                        (
+                            to_derive,
+                            match to_derive {
+                                AtomView::Fun(f) => f,
+                                _ => panic!("Last argument of der function must be a function"),
+                            },
+                            true,
+                        )
+                    } else {
+                        (*self, *f_orig, false)
+                    };
 
                 // take derivative of all the arguments and store it in a list
                 let mut args_der = Vec::with_capacity(f.get_nargs());
                 for (i, arg) in f.iter().enumerate() {
                     let mut arg_der = workspace.new_atom();
-                    if arg.derivative_with_ws_into(x, workspace, &mut arg_der) {
+                    if arg.derivative_impl(x, wrt_function, workspace, &mut arg_der) {
                         args_der.push((i, arg_der));
                     }
                 }
@@ -204,10 +264,12 @@ impl<'a> AtomView<'a> {
                 let (base, exp) = p.get_base_exp();
 
                 let mut exp_der = workspace.new_atom();
-                let exp_der_non_zero = exp.derivative_with_ws_into(x, workspace, &mut exp_der);
+                let exp_der_non_zero =
+                    exp.derivative_impl(x, wrt_function, workspace, &mut exp_der);
 
                 let mut base_der = workspace.new_atom();
-                let base_der_non_zero = base.derivative_with_ws_into(x, workspace, &mut base_der);
+                let base_der_non_zero =
+                    base.derivative_impl(x, wrt_function, workspace, &mut base_der);
 
                 if !exp_der_non_zero && !base_der_non_zero {
                     out.to_num(0.into());
@@ -287,27 +349,19 @@ impl<'a> AtomView<'a> {
                 let add = add_h.to_add();
                 let mut mul_h = workspace.new_atom();
                 let mut non_zero = false;
-                for arg in args.iter() {
+                for (i, arg) in args.iter().enumerate() {
                     let mut arg_der = workspace.new_atom();
-                    if arg.derivative_with_ws_into(x, workspace, &mut arg_der) {
-                        if let Atom::Mul(mm) = arg_der.deref_mut() {
-                            for other_arg in args.iter() {
-                                if other_arg != arg {
-                                    mm.extend(other_arg);
-                                }
-                            }
-
-                            add.extend(arg_der.as_view());
-                        } else {
-                            let mm = mul_h.to_mul();
-                            mm.extend(arg_der.as_view());
-                            for other_arg in args.iter() {
-                                if other_arg != arg {
-                                    mm.extend(other_arg);
-                                }
+                    if arg.derivative_impl(x, wrt_function, workspace, &mut arg_der) {
+                        // keep the position of the factor, as the product may be noncommutative
+                        let mm = mul_h.to_mul();
+                        for (j, other_arg) in args.iter().enumerate() {
+                            if i == j {
+                                mm.extend(arg_der.as_view());
+                            } else {
+                                mm.extend(other_arg);
                             }
-                            add.extend(mul_h.as_view());
                         }
+                        add.extend(mul_h.as_view());
 
                         non_zero = true;
                     }
@@ -327,7 +381,7 @@ impl<'a> AtomView<'a> {
                 let mut arg_der = workspace.new_atom();
                 let mut non_zero = false;
                 for arg in args.iter() {
-                    if arg.derivative_with_ws_into(x, workspace, &mut arg_der) {
+                    if arg.derivative_impl(x, wrt_function, workspace, &mut arg_der) {
                         add.extend(arg_der.as_view());
                         non_zero = true;
                     }
@@ -344,6 +398,189 @@ impl<'a> AtomView<'a> {
         }
     }
 
+    /// Rewrite `der` placeholders into concrete derivatives, using the partial derivatives
+    /// registered with [`State::set_derivative_rule`]. Nested `der` functions are combined.
+    pub fn resolve_derivatives(&self) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.resolve_derivatives_with_ws_into(ws, &mut out);
+            out.into_inner()
+        })
+    }
+
+    /// Rewrite `der` placeholders into concrete derivatives, using the partial derivatives
+    /// registered with [`State::set_derivative_rule`]. Nested `der` functions are combined.
+    /// Returns `true` iff the expression changed.
+    pub fn resolve_derivatives_with_ws_into(&self, workspace: &Workspace, out: &mut Atom) -> bool {
+        let mut new = workspace.new_atom();
+        let changed = match self {
+            AtomView::Num(_) | AtomView::Var(_) => {
+                out.set_from_view(self);
+                return false;
+            }
+            AtomView::Fun(f) if f.get_symbol() == State::DERIVATIVE => {
+                if let Some(r) = self.resolve_derivative_function(workspace) {
+                    *out = r;
+                    return true;
+                }
+
+                let fun = new.to_fun(f.get_symbol());
+                let mut changed = false;
+                let mut arg_out = workspace.new_atom();
+                for arg in f.iter() {
+                    changed |= arg.resolve_derivatives_with_ws_into(workspace, &mut arg_out);
+                    fun.add_arg(arg_out.as_view());
+                }
+                changed
+            }
+            AtomView::Fun(f) => {
+                let fun = new.to_fun(f.get_symbol());
+                let mut changed = false;
+                let mut arg_out = workspace.new_atom();
+                for arg in f.iter() {
+                    changed |= arg.resolve_derivatives_with_ws_into(workspace, &mut arg_out);
+                    fun.add_arg(arg_out.as_view());
+                }
+                changed
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let mut base_out = workspace.new_atom();
+                let mut exp_out = workspace.new_atom();
+                let changed = base.resolve_derivatives_with_ws_into(workspace, &mut base_out)
+                    | exp.resolve_derivatives_with_ws_into(workspace, &mut exp_out);
+                new.to_pow(base_out.as_view(), exp_out.as_view());
+                changed
+            }
+            AtomView::Mul(m) => {
+                let mul = new.to_mul();
+                let mut changed = false;
+                let mut arg_out = workspace.new_atom();
+                for arg in m.iter() {
+                    changed |= arg.resolve_derivatives_with_ws_into(workspace, &mut arg_out);
+                    mul.extend(arg_out.as_view());
+                }
+                changed
+            }
+            AtomView::Add(a) => {
+                let add = new.to_add();
+                let mut changed = false;
+                let mut arg_out = workspace.new_atom();
+                for arg in a.iter() {
+                    changed |= arg.resolve_derivatives_with_ws_into(workspace, &mut arg_out);
+                    add.extend(arg_out.as_view());
+                }
+                changed
+            }
+        };
+
+        if changed {
+            new.as_view().normalize(workspace, out);
+        } else {
+            out.set_from_view(self);
+        }
+
+        changed
+    }
+
+    /// Resolve a single `der` function, returning `None` if it did not change.
+    fn resolve_derivative_function(&self, workspace: &Workspace) -> Option<Atom> {
+        let AtomView::Fun(f) = self else {
+            return None;
+        };
+
+        if f.get_nargs() == 0 {
+            return None;
+        }
+
+        let mut orders = vec![];
+        for arg in f.iter().take(f.get_nargs() - 1) {
+            match arg {
+                AtomView::Num(n) => match n.get_coeff_view() {
+                    CoefficientView::Natural(o, 1) if o >= 0 => orders.push(o as usize),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+
+        // combine nested derivatives by adding their orders
+        let mut target = f.iter().last()?;
+        let mut combined = false;
+        while let AtomView::Fun(inner) = target {
+            if inner.get_symbol() != State::DERIVATIVE || inner.get_nargs() != f.get_nargs() {
+                break;
+            }
+
+            for (o, arg) in orders.iter_mut().zip(inner.iter()) {
+                match arg {
+                    AtomView::Num(n) => match n.get_coeff_view() {
+                        CoefficientView::Natural(i, 1) if i >= 0 => *o += i as usize,
+                        _ => return None,
+                    },
+                    _ => return None,
+                }
+            }
+
+            target = inner.iter().last()?;
+            combined = true;
+        }
+
+        let mut target_res = workspace.new_atom();
+        let target_changed = target.resolve_derivatives_with_ws_into(workspace, &mut target_res);
+        let target = target_res.as_view();
+
+        if let AtomView::Fun(g) = target {
+            if let Some((args, partials)) = State::get_derivative_rule(g.get_symbol()) {
+                if args.len() == orders.len() && g.get_nargs() == orders.len() {
+                    let Some(first) = orders.iter().position(|o| *o > 0) else {
+                        return Some(target.to_owned());
+                    };
+
+                    let mut expr = partials[first].clone();
+                    orders[first] -= 1;
+
+                    let mut next = workspace.new_atom();
+                    for (x, o) in args.iter().zip(&orders) {
+                        for _ in 0..*o {
+                            expr.as_view()
+                                .derivative_with_ws_into(*x, workspace, &mut next);
+                            std::mem::swap(&mut expr, &mut next);
+                        }
+                    }
+
+                    let mut lhs = workspace.new_atom();
+                    let lhs_fun = lhs.to_fun(g.get_symbol());
+                    for x in &args {
+                        lhs_fun.add_arg(workspace.new_var(*x).as_view());
+                    }
+
+                    return Some(lhs.into_pattern().replace_all(
+                        target,
+                        &expr.into_pattern(),
+                        None,
+                        None,
+                    ));
+                }
+            }
+        }
+
+        if combined || target_changed {
+            let mut der = workspace.new_atom();
+            let fun = der.to_fun(State::DERIVATIVE);
+            for o in &orders {
+                fun.add_arg(workspace.new_num(*o as i64).as_view());
+            }
+            fun.add_arg(target);
+
+            let mut out = Atom::default();
+            der.as_view().normalize(workspace, &mut out);
+            Some(out)
+        } else {
+            None
+        }
+    }
+
     /// Taylor expand in `x` around `expansion_point` to depth `depth`.
     pub fn taylor_series(&self, x: Symbol, expansion_point: AtomView, depth: u32) -> Atom {
         Workspace::get_local().with(|ws| {
@@ -449,4 +686,237 @@ impl<'a> AtomView<'a> {
 
         true
     }
+
+    /// Compute an antiderivative of the expression with respect to `x`, without
+    /// integration constant. Supported are polynomials in `x`, including `1/x`, and
+    /// `sin`, `cos` and `exp` of an argument that is linear in `x`.
+    /// Returns `None` if the expression is outside of the supported set.
+    pub fn integrate(&self, x: Symbol) -> Option<Atom> {
+        let expanded = self.expand();
+
+        if let AtomView::Add(a) = expanded.as_view() {
+            let mut res = Atom::new_num(0);
+            for t in a.iter() {
+                res = res + &t.integrate_term(x)?;
+            }
+            Some(res)
+        } else {
+            expanded.as_view().integrate_term(x)
+        }
+    }
+
+    /// Integrate a single term, which should be the product of
+    /// at most one factor that depends on `x` and a constant.
+    fn integrate_term(&self, x: Symbol) -> Option<Atom> {
+        let x_atom = Atom::new_var(x);
+
+        let (constant, dependent) = match self {
+            AtomView::Mul(m) => {
+                let mut constant = Atom::new_num(1);
+                let mut dependent = None;
+                for f in m.iter() {
+                    if f.contains_symbol(x) {
+                        if dependent.is_some() {
+                            return None;
+                        }
+                        dependent = Some(f);
+                    } else {
+                        constant = constant * &f.to_owned();
+                    }
+                }
+                (constant, dependent)
+            }
+            _ if self.contains_symbol(x) => (Atom::new_num(1), Some(*self)),
+            _ => (self.to_owned(), None),
+        };
+
+        let Some(dependent) = dependent else {
+            return Some(constant * &x_atom);
+        };
+
+        // the derivative of a linear argument, if it is a non-zero constant
+        let linear_coeff = |arg: AtomView| {
+            let d = arg.derivative(x);
+            if d.as_view().contains_symbol(x)
+                || matches!(d.as_view(), AtomView::Num(n) if n.is_zero())
+            {
+                None
+            } else {
+                Some(d)
+            }
+        };
+
+        let res = match dependent {
+            AtomView::Var(_) => x_atom.npow(2) / 2,
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                if base != x_atom.as_view() {
+                    return None;
+                }
+
+                let AtomView::Num(n) = exp else {
+                    return None;
+                };
+
+                let n = n.get_coeff_view().to_owned();
+                if n == Coefficient::from(-1) {
+                    FunctionBuilder::new(State::LOG).add_arg(&x_atom).finish()
+                } else {
+                    let n1 = n + Coefficient::from(1);
+                    x_atom.npow(n1.clone()) / n1
+                }
+            }
+            AtomView::Fun(f) if f.get_nargs() == 1 => {
+                let arg = f.iter().next().unwrap();
+                let a = linear_coeff(arg)?;
+
+                match f.get_symbol() {
+                    State::SIN => -FunctionBuilder::new(State::COS).add_arg(arg).finish() / &a,
+                    State::COS => FunctionBuilder::new(State::SIN).add_arg(arg).finish() / &a,
+                    State::EXP => FunctionBuilder::new(State::EXP).add_arg(arg).finish() / &a,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        Some(constant * &res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coefficient::Coefficient,
+        representations::Atom,
+        state::{FunctionAttribute, State},
+    };
+
+    #[test]
+    fn resolve_derivatives() {
+        let f = State::get_symbol("rd_f");
+        let x = State::get_symbol("rd_x_");
+        let y = State::get_symbol("rd_y_");
+
+        let a = Atom::parse("rd_f(rd_z^2,rd_z)")
+            .unwrap()
+            .derivative(State::get_symbol("rd_z"));
+        assert_eq!(a.resolve_derivatives(), a);
+
+        State::set_derivative_rule(
+            f,
+            vec![x, y],
+            vec![
+                Atom::parse("rd_y_*rd_x_^2").unwrap(),
+                Atom::parse("rd_x_^3/3").unwrap(),
+            ],
+        )
+        .unwrap();
+
+        // f = x^3*y/3
+        let r = a.resolve_derivatives();
+        let z = State::get_symbol("rd_z");
+        assert_eq!(
+            r.eval_to_number(&[(z, Coefficient::from(2))]),
+            Some(Coefficient::from((448, 3)))
+        );
+        assert_eq!(r.free_variables(), vec![z]);
+
+        let b = Atom::parse("der(1,0,der(1,1,rd_f(rd_u,rd_v)))").unwrap();
+        assert_eq!(b.resolve_derivatives(), Atom::parse("2*rd_u").unwrap());
+
+        // nested derivatives of functions without a rule are combined
+        let c = Atom::parse("der(1,der(2,rd_g(rd_u)))").unwrap();
+        assert_eq!(
+            c.resolve_derivatives(),
+            Atom::parse("der(3,rd_g(rd_u))").unwrap()
+        );
+
+        assert!(State::set_derivative_rule(f, vec![x], vec![]).is_err());
+
+        let d = Atom::parse("der()").unwrap();
+        assert_eq!(d.resolve_derivatives(), d);
+        assert_eq!(d.derivative(z), Atom::new_num(0));
+    }
+
+    #[test]
+    fn derivative_wrt_function() {
+        let f = State::get_symbol("f_dwf");
+
+        let a = Atom::parse("f_dwf(x)^2").unwrap();
+        let r = a.derivative_wrt_function(f);
+        assert_eq!(r, Atom::parse("2*f_dwf(x)").unwrap());
+
+        let a = Atom::parse("x*f_dwf(x) + f_dwf").unwrap();
+        let r = a.derivative_wrt_function(f);
+        assert_eq!(r, Atom::parse("x").unwrap());
+    }
+
+    #[test]
+    fn integrate() {
+        let x = State::get_symbol("int_x");
+
+        let a = Atom::parse("int_x^2").unwrap().integrate(x).unwrap();
+        assert_eq!(a, Atom::parse("int_x^3/3").unwrap());
+
+        let a = Atom::parse("1/int_x").unwrap().integrate(x).unwrap();
+        assert_eq!(a, Atom::parse("log(int_x)").unwrap());
+
+        let a = Atom::parse("3*int_x + int_y + sin(int_x) + exp(2*int_x+1)")
+            .unwrap()
+            .integrate(x)
+            .unwrap();
+        let r = Atom::parse("3/2*int_x^2 + int_y*int_x - cos(int_x) + exp(2*int_x+1)/2").unwrap();
+        assert_eq!((a - &r).expand(), Atom::new_num(0));
+
+        let a = Atom::parse("int_x*(int_x+1)")
+            .unwrap()
+            .integrate(x)
+            .unwrap();
+        assert_eq!(
+            a.derivative(x).expand(),
+            Atom::parse("int_x^2+int_x").unwrap()
+        );
+
+        assert!(Atom::parse("tan(int_x)").unwrap().integrate(x).is_none());
+        assert!(Atom::parse("sin(int_x^2)").unwrap().integrate(x).is_none());
+    }
+
+    #[test]
+    fn builtin_constants() {
+        let x = State::get_symbol("bc_x");
+        let a = Atom::parse("𝑒^bc_x + sin(2*𝜋) + cos(3*𝜋) + log(𝑒^(1/2))").unwrap();
+        assert_eq!(a, Atom::parse("𝑒^bc_x - 1/2").unwrap());
+        assert_eq!(a.derivative(x), Atom::parse("𝑒^bc_x").unwrap());
+        assert_eq!(Atom::parse("𝜋*bc_x - bc_x*𝜋").unwrap(), Atom::new_num(0));
+    }
+
+    #[test]
+    fn noncommutative_product() {
+        for name in ["dnc_a", "dnc_b"] {
+            State::get_symbol_with_attributes(name, vec![FunctionAttribute::NonCommutative])
+                .unwrap();
+        }
+
+        let x = State::get_symbol("dnc_x");
+        let a = Atom::parse("dnc_a(dnc_x)*dnc_b(dnc_x)").unwrap();
+        assert_eq!(
+            a.derivative(x),
+            Atom::parse("der(1,dnc_a(dnc_x))*dnc_b(dnc_x) + dnc_a(dnc_x)*der(1,dnc_b(dnc_x))")
+                .unwrap()
+        );
+
+        // repeated noncommutative factors are differentiated once each
+        let a = Atom::parse("dnc_a(dnc_x)*dnc_b(dnc_x)*dnc_a(dnc_x)").unwrap();
+        let d = a.derivative(x);
+        assert_eq!(
+            d,
+            Atom::parse(
+                "der(1,dnc_a(dnc_x))*dnc_b(dnc_x)*dnc_a(dnc_x) 
+                + dnc_a(dnc_x)*der(1,dnc_b(dnc_x))*dnc_a(dnc_x) 
+                + dnc_a(dnc_x)*dnc_b(dnc_x)*der(1,dnc_a(dnc_x))"
+            )
+            .unwrap()
+        );
+    }
 }
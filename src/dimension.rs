@@ -0,0 +1,261 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    coefficient::CoefficientView,
+    representations::{Atom, AtomView},
+    state::State,
+};
+
+/// A physical dimension, written as a product of powers of base dimensions,
+/// for example `length*time^-1`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Dimension {
+    exponents: BTreeMap<String, i64>,
+}
+
+impl Dimension {
+    /// The dimension of a pure number.
+    pub fn dimensionless() -> Dimension {
+        Dimension::default()
+    }
+
+    /// Create the base dimension `name`, for example `length`.
+    pub fn new(name: &str) -> Dimension {
+        Dimension {
+            exponents: BTreeMap::from([(name.to_owned(), 1)]),
+        }
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.exponents.is_empty()
+    }
+
+    /// Get the exponent of the base dimension `name`.
+    pub fn get_exponent(&self, name: &str) -> i64 {
+        self.exponents.get(name).cloned().unwrap_or(0)
+    }
+
+    /// Multiply two dimensions.
+    pub fn mul(&self, other: &Dimension) -> Dimension {
+        let mut res = self.clone();
+        for (k, e) in &other.exponents {
+            let new = res.get_exponent(k) + e;
+            if new == 0 {
+                res.exponents.remove(k);
+            } else {
+                res.exponents.insert(k.clone(), new);
+            }
+        }
+        res
+    }
+
+    /// Raise the dimension to the power `exp`.
+    pub fn pow(&self, exp: i64) -> Dimension {
+        if exp == 0 {
+            return Dimension::dimensionless();
+        }
+
+        Dimension {
+            exponents: self
+                .exponents
+                .iter()
+                .map(|(k, e)| (k.clone(), e * exp))
+                .collect(),
+        }
+    }
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.exponents.is_empty() {
+            return f.write_str("1");
+        }
+
+        for (i, (k, e)) in self.exponents.iter().enumerate() {
+            if i > 0 {
+                f.write_str("*")?;
+            }
+
+            if *e == 1 {
+                f.write_str(k)?;
+            } else {
+                write!(f, "{}^{}", k, e)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Atom {
+    /// Infer the dimension of the expression from the dimensions of its symbols,
+    /// registered with [`State::set_dimension`]. See [`AtomView::check_dimensions`].
+    pub fn check_dimensions(&self) -> Result<Dimension, String> {
+        self.as_view().check_dimensions()
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Infer the dimension of the expression from the dimensions of its symbols,
+    /// registered with [`State::set_dimension`]. Symbols without a registered dimension
+    /// are dimensionless. An error is returned if terms with different dimensions are added,
+    /// if a dimensionful quantity is raised to a non-integer or symbolic power, or if a
+    /// built-in function such as `exp` or `sin` has a dimensionful argument.
+    ///
+    /// A user-defined function has the dimension registered for its symbol, and its arguments
+    /// are checked for consistency.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{dimension::Dimension, representations::Atom, state::State};
+    /// State::set_dimension(State::get_symbol("x"), Dimension::new("length"));
+    /// State::set_dimension(State::get_symbol("t"), Dimension::new("time"));
+    /// let d = Atom::parse("x/t").unwrap().check_dimensions().unwrap();
+    /// assert_eq!(d.to_string(), "length*time^-1");
+    /// assert!(Atom::parse("x + t").unwrap().check_dimensions().is_err());
+    /// ```
+    pub fn check_dimensions(&self) -> Result<Dimension, String> {
+        match self {
+            AtomView::Num(_) => Ok(Dimension::dimensionless()),
+            AtomView::Var(v) => {
+                Ok(State::get_dimension(v.get_symbol()).unwrap_or_else(Dimension::dimensionless))
+            }
+            AtomView::Fun(f) => {
+                let mut arg_dims = Vec::with_capacity(f.get_nargs());
+                for arg in f.iter() {
+                    arg_dims.push(arg.check_dimensions()?);
+                }
+
+                match f.get_symbol() {
+                    State::EXP | State::LOG | State::SIN | State::COS => {
+                        if let Some(d) = arg_dims.iter().find(|d| !d.is_dimensionless()) {
+                            return Err(format!(
+                                "Argument of {} has dimension {} instead of being dimensionless",
+                                self, d
+                            ));
+                        }
+                        Ok(Dimension::dimensionless())
+                    }
+                    State::SQRT if arg_dims.len() == 1 => {
+                        let d = &arg_dims[0];
+                        if d.exponents.values().any(|e| e % 2 != 0) {
+                            return Err(format!(
+                                "Cannot take the square root of dimension {} in {}",
+                                d, self
+                            ));
+                        }
+
+                        Ok(Dimension {
+                            exponents: d
+                                .exponents
+                                .iter()
+                                .map(|(k, e)| (k.clone(), e / 2))
+                                .collect(),
+                        })
+                    }
+                    s => Ok(State::get_dimension(s).unwrap_or_else(Dimension::dimensionless)),
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let base_dim = base.check_dimensions()?;
+                let exp_dim = exp.check_dimensions()?;
+
+                if !exp_dim.is_dimensionless() {
+                    return Err(format!(
+                        "Exponent of {} has dimension {} instead of being dimensionless",
+                        self, exp_dim
+                    ));
+                }
+
+                if base_dim.is_dimensionless() {
+                    return Ok(base_dim);
+                }
+
+                match exp {
+                    AtomView::Num(n) => match n.get_coeff_view() {
+                        CoefficientView::Natural(e, 1) => Ok(base_dim.pow(e)),
+                        _ => Err(format!(
+                            "Cannot raise dimension {} to a non-integer power in {}",
+                            base_dim, self
+                        )),
+                    },
+                    _ => Err(format!(
+                        "Cannot raise dimension {} to a symbolic power in {}",
+                        base_dim, self
+                    )),
+                }
+            }
+            AtomView::Mul(m) => {
+                let mut res = Dimension::dimensionless();
+                for f in m.iter() {
+                    res = res.mul(&f.check_dimensions()?);
+                }
+                Ok(res)
+            }
+            AtomView::Add(a) => {
+                let mut res: Option<Dimension> = None;
+                for t in a.iter() {
+                    let d = t.check_dimensions()?;
+                    match &res {
+                        Some(r) if *r != d => {
+                            return Err(format!(
+                                "Cannot add a term with dimension {} to a term with dimension {} in {}",
+                                d, r, self
+                            ));
+                        }
+                        Some(_) => {}
+                        None => res = Some(d),
+                    }
+                }
+                Ok(res.unwrap_or_else(Dimension::dimensionless))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{representations::Atom, state::State};
+
+    use super::Dimension;
+
+    #[test]
+    fn check_dimensions() {
+        let meter = Dimension::new("length");
+        let second = Dimension::new("time");
+        State::set_dimension(State::get_symbol("dim_x"), meter.clone());
+        State::set_dimension(State::get_symbol("dim_y"), meter.clone());
+        State::set_dimension(State::get_symbol("dim_t"), second.clone());
+
+        let d = Atom::parse("dim_x + dim_y").unwrap().check_dimensions();
+        assert_eq!(d, Ok(meter.clone()));
+
+        assert!(Atom::parse("dim_x + dim_t")
+            .unwrap()
+            .check_dimensions()
+            .is_err());
+
+        let d = Atom::parse("dim_x * dim_t").unwrap().check_dimensions();
+        assert_eq!(d, Ok(meter.mul(&second)));
+
+        let d = Atom::parse("sqrt(dim_x^2 + dim_y^2)/dim_t + 3*dim_x/dim_t")
+            .unwrap()
+            .check_dimensions();
+        assert_eq!(d, Ok(meter.mul(&second.pow(-1))));
+
+        let d = Atom::parse("dim_x^2/dim_y^2 + exp(dim_t/dim_t)")
+            .unwrap()
+            .check_dimensions();
+        assert_eq!(d, Ok(Dimension::dimensionless()));
+
+        assert!(Atom::parse("sin(dim_x)")
+            .unwrap()
+            .check_dimensions()
+            .is_err());
+        assert!(Atom::parse("dim_x^dim_z")
+            .unwrap()
+            .check_dimensions()
+            .is_err());
+    }
+}
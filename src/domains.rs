@@ -1,9 +1,12 @@
 pub mod algebraic_number;
+pub mod dual;
 pub mod factorized_rational_polynomial;
 pub mod finite_field;
 pub mod float;
 pub mod integer;
+pub mod interval;
 pub mod linear_system;
+pub mod modular;
 pub mod rational;
 pub mod rational_polynomial;
 
@@ -175,10 +175,18 @@ impl<R: Ring> Ring for AlgebraicNumberRing<R> {
         }
     }
 
-    fn pow(&self, b: &Self::Element, e: u64) -> Self::Element {
+    fn pow(&self, b: &Self::Element, mut e: u64) -> Self::Element {
+        // square and multiply, as the exponent may be large
         let mut result = self.one();
-        for _ in 0..e {
-            result = self.mul(&result, b);
+        let mut b = b.clone();
+        while e > 0 {
+            if e & 1 == 1 {
+                result = self.mul(&result, &b);
+            }
+            e >>= 1;
+            if e > 0 {
+                b = self.mul(&b, &b);
+            }
         }
         result
     }
@@ -0,0 +1,408 @@
+use std::{
+    fmt::Display,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use rand::Rng;
+
+use super::{
+    float::{NumericalFloatLike, Real},
+    rational::Rational,
+};
+
+/// A dual number `re + eps*ε` with `ε^2 = 0`, used for forward-mode automatic differentiation.
+/// Evaluating an expression with `x` set to [`Dual::variable`] yields the value
+/// of the expression in `re` and its derivative with respect to `x` in `eps`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual<T: Real> {
+    pub re: T,
+    pub eps: T,
+}
+
+impl<T: Real> Dual<T> {
+    #[inline]
+    pub fn new(re: T, eps: T) -> Dual<T> {
+        Dual { re, eps }
+    }
+
+    /// A constant, whose derivative is zero.
+    #[inline]
+    pub fn constant(re: T) -> Dual<T> {
+        Dual { re, eps: T::zero() }
+    }
+
+    /// The variable of differentiation, whose derivative is one.
+    #[inline]
+    pub fn variable(re: T) -> Dual<T> {
+        Dual { re, eps: T::one() }
+    }
+
+    /// Apply a function `f` with derivative `df` at `self.re`, using the chain rule.
+    #[inline]
+    fn chain(&self, f: T, df: T) -> Dual<T> {
+        Dual {
+            re: f,
+            eps: df * self.eps,
+        }
+    }
+}
+
+impl<T: Real> Display for Dual<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}+{}ε)", self.re, self.eps)
+    }
+}
+
+impl<'a, T: Real + From<&'a Rational>> From<&'a Rational> for Dual<T> {
+    fn from(value: &'a Rational) -> Self {
+        Dual::constant(value.into())
+    }
+}
+
+impl<T: Real> Add for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Dual::new(self.re + rhs.re, self.eps + rhs.eps)
+    }
+}
+
+impl<T: Real> Add<&Dual<T>> for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: &Self) -> Self::Output {
+        self + *rhs
+    }
+}
+
+impl<T: Real> AddAssign for Dual<T> {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: Real> AddAssign<&Dual<T>> for Dual<T> {
+    #[inline]
+    fn add_assign(&mut self, rhs: &Self) {
+        *self = *self + *rhs;
+    }
+}
+
+impl<T: Real> Sub for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Dual::new(self.re - rhs.re, self.eps - rhs.eps)
+    }
+}
+
+impl<T: Real> Sub<&Dual<T>> for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: &Self) -> Self::Output {
+        self - *rhs
+    }
+}
+
+impl<T: Real> SubAssign for Dual<T> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<T: Real> SubAssign<&Dual<T>> for Dual<T> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: &Self) {
+        *self = *self - *rhs;
+    }
+}
+
+impl<T: Real> Mul for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Dual::new(
+            self.re * rhs.re,
+            self.eps.mul_add(&rhs.re, &(self.re * rhs.eps)),
+        )
+    }
+}
+
+impl<T: Real> Mul<&Dual<T>> for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: &Self) -> Self::Output {
+        self * *rhs
+    }
+}
+
+impl<T: Real> MulAssign for Dual<T> {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T: Real> MulAssign<&Dual<T>> for Dual<T> {
+    #[inline]
+    fn mul_assign(&mut self, rhs: &Self) {
+        *self = *self * *rhs;
+    }
+}
+
+impl<T: Real> Div for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        let inv = rhs.re.inv();
+        let re = self.re * inv;
+        Dual::new(re, (self.eps - re * rhs.eps) * inv)
+    }
+}
+
+impl<T: Real> Div<&Dual<T>> for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: &Self) -> Self::Output {
+        self / *rhs
+    }
+}
+
+impl<T: Real> DivAssign for Dual<T> {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl<T: Real> DivAssign<&Dual<T>> for Dual<T> {
+    #[inline]
+    fn div_assign(&mut self, rhs: &Self) {
+        *self = *self / *rhs;
+    }
+}
+
+impl<'a, T: Real> Sum<&'a Dual<T>> for Dual<T> {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        let mut res = Self::zero();
+        for x in iter {
+            res += x;
+        }
+        res
+    }
+}
+
+impl<T: Real> Neg for Dual<T> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Dual::new(-self.re, -self.eps)
+    }
+}
+
+impl<T: Real> NumericalFloatLike for Dual<T> {
+    #[inline]
+    fn mul_add(&self, a: &Self, b: &Self) -> Self {
+        *self * a + b
+    }
+
+    #[inline]
+    fn neg(&self) -> Self {
+        -*self
+    }
+
+    fn norm(&self) -> Self {
+        // the derivative changes sign with the real part
+        if self.re.norm() == self.re {
+            *self
+        } else {
+            -*self
+        }
+    }
+
+    #[inline]
+    fn zero() -> Self {
+        Dual::constant(T::zero())
+    }
+
+    #[inline]
+    fn one() -> Self {
+        Dual::constant(T::one())
+    }
+
+    fn pow(&self, e: u64) -> Self {
+        if e == 0 {
+            return Self::one();
+        }
+
+        let r = self.re.pow(e - 1);
+        self.chain(r * self.re, r * T::from_usize(e as usize))
+    }
+
+    #[inline]
+    fn inv(&self) -> Self {
+        let inv = self.re.inv();
+        self.chain(inv, -(inv * inv))
+    }
+
+    fn from_usize(a: usize) -> Self {
+        Dual::constant(T::from_usize(a))
+    }
+
+    fn from_i64(a: i64) -> Self {
+        Dual::constant(T::from_i64(a))
+    }
+
+    fn sample_unit<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Dual::constant(T::sample_unit(rng))
+    }
+}
+
+impl<T: Real> Real for Dual<T> {
+    fn sqrt(&self) -> Self {
+        let s = self.re.sqrt();
+        self.chain(s, (s + s).inv())
+    }
+
+    fn log(&self) -> Self {
+        self.chain(self.re.log(), self.re.inv())
+    }
+
+    fn exp(&self) -> Self {
+        let e = self.re.exp();
+        self.chain(e, e)
+    }
+
+    fn sin(&self) -> Self {
+        self.chain(self.re.sin(), self.re.cos())
+    }
+
+    fn cos(&self) -> Self {
+        self.chain(self.re.cos(), -self.re.sin())
+    }
+
+    fn tan(&self) -> Self {
+        let t = self.re.tan();
+        self.chain(t, t.mul_add(&t, &T::one()))
+    }
+
+    fn asin(&self) -> Self {
+        let d = (T::one() - self.re * self.re).sqrt().inv();
+        self.chain(self.re.asin(), d)
+    }
+
+    fn acos(&self) -> Self {
+        let d = (T::one() - self.re * self.re).sqrt().inv();
+        self.chain(self.re.acos(), -d)
+    }
+
+    fn atan2(&self, x: &Self) -> Self {
+        // d atan2(y, x) = (x dy - y dx) / (x^2 + y^2)
+        let r2 = x.re.mul_add(&x.re, &(self.re * self.re));
+        Dual::new(
+            self.re.atan2(&x.re),
+            (x.re * self.eps - self.re * x.eps) / r2,
+        )
+    }
+
+    fn sinh(&self) -> Self {
+        self.chain(self.re.sinh(), self.re.cosh())
+    }
+
+    fn cosh(&self) -> Self {
+        self.chain(self.re.cosh(), self.re.sinh())
+    }
+
+    fn tanh(&self) -> Self {
+        let t = self.re.tanh();
+        self.chain(t, T::one() - t * t)
+    }
+
+    fn asinh(&self) -> Self {
+        let d = self.re.mul_add(&self.re, &T::one()).sqrt().inv();
+        self.chain(self.re.asinh(), d)
+    }
+
+    fn acosh(&self) -> Self {
+        let d = (self.re * self.re - T::one()).sqrt().inv();
+        self.chain(self.re.acosh(), d)
+    }
+
+    fn atanh(&self) -> Self {
+        let d = (T::one() - self.re * self.re).inv();
+        self.chain(self.re.atanh(), d)
+    }
+
+    fn powf(&self, e: Self) -> Self {
+        // d x^e = x^e (e' log x + e x'/x)
+        let r = self.re.powf(e.re);
+        let d = if e.eps == T::zero() {
+            e.re * self.re.powf(e.re - T::one()) * self.eps
+        } else {
+            r * (e.eps * self.re.log() + e.re * self.eps / self.re)
+        };
+        Dual::new(r, d)
+    }
+
+    #[inline]
+    fn satisfies_condition(&self) -> Option<bool> {
+        self.re.satisfies_condition()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMap;
+
+    use crate::{
+        domains::float::{NumericalFloatLike, Real},
+        representations::Atom,
+    };
+
+    use super::Dual;
+
+    #[test]
+    fn derivative() {
+        let x = Dual::variable(0.7f64);
+        let f = |x: Dual<f64>| x.sin() * x.pow(2) + (x * Dual::from_i64(2)).exp() / x.sqrt();
+        let r = f(x);
+
+        let v = 0.7f64;
+        let df = v.cos() * v * v + 2. * v * v.sin() + 2. * (2. * v).exp() / v.sqrt()
+            - 0.5 * (2. * v).exp() * v.powf(-1.5);
+        assert!((r.re - (v.sin() * v * v + (2. * v).exp() / v.sqrt())).abs() < 1e-12);
+        assert!((r.eps - df).abs() < 1e-12);
+
+        let y = Dual::constant(0.3f64);
+        let a = y.atan2(&x);
+        assert!((a.eps + 0.3 / (0.3 * 0.3 + 0.7 * 0.7)).abs() < 1e-12);
+        assert!((x.powf(Dual::constant(2.5)).eps - 2.5 * v.powf(1.5)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn evaluate() {
+        let a = Atom::parse("dual_x^3*cos(dual_x) + 1/(1+dual_x^2)").unwrap();
+        let x = Atom::parse("dual_x").unwrap();
+
+        let mut const_map = HashMap::default();
+        const_map.insert(x.as_view(), Dual::variable(1.5f64));
+        let r = a.evaluate(&const_map, &HashMap::default(), &mut HashMap::default());
+
+        let v = 1.5f64;
+        let df = 3. * v * v * v.cos() - v * v * v * v.sin() - 2. * v / (1. + v * v) / (1. + v * v);
+        assert!((r.re - (v * v * v * v.cos() + 1. / (1. + v * v))).abs() < 1e-12);
+        assert!((r.eps - df).abs() < 1e-12);
+    }
+}
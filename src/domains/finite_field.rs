@@ -384,6 +384,12 @@ impl Zp64 {
         }
     }
 
+    /// Returns `true` iff `a` is a square in the field, using Euler's criterion
+    /// `a^((p-1)/2) = 1`. Zero is considered a quadratic residue, as `0 = 0^2`.
+    pub fn is_quadratic_residue(&self, a: &FiniteFieldElement<u64>) -> bool {
+        a.0 == 0 || self.is_one(&self.pow(a, (self.p - 1) / 2))
+    }
+
     /// Returns the unit element in Montgomory form, ie.e 1 + 2^64 mod a.
     fn get_one(a: u64) -> u64 {
         if a as u128 <= 1u128 << 63 {
@@ -1210,3 +1216,599 @@ impl Iterator for PrimeIteratorU64 {
         None
     }
 }
+
+/// The finite field `GF(p^n)`, represented in a polynomial basis:
+/// elements are polynomials of degree smaller than `n` in a generator `α` over `Z_p`,
+/// reduced modulo a monic irreducible polynomial of degree `n`.
+///
+/// The coefficients are stored as integers in `[0,p)` instead of as elements of [`Zp64`],
+/// so that the characteristic may be 2.
+///
+/// Polynomials over the field support arithmetic and gcds. Factorization over the
+/// field is not implemented.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExtensionField {
+    p: u64,
+    /// The coefficients of the defining polynomial, from low to high degree, without the leading 1.
+    poly: Vec<u64>,
+}
+
+/// An element of an [`ExtensionField`], stored as its `n` coefficients in `[0,p)` from low to high degree.
+#[derive(Debug, Clone, Hash, PartialEq, PartialOrd, Eq)]
+pub struct ExtensionFieldElement(pub(crate) Vec<u64>);
+
+impl ExtensionField {
+    /// Create the field `Z_p[α]/f(α)`, where `poly` contains the coefficients of `f` from low to high degree.
+    /// Panics if `p` is not a prime, or if `f` is not monic or not irreducible.
+    pub fn new(p: u64, poly: &[u64]) -> ExtensionField {
+        assert!(is_prime_u64(p), "The characteristic {} is not a prime", p);
+        if poly.len() < 2 || poly[poly.len() - 1] % p != 1 {
+            panic!("The defining polynomial must be monic and of positive degree");
+        }
+
+        let f = ExtensionField {
+            poly: poly[..poly.len() - 1].iter().map(|c| c % p).collect(),
+            p,
+        };
+
+        if !f.is_irreducible() {
+            panic!("The defining polynomial {:?} is not irreducible", poly);
+        }
+
+        f
+    }
+
+    /// Create `GF(p^n)`, using the smallest monic irreducible polynomial of degree `n`
+    /// in lexicographical order as the defining polynomial. Panics if `p` is not a prime.
+    pub fn from_degree(p: u64, n: usize) -> ExtensionField {
+        assert!(is_prime_u64(p), "The characteristic {} is not a prime", p);
+        assert!(n > 0, "The degree of the extension must be positive");
+
+        let mut coeffs = vec![0; n];
+        loop {
+            let f = ExtensionField {
+                poly: coeffs.clone(),
+                p,
+            };
+
+            if f.is_irreducible() {
+                return f;
+            }
+
+            // go to the next polynomial, counting in base p from the constant term up
+            for c in coeffs.iter_mut() {
+                *c += 1;
+                if *c < p {
+                    break;
+                }
+                *c = 0;
+            }
+        }
+    }
+
+    /// Get the characteristic `p` of the field.
+    pub fn get_prime(&self) -> u64 {
+        self.p
+    }
+
+    /// Get the degree `n` of the extension.
+    pub fn get_degree(&self) -> usize {
+        self.poly.len()
+    }
+
+    /// Get the number of elements `p^n` of the field.
+    pub fn size(&self) -> Integer {
+        Integer::from(self.get_prime()).pow(self.get_degree() as u64)
+    }
+
+    /// Get the coefficients of the defining polynomial from low to high degree.
+    pub fn get_defining_polynomial(&self) -> Vec<u64> {
+        self.poly
+            .iter()
+            .cloned()
+            .chain(std::iter::once(1))
+            .collect()
+    }
+
+    /// Create the element `c_0 + c_1 α + ...` from its coefficients, which are reduced modulo the
+    /// defining polynomial.
+    pub fn to_element(&self, coeffs: &[u64]) -> ExtensionFieldElement {
+        let mut r: Vec<_> = coeffs.iter().map(|c| c % self.p).collect();
+        self.reduce(&mut r);
+        ExtensionFieldElement(r)
+    }
+
+    /// Get the `n` coefficients of an element from low to high degree, in the range `[0,p)`.
+    pub fn from_element(&self, a: &ExtensionFieldElement) -> Vec<u64> {
+        a.0.clone()
+    }
+
+    /// Embed the element `c mod p` of the prime field.
+    pub fn from_base(&self, c: u64) -> ExtensionFieldElement {
+        let mut r = vec![0; self.get_degree()];
+        r[0] = c % self.p;
+        ExtensionFieldElement(r)
+    }
+
+    /// Get the generator `α` of the field.
+    pub fn generator(&self) -> ExtensionFieldElement {
+        self.to_element(&[0, 1])
+    }
+
+    #[inline]
+    fn add_coeff(&self, a: u64, b: u64) -> u64 {
+        let r = a as u128 + b as u128;
+        (r % self.p as u128) as u64
+    }
+
+    #[inline]
+    fn neg_coeff(&self, a: u64) -> u64 {
+        if a == 0 {
+            0
+        } else {
+            self.p - a
+        }
+    }
+
+    #[inline]
+    fn sub_coeff(&self, a: u64, b: u64) -> u64 {
+        self.add_coeff(a, self.neg_coeff(b))
+    }
+
+    #[inline]
+    fn mul_coeff(&self, a: u64, b: u64) -> u64 {
+        ((a as u128 * b as u128) % self.p as u128) as u64
+    }
+
+    /// Compute the inverse of a non-zero coefficient as `a^(p-2)`.
+    fn inv_coeff(&self, a: u64) -> u64 {
+        let (mut b, mut e, mut x) = (a, self.p - 2, 1);
+        while e != 0 {
+            if e & 1 != 0 {
+                x = self.mul_coeff(x, b);
+            }
+            b = self.mul_coeff(b, b);
+            e /= 2;
+        }
+        x
+    }
+
+    /// Reduce a dense polynomial modulo the defining polynomial, resizing it to `n` coefficients.
+    fn reduce(&self, a: &mut Vec<u64>) {
+        let n = self.get_degree();
+        for i in (n..a.len()).rev() {
+            let c = a[i];
+            if c != 0 {
+                for (j, f) in self.poly.iter().enumerate() {
+                    a[i - n + j] = self.sub_coeff(a[i - n + j], self.mul_coeff(c, *f));
+                }
+            }
+        }
+        a.resize(n, 0);
+    }
+
+    /// Multiply two dense polynomials.
+    fn poly_mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+
+        let mut r = vec![0; a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            if *x != 0 {
+                for (j, y) in b.iter().enumerate() {
+                    r[i + j] = self.add_coeff(r[i + j], self.mul_coeff(*x, *y));
+                }
+            }
+        }
+        r
+    }
+
+    /// Divide the dense polynomial `a` by the non-zero, trimmed polynomial `b`.
+    fn poly_quot_rem(&self, a: &[u64], b: &[u64]) -> (Vec<u64>, Vec<u64>) {
+        let mut r = a.to_vec();
+        Self::trim(&mut r);
+        if r.len() < b.len() {
+            return (vec![], r);
+        }
+
+        let inv_lc = self.inv_coeff(*b.last().unwrap());
+        let mut q = vec![0; r.len() - b.len() + 1];
+        for i in (0..q.len()).rev() {
+            let c = self.mul_coeff(r[i + b.len() - 1], inv_lc);
+            if c != 0 {
+                for (j, y) in b.iter().enumerate() {
+                    r[i + j] = self.sub_coeff(r[i + j], self.mul_coeff(c, *y));
+                }
+            }
+            q[i] = c;
+        }
+
+        r.truncate(b.len() - 1);
+        Self::trim(&mut r);
+        (q, r)
+    }
+
+    fn trim(a: &mut Vec<u64>) {
+        while a.last() == Some(&0) {
+            a.pop();
+        }
+    }
+
+    /// Get the full defining polynomial, including the leading coefficient.
+    fn full_poly(&self) -> Vec<u64> {
+        self.get_defining_polynomial()
+    }
+
+    /// Check if the defining polynomial is irreducible using Rabin's test:
+    /// `f` of degree `n` is irreducible iff `α^(p^n) = α` and
+    /// `gcd(α^(p^(n/q)) - α, f) = 1` for all prime divisors `q` of `n`.
+    fn is_irreducible(&self) -> bool {
+        let n = self.get_degree();
+        if n == 1 {
+            return true;
+        }
+
+        let p = self.get_prime();
+        let x = self.generator();
+        let frobenius = |k: usize| {
+            let mut h = x.clone();
+            for _ in 0..k {
+                h = self.pow(&h, p);
+            }
+            h
+        };
+
+        if frobenius(n) != x {
+            return false;
+        }
+
+        let f = self.full_poly();
+        for q in (2..=n).filter(|q| n % q == 0 && is_prime_u64(*q as u64)) {
+            let mut g = self.sub(&frobenius(n / q), &x).0;
+            Self::trim(&mut g);
+            if g.is_empty() {
+                return false;
+            }
+
+            let mut a = f.clone();
+            while !g.is_empty() {
+                let r = self.poly_quot_rem(&a, &g).1;
+                a = g;
+                g = r;
+            }
+
+            if a.len() > 1 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Display for ExtensionField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "GF({}^{})", self.get_prime(), self.get_degree())
+    }
+}
+
+impl Ring for ExtensionField {
+    type Element = ExtensionFieldElement;
+
+    fn add(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        ExtensionFieldElement(
+            a.0.iter()
+                .zip(&b.0)
+                .map(|(x, y)| self.add_coeff(*x, *y))
+                .collect(),
+        )
+    }
+
+    fn sub(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        ExtensionFieldElement(
+            a.0.iter()
+                .zip(&b.0)
+                .map(|(x, y)| self.sub_coeff(*x, *y))
+                .collect(),
+        )
+    }
+
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        let mut r = self.poly_mul(&a.0, &b.0);
+        self.reduce(&mut r);
+        ExtensionFieldElement(r)
+    }
+
+    fn add_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        for (x, y) in a.0.iter_mut().zip(&b.0) {
+            *x = self.add_coeff(*x, *y);
+        }
+    }
+
+    fn sub_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        for (x, y) in a.0.iter_mut().zip(&b.0) {
+            *x = self.sub_coeff(*x, *y);
+        }
+    }
+
+    fn mul_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.mul(a, b);
+    }
+
+    fn add_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        self.add_assign(a, &self.mul(b, c));
+    }
+
+    fn sub_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        self.sub_assign(a, &self.mul(b, c));
+    }
+
+    fn neg(&self, a: &Self::Element) -> Self::Element {
+        ExtensionFieldElement(a.0.iter().map(|x| self.neg_coeff(*x)).collect())
+    }
+
+    fn zero(&self) -> Self::Element {
+        ExtensionFieldElement(vec![0; self.get_degree()])
+    }
+
+    fn one(&self) -> Self::Element {
+        self.from_base(1)
+    }
+
+    fn nth(&self, n: u64) -> Self::Element {
+        self.from_base(n)
+    }
+
+    fn pow(&self, b: &Self::Element, mut e: u64) -> Self::Element {
+        let mut b = b.clone();
+        let mut x = self.one();
+        while e != 0 {
+            if e & 1 != 0 {
+                x = self.mul(&x, &b);
+            }
+            e /= 2;
+            if e != 0 {
+                b = self.mul(&b, &b);
+            }
+        }
+
+        x
+    }
+
+    fn is_zero(a: &Self::Element) -> bool {
+        a.0.iter().all(|c| *c == 0)
+    }
+
+    fn is_one(&self, a: &Self::Element) -> bool {
+        a.0[0] == 1 && a.0[1..].iter().all(|c| *c == 0)
+    }
+
+    fn one_is_gcd_unit() -> bool {
+        true
+    }
+
+    fn is_characteristic_zero(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, rng: &mut impl rand::RngCore, range: (i64, i64)) -> Self::Element {
+        ExtensionFieldElement(
+            (0..self.get_degree())
+                .map(|_| {
+                    rng.gen_range(range.0.max(0)..range.1.min(self.p.min(i64::MAX as u64) as i64))
+                        as u64
+                })
+                .collect(),
+        )
+    }
+
+    fn fmt_display(
+        &self,
+        element: &Self::Element,
+        opts: &PrintOptions,
+        in_product: bool,
+        f: &mut Formatter<'_>,
+    ) -> Result<(), Error> {
+        let terms: Vec<_> = element
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c != 0)
+            .collect();
+
+        if terms.is_empty() {
+            return f.write_str("0");
+        }
+
+        if f.sign_plus() {
+            f.write_str("+")?;
+        }
+
+        let add_paren = in_product && terms.len() > 1;
+        if add_paren {
+            f.write_str("(")?;
+        }
+
+        for (i, (pow, c)) in terms.iter().rev().enumerate() {
+            if i > 0 {
+                f.write_str("+")?;
+            }
+
+            if *pow == 0 || **c != 1 {
+                if opts.symmetric_representation_for_finite_field && **c > self.p / 2 {
+                    write!(f, "-{}", self.p - **c)?;
+                } else {
+                    write!(f, "{}", c)?;
+                }
+                if *pow > 0 {
+                    f.write_str("*")?;
+                }
+            }
+
+            match *pow {
+                0 => {}
+                1 => f.write_str("α")?,
+                _ => write!(f, "α^{}", pow)?,
+            }
+        }
+
+        if add_paren {
+            f.write_str(")")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl EuclideanDomain for ExtensionField {
+    fn rem(&self, _: &Self::Element, _: &Self::Element) -> Self::Element {
+        self.zero()
+    }
+
+    fn quot_rem(&self, a: &Self::Element, b: &Self::Element) -> (Self::Element, Self::Element) {
+        (self.div(a, b), self.zero())
+    }
+
+    fn gcd(&self, _: &Self::Element, _: &Self::Element) -> Self::Element {
+        self.one()
+    }
+}
+
+impl Field for ExtensionField {
+    fn div(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        self.mul(a, &self.inv(b))
+    }
+
+    fn div_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.div(a, b);
+    }
+
+    /// Compute the inverse using the extended Euclidean algorithm on the
+    /// element and the defining polynomial.
+    fn inv(&self, a: &Self::Element) -> Self::Element {
+        let mut r1 = a.0.clone();
+        Self::trim(&mut r1);
+        if r1.is_empty() {
+            panic!("Division by zero");
+        }
+
+        let mut r0 = self.full_poly();
+        let mut s0 = vec![];
+        let mut s1 = vec![1];
+        while !r1.is_empty() {
+            let (q, r) = self.poly_quot_rem(&r0, &r1);
+            r0 = std::mem::replace(&mut r1, r);
+
+            let mut s = self.poly_mul(&q, &s1);
+            s.resize(s.len().max(s0.len()), 0);
+            for (x, y) in s.iter_mut().zip(&s0) {
+                *x = self.sub_coeff(*y, *x);
+            }
+            for x in s.iter_mut().skip(s0.len()) {
+                *x = self.neg_coeff(*x);
+            }
+            s0 = std::mem::replace(&mut s1, s);
+        }
+
+        // r0 is a non-zero constant, since the defining polynomial is irreducible
+        let c = self.inv_coeff(r0[0]);
+        let mut r: Vec<_> = s0.iter().map(|x| self.mul_coeff(*x, c)).collect();
+        self.reduce(&mut r);
+        ExtensionFieldElement(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        domains::{Field, Ring},
+        poly::{polynomial::MultivariatePolynomial, Variable},
+    };
+
+    use super::{ExtensionField, Zp64};
+
+    #[test]
+    fn extension_field() {
+        let f = ExtensionField::from_degree(3, 3);
+        assert_eq!(f.get_defining_polynomial(), vec![1, 2, 0, 1]);
+        assert_eq!(f.size(), 27.into());
+
+        // every non-zero element has an inverse and satisfies x^(p^n-1) = 1
+        for i in 1..27 {
+            let x = f.to_element(&[i % 3, (i / 3) % 3, i / 9]);
+            assert_eq!(f.mul(&x, &f.inv(&x)), f.one());
+            assert!(f.is_one(&f.pow(&x, 26)));
+        }
+
+        let a = f.generator();
+        assert_eq!(f.pow(&a, 3), f.to_element(&[2, 1]));
+        assert_eq!(f.add(&f.add(&a, &a), &a), f.zero());
+
+        let g = ExtensionField::new(3, &[1, 0, 1]);
+        let b = g.to_element(&[2, 1]);
+        assert_eq!(g.from_element(&g.div(&g.one(), &b)), vec![1, 1]);
+        assert_eq!(g.printer(&g.mul(&b, &b)).to_string(), "α");
+        assert_eq!(g.to_string(), "GF(3^2)");
+    }
+
+    #[test]
+    fn extension_field_characteristic_two() {
+        let f = ExtensionField::from_degree(2, 3);
+        assert_eq!(f.get_defining_polynomial(), vec![1, 1, 0, 1]);
+        assert_eq!(f.size(), 8.into());
+
+        for i in 1..8 {
+            let x = f.to_element(&[i % 2, (i / 2) % 2, i / 4]);
+            assert_eq!(f.mul(&x, &f.inv(&x)), f.one());
+            assert!(f.is_one(&f.pow(&x, 7)));
+            assert_eq!(f.add(&x, &x), f.zero());
+            assert_eq!(f.neg(&x), x);
+        }
+
+        let a = f.generator();
+        assert_eq!(f.pow(&a, 3), f.to_element(&[1, 1]));
+        assert_eq!(f.printer(&f.pow(&a, 4)).to_string(), "α^2+α");
+
+        // GF(4) = Z_2[α]/(α^2+α+1)
+        let g = ExtensionField::new(2, &[1, 1, 1]);
+        let b = g.generator();
+        assert_eq!(g.mul(&b, &b), g.add(&b, &g.one()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn extension_field_composite_characteristic() {
+        ExtensionField::from_degree(4, 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn reducible_extension() {
+        ExtensionField::new(5, &[4, 0, 1]);
+    }
+
+    #[test]
+    fn extension_field_gcd() {
+        let f = ExtensionField::from_degree(3, 2);
+        let vars = Arc::new(vec![Variable::Temporary(0), Variable::Temporary(1)]);
+        let a = f.generator();
+
+        // (x + α*y) * (x + 1) and (x + α*y) * (y + α)
+        let mut common = MultivariatePolynomial::new(&f, None, vars.clone());
+        common.append_monomial(f.one(), &[1u8, 0]);
+        common.append_monomial(a.clone(), &[0, 1]);
+
+        let mut p1 = common.zero();
+        p1.append_monomial(f.one(), &[1, 0]);
+        p1.append_monomial(f.one(), &[0, 0]);
+        let mut p2 = common.zero();
+        p2.append_monomial(f.one(), &[0, 1]);
+        p2.append_monomial(a, &[0, 0]);
+
+        let g = (&common * &p1).gcd(&(&common * &p2));
+        assert_eq!(g, common);
+    }
+}
@@ -1,5 +1,7 @@
 use std::{
-    fmt::Display,
+    cmp::Ordering,
+    fmt::{Display, Write},
+    hash::{Hash, Hasher},
     iter::Sum,
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
@@ -8,7 +10,12 @@ use rand::Rng;
 use wide::{f64x2, f64x4};
 
 use super::rational::Rational;
-use rug::Rational as MultiPrecisionRational;
+use rug::{
+    float::{Constant, Special},
+    ops::Pow,
+    Float as MultiPrecisionFloat, Integer as MultiPrecisionInteger,
+    Rational as MultiPrecisionRational,
+};
 
 pub trait NumericalFloatLike:
     PartialEq
@@ -76,6 +83,13 @@ pub trait Real: NumericalFloatLike + Clone + Copy {
     fn acosh(&self) -> Self;
     fn atanh(&self) -> Self;
     fn powf(&self, e: Self) -> Self;
+
+    /// Check if the number, used as a condition of a `piecewise` function, is satisfied,
+    /// which is the case if it is real and strictly positive. Returns `None` if this
+    /// cannot be decided for the type, for example for vectorized numbers.
+    fn satisfies_condition(&self) -> Option<bool> {
+        None
+    }
 }
 
 impl NumericalFloatLike for f64 {
@@ -240,6 +254,11 @@ impl Real for f64 {
     fn powf(&self, e: f64) -> Self {
         (*self).powf(e)
     }
+
+    #[inline(always)]
+    fn satisfies_condition(&self) -> Option<bool> {
+        Some(*self > 0.)
+    }
 }
 
 impl From<&Rational> for f64 {
@@ -835,6 +854,14 @@ impl<T: Real> Real for Complex<T> {
     fn powf(&self, _e: Self) -> Self {
         todo!("Float exponentiation on complex numbers are not implemented yet")
     }
+
+    fn satisfies_condition(&self) -> Option<bool> {
+        if self.im == T::zero() {
+            self.re.satisfies_condition()
+        } else {
+            Some(false)
+        }
+    }
 }
 
 impl<'a, T: Real + From<&'a Rational>> From<&'a Rational> for Complex<T> {
@@ -842,3 +869,448 @@ impl<'a, T: Real + From<&'a Rational>> From<&'a Rational> for Complex<T> {
         Complex::new(value.into(), T::zero())
     }
 }
+
+/// An arbitrary-precision binary floating point number, backed by an MPFR float
+/// from [`rug`]. Every operation rounds to nearest (ties to even) to the precision
+/// of the result.
+///
+/// Two floats are equal if their values are equal, regardless of their precision.
+#[derive(Debug, Clone)]
+pub struct Float(MultiPrecisionFloat);
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Float {}
+
+impl Hash for Float {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if self.0.is_finite() {
+            self.to_parts().hash(state);
+        } else {
+            self.0.is_sign_negative().hash(state);
+            self.0.is_nan().hash(state);
+        }
+    }
+}
+
+impl PartialOrd for Float {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the sign of zero is ignored
+        if self.0.is_zero() && other.0.is_zero() {
+            return Ordering::Equal;
+        }
+
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl From<MultiPrecisionFloat> for Float {
+    fn from(value: MultiPrecisionFloat) -> Self {
+        Float(value)
+    }
+}
+
+impl From<Float> for MultiPrecisionFloat {
+    fn from(value: Float) -> Self {
+        value.0
+    }
+}
+
+impl AsRef<MultiPrecisionFloat> for Float {
+    fn as_ref(&self) -> &MultiPrecisionFloat {
+        &self.0
+    }
+}
+
+impl Float {
+    /// Create the float `mantissa * 2^exp`, rounded to `prec` bits. This is the inverse
+    /// of [`Float::to_parts`], so that a zero mantissa with exponent 1, -1 or 2
+    /// gives positive infinity, negative infinity or NaN respectively.
+    pub fn from_parts(mantissa: MultiPrecisionInteger, exp: i64, prec: u32) -> Float {
+        assert!(prec > 0, "The precision of a float must be positive");
+        if mantissa == 0 {
+            return match exp {
+                1 => Float(MultiPrecisionFloat::with_val(prec, Special::Infinity)),
+                -1 => Float(MultiPrecisionFloat::with_val(prec, Special::NegInfinity)),
+                2 => Float(MultiPrecisionFloat::with_val(prec, Special::Nan)),
+                _ => Float::zero(prec),
+            };
+        }
+
+        let exp = isize::try_from(exp).expect("Exponent of float is too large");
+        Float(MultiPrecisionFloat::with_val(prec, mantissa) << exp)
+    }
+
+    /// The zero with precision `prec`.
+    pub fn zero(prec: u32) -> Float {
+        assert!(prec > 0, "The precision of a float must be positive");
+        Float(MultiPrecisionFloat::new(prec))
+    }
+
+    /// Create the float that is closest to the rational `r`, with precision `prec`.
+    pub fn from_rational(r: &Rational, prec: u32) -> Float {
+        assert!(prec > 0, "The precision of a float must be positive");
+        match r {
+            Rational::Natural(n, d) => Float(MultiPrecisionFloat::with_val(
+                prec,
+                MultiPrecisionRational::from((*n, *d)),
+            )),
+            Rational::Large(r) => Float(MultiPrecisionFloat::with_val(prec, r)),
+        }
+    }
+
+    /// Create the float `f` with precision `prec`. As `f` is exact,
+    /// no rounding occurs for `prec >= 53`.
+    pub fn from_f64(f: f64, prec: u32) -> Float {
+        assert!(
+            f.is_finite(),
+            "Cannot convert the non-finite float {} to a Float",
+            f
+        );
+        assert!(prec > 0, "The precision of a float must be positive");
+        Float(MultiPrecisionFloat::with_val(prec, f))
+    }
+
+    /// Get the precision in bits.
+    pub fn prec(&self) -> u32 {
+        self.0.prec()
+    }
+
+    /// Get the odd mantissa `m` and exponent `e` such that the float is `m * 2^e`.
+    /// For zero, both are zero. A float that is not finite, which can be the result
+    /// of an operation that overflows, has mantissa zero and exponent 1 for positive
+    /// infinity, -1 for negative infinity and 2 for NaN.
+    pub fn to_parts(&self) -> (MultiPrecisionInteger, i64) {
+        let Some((mut m, e)) = self.0.to_integer_exp() else {
+            let e = if self.0.is_nan() {
+                2
+            } else if self.0.is_sign_negative() {
+                -1
+            } else {
+                1
+            };
+            return (MultiPrecisionInteger::new(), e);
+        };
+
+        match m.find_one(0) {
+            Some(tz) => {
+                m >>= tz;
+                (m, e as i64 + tz as i64)
+            }
+            None => (m, 0),
+        }
+    }
+
+    /// Set the precision to `prec`, rounding the value if the precision decreases.
+    pub fn with_prec(&self, prec: u32) -> Float {
+        assert!(prec > 0, "The precision of a float must be positive");
+        Float(MultiPrecisionFloat::with_val(prec, &self.0))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.0 == 1
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.0.is_integer()
+    }
+
+    /// Check if the float is neither infinite nor NaN, which can occur
+    /// when an operation overflows.
+    pub fn is_finite(&self) -> bool {
+        self.0.is_finite()
+    }
+
+    /// Convert the float to the rational number with exactly the same value.
+    /// Panics if the float is not finite.
+    pub fn to_rational(&self) -> Rational {
+        Rational::from_large(self.to_rat())
+    }
+
+    fn to_rat(&self) -> MultiPrecisionRational {
+        self.0
+            .to_rational()
+            .unwrap_or_else(|| panic!("Float {} is not finite", self.0))
+    }
+
+    /// Convert the float to the nearest `f64`.
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64()
+    }
+
+    pub fn neg(&self) -> Float {
+        Float(-self.0.clone())
+    }
+
+    pub fn abs(&self) -> Float {
+        Float(self.0.clone().abs())
+    }
+
+    /// Add two floats. The result has the highest precision of the two.
+    pub fn add(&self, other: &Float) -> Float {
+        Float(MultiPrecisionFloat::with_val(
+            self.prec().max(other.prec()),
+            &self.0 + &other.0,
+        ))
+    }
+
+    /// Subtract two floats. The result has the highest precision of the two.
+    pub fn sub(&self, other: &Float) -> Float {
+        Float(MultiPrecisionFloat::with_val(
+            self.prec().max(other.prec()),
+            &self.0 - &other.0,
+        ))
+    }
+
+    /// Multiply two floats. The result has the highest precision of the two.
+    pub fn mul(&self, other: &Float) -> Float {
+        Float(MultiPrecisionFloat::with_val(
+            self.prec().max(other.prec()),
+            &self.0 * &other.0,
+        ))
+    }
+
+    /// Divide two floats. The result has the highest precision of the two.
+    pub fn div(&self, other: &Float) -> Float {
+        if other.is_zero() {
+            panic!("Division by zero");
+        }
+
+        Float(MultiPrecisionFloat::with_val(
+            self.prec().max(other.prec()),
+            &self.0 / &other.0,
+        ))
+    }
+
+    /// Compute `1/self`.
+    pub fn inv(&self) -> Float {
+        if self.is_zero() {
+            panic!("Division by zero");
+        }
+
+        Float(self.0.clone().recip())
+    }
+
+    /// Raise the float to the integer power `e`.
+    pub fn powi(&self, e: i64) -> Float {
+        Float(MultiPrecisionFloat::with_val(self.prec(), (&self.0).pow(e)))
+    }
+
+    /// Compute the `n`th root of the float, which must be positive if `n` is even.
+    pub fn root(&self, n: u32) -> Float {
+        assert!(n > 0, "Cannot take the 0th root");
+        if self.is_negative() && n % 2 == 0 {
+            panic!("Cannot take an even root of the negative float {}", self);
+        }
+
+        Float(self.0.clone().root(n))
+    }
+
+    /// Raise the float to the rational power `num/den`.
+    pub fn pow_rational(&self, num: i64, den: u32) -> Float {
+        if den == 1 {
+            return self.powi(num);
+        }
+
+        let guard = self.prec() + 64;
+        let p = self.with_prec(guard).powi(num);
+        p.root(den).with_prec(self.prec())
+    }
+
+    /// The constant `π` with precision `prec`.
+    pub fn pi(prec: u32) -> Float {
+        Float(MultiPrecisionFloat::with_val(prec, Constant::Pi))
+    }
+
+    /// Euler's number `e` with precision `prec`.
+    pub fn e(prec: u32) -> Float {
+        Float(MultiPrecisionFloat::with_val(prec, 1).exp())
+    }
+
+    /// The Euler–Mascheroni constant `γ` with precision `prec`.
+    pub fn euler_gamma(prec: u32) -> Float {
+        Float(MultiPrecisionFloat::with_val(prec, Constant::Euler))
+    }
+
+    /// The position of the most significant bit, such that `2^(b-1) <= |self| < 2^b`.
+    /// For zero, this is 0.
+    pub(crate) fn magnitude(&self) -> i64 {
+        self.0.get_exp().unwrap_or(0) as i64
+    }
+
+    /// Compute `e^self`. The result is infinite if it is too large to be represented,
+    /// which can be checked with [`Float::is_finite`].
+    pub fn exp(&self) -> Float {
+        Float(self.0.clone().exp())
+    }
+
+    /// Compute the natural logarithm of the positive float.
+    pub fn log(&self) -> Float {
+        if self.is_negative() || self.is_zero() {
+            panic!(
+                "Cannot take the logarithm of the non-positive float {}",
+                self
+            );
+        }
+
+        Float(self.0.clone().ln())
+    }
+
+    /// Compute the sine.
+    pub fn sin(&self) -> Float {
+        Float(self.0.clone().sin())
+    }
+
+    /// Compute the cosine.
+    pub fn cos(&self) -> Float {
+        Float(self.0.clone().cos())
+    }
+}
+
+impl Display for Float {
+    /// Print the float in decimal notation with as many significant digits
+    /// as the precision allows, for example `1.5000000000000000` or `2.5e-20`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0.");
+        }
+
+        if !self.is_finite() {
+            return write!(f, "{}", self.0);
+        }
+
+        let digits = (self.prec() as f64 * std::f64::consts::LOG10_2).floor() as i64 + 1;
+
+        // estimate the decimal exponent `e` such that `10^e <= |x| < 10^(e+1)`
+        let r = self.to_rat().abs();
+        let bits = self.magnitude() - 1;
+        let mut e = (bits as f64 * std::f64::consts::LOG10_2).floor() as i64;
+
+        // round `|x|` to an integer with `digits` digits
+        let scaled = |e: i64| {
+            let k = digits - 1 - e;
+            let p = MultiPrecisionInteger::from(MultiPrecisionInteger::u_pow_u(
+                10,
+                u32::try_from(k.unsigned_abs()).unwrap(),
+            ));
+            if k >= 0 {
+                (r.clone() * p).round().into_numer_denom().0
+            } else {
+                (r.clone() / p).round().into_numer_denom().0
+            }
+        };
+
+        let mut m = scaled(e);
+        if m.to_string().len() as i64 > digits {
+            e += 1;
+            m = scaled(e);
+        } else if (m.to_string().len() as i64) < digits {
+            e -= 1;
+            m = scaled(e);
+        }
+
+        let s = m.to_string();
+        if self.is_negative() {
+            f.write_char('-')?;
+        }
+
+        if (-5..digits).contains(&e) {
+            if e >= 0 {
+                let (int, frac) = s.split_at(e as usize + 1);
+                write!(f, "{}.{}", int, frac)
+            } else {
+                write!(f, "0.{}{}", "0".repeat((-e - 1) as usize), s)
+            }
+        } else {
+            let (int, frac) = s.split_at(1);
+            write!(f, "{}.{}e{}", int, frac, e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rug::Integer as MultiPrecisionInteger;
+
+    use crate::domains::rational::Rational;
+
+    use super::Float;
+
+    /// Check that `a` and `b` agree up to a relative error of `2^-bits`.
+    fn assert_close(a: &Float, b: &Float, bits: i64) {
+        let diff = a.sub(b);
+        assert!(
+            diff.is_zero() || diff.magnitude() < b.magnitude() - bits,
+            "{} != {}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn transcendental() {
+        let prec = 256;
+        let one = Float::from_parts(MultiPrecisionInteger::from(1), 0, prec);
+        let half = Float::from_rational(&(1, 2).into(), prec);
+        let pi = Float::pi(prec + 10);
+
+        assert_close(&one.exp(), &Float::e(prec), 250);
+        assert_close(&Float::e(prec + 10).with_prec(prec).log(), &one, 250);
+        assert_close(
+            &pi.div(&Float::from_rational(&6.into(), prec)).sin(),
+            &half,
+            250,
+        );
+        assert_close(
+            &pi.div(&Float::from_rational(&3.into(), prec)).cos(),
+            &half,
+            250,
+        );
+
+        for x in [
+            Rational::new(-37, 10),
+            Rational::new(100001, 100000),
+            Rational::new(355, 113),
+            Rational::from(1000),
+        ] {
+            let x = Float::from_rational(&x, prec);
+            assert_close(&x.exp().log(), &x, 240);
+
+            let (s, c) = (x.sin(), x.cos());
+            assert_close(&s.mul(&s).add(&c.mul(&c)), &one, 250);
+            if !x.is_negative() {
+                assert_close(&x.log().exp(), &x, 240);
+            }
+        }
+
+        // sin(x) = x - x^3/6 + x^5/120 + O(x^7) for small x
+        let x = Float::from_rational(&Rational::new(1, 1 << 40), prec);
+        let x2 = x.mul(&x);
+        let series = one
+            .sub(&x2.div(&Float::from_rational(&6.into(), prec)))
+            .add(&x2.mul(&x2).div(&Float::from_rational(&120.into(), prec)));
+        assert_close(&x.sin(), &x.mul(&series), 250);
+
+        // sin(355/113) is small due to cancellation
+        let x = Float::from_rational(&Rational::new(355, 113), 53);
+        assert!((x.sin().to_f64() - (355f64 / 113.).sin()).abs() < 1e-20);
+    }
+}
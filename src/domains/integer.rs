@@ -111,7 +111,32 @@ impl From<u128> for Integer {
 impl FromStr for Integer {
     type Err = &'static str;
 
+    /// Parse an integer. Hexadecimal, binary and octal integers are
+    /// supported with the prefixes `0x`, `0b` and `0o` respectively.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(d) => (true, d),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        let radix = match digits.get(..2) {
+            Some("0x") => 16,
+            Some("0b") => 2,
+            Some("0o") => 8,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            let digits = &digits[2..];
+            if digits.is_empty() || digits.starts_with(['+', '-']) {
+                return Err("Could not parse integer");
+            }
+
+            let n = MultiPrecisionInteger::from_str_radix(digits, radix)
+                .map_err(|_| "Could not parse integer")?;
+            return Ok(Integer::from_large(if negative { -n } else { n }));
+        }
+
         if s.len() <= 20 {
             if let Ok(n) = s.parse::<i64>() {
                 return Ok(Integer::Natural(n));
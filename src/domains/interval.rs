@@ -0,0 +1,697 @@
+use std::{
+    fmt::Display,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use rand::Rng;
+use rug::{
+    float::{Constant, Round},
+    Float as MultiPrecisionFloat, Rational as MultiPrecisionRational,
+};
+
+use super::{
+    float::{Float, NumericalFloatLike, Real},
+    rational::Rational,
+};
+
+/// The precision of an `f64` in bits. The bounds are computed with MPFR at this
+/// precision and are then converted to `f64` with the same rounding direction.
+const F64_PREC: u32 = 53;
+
+/// An MPFR function that sets its argument to the result rounded in a given direction.
+type RoundFn = fn(&mut MultiPrecisionFloat, Round) -> std::cmp::Ordering;
+
+fn to_mpfr(x: f64) -> MultiPrecisionFloat {
+    MultiPrecisionFloat::with_val(F64_PREC, x)
+}
+
+/// Round the MPFR float to an `f64` in the direction `round`.
+fn to_f64(x: MultiPrecisionFloat, round: Round) -> f64 {
+    x.to_f64_round(round)
+}
+
+fn add_round(a: f64, b: f64, round: Round) -> f64 {
+    let r = MultiPrecisionFloat::with_val_round(F64_PREC, &to_mpfr(a) + &to_mpfr(b), round);
+    to_f64(r.0, round)
+}
+
+fn mul_round(a: f64, b: f64, round: Round) -> f64 {
+    let r = MultiPrecisionFloat::with_val_round(F64_PREC, &to_mpfr(a) * &to_mpfr(b), round);
+    to_f64(r.0, round)
+}
+
+fn div_round(a: f64, b: f64, round: Round) -> f64 {
+    let r = MultiPrecisionFloat::with_val_round(F64_PREC, &to_mpfr(a) / &to_mpfr(b), round);
+    to_f64(r.0, round)
+}
+
+/// Evaluate the MPFR function `f` at `x`, rounding in the direction `round`.
+fn apply_round(f: RoundFn, x: f64, round: Round) -> f64 {
+    let mut r = to_mpfr(x);
+    f(&mut r, round);
+    to_f64(r, round)
+}
+
+/// A closed interval `[lo, hi]` of double precision floats. All operations round
+/// outward, so that the result encloses the exact result for all values in the operands.
+/// This gives rigorous error bounds for numerical evaluations and exposes catastrophic
+/// cancellation as a wide interval.
+///
+/// The lower bound of every operation is computed by MPFR with [`Round::Down`] and the
+/// upper bound with [`Round::Up`]. As MPFR rounds all of its functions correctly, this
+/// also holds for the elementary functions.
+///
+/// The bounds are stored as `f64` rather than as multi-precision floats, since evaluation
+/// requires [`Real`] types to be [`Copy`]. For the same reason, intervals are not
+/// a coefficient type of expressions: use [`Coefficient::to_interval`](crate::coefficient::Coefficient::to_interval)
+/// to enclose a coefficient, including a multi-precision float, in an interval.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    lo: f64,
+    hi: f64,
+}
+
+impl Interval {
+    /// Create the interval `[lo, hi]`.
+    pub fn new(lo: f64, hi: f64) -> Interval {
+        assert!(
+            lo <= hi || lo.is_nan() || hi.is_nan(),
+            "The lower bound {} is larger than the upper bound {}",
+            lo,
+            hi
+        );
+        Interval { lo, hi }
+    }
+
+    /// Create the interval containing only `x`.
+    pub fn point(x: f64) -> Interval {
+        Interval { lo: x, hi: x }
+    }
+
+    /// The interval of all real numbers.
+    pub fn entire() -> Interval {
+        Interval {
+            lo: f64::NEG_INFINITY,
+            hi: f64::INFINITY,
+        }
+    }
+
+    pub fn lo(&self) -> f64 {
+        self.lo
+    }
+
+    pub fn hi(&self) -> f64 {
+        self.hi
+    }
+
+    /// Get the midpoint of the interval.
+    pub fn mid(&self) -> f64 {
+        self.lo / 2. + self.hi / 2.
+    }
+
+    /// Get the width of the interval, rounded up.
+    pub fn width(&self) -> f64 {
+        add_round(self.hi, -self.lo, Round::Up)
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    /// Get the number of decimal digits on which the bounds agree, relative to the
+    /// magnitude of the interval. A low number signals a loss of precision, for example
+    /// due to catastrophic cancellation.
+    pub fn significant_digits(&self) -> f64 {
+        let scale = self.lo.abs().max(self.hi.abs());
+        if self.width() == 0. {
+            f64::INFINITY
+        } else if scale == 0. {
+            0.
+        } else {
+            (scale / self.width()).log10().max(0.)
+        }
+    }
+
+    /// Apply a monotonically increasing function.
+    fn map_increasing(&self, f: RoundFn) -> Interval {
+        Interval {
+            lo: apply_round(f, self.lo, Round::Down),
+            hi: apply_round(f, self.hi, Round::Up),
+        }
+    }
+
+    /// Apply a monotonically decreasing function.
+    fn map_decreasing(&self, f: RoundFn) -> Interval {
+        Interval {
+            lo: apply_round(f, self.hi, Round::Down),
+            hi: apply_round(f, self.lo, Round::Up),
+        }
+    }
+
+    /// Check which extrema of a function with period `2π` and extrema at `offset + kπ` may lie
+    /// in the interval, where the maxima have an even `k`. The check is conservative.
+    fn periodic_extrema(&self, offset: f64) -> (bool, bool) {
+        let pi = std::f64::consts::PI;
+        // a NaN width also gives the full range
+        let width = self.hi - self.lo;
+        if width.is_nan() || width >= 2. * pi || self.lo.abs().max(self.hi.abs()) > 1e15 {
+            return (true, true);
+        }
+
+        let eps = 1e-12 * (1. + self.lo.abs().max(self.hi.abs()));
+        let k_lo = ((self.lo - offset - eps) / pi).ceil() as i64;
+        let k_hi = ((self.hi - offset + eps) / pi).floor() as i64;
+
+        let (mut has_max, mut has_min) = (false, false);
+        for k in k_lo..=k_hi {
+            if k % 2 == 0 {
+                has_max = true;
+            } else {
+                has_min = true;
+            }
+        }
+        (has_max, has_min)
+    }
+
+    /// Evaluate a function with period `2π`, range `[-1, 1]` and extrema at `offset + kπ`.
+    fn periodic(&self, f: RoundFn, offset: f64) -> Interval {
+        let (has_max, has_min) = self.periodic_extrema(offset);
+        Interval {
+            lo: if has_min {
+                -1.
+            } else {
+                apply_round(f, self.lo, Round::Down).min(apply_round(f, self.hi, Round::Down))
+            },
+            hi: if has_max {
+                1.
+            } else {
+                apply_round(f, self.lo, Round::Up).max(apply_round(f, self.hi, Round::Up))
+            },
+        }
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+impl From<f64> for Interval {
+    fn from(value: f64) -> Self {
+        Interval::point(value)
+    }
+}
+
+impl From<&Rational> for Interval {
+    /// Create a tight interval that contains the rational number.
+    fn from(value: &Rational) -> Self {
+        let exact = match value {
+            Rational::Natural(n, d) => MultiPrecisionRational::from((*n, *d)),
+            Rational::Large(r) => r.clone(),
+        };
+
+        let bound = |round| {
+            to_f64(
+                MultiPrecisionFloat::with_val_round(F64_PREC, &exact, round).0,
+                round,
+            )
+        };
+        Interval {
+            lo: bound(Round::Down),
+            hi: bound(Round::Up),
+        }
+    }
+}
+
+impl From<&Float> for Interval {
+    /// Create a tight interval that contains the float, which may have a higher
+    /// precision than an `f64`.
+    fn from(value: &Float) -> Self {
+        let f: &MultiPrecisionFloat = value.as_ref();
+        Interval {
+            lo: f.to_f64_round(Round::Down),
+            hi: f.to_f64_round(Round::Up),
+        }
+    }
+}
+
+impl Add<Interval> for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Interval {
+            lo: add_round(self.lo, rhs.lo, Round::Down),
+            hi: add_round(self.hi, rhs.hi, Round::Up),
+        }
+    }
+}
+
+impl Add<&Interval> for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: &Self) -> Self::Output {
+        self + *rhs
+    }
+}
+
+impl AddAssign for Interval {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl AddAssign<&Interval> for Interval {
+    #[inline]
+    fn add_assign(&mut self, rhs: &Self) {
+        *self = *self + *rhs;
+    }
+}
+
+impl Sub for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Sub<&Interval> for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: &Self) -> Self::Output {
+        self + -*rhs
+    }
+}
+
+impl SubAssign for Interval {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl SubAssign<&Interval> for Interval {
+    #[inline]
+    fn sub_assign(&mut self, rhs: &Self) {
+        *self = *self - *rhs;
+    }
+}
+
+impl Mul for Interval {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut lo = f64::INFINITY;
+        let mut hi = f64::NEG_INFINITY;
+        for a in [self.lo, self.hi] {
+            for b in [rhs.lo, rhs.hi] {
+                lo = lo.min(mul_round(a, b, Round::Down));
+                hi = hi.max(mul_round(a, b, Round::Up));
+            }
+        }
+        Interval { lo, hi }
+    }
+}
+
+impl Mul<&Interval> for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: &Self) -> Self::Output {
+        self * *rhs
+    }
+}
+
+impl MulAssign for Interval {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl MulAssign<&Interval> for Interval {
+    #[inline]
+    fn mul_assign(&mut self, rhs: &Self) {
+        *self = *self * *rhs;
+    }
+}
+
+impl Div for Interval {
+    type Output = Self;
+
+    /// Divide two intervals. If the divisor contains zero, the result is the entire real line.
+    fn div(self, rhs: Self) -> Self::Output {
+        if rhs.contains(0.) {
+            return Interval::entire();
+        }
+
+        let mut lo = f64::INFINITY;
+        let mut hi = f64::NEG_INFINITY;
+        for a in [self.lo, self.hi] {
+            for b in [rhs.lo, rhs.hi] {
+                lo = lo.min(div_round(a, b, Round::Down));
+                hi = hi.max(div_round(a, b, Round::Up));
+            }
+        }
+        Interval { lo, hi }
+    }
+}
+
+impl Div<&Interval> for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: &Self) -> Self::Output {
+        self / *rhs
+    }
+}
+
+impl DivAssign for Interval {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl DivAssign<&Interval> for Interval {
+    #[inline]
+    fn div_assign(&mut self, rhs: &Self) {
+        *self = *self / *rhs;
+    }
+}
+
+impl<'a> Sum<&'a Interval> for Interval {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        let mut res = Interval::point(0.);
+        for x in iter {
+            res += x;
+        }
+        res
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    #[inline]
+    fn neg(self) -> Interval {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+impl NumericalFloatLike for Interval {
+    #[inline]
+    fn mul_add(&self, a: &Self, b: &Self) -> Self {
+        *self * a + b
+    }
+
+    #[inline]
+    fn neg(&self) -> Self {
+        -*self
+    }
+
+    fn norm(&self) -> Self {
+        if self.lo >= 0. {
+            *self
+        } else if self.hi <= 0. {
+            -*self
+        } else {
+            Interval {
+                lo: 0.,
+                hi: self.hi.max(-self.lo),
+            }
+        }
+    }
+
+    #[inline]
+    fn zero() -> Self {
+        Interval::point(0.)
+    }
+
+    #[inline]
+    fn one() -> Self {
+        Interval::point(1.)
+    }
+
+    fn pow(&self, e: u64) -> Self {
+        if e == 0 {
+            return Self::one();
+        }
+
+        // raise the absolute value, so that even powers of intervals containing 0 stay tight
+        let abs = self.norm();
+        let mut r = Self::one();
+        for _ in 0..e {
+            r *= abs;
+        }
+
+        if e % 2 == 0 || self.lo >= 0. {
+            r
+        } else if self.hi <= 0. {
+            -r
+        } else {
+            // the odd power is increasing
+            Interval {
+                lo: -(Interval::point(-self.lo).pow(e).hi),
+                hi: Interval::point(self.hi).pow(e).hi,
+            }
+        }
+    }
+
+    #[inline]
+    fn inv(&self) -> Self {
+        Self::one() / *self
+    }
+
+    fn from_usize(a: usize) -> Self {
+        (&Rational::from(a as u64)).into()
+    }
+
+    fn from_i64(a: i64) -> Self {
+        (&Rational::from(a)).into()
+    }
+
+    fn sample_unit<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Interval::point(rng.gen())
+    }
+}
+
+impl Real for Interval {
+    fn sqrt(&self) -> Self {
+        Interval {
+            lo: apply_round(
+                MultiPrecisionFloat::sqrt_round,
+                self.lo.max(0.),
+                Round::Down,
+            ),
+            hi: apply_round(MultiPrecisionFloat::sqrt_round, self.hi, Round::Up),
+        }
+    }
+
+    fn log(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::ln_round)
+    }
+
+    fn exp(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::exp_round)
+    }
+
+    fn sin(&self) -> Self {
+        self.periodic(MultiPrecisionFloat::sin_round, std::f64::consts::FRAC_PI_2)
+    }
+
+    fn cos(&self) -> Self {
+        self.periodic(MultiPrecisionFloat::cos_round, 0.)
+    }
+
+    fn tan(&self) -> Self {
+        // the poles of tan are where cos has a zero
+        let (max, min) = self.periodic_extrema(std::f64::consts::FRAC_PI_2);
+        if max || min {
+            Interval::entire()
+        } else {
+            self.map_increasing(MultiPrecisionFloat::tan_round)
+        }
+    }
+
+    fn asin(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::asin_round)
+    }
+
+    fn acos(&self) -> Self {
+        self.map_decreasing(MultiPrecisionFloat::acos_round)
+    }
+
+    fn atan2(&self, x: &Self) -> Self {
+        // the angle is discontinuous on the negative real axis
+        if x.lo <= 0. && self.contains(0.) {
+            let pi = to_f64(
+                MultiPrecisionFloat::with_val_round(F64_PREC, Constant::Pi, Round::Up).0,
+                Round::Up,
+            );
+            return Interval { lo: -pi, hi: pi };
+        }
+
+        let atan2 = |y: f64, x: f64, round| {
+            let mut r = to_mpfr(y);
+            r.atan2_round(&to_mpfr(x), round);
+            to_f64(r, round)
+        };
+
+        // the extremal angles of a box that does not contain the origin are at its corners
+        let mut lo = f64::INFINITY;
+        let mut hi = f64::NEG_INFINITY;
+        for y in [self.lo, self.hi] {
+            for x in [x.lo, x.hi] {
+                lo = lo.min(atan2(y, x, Round::Down));
+                hi = hi.max(atan2(y, x, Round::Up));
+            }
+        }
+
+        Interval { lo, hi }
+    }
+
+    fn sinh(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::sinh_round)
+    }
+
+    fn cosh(&self) -> Self {
+        self.norm().map_increasing(MultiPrecisionFloat::cosh_round)
+    }
+
+    fn tanh(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::tanh_round)
+    }
+
+    fn asinh(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::asinh_round)
+    }
+
+    fn acosh(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::acosh_round)
+    }
+
+    fn atanh(&self) -> Self {
+        self.map_increasing(MultiPrecisionFloat::atanh_round)
+    }
+
+    fn powf(&self, e: Self) -> Self {
+        (self.log() * e).exp()
+    }
+
+    /// The condition is only decided if all numbers in the interval agree.
+    #[inline]
+    fn satisfies_condition(&self) -> Option<bool> {
+        if self.lo > 0. {
+            Some(true)
+        } else if self.hi <= 0. {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMap;
+    use rug::{Float as MultiPrecisionFloat, Rational as MultiPrecisionRational};
+
+    use crate::{
+        coefficient::Coefficient,
+        domains::{
+            float::{Float, NumericalFloatLike, Real},
+            rational::Rational,
+        },
+        representations::{Atom, AtomView},
+    };
+
+    use super::Interval;
+
+    #[test]
+    fn enclosure() {
+        let tenth = Interval::from(&Rational::from((1, 10)));
+        assert!(tenth.lo() < tenth.hi());
+        assert!(tenth.lo() <= 0.1 && 0.1 <= tenth.hi());
+
+        // the numerator is not exactly representable as a float
+        let exact = MultiPrecisionRational::from(((1i64 << 60) + 1, 3));
+        let r = Interval::from(&Rational::from(((1i64 << 60) + 1, 3)));
+        assert!(MultiPrecisionRational::from_f64(r.lo()).unwrap() <= exact);
+        assert!(MultiPrecisionRational::from_f64(r.hi()).unwrap() >= exact);
+
+        let three = Interval::from(&Rational::from(3));
+        assert_eq!(three + Interval::point(4.), Interval::point(7.));
+        assert_eq!(three * three, Interval::point(9.));
+
+        // 1/3 is not exact, but three times it encloses 1
+        let third = Interval::point(1.) / three;
+        assert!(third.lo() < third.hi());
+        assert!((third * three).contains(1.));
+
+        let x = Interval::new(-2., 3.);
+        assert_eq!(x.pow(2), Interval::new(0., 9.));
+        assert_eq!(x.pow(3), Interval::new(-8., 27.));
+        assert_eq!(Interval::point(1.) / x, Interval::entire());
+
+        let s = Interval::new(0., 4.).sin();
+        assert_eq!(s.hi(), 1.);
+        assert!(s.lo() < -0.75 && s.lo() > -0.76);
+        assert_eq!(Interval::new(1., 7.).cos(), Interval::new(-1., 1.));
+        assert!(Interval::point(2.).sqrt().pow(2).contains(2.));
+    }
+
+    #[test]
+    fn directed_rounding() {
+        // the product underflows, but its enclosure does not contain only zero
+        let tiny = Interval::point(1e-300) * Interval::point(1e-300);
+        assert_eq!(tiny, Interval::new(0., f64::from_bits(1)));
+
+        let e = MultiPrecisionFloat::with_val(200, 1).exp();
+        let r = Interval::point(1.).exp();
+        assert!(r.lo() < e && e < r.hi());
+        assert_eq!(r.hi(), f64::from_bits(r.lo().to_bits() + 1));
+
+        let s = Interval::point(1e22).sin();
+        let exact = MultiPrecisionFloat::with_val(200, 1e22).sin();
+        assert!(s.lo() < exact && exact < s.hi());
+
+        let third = Coefficient::from(Float::from_rational(&Rational::from((1, 3)), 200));
+        let i = third.to_interval();
+        assert!(i.lo() < i.hi() && i.hi() - i.lo() < 1e-16);
+        assert!(i.contains(1. / 3.));
+
+        let tenth = Atom::parse("1/10").unwrap();
+        let AtomView::Num(n) = tenth.as_view() else {
+            panic!("Expected a number");
+        };
+        assert_eq!(
+            n.get_coeff_view().to_interval(),
+            Interval::from(&Rational::from((1, 10)))
+        );
+    }
+
+    #[test]
+    fn cancellation() {
+        let a = Atom::parse("(ival_x + 1)^2 - ival_x^2 - 2*ival_x").unwrap();
+        let x = Atom::parse("ival_x").unwrap();
+
+        let mut const_map = HashMap::default();
+        const_map.insert(x.as_view(), Interval::point(1e8));
+        let r = a.evaluate(&const_map, &HashMap::default(), &mut HashMap::default());
+
+        // the exact result is 1, and the evaluation has lost most of its digits
+        assert!(r.contains(1.));
+        assert!(r.significant_digits() < 12.);
+    }
+}
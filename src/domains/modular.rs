@@ -0,0 +1,435 @@
+use std::fmt::{Display, Error, Formatter};
+
+use rand::Rng;
+use rug::integer::IntegerExt64;
+
+use crate::printer::PrintOptions;
+
+use super::{
+    integer::{Integer, Z},
+    EuclideanDomain, Ring,
+};
+
+/// The ring of integers modulo `n`, where `n` need not be prime.
+/// Elements are stored in the range `[0, n)`.
+///
+/// In contrast to a [`FiniteField`](super::finite_field::FiniteField), not every
+/// non-zero element is invertible. Use [`Zn64::try_inv`] to invert units.
+/// This ring is used for computations modulo prime powers, such as Hensel lifting.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IntegerModN<T> {
+    n: T,
+}
+
+/// The integers modulo `n < 2^64`.
+pub type Zn64 = IntegerModN<u64>;
+/// The integers modulo an arbitrarily large `n`.
+pub type ZnLarge = IntegerModN<Integer>;
+
+impl<T: Display> Display for IntegerModN<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Z/{}Z", self.n)
+    }
+}
+
+impl Zn64 {
+    /// Create the ring of integers modulo `n`. Panics if `n < 2`.
+    pub fn new(n: u64) -> Zn64 {
+        if n < 2 {
+            panic!("The modulus must be at least 2, but it is {}", n);
+        }
+
+        Zn64 { n }
+    }
+
+    /// Create the ring of integers modulo `p^k`. Panics if `p^k` does not fit in 64 bits.
+    pub fn new_prime_power(p: u64, k: u32) -> Zn64 {
+        Zn64::new(
+            p.checked_pow(k)
+                .unwrap_or_else(|| panic!("{}^{} does not fit in 64 bits", p, k)),
+        )
+    }
+
+    pub fn get_modulus(&self) -> u64 {
+        self.n
+    }
+
+    /// Map an integer into the ring.
+    pub fn to_element(&self, a: &Integer) -> u64 {
+        match a {
+            Integer::Natural(x) => (*x as i128).rem_euclid(self.n as i128) as u64,
+            Integer::Double(x) => x.rem_euclid(self.n as i128) as u64,
+            Integer::Large(r) => r.mod_u64(self.n),
+        }
+    }
+
+    /// Convert an element to an integer in the range `[0, n)`.
+    pub fn from_element(&self, a: &u64) -> Integer {
+        Integer::from(*a)
+    }
+
+    /// Convert an element to an integer in the range `(-n/2, n/2]`.
+    pub fn to_symmetric_integer(&self, a: &u64) -> Integer {
+        if *a > self.n / 2 {
+            Integer::from(*a as i128 - self.n as i128)
+        } else {
+            Integer::from(*a)
+        }
+    }
+
+    /// Check if `a` has a multiplicative inverse, i.e., if it is coprime to the modulus.
+    pub fn is_unit(&self, a: &u64) -> bool {
+        crate::utils::gcd_unsigned(*a, self.n) == 1
+    }
+
+    /// Compute the inverse of `a`, if `a` is a unit.
+    pub fn try_inv(&self, a: &u64) -> Option<u64> {
+        let (mut t0, mut t1) = (0i128, 1i128);
+        let (mut r0, mut r1) = (self.n as i128, *a as i128);
+
+        while r1 != 0 {
+            let q = r0 / r1;
+            (t0, t1) = (t1, t0 - q * t1);
+            (r0, r1) = (r1, r0 - q * r1);
+        }
+
+        if r0 != 1 {
+            return None;
+        }
+
+        Some(t0.rem_euclid(self.n as i128) as u64)
+    }
+}
+
+impl Ring for Zn64 {
+    type Element = u64;
+
+    #[inline]
+    fn add(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        ((*a as u128 + *b as u128) % self.n as u128) as u64
+    }
+
+    #[inline]
+    fn sub(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        if a >= b {
+            a - b
+        } else {
+            self.n - (b - a)
+        }
+    }
+
+    #[inline]
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        (*a as u128 * *b as u128 % self.n as u128) as u64
+    }
+
+    fn add_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.add(a, b);
+    }
+
+    fn sub_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.sub(a, b);
+    }
+
+    fn mul_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.mul(a, b);
+    }
+
+    fn add_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        *a = self.add(a, &self.mul(b, c));
+    }
+
+    fn sub_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        *a = self.sub(a, &self.mul(b, c));
+    }
+
+    #[inline]
+    fn neg(&self, a: &Self::Element) -> Self::Element {
+        if *a == 0 {
+            0
+        } else {
+            self.n - a
+        }
+    }
+
+    fn zero(&self) -> Self::Element {
+        0
+    }
+
+    fn one(&self) -> Self::Element {
+        1
+    }
+
+    #[inline]
+    fn nth(&self, n: u64) -> Self::Element {
+        n % self.n
+    }
+
+    fn pow(&self, b: &Self::Element, mut e: u64) -> Self::Element {
+        let mut b = *b;
+        let mut x = self.one();
+        while e != 0 {
+            if e & 1 != 0 {
+                x = self.mul(&x, &b);
+            }
+            b = self.mul(&b, &b);
+            e /= 2;
+        }
+
+        x
+    }
+
+    #[inline]
+    fn is_zero(a: &Self::Element) -> bool {
+        *a == 0
+    }
+
+    #[inline]
+    fn is_one(&self, a: &Self::Element) -> bool {
+        *a == 1
+    }
+
+    fn one_is_gcd_unit() -> bool {
+        false
+    }
+
+    fn is_characteristic_zero(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, rng: &mut impl rand::RngCore, range: (i64, i64)) -> Self::Element {
+        let r = rng.gen_range(range.0.max(0)..range.1.min(self.n.min(i64::MAX as u64) as i64));
+        r as u64
+    }
+
+    fn fmt_display(
+        &self,
+        element: &Self::Element,
+        opts: &PrintOptions,
+        _in_product: bool,
+        f: &mut Formatter<'_>,
+    ) -> Result<(), Error> {
+        if opts.symmetric_representation_for_finite_field {
+            self.to_symmetric_integer(element).fmt(f)
+        } else {
+            element.fmt(f)
+        }
+    }
+}
+
+impl ZnLarge {
+    /// Create the ring of integers modulo `n`. Panics if `n < 2`.
+    pub fn new(n: Integer) -> ZnLarge {
+        if n < Integer::Natural(2) {
+            panic!("The modulus must be at least 2, but it is {}", n);
+        }
+
+        ZnLarge { n }
+    }
+
+    /// Create the ring of integers modulo `p^k`.
+    pub fn new_prime_power(p: &Integer, k: u32) -> ZnLarge {
+        ZnLarge::new(p.pow(k as u64))
+    }
+
+    pub fn get_modulus(&self) -> &Integer {
+        &self.n
+    }
+
+    /// Map an integer into the ring.
+    pub fn to_element(&self, a: &Integer) -> Integer {
+        Z.quot_rem(a, &self.n).1
+    }
+
+    /// Convert an element to an integer in the range `[0, n)`.
+    pub fn from_element(&self, a: &Integer) -> Integer {
+        a.clone()
+    }
+
+    /// Convert an element to an integer in the range `(-n/2, n/2]`.
+    pub fn to_symmetric_integer(&self, a: &Integer) -> Integer {
+        if a * &Integer::Natural(2) > self.n {
+            a - &self.n
+        } else {
+            a.clone()
+        }
+    }
+
+    /// Check if `a` has a multiplicative inverse, i.e., if it is coprime to the modulus.
+    pub fn is_unit(&self, a: &Integer) -> bool {
+        Z.gcd(a, &self.n).is_one()
+    }
+
+    /// Compute the inverse of `a`, if `a` is a unit.
+    pub fn try_inv(&self, a: &Integer) -> Option<Integer> {
+        if self.is_unit(a) {
+            Some(a.mod_inverse(&self.n))
+        } else {
+            None
+        }
+    }
+}
+
+impl Ring for ZnLarge {
+    type Element = Integer;
+
+    fn add(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        let r = a + b;
+        if r >= self.n {
+            &r - &self.n
+        } else {
+            r
+        }
+    }
+
+    fn sub(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        let r = a - b;
+        if r.is_negative() {
+            &r + &self.n
+        } else {
+            r
+        }
+    }
+
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        Z.quot_rem(&(a * b), &self.n).1
+    }
+
+    fn add_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.add(a, b);
+    }
+
+    fn sub_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.sub(a, b);
+    }
+
+    fn mul_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.mul(a, b);
+    }
+
+    fn add_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        *a = self.add(a, &self.mul(b, c));
+    }
+
+    fn sub_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        *a = self.sub(a, &self.mul(b, c));
+    }
+
+    fn neg(&self, a: &Self::Element) -> Self::Element {
+        if a.is_zero() {
+            Integer::zero()
+        } else {
+            &self.n - a
+        }
+    }
+
+    fn zero(&self) -> Self::Element {
+        Integer::zero()
+    }
+
+    fn one(&self) -> Self::Element {
+        Integer::one()
+    }
+
+    fn nth(&self, n: u64) -> Self::Element {
+        self.to_element(&Integer::from(n))
+    }
+
+    fn pow(&self, b: &Self::Element, mut e: u64) -> Self::Element {
+        let mut b = b.clone();
+        let mut x = self.one();
+        while e != 0 {
+            if e & 1 != 0 {
+                x = self.mul(&x, &b);
+            }
+            b = self.mul(&b, &b);
+            e /= 2;
+        }
+
+        x
+    }
+
+    fn is_zero(a: &Self::Element) -> bool {
+        a.is_zero()
+    }
+
+    fn is_one(&self, a: &Self::Element) -> bool {
+        a.is_one()
+    }
+
+    fn one_is_gcd_unit() -> bool {
+        false
+    }
+
+    fn is_characteristic_zero(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, rng: &mut impl rand::RngCore, range: (i64, i64)) -> Self::Element {
+        self.to_element(&Z.sample(rng, range))
+    }
+
+    fn fmt_display(
+        &self,
+        element: &Self::Element,
+        opts: &PrintOptions,
+        _in_product: bool,
+        f: &mut Formatter<'_>,
+    ) -> Result<(), Error> {
+        if opts.symmetric_representation_for_finite_field {
+            self.to_symmetric_integer(element).fmt(f)
+        } else {
+            element.fmt(f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        domains::{integer::Integer, Ring},
+        poly::{polynomial::MultivariatePolynomial, Variable},
+    };
+
+    use super::{Zn64, ZnLarge};
+
+    #[test]
+    fn composite_modulus() {
+        let r = Zn64::new(12);
+        assert_eq!(r.mul(&4, &3), 0);
+        assert_eq!(r.sub(&2, &5), 9);
+        assert_eq!(r.to_element(&Integer::from(-1)), 11);
+        assert_eq!(r.try_inv(&5), Some(5));
+        assert_eq!(r.try_inv(&4), None);
+        assert_eq!(r.to_symmetric_integer(&11), Integer::from(-1));
+        assert_eq!(r.to_string(), "Z/12Z");
+
+        let l = ZnLarge::new_prime_power(&Integer::from(3), 50);
+        let x = l.to_element(&Integer::from(-2));
+        assert_eq!(l.mul(&x, &l.try_inv(&x).unwrap()), l.one());
+        assert_eq!(l.try_inv(&l.nth(9)), None);
+        assert_eq!(l.to_symmetric_integer(&x), Integer::from(-2));
+    }
+
+    #[test]
+    fn hensel_lift_root() {
+        // lift the root 3 of x^2 - 2 mod 7 to a root mod 7^8 with Newton iteration
+        let r = Zn64::new_prime_power(7, 8);
+        let vars = Arc::new(vec![Variable::Temporary(0)]);
+        let mut f = MultivariatePolynomial::new(&r, None, vars);
+        f.append_monomial(r.one(), &[2u8]);
+        f.append_monomial(r.to_element(&Integer::from(-2)), &[0]);
+        let df = f.derivative(0);
+
+        let mut x = 3;
+        for _ in 0..3 {
+            let d = r.try_inv(&df.replace(0, &x).lcoeff()).unwrap();
+            x = r.sub(&x, &r.mul(&f.replace(0, &x).lcoeff(), &d));
+        }
+
+        assert!(f.replace(0, &x).is_zero());
+        assert_eq!(r.mul(&x, &x), r.to_element(&Integer::from(2)));
+    }
+}
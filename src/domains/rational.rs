@@ -1,5 +1,6 @@
 use std::{
     fmt::{Display, Error, Formatter, Write},
+    hash::{Hash, Hasher},
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
@@ -46,12 +47,42 @@ impl RationalField {
 /// `gcd(n,d)=1`.
 // TODO: convert to Rational(Integer, Integer)?
 // TODO: prevent construction of explicit rational
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Debug)]
 pub enum Rational {
     Natural(i64, i64),
     Large(MultiPrecisionRational),
 }
 
+impl PartialEq for Rational {
+    /// Compare the values of two rationals. A value that fits in `Natural` may
+    /// still be stored as `Large`, so mixed representations are compared by value.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Rational::Natural(n1, d1), Rational::Natural(n2, d2)) => n1 == n2 && d1 == d2,
+            (Rational::Natural(n, d), Rational::Large(r))
+            | (Rational::Large(r), Rational::Natural(n, d)) => r.numer() == n && r.denom() == d,
+            // GMP already returns early for unequal signs or sizes, and an explicit
+            // early-out here was slower in the `rational` benchmark
+            (Rational::Large(r1), Rational::Large(r2)) => r1 == r2,
+        }
+    }
+}
+
+impl Eq for Rational {}
+
+impl Hash for Rational {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // hash by value, consistent with `PartialEq`
+        match self {
+            Rational::Natural(n, d) => (n, d).hash(state),
+            Rational::Large(r) => match (r.numer().to_i64(), r.denom().to_i64()) {
+                (Some(n), Some(d)) => (n, d).hash(state),
+                _ => r.hash(state),
+            },
+        }
+    }
+}
+
 impl From<i32> for Rational {
     #[inline]
     fn from(value: i32) -> Self {
@@ -215,6 +246,50 @@ impl Rational {
         Rational::Large(r)
     }
 
+    /// Create a rational number from a 128-bit numerator and a positive denominator,
+    /// which need not be coprime. The result is only allocated if it does not fit in 64 bits.
+    pub fn from_i128(num: i128, den: i128) -> Rational {
+        let g = utils::gcd_signed_i128(num, den) as i128;
+        let (num, den) = (num / g, den / g);
+
+        match (i64::try_from(num), i64::try_from(den)) {
+            (Ok(n), Ok(d)) => Rational::Natural(n, d),
+            _ => Rational::Large(MultiPrecisionRational::from((num, den))),
+        }
+    }
+
+    /// Add `n1/d1` and `n2/d2`, with positive denominators, using 128-bit intermediate
+    /// arithmetic. Returns `None` if an intermediate result overflows.
+    pub(crate) fn add_i128(n1: i128, d1: i128, n2: i128, d2: i128) -> Option<Rational> {
+        let g = utils::gcd_signed_i128(d1, d2) as i128;
+        let lcm = d1.checked_mul(d2 / g)?;
+        let num = n1
+            .checked_mul(lcm / d1)?
+            .checked_add(n2.checked_mul(lcm / d2)?)?;
+        Some(Rational::from_i128(num, lcm))
+    }
+
+    /// Multiply `n1/d1` and `n2/d2`, with positive denominators, using 128-bit intermediate
+    /// arithmetic. Returns `None` if an intermediate result overflows.
+    pub(crate) fn mul_i128(n1: i128, d1: i128, n2: i128, d2: i128) -> Option<Rational> {
+        let g1 = utils::gcd_signed_i128(n1, d2).max(1) as i128;
+        let g2 = utils::gcd_signed_i128(n2, d1).max(1) as i128;
+        let num = (n1 / g1).checked_mul(n2 / g2)?;
+        let den = (d1 / g2).checked_mul(d2 / g1)?;
+        Some(Rational::from_i128(num, den))
+    }
+
+    /// Compare `n1/d1` and `n2/d2`, with positive denominators, using 128-bit
+    /// arithmetic. Returns `None` if a cross product overflows.
+    pub(crate) fn cmp_i128(n1: i128, d1: i128, n2: i128, d2: i128) -> Option<std::cmp::Ordering> {
+        let (s1, s2) = (n1.signum(), n2.signum());
+        if s1 != s2 {
+            return Some(s1.cmp(&s2));
+        }
+
+        Some(n1.checked_mul(d2)?.cmp(&n2.checked_mul(d1)?))
+    }
+
     pub fn from_finite_field_u32(field: Zp, element: &<Zp as Ring>::Element) -> Rational {
         Rational::Natural(field.from_element(element) as i64, 1)
     }
@@ -355,6 +430,33 @@ impl Rational {
         }
     }
 
+    /// Compute the continued fraction `[a0; a1, ..., an]` of the rational number,
+    /// such that `self = a0 + 1/(a1 + 1/(... + 1/an))`. The first coefficient is the floor
+    /// of the number and all other coefficients are positive.
+    pub fn continued_fraction(&self) -> Vec<Integer> {
+        let (mut n, mut d) = (self.numerator(), self.denominator());
+        let mut res = vec![];
+        while !d.is_zero() {
+            let (q, r) = Z.quot_rem(&n, &d);
+            res.push(q);
+            (n, d) = (d, r);
+        }
+        res
+    }
+
+    /// Create a rational number from its continued fraction `[a0; a1, ..., an]`.
+    pub fn from_continued_fraction(coefficients: &[Integer]) -> Rational {
+        let Some((last, rest)) = coefficients.split_last() else {
+            panic!("A continued fraction needs at least one coefficient");
+        };
+
+        let (mut n, mut d) = (last.clone(), Integer::one());
+        for a in rest.iter().rev() {
+            (n, d) = (&(a * &n) + &d, n);
+        }
+        (n, d).into()
+    }
+
     /// Return a best approximation of the rational number where the denominator
     /// is less than or equal to `max_denominator`.
     pub fn truncate_denominator(&self, max_denominator: &Integer) -> Rational {
@@ -585,10 +687,14 @@ impl Ring for RationalField {
                         }
                     }
                 }
-                Rational::from_large(
-                    MultiPrecisionRational::from((*n1, *d1))
-                        + MultiPrecisionRational::from((*n2, *d2)),
-                )
+                // the intermediate results of two 64-bit fractions almost always fit in 128 bits
+                Rational::add_i128(*n1 as i128, *d1 as i128, *n2 as i128, *d2 as i128)
+                    .unwrap_or_else(|| {
+                        Rational::from_large(
+                            MultiPrecisionRational::from((*n1, *d1))
+                                + MultiPrecisionRational::from((*n2, *d2)),
+                        )
+                    })
             }
             (Rational::Natural(n1, d1), Rational::Large(r2))
             | (Rational::Large(r2), Rational::Natural(n1, d1)) => {
@@ -606,6 +712,15 @@ impl Ring for RationalField {
 
     fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
         match (a, b) {
+            (Rational::Natural(n1, 1), Rational::Natural(n2, 1)) => {
+                // integers are already canonical, so no gcds are needed
+                match n1.checked_mul(*n2) {
+                    Some(nn) => Rational::Natural(nn, 1),
+                    None => {
+                        Rational::Large(MultiPrecisionRational::from(*n1 as i128 * *n2 as i128))
+                    }
+                }
+            }
             (Rational::Natural(n1, d1), Rational::Natural(n2, d2)) => {
                 let gcd1 = utils::gcd_signed(*n1, *d2);
                 let (n1, d2) = if gcd1 == i64::MAX as u64 + 1 {
@@ -624,15 +739,9 @@ impl Ring for RationalField {
                 match (n2).checked_mul(n1) {
                     Some(nn) => match (d1).checked_mul(d2) {
                         Some(nd) => Rational::Natural(nn, nd),
-                        None => Rational::Large(MultiPrecisionRational::from((
-                            nn,
-                            MultiPrecisionInteger::from(d1) * MultiPrecisionInteger::from(d2),
-                        ))),
+                        None => Rational::from_i128(nn as i128, d1 as i128 * d2 as i128),
                     },
-                    None => Rational::Large(MultiPrecisionRational::from((
-                        MultiPrecisionInteger::from(n1) * MultiPrecisionInteger::from(n2),
-                        MultiPrecisionInteger::from(d1) * MultiPrecisionInteger::from(d2),
-                    ))),
+                    None => Rational::from_i128(n1 as i128 * n2 as i128, d1 as i128 * d2 as i128),
                 }
             }
             (Rational::Natural(n1, d1), Rational::Large(r2))
@@ -780,8 +889,49 @@ impl EuclideanDomain for RationalField {
 
 impl Field for RationalField {
     fn div(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
-        // TODO: optimize
-        self.mul(a, &self.inv(b))
+        if b.is_zero() {
+            panic!("Division by zero: cannot divide {} by 0", a);
+        }
+
+        match (a, b) {
+            (Rational::Natural(n1, d1), Rational::Natural(n2, d2)) => {
+                if *n1 == 0 {
+                    return Rational::zero();
+                }
+
+                // cross-multiply after removing the common factors of the
+                // numerators and the denominators, which keeps the result canonical
+                let gcd1 = utils::gcd_signed(*n1, *n2);
+                let gcd2 = utils::gcd_signed(*d1, *d2);
+
+                if gcd1 <= i64::MAX as u64 && gcd2 <= i64::MAX as u64 {
+                    let (n1, n2) = (n1 / gcd1 as i64, n2 / gcd1 as i64);
+                    let (d1, d2) = (d1 / gcd2 as i64, d2 / gcd2 as i64);
+
+                    if let (Some(nn), Some(nd)) = (n1.checked_mul(d2), d1.checked_mul(n2)) {
+                        if nd > 0 {
+                            return Rational::Natural(nn, nd);
+                        }
+
+                        if let (Some(nn), Some(nd)) = (nn.checked_neg(), nd.checked_neg()) {
+                            return Rational::Natural(nn, nd);
+                        }
+                    }
+                }
+
+                Rational::from_large(MultiPrecisionRational::from((
+                    MultiPrecisionInteger::from(*n1) * MultiPrecisionInteger::from(*d2),
+                    MultiPrecisionInteger::from(*d1) * MultiPrecisionInteger::from(*n2),
+                )))
+            }
+            (Rational::Natural(n1, d1), Rational::Large(r2)) => {
+                Rational::from_large(MultiPrecisionRational::from((*n1, *d1)) / r2)
+            }
+            (Rational::Large(r1), Rational::Natural(n2, d2)) => {
+                Rational::from_large(r1 / MultiPrecisionRational::from((*n2, *d2)))
+            }
+            (Rational::Large(r1), Rational::Large(r2)) => Rational::from_large((r1 / r2).into()),
+        }
     }
 
     fn div_assign(&self, a: &mut Self::Element, b: &Self::Element) {
@@ -795,8 +945,12 @@ impl Field for RationalField {
 
 impl PartialOrd for Rational {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if let (Rational::Large(n1), Rational::Large(n2)) = (self, other) {
-            return n1.partial_cmp(n2);
+        match (self, other) {
+            (Rational::Large(n1), Rational::Large(n2)) => return n1.partial_cmp(n2),
+            (Rational::Natural(n1, d1), Rational::Natural(n2, d2)) => {
+                return Rational::cmp_i128(*n1 as i128, *d1 as i128, *n2 as i128, *d2 as i128);
+            }
+            _ => {}
         }
 
         let a = &self.numerator() * &other.denominator();
@@ -806,6 +960,68 @@ impl PartialOrd for Rational {
     }
 }
 
+/// A rational number with a precomputed floating point approximation, which
+/// speeds up repeated comparisons of large rationals, for example when sorting.
+/// The approximation is only used to order numbers whose approximations differ;
+/// otherwise the exact comparison is used.
+#[derive(Clone, Debug)]
+pub struct SortableRational {
+    value: Rational,
+    approx: Option<f64>,
+}
+
+impl SortableRational {
+    pub fn new(value: Rational) -> SortableRational {
+        // the conversion of rug rounds towards zero and is therefore monotonic
+        let approx = match &value {
+            Rational::Natural(_, _) => None,
+            Rational::Large(r) => Some(r.to_f64()),
+        };
+
+        SortableRational { value, approx }
+    }
+
+    pub fn get(&self) -> &Rational {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> Rational {
+        self.value
+    }
+}
+
+impl From<Rational> for SortableRational {
+    fn from(value: Rational) -> Self {
+        SortableRational::new(value)
+    }
+}
+
+impl PartialEq for SortableRational {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for SortableRational {}
+
+impl PartialOrd for SortableRational {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortableRational {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if let (Some(a), Some(b)) = (self.approx, other.approx) {
+            if a != b {
+                return a.partial_cmp(&b).unwrap();
+            }
+        }
+
+        self.value.partial_cmp(&other.value).unwrap()
+    }
+}
+
 impl Add<Rational> for Rational {
     type Output = Rational;
 
@@ -962,3 +1178,148 @@ impl<'a> std::iter::Sum<&'a Self> for Rational {
         iter.fold(Rational::zero(), |a, b| a + b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    };
+
+    use rug::Rational as MultiPrecisionRational;
+
+    use super::{Rational, SortableRational};
+
+    #[test]
+    fn eq_value() {
+        let hash = |r: &Rational| {
+            let mut h = DefaultHasher::new();
+            r.hash(&mut h);
+            h.finish()
+        };
+
+        let small = Rational::Large(MultiPrecisionRational::from((-5, 3)));
+        assert_eq!(small, Rational::Natural(-5, 3));
+        assert_eq!(Rational::Natural(-5, 3), small);
+        assert_eq!(hash(&small), hash(&Rational::Natural(-5, 3)));
+        assert_ne!(small, Rational::Natural(5, 3));
+        assert_eq!(Rational::from(7).pow(0), Rational::Large(7.into()).pow(0));
+
+        let big = MultiPrecisionRational::from(i64::MAX) * 3i32;
+        let a = Rational::from_large(big.clone() / 7);
+        let b = Rational::from_large((big.clone() * 2) / 14);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(a, Rational::from_large(big.clone() / -7));
+        assert_ne!(a, Rational::from_large(big.clone() / 11));
+        assert_ne!(a, Rational::from_large((big + 1) / 7));
+    }
+
+    #[test]
+    fn sortable() {
+        let big = MultiPrecisionRational::from(i64::MAX);
+        let mut values = vec![];
+        for i in 0..1000i64 {
+            let r = (big.clone() * ((i * 7919) % 1009 - 500)
+                + MultiPrecisionRational::from((i % 3, 1 + i % 5)))
+                / (1 + i % 4);
+            values.push(Rational::from_large(r));
+            values.push(Rational::Natural(i % 13 - 6, 1 + i % 7));
+        }
+
+        let mut sorted = values
+            .iter()
+            .cloned()
+            .map(SortableRational::new)
+            .collect::<Vec<_>>();
+        sorted.sort();
+
+        let mut exact = values;
+        exact.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert!(sorted
+            .into_iter()
+            .map(|x| x.into_inner())
+            .eq(exact.into_iter()));
+    }
+
+    #[test]
+    fn div() {
+        assert_eq!(
+            Rational::Natural(3, 4) / &Rational::Natural(1, 2),
+            Rational::Natural(3, 2)
+        );
+        assert_eq!(
+            Rational::Natural(3, 4) / &Rational::Natural(-9, 2),
+            Rational::Natural(-1, 6)
+        );
+        assert_eq!(
+            Rational::Natural(0, 1) / &Rational::Natural(-9, 2),
+            Rational::Natural(0, 1)
+        );
+        assert_eq!(
+            Rational::Natural(i64::MAX, 1) / &Rational::Natural(1, 2),
+            Rational::Large(MultiPrecisionRational::from(i64::MAX) * 2)
+        );
+        assert_eq!(
+            Rational::Natural(i64::MIN, 1) / &Rational::Natural(-1, 1),
+            Rational::Large(-MultiPrecisionRational::from(i64::MIN))
+        );
+        assert_eq!(
+            Rational::Large(MultiPrecisionRational::from(i64::MAX) * 2) / &Rational::Natural(2, 1),
+            Rational::Natural(i64::MAX, 1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn div_by_zero() {
+        let _ = Rational::Natural(3, 4) / &Rational::Natural(0, 1);
+    }
+
+    #[test]
+    fn mul() {
+        assert_eq!(
+            Rational::Natural(6, 1) * &Rational::Natural(7, 1),
+            Rational::Natural(42, 1)
+        );
+        assert_eq!(
+            Rational::Natural(2, 3) * &Rational::Natural(3, 4),
+            Rational::Natural(1, 2)
+        );
+        assert_eq!(
+            Rational::Natural(i64::MAX, 1) * &Rational::Natural(2, 1),
+            Rational::Large(MultiPrecisionRational::from(i64::MAX) * 2)
+        );
+        assert_eq!(
+            Rational::Natural(-3, 1) * &Rational::Natural(0, 1),
+            Rational::Natural(0, 1)
+        );
+    }
+
+    #[test]
+    fn i128_fast_path() {
+        let a = Rational::Natural(i64::MAX, 3);
+        let b = Rational::Natural(i64::MAX - 1, 5);
+        let big = |r: &Rational| match r {
+            Rational::Natural(n, d) => MultiPrecisionRational::from((*n, *d)),
+            Rational::Large(r) => r.clone(),
+        };
+
+        assert_eq!(big(&(&a + &b)), big(&a) + big(&b));
+        assert_eq!(big(&(&a * &b)), big(&a) * big(&b));
+        assert!(a > b);
+        assert!(Rational::Natural(-1, i64::MAX) < Rational::Natural(1, i64::MAX));
+
+        // an intermediate overflow that cancels is not promoted
+        let c = Rational::Natural(i64::MAX, 2) + Rational::Natural(-i64::MAX, 2);
+        assert_eq!(c, Rational::Natural(0, 1));
+
+        assert_eq!(Rational::from_i128(6, 4), Rational::Natural(3, 2));
+        assert_eq!(
+            Rational::from_i128(i64::MAX as i128 * 2, 3),
+            Rational::Large(MultiPrecisionRational::from((i64::MAX as i128 * 2, 3)))
+        );
+        assert_eq!(Rational::mul_i128(i128::MAX, 1, 2, 1), None);
+    }
+}
@@ -47,6 +47,11 @@ impl<R: Ring, E: Exponent> RationalPolynomialField<R, E> {
             _phantom_exp: PhantomData,
         }
     }
+
+    /// Get the variables of the rational functions in this field.
+    pub fn get_variables(&self) -> &Arc<Vec<Variable>> {
+        &self.var_map
+    }
 }
 
 pub trait FromNumeratorAndDenominator<R: Ring, OR: Ring, E: Exponent> {
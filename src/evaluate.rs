@@ -1,12 +1,37 @@
+use std::sync::OnceLock;
+
 use ahash::HashMap;
+use rug::Integer as MultiPrecisionInteger;
 
 use crate::{
-    coefficient::CoefficientView,
-    domains::{float::Real, rational::Rational},
-    representations::{Atom, AtomView, Symbol},
+    coefficient::{Coefficient, CoefficientView, ConvertToRing},
+    domains::{
+        float::{Float, Real},
+        rational::{Rational, Q},
+    },
+    representations::{Atom, AtomView, FunctionBuilder, Symbol},
     state::State,
 };
 
+/// The rational approximation of a built-in constant, accurate to 128 bits.
+fn builtin_constant(name: Symbol) -> Option<&'static Rational> {
+    static CONSTANTS: OnceLock<[Rational; 3]> = OnceLock::new();
+    let c = CONSTANTS.get_or_init(|| {
+        [
+            Float::pi(128).to_rational(),
+            Float::e(128).to_rational(),
+            Float::euler_gamma(128).to_rational(),
+        ]
+    });
+
+    match name {
+        State::PI => Some(&c[0]),
+        State::E => Some(&c[1]),
+        State::EULER_GAMMA => Some(&c[2]),
+        _ => None,
+    }
+}
+
 type EvalFnType<T> = Box<
     dyn Fn(
         &[T],
@@ -29,6 +54,179 @@ impl<T> EvaluationFn<T> {
     }
 }
 
+/// The precision of the radius of a [`Ball`].
+const RAD_PREC: u32 = 30;
+
+/// A float `mid` with an absolute error of at most `rad`. Every operation adds the
+/// rounding error of the midpoint to the radius. The radius itself is computed with
+/// low precision and slightly enlarged to make up for its own rounding.
+struct Ball {
+    mid: Float,
+    rad: Float,
+}
+
+impl Ball {
+    fn zero(prec: u32) -> Ball {
+        Ball {
+            mid: Float::zero(prec),
+            rad: Float::zero(RAD_PREC),
+        }
+    }
+
+    fn from_rational(r: &Rational, prec: u32) -> Ball {
+        let mid = Float::from_rational(r, prec);
+        if &mid.to_rational() == r {
+            Ball {
+                mid,
+                rad: Float::zero(RAD_PREC),
+            }
+        } else {
+            Ball::rounded(mid, Float::zero(RAD_PREC))
+        }
+    }
+
+    fn from_float(f: &Float, prec: u32) -> Ball {
+        let mid = f.with_prec(prec);
+        if &mid == f {
+            Ball {
+                mid,
+                rad: Float::zero(RAD_PREC),
+            }
+        } else {
+            Ball::rounded(mid, Float::zero(RAD_PREC))
+        }
+    }
+
+    /// Create a ball from a rounded midpoint with an error of at most
+    /// four units in the last place, and a propagated error `rad`.
+    fn rounded(mid: Float, rad: Float) -> Ball {
+        if mid.is_zero() {
+            return Ball {
+                mid,
+                rad: enlarge(&rad),
+            };
+        }
+
+        let ulp = Float::from_parts(
+            MultiPrecisionInteger::from(1),
+            mid.magnitude() - mid.prec() as i64 + 2,
+            RAD_PREC,
+        );
+        Ball {
+            rad: enlarge(&rad.add(&ulp)),
+            mid,
+        }
+    }
+
+    fn add(&self, other: &Ball) -> Ball {
+        Ball::rounded(self.mid.add(&other.mid), self.rad.add(&other.rad))
+    }
+
+    fn mul(&self, other: &Ball) -> Ball {
+        let a = self.mid.abs().with_prec(RAD_PREC);
+        let b = other.mid.abs().with_prec(RAD_PREC);
+        let rad = enlarge(&a.mul(&other.rad))
+            .add(&enlarge(&b.mul(&self.rad)))
+            .add(&enlarge(&self.rad.mul(&other.rad)));
+        Ball::rounded(self.mid.mul(&other.mid), rad)
+    }
+
+    fn powi(&self, mut e: u64) -> Ball {
+        let mut base = Ball {
+            mid: self.mid.clone(),
+            rad: self.rad.clone(),
+        };
+        let mut res = Ball::from_rational(&Rational::one(), self.mid.prec());
+        while e > 0 {
+            if e & 1 == 1 {
+                res = res.mul(&base);
+            }
+            e >>= 1;
+            if e > 0 {
+                base = base.mul(&base);
+            }
+        }
+        res
+    }
+
+    /// The distance of the ball to zero, or an error if it contains zero.
+    fn distance_to_zero(&self) -> Result<Float, String> {
+        let d = self.mid.abs().with_prec(RAD_PREC).sub(&enlarge(&self.rad));
+        if d.is_negative() || d.is_zero() {
+            Err(format!(
+                "Cannot distinguish {} with error {} from zero",
+                self.mid, self.rad
+            ))
+        } else {
+            Ok(d)
+        }
+    }
+
+    fn inv(&self) -> Result<Ball, String> {
+        // |1/x - 1/m| <= r / (|m| (|m| - r))
+        let d = self.distance_to_zero()?;
+        let rad = self.rad.div(&d.mul(&self.mid.abs().with_prec(RAD_PREC)));
+        Ok(Ball::rounded(self.mid.inv(), enlarge(&rad)))
+    }
+
+    fn exp(&self) -> Result<Ball, String> {
+        // |e^x - e^m| <= e^m (e^r - 1)
+        let mid = self.mid.exp();
+        let one = Float::from_parts(MultiPrecisionInteger::from(1), 0, RAD_PREC);
+        let growth = enlarge(&self.rad.exp().sub(&one));
+        let rad = enlarge(&mid.abs().with_prec(RAD_PREC).mul(&growth));
+        if !mid.is_finite() || !rad.is_finite() {
+            return Err(format!("Overflow in exp({})", self.mid));
+        }
+
+        Ok(Ball::rounded(mid, rad))
+    }
+
+    fn log(&self) -> Result<Ball, String> {
+        // |log(x) - log(m)| <= r / (m - r)
+        if self.mid.is_negative() {
+            return Err(format!(
+                "Cannot take the logarithm of the negative number {}",
+                self.mid
+            ));
+        }
+        let d = self.distance_to_zero()?;
+        Ok(Ball::rounded(self.mid.log(), enlarge(&self.rad.div(&d))))
+    }
+
+    fn sin_cos(&self, cos: bool) -> Ball {
+        let mid = if cos { self.mid.cos() } else { self.mid.sin() };
+        Ball::rounded(mid, self.rad.clone())
+    }
+
+    fn sqrt(&self) -> Result<Ball, String> {
+        // |sqrt(x) - sqrt(m)| <= r / (sqrt(m - r) + sqrt(m))
+        if self.mid.is_zero() && self.rad.is_zero() {
+            return Ok(Ball::zero(self.mid.prec()));
+        }
+        if self.mid.is_negative() {
+            return Err(format!(
+                "Cannot take the square root of the negative number {}",
+                self.mid
+            ));
+        }
+        let d = self.distance_to_zero()?;
+        Ok(Ball::rounded(
+            self.mid.root(2),
+            enlarge(&self.rad.div(&d.root(2))),
+        ))
+    }
+}
+
+/// Enlarge a radius to account for its rounding.
+fn enlarge(r: &Float) -> Float {
+    r.mul(&Float::from_parts(
+        MultiPrecisionInteger::from((1 << (RAD_PREC - 2)) + 1),
+        -(RAD_PREC as i64 - 2),
+        RAD_PREC,
+    ))
+}
+
 impl Atom {
     /// Evaluate an expression using a constant map and a function map.
     /// The constant map can map any literal expression to a value, for example
@@ -43,9 +241,193 @@ impl Atom {
     ) -> T {
         self.as_view().evaluate(const_map, function_map, cache)
     }
+
+    /// Substitute the numerical values of `bindings` for the corresponding variables
+    /// and fold the result into a single number. If the result is not a number,
+    /// for example because a variable is not bound, `None` is returned.
+    pub fn eval_to_number(&self, bindings: &[(Symbol, Coefficient)]) -> Option<Coefficient> {
+        self.as_view().eval_to_number(bindings)
+    }
+
+    /// Evaluate the expression with floats of `prec` bits, using the values
+    /// in `const_map` for the variables. Returns the value and a bound on its absolute error,
+    /// which can be used to verify the result of an `f64` evaluation.
+    pub fn eval_with_prec(
+        &self,
+        const_map: &HashMap<Symbol, Float>,
+        prec: u32,
+    ) -> Result<(Float, Float), String> {
+        self.as_view().eval_with_prec(const_map, prec)
+    }
+
+    /// Remove additive terms whose rational coefficient is smaller than `threshold`
+    /// in absolute value and round coefficients that are within `threshold` of an
+    /// integer to that integer. Finite field and rational polynomial coefficients are kept.
+    pub fn chop(&self, threshold: f64) -> Atom {
+        self.as_view().chop(threshold)
+    }
 }
 
 impl<'a> AtomView<'a> {
+    /// Substitute the numerical values of `bindings` for the corresponding variables
+    /// and fold the result into a single number. If the result is not a number,
+    /// for example because a variable is not bound, `None` is returned.
+    pub fn eval_to_number(&self, bindings: &[(Symbol, Coefficient)]) -> Option<Coefficient> {
+        let mut expr = self.to_owned();
+        for (s, v) in bindings {
+            let pat = Atom::new_var(*s).into_pattern();
+            let rhs = Atom::new_num(v.clone()).into_pattern();
+            expr = pat.replace_all(expr.as_view(), &rhs, None, None);
+        }
+
+        if let AtomView::Num(n) = expr.as_view() {
+            Some(n.get_coeff_view().to_owned())
+        } else {
+            None
+        }
+    }
+
+    /// Remove additive terms whose rational coefficient is smaller than `threshold`
+    /// in absolute value and round coefficients that are within `threshold` of an
+    /// integer to that integer. Finite field and rational polynomial coefficients are kept.
+    pub fn chop(&self, threshold: f64) -> Atom {
+        match self {
+            AtomView::Num(n) => {
+                let c = n.get_coeff_view();
+                if !matches!(c, CoefficientView::Natural(..) | CoefficientView::Large(_)) {
+                    return self.to_owned();
+                }
+
+                let f = c.to_f64();
+                let r = f.round();
+                if f.abs() < threshold {
+                    Atom::new_num(0)
+                } else if !c.is_integer() && (f - r).abs() < threshold && r.abs() < 2f64.powi(53) {
+                    Atom::new_num(r as i64)
+                } else {
+                    self.to_owned()
+                }
+            }
+            AtomView::Var(_) => self.to_owned(),
+            AtomView::Fun(f) => {
+                let mut fb = FunctionBuilder::new(f.get_symbol());
+                for arg in f.iter() {
+                    fb = fb.add_arg(&arg.chop(threshold));
+                }
+                fb.finish()
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.chop(threshold).pow(exp)
+            }
+            AtomView::Mul(m) => {
+                let mut res = Atom::new_num(1);
+                for f in m.iter() {
+                    res = res * &f.chop(threshold);
+                }
+                res
+            }
+            AtomView::Add(a) => {
+                let mut res = Atom::new_num(0);
+                for t in a.iter() {
+                    res = res + &t.chop(threshold);
+                }
+                res
+            }
+        }
+    }
+
+    /// Evaluate the expression with floats of `prec` bits, using the values
+    /// in `const_map` for the variables. Returns the value and a bound on its absolute error,
+    /// which can be used to verify the result of an `f64` evaluation.
+    ///
+    /// The values in `const_map` are considered exact. An error is returned if a variable
+    /// is missing, if the expression contains an unsupported function or if the argument
+    /// of a division or logarithm cannot be distinguished from zero at this precision.
+    pub fn eval_with_prec(
+        &self,
+        const_map: &HashMap<Symbol, Float>,
+        prec: u32,
+    ) -> Result<(Float, Float), String> {
+        let b = self.eval_ball(const_map, prec)?;
+        if !b.mid.is_finite() || !b.rad.is_finite() {
+            return Err(format!("Overflow in the evaluation of {}", self));
+        }
+
+        Ok((b.mid, b.rad))
+    }
+
+    fn eval_ball(&self, const_map: &HashMap<Symbol, Float>, prec: u32) -> Result<Ball, String> {
+        match self {
+            AtomView::Num(n) => match n.get_coeff_view() {
+                c @ (CoefficientView::Natural(..) | CoefficientView::Large(_)) => Ok(
+                    Ball::from_rational(&Q.element_from_coefficient_view(c), prec),
+                ),
+                CoefficientView::Float(f) => Ok(Ball::from_float(&f.to_float(), prec)),
+                _ => Err(format!("Cannot evaluate the coefficient {}", self)),
+            },
+            AtomView::Var(v) => {
+                let name = v.get_symbol();
+                if let Some(c) = const_map.get(&name) {
+                    return Ok(Ball::from_float(c, prec));
+                }
+
+                let c = match name {
+                    State::PI => Float::pi(prec),
+                    State::E => Float::e(prec),
+                    State::EULER_GAMMA => Float::euler_gamma(prec),
+                    _ => return Err(format!("Variable {} not in constant map", self)),
+                };
+                Ok(Ball::rounded(c, Float::zero(RAD_PREC)))
+            }
+            AtomView::Fun(f) => {
+                let name = f.get_symbol();
+                if ![State::EXP, State::LOG, State::SIN, State::COS, State::SQRT].contains(&name)
+                    || f.get_nargs() != 1
+                {
+                    return Err(format!("Cannot evaluate the function {}", self));
+                }
+
+                let arg = f.iter().next().unwrap().eval_ball(const_map, prec)?;
+                match name {
+                    State::EXP => arg.exp(),
+                    State::LOG => arg.log(),
+                    State::SIN | State::COS => Ok(arg.sin_cos(name == State::COS)),
+                    State::SQRT => arg.sqrt(),
+                    _ => unreachable!(),
+                }
+            }
+            AtomView::Pow(p) => {
+                let (b, e) = p.get_base_exp();
+                let b_eval = b.eval_ball(const_map, prec)?;
+
+                if let AtomView::Num(n) = e {
+                    if let CoefficientView::Natural(num, 1) = n.get_coeff_view() {
+                        let r = b_eval.powi(num.unsigned_abs());
+                        return if num < 0 { r.inv() } else { Ok(r) };
+                    }
+                }
+
+                let e_eval = e.eval_ball(const_map, prec)?;
+                e_eval.mul(&b_eval.log()?).exp()
+            }
+            AtomView::Mul(m) => {
+                let mut r = Ball::from_rational(&Rational::one(), prec);
+                for arg in m.iter() {
+                    r = r.mul(&arg.eval_ball(const_map, prec)?);
+                }
+                Ok(r)
+            }
+            AtomView::Add(a) => {
+                let mut r = Ball::zero(prec);
+                for arg in a.iter() {
+                    r = r.add(&arg.eval_ball(const_map, prec)?);
+                }
+                Ok(r)
+            }
+        }
+    }
+
     /// Evaluate an expression using a constant map and a function map.
     /// The constant map can map any literal expression to a value, for example
     /// a variable or a function with fixed arguments.
@@ -71,10 +453,55 @@ impl<'a> AtomView<'a> {
                 CoefficientView::RationalPolynomial(_) => unimplemented!(
                     "Rational polynomial coefficient not yet supported for evaluation"
                 ),
+                CoefficientView::Float(f) => (&f.to_float().to_rational()).into(),
+                CoefficientView::Complex(c) => {
+                    let i = Atom::new_num(Coefficient::i());
+                    let Some(i) = const_map.get(&i.as_view()) else {
+                        panic!("The imaginary unit is not in the constant map");
+                    };
+
+                    let part = |p: CoefficientView| -> T {
+                        match p {
+                            CoefficientView::Float(f) => (&f.to_float().to_rational()).into(),
+                            _ => (&Q.element_from_coefficient_view(p)).into(),
+                        }
+                    };
+                    part(c.re()) + part(c.im()) * *i
+                }
             },
-            AtomView::Var(v) => panic!("Variable id {:?} not in constant map", v.get_symbol()),
+            AtomView::Var(v) => {
+                let name = v.get_symbol();
+                if let Some(c) = builtin_constant(name) {
+                    return c.into();
+                }
+                panic!("Variable id {:?} not in constant map", name)
+            }
             AtomView::Fun(f) => {
                 let name = f.get_symbol();
+                if name == State::PIECEWISE {
+                    // evaluate the value of the first condition that is positive
+                    let mut args = f.iter();
+                    while let Some(cond) = args.next() {
+                        let Some(value) = args.next() else {
+                            return cond.evaluate(const_map, function_map, cache);
+                        };
+
+                        match cond
+                            .evaluate(const_map, function_map, cache)
+                            .satisfies_condition()
+                        {
+                            Some(true) => return value.evaluate(const_map, function_map, cache),
+                            Some(false) => {}
+                            None => panic!(
+                                "Cannot decide the condition {} of a piecewise function for this number type",
+                                cond
+                            ),
+                        }
+                    }
+
+                    panic!("No condition of piecewise function is satisfied");
+                }
+
                 if [State::EXP, State::LOG, State::SIN, State::COS, State::SQRT].contains(&name) {
                     assert!(f.get_nargs() == 1);
                     let arg = f.iter().next().unwrap();
@@ -143,3 +570,162 @@ impl<'a> AtomView<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMap;
+
+    use std::str::FromStr;
+
+    use crate::{
+        coefficient::Coefficient,
+        domains::{
+            float::Float,
+            integer::Integer,
+            rational::{Rational, Q},
+            Ring,
+        },
+        representations::Atom,
+        state::State,
+    };
+
+    #[test]
+    fn eval_to_number() {
+        let x = State::get_symbol("etn_x");
+        let a = Atom::parse("etn_x^2 + 1").unwrap();
+        assert_eq!(
+            a.eval_to_number(&[(x, Coefficient::from(3))]),
+            Some(Coefficient::from(10))
+        );
+        assert_eq!(
+            a.eval_to_number(&[(x, Coefficient::from((1, 2)))]),
+            Some(Coefficient::from((5, 4)))
+        );
+
+        let b = Atom::parse("etn_x + etn_y").unwrap();
+        assert_eq!(b.eval_to_number(&[(x, Coefficient::from(3))]), None);
+
+        let c = Atom::parse("etn_f(etn_x)").unwrap();
+        assert_eq!(c.eval_to_number(&[(x, Coefficient::from(3))]), None);
+    }
+
+    #[test]
+    fn piecewise() {
+        let x = Atom::parse("pw_x").unwrap();
+        let a = Atom::parse("piecewise(pw_x, 1, -1)").unwrap();
+
+        for (v, r) in [(2., 1.), (-3., -1.), (0., -1.)] {
+            let mut const_map = HashMap::default();
+            const_map.insert(x.as_view(), v);
+            let e: f64 = a.evaluate(&const_map, &HashMap::default(), &mut HashMap::default());
+            assert_eq!(e, r);
+        }
+
+        let b = Atom::parse("piecewise(pw_x - 1, pw_x^2, 2 - pw_x, pw_x)").unwrap();
+        let mut const_map = HashMap::default();
+        const_map.insert(x.as_view(), 1.5);
+        let e: f64 = b.evaluate(&const_map, &HashMap::default(), &mut HashMap::default());
+        assert_eq!(e, 2.25);
+
+        assert_eq!(Atom::parse(&a.to_string()).unwrap(), a);
+
+        // branches with numerical conditions are simplified
+        assert_eq!(
+            Atom::parse("piecewise(-1, pw_a, 2, pw_b, pw_c)").unwrap(),
+            Atom::parse("pw_b").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("piecewise(0, pw_a, pw_x, pw_b, 1/2, pw_c, pw_d)").unwrap(),
+            Atom::parse("piecewise(pw_x, pw_b, pw_c)").unwrap()
+        );
+    }
+
+    #[test]
+    fn chop() {
+        let a = Atom::parse("chop_x + 10^-20*chop_y").unwrap();
+        assert_eq!(a.chop(1e-10), Atom::parse("chop_x").unwrap());
+
+        let a = Atom::parse("20000000001/10000000000*chop_x").unwrap();
+        assert_eq!(a.chop(1e-8), Atom::parse("2*chop_x").unwrap());
+
+        let a = Atom::parse("f(1/3*chop_x + 10^-30)^2").unwrap();
+        assert_eq!(a.chop(1e-10), Atom::parse("f(1/3*chop_x)^2").unwrap());
+        assert_eq!(a.chop(1e-40), a);
+    }
+
+    #[test]
+    fn builtin_constants() {
+        let digits = [
+            (
+                Float::pi(256),
+                "314159265358979323846264338327950288419716939937510",
+            ),
+            (
+                Float::e(256),
+                "271828182845904523536028747135266249775724709369995",
+            ),
+            (
+                Float::euler_gamma(256),
+                "57721566490153286060651209008240243104215933593992",
+            ),
+        ];
+
+        let scale = Rational::from(Integer::from(10).pow(50));
+        for (f, d) in digits {
+            let exact = Integer::from_str(d).unwrap();
+            let diff = Q.sub(&Q.mul(&f.to_rational(), &scale), &exact.into());
+            assert!(diff.abs() < Rational::from(10), "{}: {}", d, diff);
+        }
+
+        let a = Atom::parse("𝜋^2/6 - 𝑒 + 𝛾").unwrap();
+        let r: f64 = a.evaluate(
+            &HashMap::default(),
+            &HashMap::default(),
+            &mut HashMap::default(),
+        );
+        assert!(
+            (r - (std::f64::consts::PI.powi(2) / 6. - std::f64::consts::E + 0.5772156649015329))
+                .abs()
+                < 1e-14
+        );
+    }
+
+    #[test]
+    fn eval_with_prec() {
+        let x = State::get_symbol("ewp_x");
+        let a =
+            Atom::parse("exp(ewp_x)*sin(ewp_x)^2 + log(1+ewp_x^2)/sqrt(ewp_x) - cos(𝜋*ewp_x)^-1")
+                .unwrap();
+
+        let mut const_map = HashMap::default();
+        const_map.insert(x, Float::from_f64(1.3, 53));
+        let (v1, e1) = a.eval_with_prec(&const_map, 200).unwrap();
+        let (v2, e2) = a.eval_with_prec(&const_map, 400).unwrap();
+        assert!(e1.to_f64() < 1e-55 && e2.to_f64() < 1e-115);
+        assert!(v1.sub(&v2).abs().to_f64() <= e1.to_f64() + e2.to_f64());
+
+        let v = 1.3f64;
+        let f = v.exp() * v.sin().powi(2) + (1. + v * v).ln() / v.sqrt()
+            - 1. / (std::f64::consts::PI * v).cos();
+        assert!((f - v1.to_f64()).abs() < 1e-13);
+
+        assert!(Atom::parse("ewp_y + 1")
+            .unwrap()
+            .eval_with_prec(&const_map, 100)
+            .is_err());
+        assert!(Atom::parse("log(ewp_x - 2)")
+            .unwrap()
+            .eval_with_prec(&const_map, 100)
+            .is_err());
+        for s in [
+            "exp(10^20*ewp_x)",
+            "exp(-exp(10^20*ewp_x))",
+            "(10^9*ewp_x)^(10^10)",
+        ] {
+            assert!(Atom::parse(s)
+                .unwrap()
+                .eval_with_prec(&const_map, 100)
+                .is_err());
+        }
+    }
+}
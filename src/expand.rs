@@ -3,11 +3,11 @@ use std::ops::DerefMut;
 use smallvec::SmallVec;
 
 use crate::{
-    coefficient::CoefficientView,
+    coefficient::{Coefficient, CoefficientView},
     combinatorics::CombinationWithReplacementIterator,
     domains::integer::Integer,
     representations::{Atom, AtomView},
-    state::{RecycledAtom, Workspace},
+    state::{RecycledAtom, State, Workspace},
 };
 
 impl Atom {
@@ -20,6 +20,22 @@ impl Atom {
     pub fn expand_into(&self, out: &mut Atom) -> bool {
         self.as_view().expand_into(out)
     }
+
+    /// Expand only the powers of sums with a natural exponent of at most `max_exponent`,
+    /// using the multinomial theorem. Products of sums are not expanded.
+    pub fn expand_powers(&self, max_exponent: u32) -> Atom {
+        self.as_view().expand_powers(max_exponent)
+    }
+
+    /// Write the expanded expression as a sum of products. See [`AtomView::to_sum_of_products`].
+    pub fn to_sum_of_products(&self) -> Vec<(Coefficient, Vec<(Atom, i64)>)> {
+        self.as_view().to_sum_of_products()
+    }
+
+    /// Remove square roots from denominators. See [`AtomView::rationalize_denominator`].
+    pub fn rationalize_denominator(&self) -> Atom {
+        self.as_view().rationalize_denominator()
+    }
 }
 
 impl<'a> AtomView<'a> {
@@ -50,6 +66,436 @@ impl<'a> AtomView<'a> {
         changed
     }
 
+    /// Write the expanded expression as a sum of products, where every term
+    /// is a coefficient and a sorted list of factors with their integer exponent.
+    /// Factors that are not variables, such as functions or powers with non-integer exponents,
+    /// are treated as atomic.
+    ///
+    /// For example, `3*x^2*y + x - 2` yields `[(3, [(x, 2), (y, 1)]), (1, [(x, 1)]), (-2, [])]`.
+    pub fn to_sum_of_products(&self) -> Vec<(Coefficient, Vec<(Atom, i64)>)> {
+        let expanded = self.expand();
+
+        match expanded.as_view() {
+            AtomView::Add(a) => a.iter().map(Self::to_product).collect(),
+            AtomView::Num(n) if n.is_zero() => vec![],
+            x => vec![Self::to_product(x)],
+        }
+    }
+
+    /// Write a term as a coefficient and a sorted list of factors with their integer exponent.
+    fn to_product(term: AtomView<'_>) -> (Coefficient, Vec<(Atom, i64)>) {
+        let mut coeff = Coefficient::one();
+        let mut factors: Vec<(Atom, i64)> = vec![];
+
+        let mut add_factor = |f: AtomView<'_>| match f {
+            AtomView::Num(n) => {
+                coeff = if coeff.is_one() {
+                    n.get_coeff_view().to_owned()
+                } else {
+                    std::mem::take(&mut coeff) * n.get_coeff_view().to_owned()
+                };
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                if let AtomView::Num(n) = exp {
+                    if let CoefficientView::Natural(e, 1) = n.get_coeff_view() {
+                        factors.push((base.to_owned(), e));
+                        return;
+                    }
+                }
+
+                factors.push((f.to_owned(), 1));
+            }
+            _ => factors.push((f.to_owned(), 1)),
+        };
+
+        if let AtomView::Mul(m) = term {
+            for f in m.iter() {
+                add_factor(f);
+            }
+        } else {
+            add_factor(term);
+        }
+
+        factors.sort_by(|a, b| a.0.as_view().cmp(&b.0.as_view()));
+        (coeff, factors)
+    }
+
+    /// Remove square roots from the denominators of every term, by multiplying
+    /// the numerator and denominator with the conjugate. Supported denominators are
+    /// `sqrt(c)` and `a + b*sqrt(c)`, where `a` and `b` contain no square roots. A numerical
+    /// power such as `2^(1/2)` is also treated as a square root. Terms that are
+    /// rewritten are expanded.
+    ///
+    /// For example, `1/(1+sqrt(2))` becomes `sqrt(2)-1` and `1/sqrt(3)` becomes `sqrt(3)/3`.
+    pub fn rationalize_denominator(&self) -> Atom {
+        match self {
+            AtomView::Add(a) => {
+                let mut res = Atom::new_num(0);
+                for t in a.iter() {
+                    res = res + &t.rationalize_term();
+                }
+                res
+            }
+            _ => self.rationalize_term(),
+        }
+    }
+
+    fn rationalize_term(&self) -> Atom {
+        let factors: Vec<_> = match self {
+            AtomView::Mul(m) => m.iter().collect(),
+            _ => vec![*self],
+        };
+
+        let mut changed = false;
+        let mut res = Atom::new_num(1);
+        for f in factors {
+            if let Some(r) = f.rationalize_factor() {
+                res = res * &r;
+                changed = true;
+            } else {
+                res = res * &f.to_owned();
+            }
+        }
+
+        if !changed {
+            return self.to_owned();
+        }
+
+        // simplify sqrt(c)^n that are created by the expansion
+        res.expand().map_subterms(
+            |a| {
+                if let AtomView::Pow(p) = a {
+                    if let (AtomView::Fun(_), AtomView::Num(n)) = p.get_base_exp() {
+                        return p.get_base().get_radicand().is_some()
+                            && matches!(n.get_coeff_view(), CoefficientView::Natural(n, 1) if n.abs() > 1);
+                    }
+                }
+                false
+            },
+            |a| {
+                let AtomView::Pow(p) = a else { unreachable!() };
+                let (base, exp) = p.get_base_exp();
+                let AtomView::Num(n) = exp else { unreachable!() };
+                let CoefficientView::Natural(n, 1) = n.get_coeff_view() else {
+                    unreachable!()
+                };
+
+                let c = base.get_radicand().unwrap().to_owned();
+                c.npow(n.div_euclid(2)) * &base.to_owned().npow(n.rem_euclid(2))
+            },
+        )
+    }
+
+    /// Rewrite a factor `d^-k`, where `d` contains a square root, to a form without
+    /// square roots in the denominator.
+    fn rationalize_factor(&self) -> Option<Atom> {
+        let AtomView::Pow(p) = self else {
+            return None;
+        };
+
+        let (base, exp) = p.get_base_exp();
+        let AtomView::Num(n) = exp else {
+            return None;
+        };
+
+        match n.get_coeff_view() {
+            CoefficientView::Natural(k, 1) if k < 0 => {
+                // 1/sqrt(c)^k = sqrt(c)^k/c^k
+                if let Some(c) = base.get_radicand() {
+                    return Some(base.to_owned().npow(-k) * &c.to_owned().npow(k));
+                }
+
+                // 1/(a + b*sqrt(c))^k = (a - b*sqrt(c))^k/(a^2 - b^2*c)^k
+                let (a, b, r) = base.split_radical()?;
+                let c = r.get_radicand().unwrap().to_owned();
+                let conj = &a - &(&b * &r.to_owned());
+                let den = (&(&a * &a) - &(&(&b * &b) * &c)).expand();
+                if matches!(den.as_view(), AtomView::Num(n) if n.is_zero()) {
+                    // for example a + sqrt(a^2), which cannot be rationalized this way
+                    return None;
+                }
+                Some(conj.npow(-k) * &den.npow(k))
+            }
+            CoefficientView::Natural(k, 2) if k > 0 => {
+                // (n/d)^(k/2) = (n*d)^(k/2)/d^k, as numbers to a negative power are normalized to this form
+                let AtomView::Num(c) = base else {
+                    return None;
+                };
+                let CoefficientView::Natural(n, d) = c.get_coeff_view() else {
+                    return None;
+                };
+                if d == 1 {
+                    return None;
+                }
+
+                let nd = Atom::new_num(n.checked_mul(d)?);
+                Some(nd.npow((k, 2)) * &Atom::new_num(d).npow(-k))
+            }
+            _ => None,
+        }
+    }
+
+    /// Split the sum `a + b*r` where `r` is a square root and `a` and `b` contain
+    /// no square roots.
+    fn split_radical(&self) -> Option<(Atom, Atom, AtomView<'a>)> {
+        let AtomView::Add(add) = self else {
+            return None;
+        };
+
+        let factors = |t: AtomView<'a>| -> Vec<AtomView<'a>> {
+            match t {
+                AtomView::Mul(m) => m.iter().collect(),
+                _ => vec![t],
+            }
+        };
+
+        let r = add
+            .iter()
+            .find_map(|t| factors(t).into_iter().find(|f| f.get_radicand().is_some()))?;
+
+        let mut a = Atom::new_num(0);
+        let mut b = Atom::new_num(0);
+        for t in add.iter() {
+            let mut found = false;
+            let mut rest = Atom::new_num(1);
+            for f in factors(t) {
+                if f == r && !found {
+                    found = true;
+                } else if f.has_radical() {
+                    return None;
+                } else {
+                    rest = rest * &f.to_owned();
+                }
+            }
+
+            if found {
+                b = b + &rest;
+            } else {
+                a = a + &rest;
+            }
+        }
+
+        Some((a, b, r))
+    }
+
+    /// Get `c` if the atom is `sqrt(c)` or `c^(1/2)` for a number `c`.
+    fn get_radicand(&self) -> Option<AtomView<'a>> {
+        match self {
+            AtomView::Fun(f) if f.get_symbol() == State::SQRT && f.get_nargs() == 1 => {
+                f.iter().next()
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                match (base, exp) {
+                    (AtomView::Num(_), AtomView::Num(n))
+                        if n.get_coeff_view() == CoefficientView::Natural(1, 2) =>
+                    {
+                        Some(base)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn has_radical(&self) -> bool {
+        if self.get_radicand().is_some() {
+            return true;
+        }
+
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => false,
+            AtomView::Fun(f) => f.iter().any(|a| a.has_radical()),
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.has_radical() || exp.has_radical()
+            }
+            AtomView::Mul(m) => m.iter().any(|a| a.has_radical()),
+            AtomView::Add(a) => a.iter().any(|a| a.has_radical()),
+        }
+    }
+
+    /// Expand only the powers of sums with a natural exponent of at most `max_exponent`,
+    /// using the multinomial theorem. Products of sums are not expanded.
+    ///
+    /// For example, `f((a+b)^2)*(c+d)` becomes `f(a^2+2*a*b+b^2)*(c+d)`.
+    pub fn expand_powers(&self, max_exponent: u32) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut a = ws.new_atom();
+            self.expand_powers_with_ws_into(max_exponent, ws, &mut a);
+            a.into_inner()
+        })
+    }
+
+    /// Expand only the powers of sums with a natural exponent of at most `max_exponent`,
+    /// returning `true` iff the expression changed.
+    pub fn expand_powers_with_ws_into(
+        &self,
+        max_exponent: u32,
+        workspace: &Workspace,
+        out: &mut Atom,
+    ) -> bool {
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => {
+                out.set_from_view(self);
+                false
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+
+                let mut new_base = workspace.new_atom();
+                let mut changed =
+                    base.expand_powers_with_ws_into(max_exponent, workspace, &mut new_base);
+
+                let mut new_exp = workspace.new_atom();
+                changed |= exp.expand_powers_with_ws_into(max_exponent, workspace, &mut new_exp);
+
+                if let (AtomView::Add(a), AtomView::Num(n)) =
+                    (new_base.as_view(), new_exp.as_view())
+                {
+                    if let CoefficientView::Natural(n, 1) = n.get_coeff_view() {
+                        if n > 1 && n <= max_exponent as i64 {
+                            let args: SmallVec<[AtomView; 10]> = a.iter().collect();
+
+                            let mut add_h = workspace.new_atom();
+                            let add = add_h.to_add();
+
+                            let mut ci =
+                                CombinationWithReplacementIterator::new(args.len(), n as u32);
+                            while let Some(new_term) = ci.next() {
+                                let mut mul_h = workspace.new_atom();
+                                let mul = mul_h.to_mul();
+
+                                let mut pow_h = workspace.new_atom();
+                                for (arg, pow) in args.iter().zip(new_term) {
+                                    if *pow != 0 {
+                                        let exp_h = workspace.new_num(*pow as i64);
+                                        pow_h.to_pow(*arg, exp_h.as_view());
+                                        mul.extend(pow_h.as_view());
+                                    }
+                                }
+
+                                let coeff = workspace.new_num(Integer::multinom(new_term));
+                                mul.extend(coeff.as_view());
+                                add.extend(mul_h.as_view());
+                            }
+
+                            add_h.as_view().normalize(workspace, out);
+                            return true;
+                        }
+                    }
+                }
+
+                if changed {
+                    let mut pow_h = workspace.new_atom();
+                    pow_h.to_pow(new_base.as_view(), new_exp.as_view());
+                    pow_h.as_view().normalize(workspace, out);
+                } else {
+                    out.set_from_view(self);
+                }
+
+                changed
+            }
+            AtomView::Fun(f) => {
+                let mut fun_h = workspace.new_atom();
+                let fun = fun_h.to_fun(f.get_symbol());
+
+                let mut changed = false;
+                let mut new_arg = workspace.new_atom();
+                for arg in f.iter() {
+                    changed |=
+                        arg.expand_powers_with_ws_into(max_exponent, workspace, &mut new_arg);
+                    fun.add_arg(new_arg.as_view());
+                }
+
+                if changed {
+                    fun_h.as_view().normalize(workspace, out);
+                } else {
+                    out.set_from_view(self);
+                }
+
+                changed
+            }
+            AtomView::Mul(m) => {
+                let mut mul_h = workspace.new_atom();
+                let mul = mul_h.to_mul();
+
+                let mut changed = false;
+                let mut new_arg = workspace.new_atom();
+                for arg in m.iter() {
+                    changed |=
+                        arg.expand_powers_with_ws_into(max_exponent, workspace, &mut new_arg);
+                    mul.extend(new_arg.as_view());
+                }
+
+                if changed {
+                    mul_h.as_view().normalize(workspace, out);
+                } else {
+                    out.set_from_view(self);
+                }
+
+                changed
+            }
+            AtomView::Add(a) => {
+                let mut add_h = workspace.new_atom();
+                let add = add_h.to_add();
+
+                let mut changed = false;
+                let mut new_arg = workspace.new_atom();
+                for arg in a.iter() {
+                    changed |=
+                        arg.expand_powers_with_ws_into(max_exponent, workspace, &mut new_arg);
+                    add.extend(new_arg.as_view());
+                }
+
+                if changed {
+                    add_h.as_view().normalize(workspace, out);
+                } else {
+                    out.set_from_view(self);
+                }
+
+                changed
+            }
+        }
+    }
+
+    /// Expand `(x*y)^exp` to `x^exp*y^exp`, with `exp` a positive integer.
+    /// If `negative` is set, the result is inverted.
+    fn expand_mul_power(
+        base: AtomView,
+        exp: AtomView,
+        negative: bool,
+        workspace: &Workspace,
+        out: &mut Atom,
+    ) -> bool {
+        let AtomView::Mul(m) = base else {
+            unreachable!("Base is not a product");
+        };
+
+        let mut mul_h = workspace.new_atom();
+        let mul = mul_h.to_mul();
+
+        for arg in m.iter() {
+            let mut pow_h = workspace.new_atom();
+            pow_h.to_pow(arg, exp);
+            mul.extend(pow_h.as_view());
+        }
+
+        if negative {
+            let mut num_h = workspace.new_atom();
+            num_h.to_num((-1).into());
+
+            let mut pow_h = workspace.new_atom();
+            pow_h.to_pow(mul_h.as_view(), num_h.as_view());
+            pow_h.as_view().normalize(workspace, out);
+        } else {
+            mul_h.as_view().normalize(workspace, out);
+        }
+        true
+    }
+
     /// Expand an expression, but do not normalize the result.
     fn expand_no_norm(&self, workspace: &Workspace, out: &mut Atom) -> bool {
         match self {
@@ -69,6 +515,24 @@ impl<'a> AtomView<'a> {
                                 break 'get_num (n < 0, n.unsigned_abs() as u32);
                             }
                         }
+
+                        // a product can be raised to an integer power of any size
+                        let e = n.get_coeff_view();
+                        if matches!(e, CoefficientView::Natural(..) | CoefficientView::Large(_))
+                            && e.is_integer()
+                            && matches!(new_base.as_view(), AtomView::Mul(_))
+                            && !new_base.as_view().is_noncommutative_factor()
+                        {
+                            let mut exp_h = workspace.new_atom();
+                            exp_h.to_num(e.abs_sub(&CoefficientView::Natural(0, 1)));
+                            return Self::expand_mul_power(
+                                new_base.as_view(),
+                                exp_h.as_view(),
+                                e.is_negative(),
+                                workspace,
+                                out,
+                            );
+                        }
                     }
 
                     let mut pow_h = workspace.new_atom();
@@ -78,7 +542,32 @@ impl<'a> AtomView<'a> {
                     return changed;
                 };
 
-                if let AtomView::Add(a) = new_base.as_view() {
+                if matches!(new_base.as_view(), AtomView::Add(_) | AtomView::Mul(_))
+                    && new_base.as_view().is_noncommutative_factor()
+                {
+                    // the multinomial theorem does not apply, so write out the product
+                    let mut mul_h = workspace.new_atom();
+                    let mul = mul_h.to_mul();
+                    for _ in 0..num {
+                        mul.extend(new_base.as_view());
+                    }
+
+                    let mut expanded = workspace.new_atom();
+                    mul_h.as_view().expand_no_norm(workspace, &mut expanded);
+
+                    if negative {
+                        let mut num_h = workspace.new_atom();
+                        num_h.to_num((-1i64).into());
+
+                        let mut pow_h = workspace.new_atom();
+                        pow_h.to_pow(expanded.as_view(), num_h.as_view());
+                        pow_h.as_view().normalize(workspace, out);
+                    } else {
+                        expanded.as_view().normalize(workspace, out);
+                    }
+
+                    true
+                } else if let AtomView::Add(a) = new_base.as_view() {
                     // expand (a+b+c+..)^n
                     let mut args: SmallVec<[AtomView; 10]> = SmallVec::with_capacity(a.get_nargs());
                     for arg in a.iter() {
@@ -145,30 +634,16 @@ impl<'a> AtomView<'a> {
                     }
 
                     true
-                } else if let AtomView::Mul(m) = new_base.as_view() {
-                    let mut mul_h = workspace.new_atom();
-                    let mul = mul_h.to_mul();
-
+                } else if let AtomView::Mul(_) = new_base.as_view() {
                     let mut exp_h = workspace.new_atom();
                     exp_h.to_num((num as i64).into());
-
-                    for arg in m.iter() {
-                        let mut pow_h = workspace.new_atom();
-                        pow_h.to_pow(arg, exp_h.as_view());
-                        mul.extend(pow_h.as_view());
-                    }
-
-                    if negative {
-                        let mut num_h = workspace.new_atom();
-                        num_h.to_num((-1).into());
-
-                        let mut pow_h = workspace.new_atom();
-                        pow_h.to_pow(mul_h.as_view(), num_h.as_view());
-                        pow_h.as_view().normalize(workspace, out);
-                    } else {
-                        mul_h.as_view().normalize(workspace, out);
-                    }
-                    true
+                    Self::expand_mul_power(
+                        new_base.as_view(),
+                        exp_h.as_view(),
+                        negative,
+                        workspace,
+                        out,
+                    )
                 } else {
                     let mut pow_h = workspace.new_atom();
                     let pow = pow_h.to_pow(new_base.as_view(), new_exp.as_view());
@@ -273,3 +748,88 @@ impl<'a> AtomView<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{coefficient::Coefficient, representations::Atom};
+
+    #[test]
+    fn rationalize_denominator() {
+        let a = Atom::parse("1/(1+sqrt(2))")
+            .unwrap()
+            .rationalize_denominator();
+        assert_eq!(a, Atom::parse("sqrt(2) - 1").unwrap());
+
+        let a = Atom::parse("1/sqrt(3)").unwrap().rationalize_denominator();
+        assert_eq!(a, Atom::parse("sqrt(3)/3").unwrap());
+
+        let a = Atom::parse("3^(-1/2)").unwrap().rationalize_denominator();
+        assert_eq!(a, Atom::parse("3^(1/2)/3").unwrap());
+
+        let a = Atom::parse("(2/3)^(1/2)")
+            .unwrap()
+            .rationalize_denominator();
+        assert_eq!(a, Atom::parse("6^(1/2)/3").unwrap());
+
+        let a = Atom::parse("rd_x/(rd_y - 2*sqrt(rd_z)) + sqrt(5)/(3+sqrt(5))^2")
+            .unwrap()
+            .rationalize_denominator();
+        let r =
+            Atom::parse("(rd_x*rd_y + 2*rd_x*sqrt(rd_z))/(rd_y^2 - 4*rd_z) + (-15+7*sqrt(5))/8")
+                .unwrap();
+        assert_eq!((a - &r).expand(), Atom::new_num(0));
+
+        for s in [
+            "1/(1+rd_x)",
+            "1/(sqrt(2)+sqrt(3))",
+            "sqrt(2)/rd_x",
+            "1/(rd_x+sqrt(rd_x^2))",
+        ] {
+            let a = Atom::parse(s).unwrap();
+            assert_eq!(a.rationalize_denominator(), a);
+        }
+    }
+
+    #[test]
+    fn expand_powers() {
+        let a = Atom::parse("(a+b+c)^2").unwrap().expand_powers(2);
+        let r = Atom::parse("a^2+b^2+c^2+2*a*b+2*a*c+2*b*c").unwrap();
+        assert_eq!(a, r);
+
+        let a = Atom::parse("f((a+b)^2)*(c+d)+(a+b)^3")
+            .unwrap()
+            .expand_powers(2);
+        let r = Atom::parse("f(a^2+2*a*b+b^2)*(c+d)+(a+b)^3").unwrap();
+        assert_eq!(a, r);
+    }
+
+    #[test]
+    fn sum_of_products() {
+        let x = Atom::parse("sp_x").unwrap();
+        let y = Atom::parse("sp_y").unwrap();
+
+        let mut terms = Atom::parse("3*sp_x^2*sp_y + sp_x - 2")
+            .unwrap()
+            .to_sum_of_products();
+        terms.sort_by(|a, b| a.1.len().cmp(&b.1.len()));
+
+        assert_eq!(
+            terms,
+            vec![
+                (Coefficient::from(-2), vec![]),
+                (Coefficient::from(1), vec![(x.clone(), 1)]),
+                (Coefficient::from(3), vec![(x.clone(), 2), (y.clone(), 1)]),
+            ]
+        );
+
+        let terms = Atom::parse("sp_x*(sin(sp_x)+sp_y^(1/2))")
+            .unwrap()
+            .to_sum_of_products();
+        assert_eq!(terms.len(), 2);
+        assert!(terms
+            .iter()
+            .all(|(c, f)| *c == Coefficient::from(1) && f.len() == 2));
+
+        assert!(Atom::new().to_sum_of_products().is_empty());
+    }
+}
@@ -1,8 +1,15 @@
+use std::sync::Arc;
+
+use ahash::{HashMap, HashSet};
 use dyn_clone::DynClone;
 
 use crate::{
-    representations::{default::ListSlice, Atom, AtomView, Num, SliceType, Symbol},
-    state::{State, Workspace},
+    coefficient::{Coefficient, CoefficientView},
+    representations::{
+        default::{ListSlice, ListSliceIterator},
+        Atom, AtomView, FunctionBuilder, Num, SliceType, Symbol,
+    },
+    state::{RecycledAtom, State, Workspace},
     transformer::{Transformer, TransformerError},
 };
 
@@ -21,12 +28,222 @@ impl Atom {
     pub fn into_pattern(&self) -> Pattern {
         Pattern::from_view(self.as_view(), true)
     }
+
+    /// Replace every subexpression for which `predicate` holds by `transform` of
+    /// that subexpression. See [`AtomView::map_subterms`].
+    pub fn map_subterms<P: Fn(AtomView) -> bool, F: Fn(AtomView) -> Atom>(
+        &self,
+        predicate: P,
+        transform: F,
+    ) -> Atom {
+        self.as_view().map_subterms(&predicate, &transform)
+    }
+
+    /// Iterate over all subexpressions, including the expression itself.
+    /// See [`AtomView::iter_subexpressions`].
+    pub fn iter_subexpressions(&self, order: TraversalOrder) -> SubexpressionIterator<'_> {
+        self.as_view().iter_subexpressions(order)
+    }
+
+    /// Rebuild the expression bottom-up by applying `f` to every subexpression.
+    /// See [`AtomView::map_subexpressions`].
+    pub fn map_subexpressions<F: FnMut(AtomView) -> Atom>(&self, mut f: F) -> Atom {
+        self.as_view().map_subexpressions(&mut f)
+    }
+}
+
+/// The order in which subexpressions are visited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraversalOrder {
+    /// Visit an expression before its subexpressions.
+    PreOrder,
+    /// Visit an expression after its subexpressions.
+    PostOrder,
+}
+
+/// An iterator over all subexpressions of an expression, including the expression itself,
+/// created with [`AtomView::iter_subexpressions`]. The arguments of a function, the
+/// base and exponent of a power and the factors and terms of products and sums are visited
+/// in the order in which they are stored.
+pub struct SubexpressionIterator<'a> {
+    root: Option<AtomView<'a>>,
+    stack: Vec<(AtomView<'a>, Option<ListSliceIterator<'a>>)>,
+    order: TraversalOrder,
+}
+
+impl<'a> SubexpressionIterator<'a> {
+    fn push(&mut self, a: AtomView<'a>) {
+        let children = match a {
+            AtomView::Num(_) | AtomView::Var(_) => None,
+            AtomView::Fun(f) => Some(f.to_slice().iter()),
+            AtomView::Pow(p) => Some(p.to_slice().iter()),
+            AtomView::Mul(m) => Some(m.to_slice().iter()),
+            AtomView::Add(a) => Some(a.to_slice().iter()),
+        };
+        self.stack.push((a, children));
+    }
+}
+
+impl<'a> Iterator for SubexpressionIterator<'a> {
+    type Item = AtomView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            self.push(root);
+            if self.order == TraversalOrder::PreOrder {
+                return Some(root);
+            }
+        }
+
+        loop {
+            let (_, children) = self.stack.last_mut()?;
+            if let Some(c) = children.as_mut().and_then(|c| c.next()) {
+                self.push(c);
+                if self.order == TraversalOrder::PreOrder {
+                    return Some(c);
+                }
+            } else {
+                let (a, _) = self.stack.pop().unwrap();
+                if self.order == TraversalOrder::PostOrder {
+                    return Some(a);
+                }
+            }
+        }
+    }
 }
 
 impl<'a> AtomView<'a> {
     pub fn into_pattern(self) -> Pattern {
         Pattern::from_view(self, true)
     }
+
+    /// Match the pattern `pat` anywhere in the expression and return the wildcard
+    /// bindings of the first match, or `None` if there is no match.
+    pub fn match_first<'c>(
+        &self,
+        pat: &Pattern,
+        conditions: &'c Condition<WildcardAndRestriction>,
+        settings: &'c MatchSettings,
+    ) -> Option<HashMap<Symbol, Match<'c>>>
+    where
+        'a: 'c,
+    {
+        let mut it = PatternAtomTreeIterator::new(pat, *self, conditions, settings);
+        it.next().map(|(_, _, _, match_stack)| {
+            match_stack
+                .into_iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect()
+        })
+    }
+
+    /// Iterate over all subexpressions, including the expression itself, in the given order.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{id::TraversalOrder, representations::Atom};
+    /// let a = Atom::parse("f(x)^2").unwrap();
+    /// let pre: Vec<_> = a.iter_subexpressions(TraversalOrder::PreOrder).map(|x| x.to_string()).collect();
+    /// assert_eq!(pre, ["f(x)^2", "f(x)", "x", "2"]);
+    /// let post: Vec<_> = a.iter_subexpressions(TraversalOrder::PostOrder).map(|x| x.to_string()).collect();
+    /// assert_eq!(post, ["x", "f(x)", "2", "f(x)^2"]);
+    /// ```
+    pub fn iter_subexpressions(&self, order: TraversalOrder) -> SubexpressionIterator<'a> {
+        SubexpressionIterator {
+            root: Some(*self),
+            stack: vec![],
+            order,
+        }
+    }
+
+    /// Rebuild the expression bottom-up, replacing every subexpression by the output of `f`.
+    /// The function `f` is called on a subexpression after its children have been replaced,
+    /// and its output is not traversed again. The result is normalized.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::{Atom, AtomView};
+    /// let a = Atom::parse("f(x, 2)*x + 3").unwrap();
+    /// let r = a.map_subexpressions(|x| match x {
+    ///     AtomView::Num(_) => Atom::new_num(1),
+    ///     _ => x.to_owned(),
+    /// });
+    /// assert_eq!(r, Atom::parse("f(x, 1)*x + 1").unwrap());
+    /// ```
+    pub fn map_subexpressions<F: FnMut(AtomView) -> Atom>(&self, f: &mut F) -> Atom {
+        let new = match self {
+            AtomView::Num(_) | AtomView::Var(_) => self.to_owned(),
+            AtomView::Fun(fun) => {
+                let mut fb = FunctionBuilder::new(fun.get_symbol());
+                for arg in fun.iter() {
+                    fb = fb.add_arg(&arg.map_subexpressions(f));
+                }
+                fb.finish()
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.map_subexpressions(f).pow(&exp.map_subexpressions(f))
+            }
+            AtomView::Mul(m) => {
+                // collect all factors first, so that the product is normalized only once
+                let mut res = RecycledAtom::new();
+                let mul = res.to_mul();
+                for factor in m.iter() {
+                    mul.extend(factor.map_subexpressions(f).as_view());
+                }
+                Workspace::get_local().with(|ws| {
+                    let mut out = Atom::new();
+                    res.as_view().normalize(ws, &mut out);
+                    out
+                })
+            }
+            AtomView::Add(a) => {
+                let mut res = RecycledAtom::new();
+                let add = res.to_add();
+                for t in a.iter() {
+                    add.extend(t.map_subexpressions(f).as_view());
+                }
+                Workspace::get_local().with(|ws| {
+                    let mut out = Atom::new();
+                    res.as_view().normalize(ws, &mut out);
+                    out
+                })
+            }
+        };
+
+        f(new.as_view())
+    }
+
+    /// Replace every subexpression for which `predicate` holds by `transform` of
+    /// that subexpression. The expression is traversed bottom-up: the predicate
+    /// is tested on a subexpression after its children have been replaced, and
+    /// the output of `transform` is not traversed again.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::{Atom, AtomView, FunctionBuilder};
+    /// # use symbolica::state::State;
+    /// let g = State::get_symbol("g");
+    /// let a = Atom::parse("2 + f(3)").unwrap();
+    /// let r = a.map_subterms(
+    ///     |x| matches!(x, AtomView::Num(_)),
+    ///     |x| FunctionBuilder::new(g).add_arg(x).finish(),
+    /// );
+    /// assert_eq!(r, Atom::parse("g(2) + f(g(3))").unwrap());
+    /// ```
+    pub fn map_subterms<P: Fn(AtomView) -> bool, F: Fn(AtomView) -> Atom>(
+        &self,
+        predicate: &P,
+        transform: &F,
+    ) -> Atom {
+        self.map_subexpressions(&mut |x| {
+            if predicate(x) {
+                transform(x)
+            } else {
+                x.to_owned()
+            }
+        })
+    }
 }
 
 impl Pattern {
@@ -38,9 +255,9 @@ impl Pattern {
     /// Convert the pattern to an atom, if there are not transformers present.
     pub fn to_atom(&self) -> Result<Atom, &'static str> {
         Workspace::get_local().with(|ws| {
-            let mut out = Atom::new();
+            let mut out = ws.new_atom();
             self.to_atom_impl(ws, &mut out)?;
-            Ok(out)
+            Ok(out.into_inner())
         })
     }
 
@@ -115,10 +332,10 @@ impl Pattern {
                 a.extend(l1.as_view());
                 a.extend(l2.as_view());
 
-                let mut b = Atom::default();
+                let mut b = workspace.new_atom();
                 e.as_view().normalize(workspace, &mut b);
 
-                return Pattern::Literal(b);
+                return Pattern::Literal(b.into_inner());
             }
         }
 
@@ -147,10 +364,10 @@ impl Pattern {
                 a.extend(l1.as_view());
                 a.extend(l2.as_view());
 
-                let mut b = Atom::default();
+                let mut b = workspace.new_atom();
                 e.as_view().normalize(workspace, &mut b);
 
-                return Pattern::Literal(b);
+                return Pattern::Literal(b.into_inner());
             }
         }
 
@@ -178,13 +395,13 @@ impl Pattern {
             let mut e = workspace.new_atom();
             e.to_pow(l2.as_view(), pow.as_view());
 
-            let mut b = Atom::default();
+            let mut b = workspace.new_atom();
             e.as_view().normalize(workspace, &mut b);
 
             match self {
                 Pattern::Mul(m) => {
                     let mut new_args = m.clone();
-                    new_args.push(Pattern::Literal(b));
+                    new_args.push(Pattern::Literal(b.into_inner()));
                     Pattern::Mul(new_args)
                 }
                 Pattern::Literal(l1) => {
@@ -194,11 +411,11 @@ impl Pattern {
                     md.extend(l1.as_view());
                     md.extend(b.as_view());
 
-                    let mut b = Atom::default();
+                    let mut b = workspace.new_atom();
                     m.as_view().normalize(workspace, &mut b);
-                    Pattern::Literal(b)
+                    Pattern::Literal(b.into_inner())
                 }
-                _ => Pattern::Mul(vec![self.clone(), Pattern::Literal(b)]),
+                _ => Pattern::Mul(vec![self.clone(), Pattern::Literal(b.into_inner())]),
             }
         } else {
             let exp = Num::new((-1).into()).into();
@@ -225,10 +442,10 @@ impl Pattern {
                 let mut e = workspace.new_atom();
                 e.to_pow(l1.as_view(), l2.as_view());
 
-                let mut b = Atom::default();
+                let mut b = workspace.new_atom();
                 e.as_view().normalize(workspace, &mut b);
 
-                return Pattern::Literal(b);
+                return Pattern::Literal(b.into_inner());
             }
         }
 
@@ -246,10 +463,10 @@ impl Pattern {
             a.extend(l1.as_view());
             a.extend(sign.as_view());
 
-            let mut b = Atom::default();
+            let mut b = workspace.new_atom();
             e.as_view().normalize(workspace, &mut b);
 
-            Pattern::Literal(b)
+            Pattern::Literal(b.into_inner())
         } else {
             let sign = Num::new((-1).into()).into();
 
@@ -271,12 +488,64 @@ impl Pattern {
             (Pattern::Add(_), AtomView::Add(_)) => true,
             (Pattern::Wildcard(_), _) => true,
             (Pattern::Pow(_), AtomView::Pow(_)) => true,
-            (Pattern::Literal(p), _) => p.as_view() == target,
+            (Pattern::Literal(p), _) => Self::literal_matches(p.as_view(), target),
             (Pattern::Transformer(_), _) => unreachable!(),
             (_, _) => false,
         }
     }
 
+    /// Check if the literal pattern `literal` matches `target`. If `target` contains
+    /// finite field elements, the rational numbers in `literal` are mapped into the
+    /// same field, so that `3` matches the element `10 mod 7`. The numbers are mapped
+    /// one by one while both are traversed, so that the check stops at the first mismatch.
+    fn literal_matches(literal: AtomView<'_>, target: AtomView<'_>) -> bool {
+        match (literal, target) {
+            (AtomView::Num(l), AtomView::Num(t)) => {
+                if l == t {
+                    return true;
+                }
+
+                match (l.get_coeff_view(), t.get_coeff_view()) {
+                    (
+                        c @ (CoefficientView::Natural(..) | CoefficientView::Large(_)),
+                        CoefficientView::FiniteField(e, field),
+                    ) => matches!(
+                        c.to_owned().to_finite_field(field),
+                        Ok(Coefficient::FiniteField(r, _)) if r == e
+                    ),
+                    _ => false,
+                }
+            }
+            (AtomView::Var(_), AtomView::Var(_)) => literal == target,
+            (AtomView::Fun(l), AtomView::Fun(t)) => {
+                l.get_symbol() == t.get_symbol()
+                    && l.get_nargs() == t.get_nargs()
+                    && l.iter()
+                        .zip(t.iter())
+                        .all(|(a, b)| Self::literal_matches(a, b))
+            }
+            (AtomView::Pow(l), AtomView::Pow(t)) => {
+                // exponents are never mapped into a finite field
+                let (lb, le) = l.get_base_exp();
+                let (tb, te) = t.get_base_exp();
+                le == te && Self::literal_matches(lb, tb)
+            }
+            (AtomView::Mul(l), AtomView::Mul(t)) => {
+                l.get_nargs() == t.get_nargs()
+                    && l.iter()
+                        .zip(t.iter())
+                        .all(|(a, b)| Self::literal_matches(a, b))
+            }
+            (AtomView::Add(l), AtomView::Add(t)) => {
+                l.get_nargs() == t.get_nargs()
+                    && l.iter()
+                        .zip(t.iter())
+                        .all(|(a, b)| Self::literal_matches(a, b))
+            }
+            _ => false,
+        }
+    }
+
     /// Check if the expression `atom` contains a wildcard.
     fn has_wildcard(atom: AtomView<'_>) -> bool {
         match atom {
@@ -364,9 +633,9 @@ impl Pattern {
                 AtomView::Num(_) => unreachable!("Number cannot have wildcard"),
             }
         } else {
-            let mut oa = Atom::default();
+            let mut oa = RecycledAtom::new();
             oa.set_from_view(&atom);
-            Pattern::Literal(oa)
+            Pattern::Literal(oa.into_inner())
         }
     }
 
@@ -579,6 +848,50 @@ impl Pattern {
         ReplaceIterator::new(self, target, rhs, conditions, settings)
     }
 
+    /// Replace only the first occurrence of the pattern in the target, visiting
+    /// the expression tree from the top down and from left to right. The returned flag
+    /// is `true` iff a match was found. If there is no match, the target is returned unchanged.
+    ///
+    /// This can be used to apply a rule a single step at a time.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{id::Pattern, representations::Atom};
+    /// let expr = Atom::parse("f(1) + f(2)").unwrap();
+    /// let pat = Pattern::parse("f(x_)").unwrap();
+    /// let rhs = Pattern::parse("x_^2 + 10").unwrap();
+    /// let (r, changed) = pat.replace_first(expr.as_view(), &rhs, None, None);
+    /// assert!(changed);
+    /// assert_eq!(r, Atom::parse("11 + f(2)").unwrap());
+    /// ```
+    pub fn replace_first(
+        &self,
+        target: AtomView<'_>,
+        rhs: &Pattern,
+        conditions: Option<&Condition<WildcardAndRestriction>>,
+        settings: Option<&MatchSettings>,
+    ) -> (Atom, bool) {
+        let (default_conditions, default_settings) =
+            (Condition::default(), MatchSettings::default());
+        let conditions = conditions.unwrap_or(&default_conditions);
+        let settings = settings.unwrap_or(&default_settings);
+
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            if self
+                .replace_iter(target, rhs, conditions, settings)
+                .next(&mut out)
+                .is_none()
+            {
+                return (target.to_owned(), false);
+            }
+
+            let mut norm = ws.new_atom();
+            out.as_view().normalize(ws, &mut norm);
+            (norm.into_inner(), true)
+        })
+    }
+
     /// Replace all occurrences of the pattern in the target
     /// For every matched atom, the first canonical match is used and then the atom is skipped.
     pub fn replace_all(
@@ -620,15 +933,15 @@ impl Pattern {
         settings: Option<&MatchSettings>,
         out: &mut Atom,
     ) -> bool {
-        let matched = self.replace_all_no_norm(
-            target,
+        let skeleton = Skeleton::new(self);
+        let matched = Replacement::new(
+            self,
+            &skeleton,
             rhs,
-            workspace,
-            conditions.unwrap_or(&Condition::default()),
+            conditions,
             settings.unwrap_or(&MatchSettings::default()),
-            0,
-            out,
-        );
+        )
+        .replace_all_no_norm(target, workspace, 0, out);
 
         if matched {
             let mut norm = workspace.new_atom();
@@ -639,17 +952,54 @@ impl Pattern {
         matched
     }
 
+    pub fn pattern_match<'a>(
+        &'a self,
+        target: AtomView<'a>,
+        conditions: &'a Condition<WildcardAndRestriction>,
+        settings: &'a MatchSettings,
+    ) -> PatternAtomTreeIterator<'a, 'a> {
+        PatternAtomTreeIterator::new(self, target, conditions, settings)
+    }
+}
+
+/// The data of a replacement that is the same for every subexpression of the target.
+/// The conditions are reordered once so that cheap restrictions are checked first.
+struct Replacement<'a> {
+    pattern: &'a Pattern,
+    skeleton: &'a Skeleton,
+    rhs: &'a Pattern,
+    conditions: Condition<WildcardAndRestriction>,
+    settings: &'a MatchSettings,
+}
+
+impl<'a> Replacement<'a> {
+    fn new(
+        pattern: &'a Pattern,
+        skeleton: &'a Skeleton,
+        rhs: &'a Pattern,
+        conditions: Option<&Condition<WildcardAndRestriction>>,
+        settings: &'a MatchSettings,
+    ) -> Replacement<'a> {
+        Replacement {
+            pattern,
+            skeleton,
+            rhs,
+            conditions: conditions.map(|c| c.order_by_cost()).unwrap_or_default(),
+            settings,
+        }
+    }
+
     /// Replace all occurrences of the pattern in the target, without normalizing the output.
+    /// The matcher is only set up for subexpressions that have the skeleton of the pattern.
     fn replace_all_no_norm(
         &self,
         target: AtomView<'_>,
-        rhs: &Pattern,
         workspace: &Workspace,
-        conditions: &Condition<WildcardAndRestriction>,
-        settings: &MatchSettings,
         level: usize,
         out: &mut Atom,
     ) -> bool {
+        let settings = self.settings;
+
         if let Some(max_level) = settings.level_range.1 {
             if level > max_level {
                 out.set_from_view(&target);
@@ -657,14 +1007,18 @@ impl Pattern {
             }
         }
 
-        if level >= settings.level_range.0 && self.could_match(target) {
-            let mut match_stack = MatchStack::new(conditions, settings);
+        if level >= settings.level_range.0
+            && self.skeleton.could_match(target)
+            && self.pattern.could_match(target)
+        {
+            let mut match_stack = MatchStack::new(&self.conditions, settings);
 
-            let mut it = AtomMatchIterator::new(self, target);
+            let mut it = AtomMatchIterator::new(self.pattern, target);
             //let mut it = SubSliceIterator::new(self, target, &match_stack, true);
             if let Some((_, used_flags)) = it.next(&mut match_stack) {
                 let mut rhs_subs = workspace.new_atom();
-                rhs.substitute_wildcards(workspace, &mut rhs_subs, &match_stack)
+                self.rhs
+                    .substitute_wildcards(workspace, &mut rhs_subs, &match_stack)
                     .unwrap(); // TODO: escalate?
 
                 if used_flags.iter().all(|x| *x) {
@@ -707,6 +1061,10 @@ impl Pattern {
 
         // no match found at this level, so check the children
         let submatch = match target {
+            AtomView::Fun(f) if settings.opaque_functions.contains(&f.get_symbol()) => {
+                out.set_from_view(&target);
+                false
+            }
             AtomView::Fun(f) => {
                 let out = out.to_fun(f.get_symbol());
 
@@ -715,15 +1073,8 @@ impl Pattern {
                 for child in f.iter() {
                     let mut child_buf = workspace.new_atom();
 
-                    submatch |= self.replace_all_no_norm(
-                        child,
-                        rhs,
-                        workspace,
-                        conditions,
-                        settings,
-                        level + 1,
-                        &mut child_buf,
-                    );
+                    submatch |=
+                        self.replace_all_no_norm(child, workspace, level + 1, &mut child_buf);
 
                     out.add_arg(child_buf.as_view());
                 }
@@ -737,10 +1088,7 @@ impl Pattern {
                 let mut base_out = workspace.new_atom();
                 let mut submatch = self.replace_all_no_norm(
                     base,
-                    rhs,
                     workspace,
-                    conditions,
-                    settings,
                     if settings.level_is_tree_depth {
                         level + 1
                     } else {
@@ -752,10 +1100,7 @@ impl Pattern {
                 let mut exp_out = workspace.new_atom();
                 submatch |= self.replace_all_no_norm(
                     exp,
-                    rhs,
                     workspace,
-                    conditions,
-                    settings,
                     if settings.level_is_tree_depth {
                         level + 1
                     } else {
@@ -777,10 +1122,7 @@ impl Pattern {
 
                     submatch |= self.replace_all_no_norm(
                         child,
-                        rhs,
                         workspace,
-                        conditions,
-                        settings,
                         if settings.level_is_tree_depth {
                             level + 1
                         } else {
@@ -804,10 +1146,7 @@ impl Pattern {
 
                     submatch |= self.replace_all_no_norm(
                         child,
-                        rhs,
                         workspace,
-                        conditions,
-                        settings,
                         if settings.level_is_tree_depth {
                             level + 1
                         } else {
@@ -829,14 +1168,323 @@ impl Pattern {
 
         submatch
     }
+}
 
-    pub fn pattern_match<'a>(
-        &'a self,
-        target: AtomView<'a>,
-        conditions: &'a Condition<WildcardAndRestriction>,
-        settings: &'a MatchSettings,
-    ) -> PatternAtomTreeIterator<'a, 'a> {
-        PatternAtomTreeIterator::new(self, target, conditions, settings)
+/// A pattern that has been analyzed once, so that it can be applied
+/// repeatedly without redoing the analysis. Cloning a compiled pattern is cheap.
+///
+/// Before matching, the target is scanned for all symbols that any match
+/// requires, so that targets that cannot possibly match are skipped quickly.
+/// During a replacement, every subexpression is first compared to the
+/// [`Skeleton`] of the pattern, before the matcher is set up.
+#[derive(Clone, Debug)]
+pub struct CompiledPattern {
+    pattern: Arc<Pattern>,
+    wildcards: Arc<[Symbol]>,
+    required_symbols: Arc<[Symbol]>,
+    skeleton: Skeleton,
+}
+
+/// The structure of the top level of a pattern that every match must have.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Skeleton {
+    /// The pattern may match any expression, for example a wildcard.
+    Any,
+    /// A function with the given name, or any name if the name is a wildcard,
+    /// with at least `min_args` arguments.
+    Fn {
+        name: Option<Symbol>,
+        min_args: usize,
+    },
+    Pow,
+    /// A sum or product with at least `min_args` arguments, which contains
+    /// a function with each of the names in `heads` as a direct argument.
+    /// A name appears as often in `heads` as the pattern requires.
+    List {
+        kind: SliceType,
+        min_args: usize,
+        heads: Arc<[Symbol]>,
+    },
+}
+
+impl Skeleton {
+    fn new(pattern: &Pattern) -> Skeleton {
+        // every pattern that is not a wildcard or transformer matches exactly one argument
+        let min_args = |args: &[Pattern]| {
+            args.iter()
+                .filter(|a| !matches!(a, Pattern::Wildcard(_) | Pattern::Transformer(_)))
+                .count()
+        };
+
+        match pattern {
+            Pattern::Fn(name, args) => Skeleton::Fn {
+                name: if name.get_wildcard_level() > 0 {
+                    None
+                } else {
+                    Some(*name)
+                },
+                min_args: min_args(args),
+            },
+            Pattern::Pow(_) => Skeleton::Pow,
+            Pattern::Mul(args) | Pattern::Add(args) => {
+                let mut heads: Vec<_> = args
+                    .iter()
+                    .filter_map(|a| match a {
+                        Pattern::Fn(name, _) if name.get_wildcard_level() == 0 => Some(*name),
+                        _ => None,
+                    })
+                    .collect();
+                heads.sort();
+
+                Skeleton::List {
+                    kind: if matches!(pattern, Pattern::Mul(_)) {
+                        SliceType::Mul
+                    } else {
+                        SliceType::Add
+                    },
+                    min_args: min_args(args),
+                    heads: heads.into(),
+                }
+            }
+            Pattern::Literal(_) | Pattern::Wildcard(_) | Pattern::Transformer(_) => Skeleton::Any,
+        }
+    }
+
+    /// Check if `target` has the structure of the skeleton. If this returns `false`,
+    /// the pattern does not match `target`, although it may match a subexpression.
+    pub fn could_match(&self, target: AtomView<'_>) -> bool {
+        match (self, target) {
+            (Skeleton::Any, _) => true,
+            (Skeleton::Fn { name, min_args }, AtomView::Fun(f)) => {
+                name.map_or(true, |n| n == f.get_symbol()) && f.get_nargs() >= *min_args
+            }
+            (Skeleton::Pow, AtomView::Pow(_)) => true,
+            (
+                Skeleton::List {
+                    kind: SliceType::Mul,
+                    min_args,
+                    heads,
+                },
+                AtomView::Mul(m),
+            ) => m.get_nargs() >= *min_args && Self::has_heads(heads, m.iter()),
+            (
+                Skeleton::List {
+                    kind: SliceType::Add,
+                    min_args,
+                    heads,
+                },
+                AtomView::Add(a),
+            ) => a.get_nargs() >= *min_args && Self::has_heads(heads, a.iter()),
+            _ => false,
+        }
+    }
+
+    /// Check if the arguments contain a function for every name in the sorted `heads`.
+    fn has_heads<'a>(heads: &[Symbol], args: impl Iterator<Item = AtomView<'a>> + Clone) -> bool {
+        let mut i = 0;
+        while i < heads.len() {
+            let name = heads[i];
+            let needed = heads[i..].iter().take_while(|h| **h == name).count();
+            let found = args
+                .clone()
+                .filter(|a| matches!(a, AtomView::Fun(f) if f.get_symbol() == name))
+                .take(needed)
+                .count();
+            if found < needed {
+                return false;
+            }
+            i += needed;
+        }
+        true
+    }
+}
+
+impl Pattern {
+    /// Analyze the pattern once for repeated use.
+    pub fn compile(&self) -> CompiledPattern {
+        let mut wildcards = vec![];
+        let mut required_symbols = vec![];
+        self.collect_symbols(&mut wildcards, &mut required_symbols);
+
+        CompiledPattern {
+            pattern: Arc::new(self.clone()),
+            wildcards: wildcards.into(),
+            required_symbols: required_symbols.into(),
+            skeleton: Skeleton::new(self),
+        }
+    }
+
+    /// Collect all wildcards and all non-wildcard symbols that must occur in any match.
+    fn collect_symbols(&self, wildcards: &mut Vec<Symbol>, required: &mut Vec<Symbol>) {
+        match self {
+            Pattern::Literal(a) => Self::collect_atom_symbols(a.as_view(), required),
+            Pattern::Wildcard(s) => {
+                if !wildcards.contains(s) {
+                    wildcards.push(*s);
+                }
+            }
+            Pattern::Fn(name, args) => {
+                if name.get_wildcard_level() > 0 {
+                    if !wildcards.contains(name) {
+                        wildcards.push(*name);
+                    }
+                } else if *name != State::ARG && !required.contains(name) {
+                    required.push(*name);
+                }
+
+                for a in args {
+                    a.collect_symbols(wildcards, required);
+                }
+            }
+            Pattern::Pow(p) => {
+                p[0].collect_symbols(wildcards, required);
+                p[1].collect_symbols(wildcards, required);
+            }
+            Pattern::Mul(args) | Pattern::Add(args) => {
+                for a in args {
+                    a.collect_symbols(wildcards, required);
+                }
+            }
+            Pattern::Transformer(_) => {
+                // the result of a transformer is not known in advance
+            }
+        }
+    }
+
+    fn collect_atom_symbols(atom: AtomView<'_>, out: &mut Vec<Symbol>) {
+        match atom {
+            AtomView::Num(_) => {}
+            AtomView::Var(v) => {
+                let s = v.get_symbol();
+                if !out.contains(&s) {
+                    out.push(s);
+                }
+            }
+            AtomView::Fun(f) => {
+                let s = f.get_symbol();
+                if s != State::ARG && !out.contains(&s) {
+                    out.push(s);
+                }
+
+                for arg in f.iter() {
+                    Self::collect_atom_symbols(arg, out);
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                Self::collect_atom_symbols(base, out);
+                Self::collect_atom_symbols(exp, out);
+            }
+            AtomView::Mul(m) => {
+                for child in m.iter() {
+                    Self::collect_atom_symbols(child, out);
+                }
+            }
+            AtomView::Add(a) => {
+                for child in a.iter() {
+                    Self::collect_atom_symbols(child, out);
+                }
+            }
+        }
+    }
+}
+
+impl CompiledPattern {
+    /// Get the underlying pattern.
+    pub fn get_pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Get all wildcards that appear in the pattern, in order of first appearance.
+    pub fn get_wildcards(&self) -> &[Symbol] {
+        &self.wildcards
+    }
+
+    /// Get the symbols that must appear in any expression that the pattern matches.
+    pub fn get_required_symbols(&self) -> &[Symbol] {
+        &self.required_symbols
+    }
+
+    /// Get the structure that every expression that the pattern matches has.
+    pub fn get_skeleton(&self) -> &Skeleton {
+        &self.skeleton
+    }
+
+    /// A quick check to see if the pattern can match `target` itself.
+    pub fn could_match(&self, target: AtomView) -> bool {
+        self.skeleton.could_match(target) && self.pattern.could_match(target)
+    }
+
+    /// A quick check to see if the pattern could match anywhere in `target`.
+    /// If this returns `false`, no subexpression of `target` matches.
+    pub fn could_match_anywhere(&self, target: AtomView) -> bool {
+        self.required_symbols
+            .iter()
+            .all(|s| target.contains_symbol(*s))
+    }
+
+    /// Replace all occurrences of the pattern in the target.
+    /// See [Pattern::replace_all].
+    pub fn replace_all(
+        &self,
+        target: AtomView<'_>,
+        rhs: &Pattern,
+        conditions: Option<&Condition<WildcardAndRestriction>>,
+        settings: Option<&MatchSettings>,
+    ) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.replace_all_with_ws_into(target, rhs, ws, conditions, settings, &mut out);
+            out.into_inner()
+        })
+    }
+
+    /// Replace all occurrences of the pattern in the target, returning `true` iff a match was found.
+    /// See [Pattern::replace_all_into].
+    pub fn replace_all_into(
+        &self,
+        target: AtomView<'_>,
+        rhs: &Pattern,
+        conditions: Option<&Condition<WildcardAndRestriction>>,
+        settings: Option<&MatchSettings>,
+        out: &mut Atom,
+    ) -> bool {
+        Workspace::get_local()
+            .with(|ws| self.replace_all_with_ws_into(target, rhs, ws, conditions, settings, out))
+    }
+
+    /// Replace all occurrences of the pattern in the target, returning `true` iff a match was found.
+    /// See [Pattern::replace_all_with_ws_into].
+    pub fn replace_all_with_ws_into(
+        &self,
+        target: AtomView<'_>,
+        rhs: &Pattern,
+        workspace: &Workspace,
+        conditions: Option<&Condition<WildcardAndRestriction>>,
+        settings: Option<&MatchSettings>,
+        out: &mut Atom,
+    ) -> bool {
+        if !self.could_match_anywhere(target) {
+            out.set_from_view(&target);
+            return false;
+        }
+
+        let matched = Replacement::new(
+            &self.pattern,
+            &self.skeleton,
+            rhs,
+            conditions,
+            settings.unwrap_or(&MatchSettings::default()),
+        )
+        .replace_all_no_norm(target, workspace, 0, out);
+
+        if matched {
+            let mut norm = workspace.new_atom();
+            out.as_view().normalize(workspace, &mut norm);
+            std::mem::swap(out, &mut norm);
+        }
+
+        matched
     }
 }
 
@@ -863,6 +1511,10 @@ dyn_clone::clone_trait_object!(CmpFn);
 impl<T: Clone + Send + Sync + for<'a, 'b> Fn(&Match<'_>, &Match<'_>) -> bool> CmpFn for T {}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serialization",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 pub enum AtomType {
     Num,
     Var,
@@ -881,6 +1533,9 @@ pub enum PatternRestriction {
     Length(usize, Option<usize>), // min-max range
     IsAtomType(AtomType),
     IsLiteralWildcard(Symbol),
+    Ground,
+    /// The match must be equal to one of the atoms. A function name matches a variable with the same name.
+    OneOf(Vec<Atom>),
     Filter(Box<dyn FilterFn>),
     Cmp(Symbol, Box<dyn CmpFn>),
     NotGreedy,
@@ -890,6 +1545,10 @@ pub type WildcardAndRestriction = (Symbol, PatternRestriction);
 
 /// A logical expression.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serialization",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 pub enum Condition<T> {
     And(Box<(Condition<T>, Condition<T>)>),
     Or(Box<(Condition<T>, Condition<T>)>),
@@ -990,10 +1649,18 @@ impl Condition<WildcardAndRestriction> {
     fn check_possible(&self, var: Symbol, value: &Match, stack: &MatchStack) -> ConditionResult {
         match self {
             Condition::And(a) => {
-                a.0.check_possible(var, value, stack) & a.1.check_possible(var, value, stack)
+                let r = a.0.check_possible(var, value, stack);
+                if r == ConditionResult::False {
+                    return r;
+                }
+                r & a.1.check_possible(var, value, stack)
             }
             Condition::Or(o) => {
-                o.0.check_possible(var, value, stack) | o.1.check_possible(var, value, stack)
+                let r = o.0.check_possible(var, value, stack);
+                if r == ConditionResult::True {
+                    return r;
+                }
+                r | o.1.check_possible(var, value, stack)
             }
             Condition::Not(n) => !n.check_possible(var, value, stack),
             Condition::True => ConditionResult::True,
@@ -1048,6 +1715,19 @@ impl Condition<WildcardAndRestriction> {
                             && max.map(|m| m >= slice.len()).unwrap_or(true))
                         .into(),
                     },
+                    PatternRestriction::Ground => value.wildcards().is_empty().into(),
+                    PatternRestriction::OneOf(atoms) => match value {
+                        Match::Single(a) => atoms.iter().any(|x| x.as_view() == *a),
+                        Match::FunctionName(f) => atoms.iter().any(
+                            |x| matches!(x.as_view(), AtomView::Var(v) if v.get_symbol() == *f),
+                        ),
+                        Match::Multiple(..) => {
+                            let mut a = RecycledAtom::new();
+                            value.to_atom(&mut a);
+                            atoms.contains(&*a)
+                        }
+                    }
+                    .into(),
                     PatternRestriction::Filter(f) => f(value).into(),
                     PatternRestriction::Cmp(v2, f) => {
                         if *v == var {
@@ -1068,6 +1748,48 @@ impl Condition<WildcardAndRestriction> {
         }
     }
 
+    /// An estimate of the cost of checking the condition.
+    fn cost(&self) -> usize {
+        match self {
+            Condition::And(a) | Condition::Or(a) => a.0.cost().max(a.1.cost()),
+            Condition::Not(n) => n.cost(),
+            Condition::True | Condition::False => 0,
+            Condition::Yield((_, r)) => match r {
+                PatternRestriction::Length(..)
+                | PatternRestriction::IsAtomType(_)
+                | PatternRestriction::IsLiteralWildcard(_)
+                | PatternRestriction::NotGreedy => 0,
+                PatternRestriction::Ground => 1,
+                PatternRestriction::OneOf(_) => 2,
+                PatternRestriction::Cmp(..) => 3,
+                PatternRestriction::Filter(_) => 4,
+            },
+        }
+    }
+
+    /// Reorder the operands of every conjunction and disjunction so that
+    /// the cheapest one is checked first. Since a conjunction stops at the
+    /// first operand that is false and a disjunction at the first operand that
+    /// is true, user filters are often not called at all.
+    fn order_by_cost(&self) -> Condition<WildcardAndRestriction> {
+        match self {
+            Condition::And(a) | Condition::Or(a) => {
+                let (mut c1, mut c2) = (a.0.order_by_cost(), a.1.order_by_cost());
+                if c2.cost() < c1.cost() {
+                    std::mem::swap(&mut c1, &mut c2);
+                }
+
+                if let Condition::And(_) = self {
+                    Condition::And(Box::new((c1, c2)))
+                } else {
+                    Condition::Or(Box::new((c1, c2)))
+                }
+            }
+            Condition::Not(n) => Condition::Not(Box::new(n.order_by_cost())),
+            c => c.clone(),
+        }
+    }
+
     fn get_range_hint(&self, var: Symbol) -> (Option<usize>, Option<usize>) {
         match self {
             Condition::And(a) => {
@@ -1137,6 +1859,8 @@ impl Clone for PatternRestriction {
             Self::Length(min, max) => Self::Length(*min, *max),
             Self::IsAtomType(t) => Self::IsAtomType(*t),
             Self::IsLiteralWildcard(w) => Self::IsLiteralWildcard(*w),
+            Self::Ground => Self::Ground,
+            Self::OneOf(a) => Self::OneOf(a.clone()),
             Self::Filter(f) => Self::Filter(dyn_clone::clone_box(f)),
             Self::Cmp(i, f) => Self::Cmp(*i, dyn_clone::clone_box(f)),
             Self::NotGreedy => Self::NotGreedy,
@@ -1152,6 +1876,8 @@ impl std::fmt::Debug for PatternRestriction {
             Self::IsLiteralWildcard(arg0) => {
                 f.debug_tuple("IsLiteralWildcard").field(arg0).finish()
             }
+            Self::Ground => write!(f, "Ground"),
+            Self::OneOf(a) => f.debug_tuple("OneOf").field(a).finish(),
             Self::Filter(_) => f.debug_tuple("Filter").finish(),
             Self::Cmp(arg0, _) => f.debug_tuple("Cmp").field(arg0).finish(),
             Self::NotGreedy => write!(f, "NotGreedy"),
@@ -1177,6 +1903,25 @@ impl<'a> std::fmt::Debug for Match<'a> {
 }
 
 impl<'a> Match<'a> {
+    /// Get all distinct wildcards in the matched subexpression,
+    /// in order of first appearance.
+    pub fn wildcards(&self) -> Vec<Symbol> {
+        match self {
+            Self::Single(v) => v.wildcards(),
+            Self::Multiple(_, wargs) => {
+                let mut out = vec![];
+                for s in wargs.iter().flat_map(|a| a.wildcards()) {
+                    if !out.contains(&s) {
+                        out.push(s);
+                    }
+                }
+                out
+            }
+            Self::FunctionName(n) if n.get_wildcard_level() > 0 => vec![*n],
+            Self::FunctionName(_) => vec![],
+        }
+    }
+
     /// Create a new atom from a matched subexpression.
     /// Arguments lists are wrapped in the function `arg`.
     pub fn to_atom(&self, out: &mut Atom) {
@@ -1241,6 +1986,9 @@ pub struct MatchSettings {
     pub level_range: (usize, Option<usize>),
     /// Determine whether a level reflects the expression tree depth or the function depth.
     pub level_is_tree_depth: bool,
+    /// Functions whose arguments are not entered by the matcher, e.g. `hold`.
+    /// The functions themselves can still be matched as a whole.
+    pub opaque_functions: HashSet<Symbol>,
 }
 
 /// An insertion-ordered map of wildcard identifiers to a subexpressions.
@@ -1432,7 +2180,7 @@ impl<'a, 'b> AtomMatchIterator<'a, 'b> {
                     }
                 }
             } else if let Pattern::Literal(w) = self.pattern {
-                if w.as_view() == self.target {
+                if Pattern::literal_matches(w.as_view(), self.target) {
                     return Some((match_stack.len(), &[]));
                 }
             }
@@ -1622,6 +2370,44 @@ impl<'a, 'b> SubSliceIterator<'a, 'b> {
         }
     }
 
+    /// Check that the noncommutative factors of a product are matched in the order of the
+    /// pattern, and that no unmatched noncommutative factor lies in between them.
+    fn preserves_noncommutative_order(&self) -> bool {
+        let mut first = None;
+        let mut last = None;
+        let mut in_order = |i: usize| {
+            if self.target.get(i).is_noncommutative_factor() {
+                if last.is_some_and(|l| i < l) {
+                    return false;
+                }
+                first.get_or_insert(i);
+                last = Some(i);
+            }
+            true
+        };
+
+        for it in &self.iterators {
+            let ok = match it {
+                PatternIter::Wildcard(w) => w.indices.iter().all(|i| in_order(*i as usize)),
+                PatternIter::Literal(Some(i), _)
+                | PatternIter::Fn(Some(i), ..)
+                | PatternIter::Sequence(Some(i), ..) => in_order(*i),
+                _ => true,
+            };
+
+            if !ok {
+                return false;
+            }
+        }
+
+        if let (Some(first), Some(last)) = (first, last) {
+            (first..last)
+                .all(|i| self.used_flag[i] || !self.target.get(i).is_noncommutative_factor())
+        } else {
+            true
+        }
+    }
+
     /// Get the next matches, where the map of matches is written into `match_stack`.
     /// The function returns the length of the match stack before the last subiterator
     /// matched. This value can be ignored by the end-user. If `None` is returned,
@@ -1645,6 +2431,11 @@ impl<'a, 'b> SubSliceIterator<'a, 'b> {
                 {
                     // not done as the entire target is not used
                     forward_pass = false;
+                } else if self.target.get_type() == SliceType::Mul
+                    && !self.preserves_noncommutative_order()
+                {
+                    // the noncommutative factors are matched in a different order
+                    forward_pass = false;
                 } else {
                     // yield the current match
                     return Some((*self.matches.last().unwrap(), &self.used_flag));
@@ -1902,6 +2693,12 @@ impl<'a, 'b> SubSliceIterator<'a, 'b> {
                         if let AtomView::Fun(f) = self.target.get(ii) {
                             let target_name = f.get_symbol();
                             let name_match = if name.get_wildcard_level() > 0 {
+                                // a list is not a function
+                                if target_name == State::LIST {
+                                    ii += 1;
+                                    continue;
+                                }
+
                                 if let Some(new_stack_len) =
                                     match_stack.insert(*name, Match::FunctionName(target_name))
                                 {
@@ -1970,7 +2767,7 @@ impl<'a, 'b> SubSliceIterator<'a, 'b> {
 
                         tried_first_option = true;
 
-                        if self.target.get(ii) == *atom {
+                        if Pattern::literal_matches(*atom, self.target.get(ii)) {
                             *index = Some(ii);
                             self.matches.push(match_stack.len());
                             self.used_flag[ii] = true;
@@ -2111,7 +2908,10 @@ impl<'a> Iterator for AtomTreeIterator<'a> {
                     level
                 };
 
-                self.stack.push((Some(0), new_level, atom));
+                let is_opaque = matches!(atom, AtomView::Fun(f) if self.settings.opaque_functions.contains(&f.get_symbol()));
+                if !is_opaque {
+                    self.stack.push((Some(0), new_level, atom));
+                }
 
                 if level >= self.settings.level_range.0 {
                     return Some((location, atom));
@@ -2357,3 +3157,419 @@ impl<'a: 'b, 'b> ReplaceIterator<'a, 'b> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domains::finite_field::{FiniteFieldCore, Zp64},
+        representations::{Atom, AtomView, FunctionBuilder, SliceType},
+        state::{FunctionAttribute, RecycledAtom, State},
+    };
+
+    use super::{
+        AtomType, Condition, Match, MatchSettings, Pattern, PatternRestriction, Skeleton,
+        TraversalOrder,
+    };
+
+    #[test]
+    fn subexpressions() {
+        let a = Atom::parse("se_f(se_x, se_y^2) + se_x").unwrap();
+        let pre: Vec<_> = a.iter_subexpressions(TraversalOrder::PreOrder).collect();
+        let post: Vec<_> = a.iter_subexpressions(TraversalOrder::PostOrder).collect();
+        assert_eq!(pre.len(), 7);
+        assert_eq!(pre[0], a.as_view());
+        assert_eq!(post.last(), Some(&a.as_view()));
+        let mut sorted_pre = pre.clone();
+        sorted_pre.sort();
+        let mut sorted_post = post.clone();
+        sorted_post.sort();
+        assert_eq!(sorted_pre, sorted_post);
+
+        // number the occurrences of se_x in the order in which they are stored
+        let x = State::get_symbol("se_x");
+        let mut count = 0;
+        let r = a.map_subexpressions(|e| match e {
+            AtomView::Var(v) if v.get_symbol() == x => {
+                count += 1;
+                FunctionBuilder::new(x)
+                    .add_arg(&Atom::new_num(count))
+                    .finish()
+            }
+            _ => e.to_owned(),
+        });
+        assert_eq!(count, 2);
+        assert!(
+            r == Atom::parse("se_f(se_x(1), se_y^2) + se_x(2)").unwrap()
+                || r == Atom::parse("se_f(se_x(2), se_y^2) + se_x(1)").unwrap()
+        );
+    }
+
+    #[test]
+    fn replace_first() {
+        let expr = Atom::parse("rf_f(1) + rf_f(2)").unwrap();
+        let pat = Pattern::parse("rf_f(rf_x_)").unwrap();
+        let rhs = Pattern::parse("rf_x_^2").unwrap();
+
+        let (r, changed) = pat.replace_first(expr.as_view(), &rhs, None, None);
+        assert!(changed);
+        assert_eq!(r, Atom::parse("1 + rf_f(2)").unwrap());
+
+        let (r, changed) = pat.replace_first(r.as_view(), &rhs, None, None);
+        assert!(changed);
+        assert_eq!(r, Atom::parse("5").unwrap());
+
+        let (r2, changed) = pat.replace_first(r.as_view(), &rhs, None, None);
+        assert!(!changed);
+        assert_eq!(r2, r);
+
+        let expr = Atom::parse("rf_g(rf_f(3)*rf_y, rf_f(4))").unwrap();
+        let (r, changed) = pat.replace_first(expr.as_view(), &rhs, None, None);
+        assert!(changed);
+        assert_eq!(r, Atom::parse("rf_g(9*rf_y, rf_f(4))").unwrap());
+    }
+
+    #[test]
+    fn compiled_pattern() {
+        let pat = Pattern::parse("cp_f(cp_x_)*cp_y^2").unwrap();
+        let rhs = Pattern::parse("cp_g(cp_x_)").unwrap();
+        let compiled = pat.compile();
+
+        assert_eq!(compiled.get_wildcards().len(), 1);
+        assert_eq!(compiled.get_required_symbols().len(), 2);
+
+        for input in [
+            "cp_f(1)*cp_y^2 + cp_f(cp_z)*cp_y^2*cp_z",
+            "cp_f(1)*cp_y + cp_z",
+            "cp_h(cp_f(2)*cp_y^2)",
+        ] {
+            let target = Atom::parse(input).unwrap();
+            let mut r = target.clone();
+            let mut rc = target.clone();
+            for _ in 0..3 {
+                r = pat.replace_all(r.as_view(), &rhs, None, None);
+                rc = compiled.replace_all(rc.as_view(), &rhs, None, None);
+            }
+            assert_eq!(r, rc);
+        }
+
+        // the skeleton requires a product with a cp_f function and one other factor
+        assert_eq!(
+            compiled.get_skeleton(),
+            &Skeleton::List {
+                kind: SliceType::Mul,
+                min_args: 2,
+                heads: vec![State::get_symbol("cp_f")].into(),
+            }
+        );
+        let a = Atom::parse("cp_g(1)*cp_y^2").unwrap();
+        assert!(!compiled.could_match(a.as_view()));
+        let a = Atom::parse("cp_f(1)*cp_y^2").unwrap();
+        assert!(compiled.could_match(a.as_view()));
+
+        for (lhs, rhs) in [
+            ("cp_f(cp_x_)*cp_f(cp_z_)", "cp_g(cp_x_,cp_z_)"),
+            ("cp_f(cp_x_,1,cp_z___)", "cp_g(cp_z___)"),
+            ("cp_f(cp_x_)+cp_y", "cp_x_"),
+        ] {
+            let pat = Pattern::parse(lhs).unwrap();
+            let rhs = Pattern::parse(rhs).unwrap();
+            let compiled = pat.compile();
+
+            for input in [
+                "cp_f(1)*cp_f(2)*cp_y + cp_f(3)*cp_y",
+                "cp_h(cp_f(1,1), cp_f(2,1,3,4), cp_f(1,2))",
+                "cp_f(1)+cp_y+cp_z + cp_h(cp_f(2)+cp_y)",
+            ] {
+                let target = Atom::parse(input).unwrap();
+                assert_eq!(
+                    pat.replace_all(target.as_view(), &rhs, None, None),
+                    compiled.replace_all(target.as_view(), &rhs, None, None)
+                );
+            }
+        }
+
+        let target = Atom::parse("cp_f(1)*cp_z^2").unwrap();
+        assert!(!compiled.could_match_anywhere(target.as_view()));
+        let mut out = RecycledAtom::new();
+        assert!(!compiled.replace_all_into(target.as_view(), &rhs, None, None, &mut out));
+        assert_eq!(*out, target);
+    }
+
+    #[test]
+    fn condition_cost_order() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let x = State::get_symbol("cco_x_");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let c = calls.clone();
+        let filter: Condition<_> = (
+            x,
+            PatternRestriction::Filter(Box::new(move |_| {
+                c.fetch_add(1, Ordering::Relaxed);
+                true
+            })),
+        )
+            .into();
+        let is_var: Condition<_> = (x, PatternRestriction::IsAtomType(AtomType::Var)).into();
+
+        let pat = Pattern::parse("cco_f(cco_x_)").unwrap();
+        let rhs = Pattern::parse("cco_g(cco_x_)").unwrap();
+        let target = Atom::parse("cco_f(1) + cco_f(2) + cco_f(cco_y)").unwrap();
+        let expected = Atom::parse("cco_f(1) + cco_f(2) + cco_g(cco_y)").unwrap();
+
+        // the filter is only called for the argument that passes the type check
+        let conditions = filter.clone() & is_var.clone();
+        let r = pat.replace_all(target.as_view(), &rhs, Some(&conditions), None);
+        assert_eq!(r, expected);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        let r = pat
+            .compile()
+            .replace_all(target.as_view(), &rhs, Some(&(is_var & filter)), None);
+        assert_eq!(r, expected);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn match_finite_field_literal() {
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        let conditions = Condition::default();
+        let settings = MatchSettings::default();
+
+        // the literal is reduced into the field of the target, so 3 matches 10 mod 7
+        let target = Atom::parse("ffm_f(10)").unwrap().to_finite_field(field);
+        let pat = Pattern::parse("ffm_f(3)").unwrap();
+        assert!(target
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_some());
+
+        let pat = Pattern::parse("ffm_f(4)").unwrap();
+        assert!(target
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_none());
+
+        let target = Atom::parse("ffm_f(10,2)").unwrap().to_finite_field(field);
+        let pat = Pattern::parse("ffm_f(3,ffm_x_)").unwrap();
+        let m = target
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .unwrap();
+        let two = Atom::new_num(2).to_finite_field(field);
+        assert_eq!(
+            m.get(&State::get_symbol("ffm_x_")),
+            Some(&Match::Single(two.as_view()))
+        );
+
+        // numbers inside nested literals are reduced as well
+        let target = Atom::parse("ffm_f(10*ffm_y^2, ffm_g(1/2))")
+            .unwrap()
+            .to_finite_field(field);
+        let pat = Pattern::parse("ffm_f(3*ffm_y^2, ffm_g(4))").unwrap();
+        assert!(target
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_some());
+
+        let pat = Pattern::parse("ffm_f(3*ffm_y^3, ffm_g(4))").unwrap();
+        assert!(target
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_none());
+    }
+
+    #[test]
+    fn opaque_functions() {
+        let hold = State::get_symbol("opq_hold");
+        let expr = Atom::parse("opq_x + opq_hold(opq_x)").unwrap();
+        let pat = Pattern::parse("opq_x").unwrap();
+        let rhs = Pattern::parse("opq_y").unwrap();
+
+        let mut settings = MatchSettings::default();
+        settings.opaque_functions.insert(hold);
+
+        let r = pat.replace_all(expr.as_view(), &rhs, None, Some(&settings));
+        assert_eq!(r, Atom::parse("opq_y + opq_hold(opq_x)").unwrap());
+
+        let r = pat.replace_all(expr.as_view(), &rhs, None, None);
+        assert_eq!(r, Atom::parse("opq_y + opq_hold(opq_y)").unwrap());
+
+        // the function itself can still be matched
+        let conditions = Condition::default();
+        let pat = Pattern::parse("opq_hold(opq_z_)").unwrap();
+        assert!(expr
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_some());
+        let pat = Pattern::parse("opq_x").unwrap();
+        let mut it = pat.pattern_match(expr.as_view(), &conditions, &settings);
+        let mut count = 0;
+        while it.next().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn match_first() {
+        let pat = Pattern::parse("mf_f(mf_x_,mf_y_)").unwrap();
+        let conditions = Condition::default();
+        let settings = MatchSettings::default();
+
+        let target = Atom::parse("mf_f(3,4)").unwrap();
+        let m = target
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .unwrap();
+
+        let three = Atom::new_num(3);
+        let four = Atom::new_num(4);
+        assert_eq!(
+            m.get(&State::get_symbol("mf_x_")),
+            Some(&Match::Single(three.as_view()))
+        );
+        assert_eq!(
+            m.get(&State::get_symbol("mf_y_")),
+            Some(&Match::Single(four.as_view()))
+        );
+
+        let target = Atom::parse("mf_g(3,4)").unwrap();
+        assert!(target
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_none());
+    }
+
+    #[test]
+    fn one_of_restriction() {
+        let x = State::get_symbol("oo_x_");
+        let allowed = vec![Atom::parse("oo_a").unwrap(), Atom::parse("oo_b").unwrap()];
+        let conditions: Condition<_> = (x, PatternRestriction::OneOf(allowed)).into();
+        let settings = MatchSettings::default();
+        let pat = Pattern::parse("oo_f(oo_x_)").unwrap();
+
+        for (expr, matches) in [
+            ("oo_f(oo_a)", true),
+            ("oo_f(oo_b)", true),
+            ("oo_f(oo_c)", false),
+        ] {
+            let expr = Atom::parse(expr).unwrap();
+            assert_eq!(
+                expr.as_view()
+                    .match_first(&pat, &conditions, &settings)
+                    .is_some(),
+                matches
+            );
+        }
+
+        // restrict a function name
+        let f = State::get_symbol("oo_f_");
+        let allowed = vec![Atom::new_var(State::SIN), Atom::new_var(State::COS)];
+        let conditions: Condition<_> = (f, PatternRestriction::OneOf(allowed)).into();
+        let pat = Pattern::parse("oo_f_(oo_y)").unwrap();
+        let expr = Atom::parse("sin(oo_y)").unwrap();
+        assert!(expr
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_some());
+        let expr = Atom::parse("exp(oo_y)").unwrap();
+        assert!(expr
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_none());
+    }
+
+    #[test]
+    fn ground_restriction() {
+        let x = State::get_symbol("gr_x_");
+        let conditions: Condition<_> = (x, PatternRestriction::Ground).into();
+        let settings = MatchSettings::default();
+        let pat = Pattern::parse("gr_f(gr_x_)").unwrap();
+
+        let expr = Atom::parse("gr_f(gr_a+gr_b)").unwrap();
+        assert!(expr
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_some());
+
+        let expr = Atom::parse("gr_f(gr_y_)").unwrap();
+        assert!(expr
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_none());
+        assert!(expr
+            .as_view()
+            .match_first(&pat, &Condition::default(), &settings)
+            .is_some());
+
+        let expr = Atom::parse("gr_f(gr_g__(gr_a))").unwrap();
+        assert!(expr
+            .as_view()
+            .match_first(&pat, &conditions, &settings)
+            .is_none());
+    }
+
+    #[test]
+    fn map_subterms() {
+        let g = State::get_symbol("ms_g");
+        let a = Atom::parse("2 + ms_f(3)").unwrap();
+        let r = a.map_subterms(
+            |x| matches!(x, AtomView::Num(_)),
+            |x| FunctionBuilder::new(g).add_arg(x).finish(),
+        );
+        assert_eq!(r, Atom::parse("ms_g(2) + ms_f(ms_g(3))").unwrap());
+
+        // nested functions are wrapped from the inside out
+        let a = Atom::parse("ms_f(ms_f(ms_x))").unwrap();
+        let r = a.map_subterms(
+            |x| matches!(x, AtomView::Fun(f) if f.get_symbol() != g),
+            |x| FunctionBuilder::new(g).add_arg(x).finish(),
+        );
+        assert_eq!(r, Atom::parse("ms_g(ms_f(ms_g(ms_f(ms_x))))").unwrap());
+    }
+
+    #[test]
+    fn noncommutative_order() {
+        for name in ["nco_a", "nco_b", "nco_c"] {
+            State::get_symbol_with_attributes(name, vec![FunctionAttribute::NonCommutative])
+                .unwrap();
+        }
+
+        let rhs = Pattern::parse("1").unwrap();
+        let replace = |pat: &str, target: &str| {
+            Pattern::parse(pat).unwrap().replace_all(
+                Atom::parse(target).unwrap().as_view(),
+                &rhs,
+                None,
+                None,
+            )
+        };
+
+        let target = Atom::parse("nco_a*nco_b").unwrap();
+        assert_eq!(replace("nco_b*nco_a", "nco_a*nco_b"), target);
+        assert_eq!(replace("nco_a*nco_b", "nco_a*nco_b"), Atom::new_num(1));
+        assert_eq!(
+            replace("nco_a*nco_b", "2*x*nco_a*nco_b*nco_c"),
+            Atom::parse("2*x*nco_c").unwrap()
+        );
+
+        // an unmatched noncommutative factor in between prevents the match
+        assert_eq!(
+            replace("nco_a*nco_c", "nco_a*nco_b*nco_c"),
+            Atom::parse("nco_a*nco_b*nco_c").unwrap()
+        );
+
+        // wildcards match in the position they have in the pattern
+        let r = Pattern::parse("x_*nco_b").unwrap().replace_all(
+            target.as_view(),
+            &Pattern::parse("f(x_)").unwrap(),
+            None,
+            None,
+        );
+        assert_eq!(r, Atom::parse("f(nco_a)").unwrap());
+    }
+}
@@ -17,6 +17,7 @@ pub mod coefficient;
 pub mod collect;
 pub mod combinatorics;
 pub mod derivative;
+pub mod dimension;
 pub mod domains;
 pub mod evaluate;
 pub mod expand;
@@ -27,6 +28,8 @@ pub mod parser;
 pub mod poly;
 pub mod printer;
 pub mod representations;
+#[cfg(feature = "serialization")]
+pub mod serialize;
 pub mod solve;
 pub mod state;
 pub mod streaming;
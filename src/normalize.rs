@@ -2,12 +2,19 @@ use std::{cmp::Ordering, ops::DerefMut};
 
 use smallvec::SmallVec;
 
+use ahash::HashMap;
+
 use crate::{
     coefficient::{Coefficient, CoefficientView},
-    domains::{integer::Z, rational::Q},
+    domains::{
+        algebraic_number::AlgebraicNumberRing,
+        integer::Z,
+        rational::{Rational, Q},
+        Field, Ring,
+    },
     poly::Variable,
-    representations::{Atom, AtomView, Fun, Symbol},
-    state::{RecycledAtom, State, Workspace},
+    representations::{Atom, AtomView, Fun, MulView, Symbol},
+    state::{RecycledAtom, State, TermOrder, Workspace},
 };
 
 impl<'a> AtomView<'a> {
@@ -100,6 +107,237 @@ impl<'a> AtomView<'a> {
         }
     }
 
+    /// Returns `true` iff the factor does not commute with other noncommutative factors,
+    /// which is the case when it contains a noncommutative symbol outside of a function argument.
+    /// The derivative of a noncommutative expression is also noncommutative.
+    #[inline]
+    pub(crate) fn is_noncommutative_factor(&self) -> bool {
+        State::has_noncommutative_symbols() && self.is_noncommutative_factor_impl()
+    }
+
+    fn is_noncommutative_factor_impl(&self) -> bool {
+        match self {
+            AtomView::Num(_) => false,
+            AtomView::Var(v) => v.get_symbol().is_noncommutative(),
+            AtomView::Fun(f) => {
+                f.get_symbol().is_noncommutative()
+                    || f.get_symbol() == State::DERIVATIVE
+                        && f.iter()
+                            .last()
+                            .is_some_and(|a| a.is_noncommutative_factor_impl())
+            }
+            AtomView::Pow(p) => p.get_base().is_noncommutative_factor_impl(),
+            AtomView::Mul(m) => m.iter().any(|x| x.is_noncommutative_factor_impl()),
+            AtomView::Add(a) => a.iter().any(|x| x.is_noncommutative_factor_impl()),
+        }
+    }
+
+    /// Write the complex conjugate of the normalized expression into `out`, without
+    /// normalizing the result. Conjugation is distributed over sums, products,
+    /// integer powers and real functions. Returns `false` if no rule applies.
+    fn conj_no_norm(&self, workspace: &Workspace, out: &mut Atom) -> bool {
+        if self.is_real() {
+            out.set_from_view(self);
+            return true;
+        }
+
+        let conj = |a: AtomView| {
+            let mut c = workspace.new_atom();
+            c.to_fun(State::CONJ).add_arg(a);
+            c
+        };
+
+        match self {
+            AtomView::Num(n) => {
+                if let Coefficient::Complex(c) = n.get_coeff_view().to_owned() {
+                    let (re, im) = *c;
+                    out.to_num(Coefficient::complex(re, im * Coefficient::from(-1)));
+                    true
+                } else {
+                    false
+                }
+            }
+            AtomView::Var(v) if v.get_symbol() == State::I => {
+                let m = out.to_mul();
+                m.extend(*self);
+                m.extend(workspace.new_num(-1).as_view());
+                true
+            }
+            AtomView::Fun(f) if f.get_symbol() == State::CONJ && f.get_nargs() == 1 => {
+                out.set_from_view(&f.iter().next().unwrap());
+                true
+            }
+            AtomView::Fun(f)
+                if f.get_symbol().is_real()
+                    || [State::EXP, State::SIN, State::COS].contains(&f.get_symbol()) =>
+            {
+                let out = out.to_fun(f.get_symbol());
+                for a in f.iter() {
+                    out.add_arg(conj(a).as_view());
+                }
+                true
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                if let AtomView::Num(n) = exp {
+                    if let CoefficientView::Natural(_, 1) = n.get_coeff_view() {
+                        out.to_pow(conj(base).as_view(), exp);
+                        return true;
+                    }
+                }
+                false
+            }
+            AtomView::Mul(m) => {
+                let out = out.to_mul();
+                for a in m.iter() {
+                    out.extend(conj(a).as_view());
+                }
+                true
+            }
+            AtomView::Add(a) => {
+                let out = out.to_add();
+                for t in a.iter() {
+                    out.extend(conj(t).as_view());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Write the real part, or the imaginary part if `imaginary` is set, of the normalized
+    /// expression into `out`, without normalizing the result. Returns `false` if no rule applies.
+    fn real_imag_part_no_norm(
+        &self,
+        imaginary: bool,
+        workspace: &Workspace,
+        out: &mut Atom,
+    ) -> bool {
+        let part_id = if imaginary { State::IM } else { State::RE };
+
+        if self.is_real() {
+            if imaginary {
+                out.to_num(Coefficient::zero());
+            } else {
+                out.set_from_view(self);
+            }
+            return true;
+        }
+
+        match self {
+            AtomView::Num(n) => {
+                if let Coefficient::Complex(c) = n.get_coeff_view().to_owned() {
+                    let (re, im) = *c;
+                    out.to_num(if imaginary { im } else { re });
+                    true
+                } else {
+                    false
+                }
+            }
+            AtomView::Var(v) if v.get_symbol() == State::I => {
+                out.to_num(if imaginary { 1 } else { 0 }.into());
+                true
+            }
+            AtomView::Fun(f) if f.get_symbol() == State::CONJ && f.get_nargs() == 1 => {
+                // re(conj(x)) = re(x) and im(conj(x)) = -im(x)
+                let m = out.to_mul();
+                let mut p = workspace.new_atom();
+                p.to_fun(part_id).add_arg(f.iter().next().unwrap());
+                m.extend(p.as_view());
+                if imaginary {
+                    m.extend(workspace.new_num(-1).as_view());
+                }
+                true
+            }
+            AtomView::Add(a) => {
+                let out = out.to_add();
+                for t in a.iter() {
+                    let mut p = workspace.new_atom();
+                    p.to_fun(part_id).add_arg(t);
+                    out.extend(p.as_view());
+                }
+                true
+            }
+            AtomView::Mul(m) => {
+                // pull out real factors and the coefficient c = a + b*i, using
+                // re(c*x) = a*re(x) - b*im(x) and im(c*x) = a*im(x) + b*re(x)
+                let mut coeff = Coefficient::one();
+                let mut rest = workspace.new_atom();
+                let rest_mul = rest.to_mul();
+                let mut real = workspace.new_atom();
+                let real_mul = real.to_mul();
+                for f in m.iter() {
+                    match f {
+                        AtomView::Num(n) => {
+                            coeff = coeff * n.get_coeff_view().to_owned();
+                        }
+                        AtomView::Var(v) if v.get_symbol() == State::I => {
+                            coeff = coeff * Coefficient::i();
+                        }
+                        _ if f.is_real() => {
+                            real_mul.extend(f);
+                        }
+                        _ => {
+                            rest_mul.extend(f);
+                        }
+                    }
+                }
+
+                if coeff.is_one() && real_mul.to_mul_view().get_nargs() == 0 {
+                    return false;
+                }
+
+                let (a, b) = match coeff {
+                    Coefficient::Complex(c) => *c,
+                    Coefficient::Rational(_) | Coefficient::Float(_) => {
+                        (coeff, Coefficient::zero())
+                    }
+                    _ => return false,
+                };
+
+                let rest_view = rest.as_view();
+                let mut w = workspace.new_atom();
+                match rest_view {
+                    AtomView::Mul(r) if r.get_nargs() == 0 => {
+                        w.to_num(Coefficient::one());
+                    }
+                    AtomView::Mul(r) if r.get_nargs() == 1 => {
+                        w.set_from_view(&r.iter().next().unwrap());
+                    }
+                    _ => w.set_from_view(&rest_view),
+                }
+
+                let mut sum = workspace.new_atom();
+                let add = sum.to_add();
+                let (c_re, c_im) = if imaginary {
+                    (b, a)
+                } else {
+                    (a, b * Coefficient::from(-1))
+                };
+                for (c, part) in [(c_re, State::RE), (c_im, State::IM)] {
+                    if !c.is_zero() {
+                        let mut t = workspace.new_atom();
+                        let t_mul = t.to_mul();
+                        t_mul.extend(workspace.new_num(c).as_view());
+                        let mut p = workspace.new_atom();
+                        p.to_fun(part).add_arg(w.as_view());
+                        t_mul.extend(p.as_view());
+                        add.extend(t.as_view());
+                    }
+                }
+
+                if add.to_add_view().get_nargs() == 0 {
+                    out.to_num(Coefficient::zero());
+                } else {
+                    real_mul.extend(sum.as_view());
+                    out.set_from_view(&real.as_view());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Compare factors in a term. `x` and `x^2` are placed next to each other by sorting a power based on the base only.
     pub(crate) fn cmp_factors(&self, other: &AtomView<'_>) -> Ordering {
         match (&self, other) {
@@ -274,6 +512,14 @@ impl<'a> AtomView<'a> {
 }
 
 impl Atom {
+    /// Normalize a power that is created by merging factors, since it may simplify
+    /// further, for example `4^(1/4)*4^(1/4) = 4^(1/2) = 2`. This also marks it as normalized.
+    fn normalize_merged_pow(&mut self, workspace: &Workspace) {
+        let mut norm = workspace.new_atom();
+        self.as_view().normalize(workspace, &mut norm);
+        std::mem::swap(self, &mut norm);
+    }
+
     /// Merge two factors if possible. If this function returns `true`, `self`
     /// will have been updated by the merge from `other` and `other` should be discarded.
     /// If the function return `false`, no merge was possible and no modifications were made.
@@ -284,6 +530,15 @@ impl Atom {
 
         workspace: &Workspace,
     ) -> bool {
+        // lists and their powers are not combined
+        let is_list = |a: AtomView| match a {
+            AtomView::Pow(p) => p.get_base().is_list(),
+            a => a.is_list(),
+        };
+        if is_list(self.as_view()) || is_list(other.as_view()) {
+            return false;
+        }
+
         // x^a * x^b = x^(a + b)
         if let Atom::Pow(p1) = self {
             if let Atom::Pow(p2) = other {
@@ -305,6 +560,7 @@ impl Atom {
                             self.set_from_view(&base2);
                         } else {
                             p1.set_from_base_and_exp(base2, helper.as_view());
+                            self.normalize_merged_pow(workspace);
                         }
 
                         return true;
@@ -317,10 +573,24 @@ impl Atom {
                 let mut helper2 = workspace.new_atom();
                 helper.as_view().normalize(workspace, &mut helper2);
                 p1.set_from_base_and_exp(base2, helper2.as_view());
+                self.normalize_merged_pow(workspace);
                 return true;
             }
         }
 
+        // a number is kept as the coefficient instead of being merged into a power of
+        // the same number, since 2*2^(1/2) would otherwise become 8^(1/2) and be split again
+        if matches!(self, Atom::Num(_)) != matches!(other, Atom::Num(_)) {
+            return false;
+        }
+
+        // x^n * x = x * x^n
+        if let Atom::Pow(p) = self {
+            if p.to_pow_view().get_base_exp().0 == other.as_view() {
+                std::mem::swap(self, other);
+            }
+        }
+
         // x * x^n = x^(n+1)
         if let Atom::Pow(p) = other {
             let pv = p.to_pow_view();
@@ -337,6 +607,7 @@ impl Atom {
                     } else {
                         let num = helper.to_num(new_exp);
                         self.to_pow(base, AtomView::Num(num.to_num_view()));
+                        self.normalize_merged_pow(workspace);
                     }
                 } else {
                     self.to_num(1.into());
@@ -347,6 +618,7 @@ impl Atom {
                     let mut helper2 = workspace.new_atom();
                     helper.as_view().normalize(workspace, &mut helper2);
                     self.to_pow(base, helper2.as_view());
+                    self.normalize_merged_pow(workspace);
                 }
 
                 return true;
@@ -380,6 +652,7 @@ impl Atom {
 
             // overwrite self with the new power view
             std::mem::swap(self, helper);
+            self.normalize_merged_pow(workspace);
 
             return true;
         }
@@ -422,7 +695,8 @@ impl Atom {
                     m2.to_slice()
                 };
 
-                if non_coeff1.eq(&non_coeff2) {
+                // terms with a list are not combined
+                if non_coeff1.eq(&non_coeff2) && !non_coeff1.iter().any(|x| x.is_list()) {
                     // TODO: not correct for finite fields!
                     let num = if let AtomView::Num(n) = &last_elem {
                         n.get_coeff_view()
@@ -468,12 +742,14 @@ impl Atom {
                         m.replace_last(on.to_num_view().as_view());
                     } else {
                         m.extend(on.to_num_view().as_view());
+                        m.set_has_coefficient(true);
+                        m.set_normalized(true);
                     }
 
                     return true;
                 }
             } else {
-                if non_coeff1.len() != 1 || other != slice.get(0) {
+                if non_coeff1.len() != 1 || other != slice.get(0) || other.is_list() {
                     return false;
                 }
 
@@ -504,7 +780,7 @@ impl Atom {
 
             let last_elem = slice.get(slice.len() - 1);
 
-            if self.as_view() == slice.get(0) {
+            if self.as_view() == slice.get(0) && !self.as_view().is_list() {
                 let (new_coeff, has_num) = if let AtomView::Num(n) = &last_elem {
                     (n.get_coeff_view() + 1, true)
                 } else {
@@ -526,12 +802,14 @@ impl Atom {
                         m.replace_last(on.to_num_view().as_view());
                     } else {
                         m.extend(on.to_num_view().as_view());
+                        m.set_has_coefficient(true);
+                        m.set_normalized(true);
                     }
                 }
 
                 return true;
             }
-        } else if self.as_view() == other {
+        } else if self.as_view() == other && !other.is_list() {
             let mul = helper.to_mul();
             mul.extend(self.as_view());
             self.to_num((2, 1).into());
@@ -551,7 +829,8 @@ impl<'a> AtomView<'a> {
     #[inline(always)]
     pub fn needs_normalization(&self) -> bool {
         match self {
-            AtomView::Num(_) | AtomView::Var(_) => false,
+            AtomView::Num(_) => false,
+            AtomView::Var(v) => v.get_symbol() == State::I,
             AtomView::Fun(f) => !f.is_normalized(),
             AtomView::Pow(p) => !p.is_normalized(),
             AtomView::Mul(m) => !m.is_normalized(),
@@ -579,6 +858,13 @@ impl<'a> AtomView<'a> {
                         handle.set_from_view(&a);
                     }
 
+                    // the imaginary unit is absorbed into the coefficient
+                    if let AtomView::Var(v) = handle.as_view() {
+                        if v.get_symbol() == State::I {
+                            handle.to_num(Coefficient::i());
+                        }
+                    }
+
                     if let Atom::Mul(mul) = handle.deref_mut() {
                         for c in mul.to_mul_view().iter() {
                             // TODO: remove this copy
@@ -614,7 +900,31 @@ impl<'a> AtomView<'a> {
                     }
                 }
 
-                atom_test_buf.sort_by(|a, b| a.as_view().cmp_factors(&b.as_view()));
+                if atom_test_buf
+                    .iter()
+                    .any(|a| a.as_view().is_noncommutative_factor())
+                {
+                    // commutative factors are sorted and moved to the front, followed by the noncommutative
+                    // factors in their original order and finally the coefficient
+                    let class = |a: AtomView| match a {
+                        AtomView::Num(_) => 2,
+                        _ if a.is_noncommutative_factor() => 1,
+                        _ => 0,
+                    };
+
+                    let order = State::get_custom_term_order().unwrap_or_default();
+                    atom_test_buf.sort_by(|a, b| {
+                        let (a, b) = (a.as_view(), b.as_view());
+                        match (class(a), class(b)) {
+                            (0, 0) => order.cmp_factors(&a, &b),
+                            (c1, c2) => c1.cmp(&c2),
+                        }
+                    });
+                } else if let Some(order) = State::get_custom_term_order() {
+                    atom_test_buf.sort_by(|a, b| order.cmp_factors(&a.as_view(), &b.as_view()));
+                } else {
+                    atom_test_buf.sort_by(|a, b| a.as_view().cmp_factors(&b.as_view()));
+                }
 
                 if !atom_test_buf.is_empty() {
                     let out_mul = out.to_mul();
@@ -624,9 +934,16 @@ impl<'a> AtomView<'a> {
                     let mut tmp = workspace.new_atom();
                     let mut cur_len = 0;
 
+                    // merged factors that become a product or sum, such as reduced
+                    // powers of algebraic numbers, require the product to be normalized again
+                    let mut renormalize = false;
+
                     atom_test_buf.reverse();
                     while let Some(mut cur_buf) = atom_test_buf.pop() {
-                        if !last_buf.merge_factors(&mut cur_buf, &mut tmp, workspace) {
+                        if last_buf.merge_factors(&mut cur_buf, &mut tmp, workspace) {
+                            renormalize |=
+                                matches!(last_buf.as_view(), AtomView::Mul(_) | AtomView::Add(_));
+                        } else {
                             // we are done merging
                             {
                                 let v = last_buf.as_view();
@@ -659,12 +976,25 @@ impl<'a> AtomView<'a> {
                                 // downgrade
                                 last_buf.set_from_view(&out_mul.to_mul_view().to_slice().get(0));
                                 out.set_from_view(&last_buf.as_view());
+                            } else {
+                                out_mul.set_normalized(true);
                             }
                         } else {
                             out_mul.extend(v);
                             out_mul.set_normalized(true);
                         }
                     }
+
+                    if renormalize {
+                        if let AtomView::Mul(m) = out.as_view() {
+                            let mut new_mul = workspace.new_atom();
+                            let nm = new_mul.to_mul();
+                            for f in m.iter() {
+                                nm.extend(f);
+                            }
+                            new_mul.as_view().normalize(workspace, out);
+                        }
+                    }
                 } else {
                     out.to_num(1.into());
                 }
@@ -673,8 +1003,13 @@ impl<'a> AtomView<'a> {
                 let normalized_num = n.get_coeff_view().normalize();
                 out.to_num(normalized_num);
             }
-            AtomView::Var(_) => {
-                self.clone_into(out);
+            AtomView::Var(v) => {
+                // the imaginary unit is a number
+                if v.get_symbol() == State::I {
+                    out.to_num(Coefficient::i());
+                } else {
+                    self.clone_into(out);
+                }
             }
             AtomView::Fun(f) => {
                 let id = f.get_symbol();
@@ -754,6 +1089,136 @@ impl<'a> AtomView<'a> {
                     }
                 }
 
+                // exact values of functions at the built-in constants
+                if [State::COS, State::SIN, State::LOG].contains(&id)
+                    && out_f.to_fun_view().get_nargs() == 1
+                {
+                    let arg = out_f.to_fun_view().iter().next().unwrap();
+                    if id == State::LOG {
+                        // log(𝑒) = 1 and log(𝑒^q) = q for rational q
+                        match arg {
+                            AtomView::Var(v) if v.get_symbol() == State::E => {
+                                let buffer = workspace.new_num(Coefficient::one());
+                                out.set_from_view(&buffer.as_view());
+                                return;
+                            }
+                            AtomView::Pow(p) => {
+                                let (base, exp) = p.get_base_exp();
+                                if let (AtomView::Var(v), AtomView::Num(n)) = (base, exp) {
+                                    if v.get_symbol() == State::E
+                                        && matches!(
+                                            n.get_coeff_view(),
+                                            CoefficientView::Natural(_, _)
+                                                | CoefficientView::Large(_)
+                                        )
+                                    {
+                                        let buffer =
+                                            workspace.new_num(n.get_coeff_view().to_owned());
+                                        out.set_from_view(&buffer.as_view());
+                                        return;
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    } else {
+                        // sin(n𝜋) = 0 and cos(n𝜋) = (-1)^n for integer n
+                        let multiple = match arg {
+                            AtomView::Var(v) if v.get_symbol() == State::PI => Some(1),
+                            AtomView::Mul(m) if m.get_nargs() == 2 => {
+                                let mut it = m.iter();
+                                match (it.next().unwrap(), it.next().unwrap()) {
+                                    (AtomView::Var(v), AtomView::Num(n))
+                                    | (AtomView::Num(n), AtomView::Var(v))
+                                        if v.get_symbol() == State::PI =>
+                                    {
+                                        if let CoefficientView::Natural(n, 1) = n.get_coeff_view() {
+                                            Some(n)
+                                        } else {
+                                            None
+                                        }
+                                    }
+                                    _ => None,
+                                }
+                            }
+                            _ => None,
+                        };
+
+                        if let Some(n) = multiple {
+                            let r = if id == State::SIN {
+                                0
+                            } else if n % 2 == 0 {
+                                1
+                            } else {
+                                -1
+                            };
+                            let buffer = workspace.new_num(r);
+                            out.set_from_view(&buffer.as_view());
+                            return;
+                        }
+                    }
+                }
+
+                // remove branches of a piecewise function with a numerical condition
+                if id == State::PIECEWISE {
+                    let args: Vec<_> = out_f.to_fun_view().iter().collect();
+                    let mut kept = Vec::with_capacity(args.len());
+                    let mut changed = false;
+
+                    let mut i = 0;
+                    while i < args.len() {
+                        if i + 1 == args.len() {
+                            kept.push(args[i]); // the default value
+                            break;
+                        }
+
+                        let (cond, value) = (args[i], args[i + 1]);
+                        i += 2;
+
+                        let holds = if let AtomView::Num(n) = cond {
+                            match n.get_coeff_view() {
+                                CoefficientView::Natural(n, _) => Some(n > 0),
+                                CoefficientView::Large(r) => Some(!r.is_negative()),
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        };
+
+                        match holds {
+                            Some(true) => {
+                                // all later branches are unreachable
+                                kept.push(value);
+                                changed = true;
+                                break;
+                            }
+                            Some(false) => {
+                                changed = true;
+                            }
+                            None => {
+                                kept.push(cond);
+                                kept.push(value);
+                            }
+                        }
+                    }
+
+                    if kept.len() == 1 {
+                        let mut buffer = workspace.new_atom();
+                        buffer.set_from_view(&kept[0]);
+                        out.set_from_view(&buffer.as_view());
+                        return;
+                    } else if changed {
+                        let mut buffer = workspace.new_atom();
+                        let f = buffer.to_fun(State::PIECEWISE);
+                        for a in kept {
+                            f.add_arg(a);
+                        }
+                        f.set_normalized(true);
+                        out.set_from_view(&buffer.as_view());
+                        return;
+                    }
+                }
+
                 // try to turn the argument into a number
                 if id == State::COEFF && out_f.to_fun_view().get_nargs() == 1 {
                     let arg = out_f.to_fun_view().iter().next().unwrap();
@@ -779,19 +1244,42 @@ impl<'a> AtomView<'a> {
                     }
                 }
 
+                // complex conjugation and the real and imaginary parts
+                if [State::CONJ, State::RE, State::IM].contains(&id)
+                    && out_f.to_fun_view().get_nargs() == 1
+                {
+                    let arg = out_f.to_fun_view().iter().next().unwrap();
+                    let mut buffer = workspace.new_atom();
+                    let changed = if id == State::CONJ {
+                        arg.conj_no_norm(workspace, &mut buffer)
+                    } else {
+                        arg.real_imag_part_no_norm(id == State::IM, workspace, &mut buffer)
+                    };
+
+                    if changed {
+                        buffer.as_view().normalize(workspace, out);
+                        return;
+                    }
+                }
+
                 if id.is_linear() {
                     // linearize sums
                     if out_f
                         .to_fun_view()
                         .iter()
-                        .any(|a| matches!(a, AtomView::Add(_)))
+                        .enumerate()
+                        .any(|(i, a)| id.is_linear_in(i) && matches!(a, AtomView::Add(_)))
                     {
                         let mut arg_buf = Vec::with_capacity(out_f.to_fun_view().get_nargs());
 
-                        for a in out_f.to_fun_view().iter() {
+                        for (i, a) in out_f.to_fun_view().iter().enumerate() {
                             let mut vec = vec![];
                             if let AtomView::Add(aa) = a {
-                                for a in aa.iter() {
+                                if id.is_linear_in(i) {
+                                    for a in aa.iter() {
+                                        vec.push(a);
+                                    }
+                                } else {
                                     vec.push(a);
                                 }
                             } else {
@@ -816,10 +1304,18 @@ impl<'a> AtomView<'a> {
                         return;
                     }
 
-                    // linearize products
-                    if out_f.to_fun_view().iter().any(|a| {
+                    // linearize products by pulling out the scalar factors, which are
+                    // the numbers and, in a product with noncommutative factors, the
+                    // commutative factors
+                    let is_scalar = |m: &MulView, a: AtomView| {
+                        matches!(a, AtomView::Num(_))
+                            || !a.is_noncommutative_factor()
+                                && m.iter().any(|x| x.is_noncommutative_factor())
+                    };
+
+                    if out_f.to_fun_view().iter().enumerate().any(|(i, a)| {
                         if let AtomView::Mul(m) = a {
-                            m.has_coefficient()
+                            id.is_linear_in(i) && m.iter().any(|x| is_scalar(&m, x))
                         } else {
                             false
                         }
@@ -828,16 +1324,15 @@ impl<'a> AtomView<'a> {
                         let t = new_term.to_mul();
                         let mut new_fun = workspace.new_atom();
                         let nf = new_fun.to_fun(id);
-                        let mut coeff: Coefficient = 1.into();
-                        for a in out_f.to_fun_view().iter() {
+                        for (i, a) in out_f.to_fun_view().iter().enumerate() {
                             if let AtomView::Mul(m) = a {
-                                if m.has_coefficient() {
+                                if id.is_linear_in(i) {
                                     let mut stripped = workspace.new_atom();
                                     let mul = stripped.to_mul();
 
                                     for a in m.iter() {
-                                        if let AtomView::Num(n) = a {
-                                            coeff = coeff * n.get_coeff_view().to_owned();
+                                        if is_scalar(&m, a) {
+                                            t.extend(a);
                                         } else {
                                             mul.extend(a);
                                         }
@@ -853,7 +1348,6 @@ impl<'a> AtomView<'a> {
                         }
 
                         t.extend(new_fun.as_view());
-                        t.extend(workspace.new_num(coeff).as_view());
                         t.as_view().normalize(workspace, out);
                         return;
                     }
@@ -950,18 +1444,62 @@ impl<'a> AtomView<'a> {
                             out.set_from_view(&base_handle.as_view());
                             break 'pow_simplify;
                         } else if let AtomView::Num(n) = base_handle.as_view() {
-                            // simplify a number to a numerical power
-                            let (new_base_num, new_exp_num) = n.get_coeff_view().pow(&exp_num);
+                            // simplify a number to a numerical power, or keep the power
+                            // unevaluated if it cannot be computed
+                            if let Ok((new_base_num, new_exp_num)) =
+                                n.get_coeff_view().try_pow(&exp_num)
+                            {
+                                if new_exp_num == 1.into() {
+                                    out.to_num(new_base_num);
+                                    break 'pow_simplify;
+                                }
 
-                            if new_exp_num == 1.into() {
-                                out.to_num(new_base_num);
-                                break 'pow_simplify;
-                            }
+                                // extract perfect powers, e.g. 12^(1/2) = 2*3^(1/2)
+                                if let Coefficient::Rational(Rational::Natural(1, d)) = new_exp_num
+                                {
+                                    if let Some((c, r)) = u32::try_from(d)
+                                        .ok()
+                                        .and_then(|d| new_base_num.extract_root(d))
+                                    {
+                                        if r.is_one() {
+                                            out.to_num(c);
+                                            break 'pow_simplify;
+                                        }
+
+                                        if !c.is_one() {
+                                            let mut pow = workspace.new_atom();
+                                            base_handle.to_num(r);
+                                            exp_handle.to_num(new_exp_num);
+                                            pow.to_pow(base_handle.as_view(), exp_handle.as_view())
+                                                .set_normalized(true);
+
+                                            let mut mul = workspace.new_atom();
+                                            let m = mul.to_mul();
+                                            m.extend(pow.as_view());
+                                            base_handle.to_num(c);
+                                            m.extend(base_handle.as_view());
+                                            mul.as_view().normalize(workspace, out);
+                                            break 'pow_simplify;
+                                        }
+                                    }
+                                }
 
-                            base_handle.to_num(new_base_num);
-                            exp_handle.to_num(new_exp_num);
+                                base_handle.to_num(new_base_num);
+                                exp_handle.to_num(new_exp_num);
+                            }
                         } else if let AtomView::Var(v) = base_handle.as_view() {
                             if v.get_symbol() == State::I {
+                                // reduce large integer exponents, as 𝑖^4 = 1
+                                let exp_num = match exp_num {
+                                    CoefficientView::Large(r) if r.to_rat().is_integer() => {
+                                        CoefficientView::Natural(
+                                            r.to_rat().numer().mod_u(4) as i64,
+                                            1,
+                                        )
+                                    }
+                                    e => e,
+                                };
+
                                 if let CoefficientView::Natural(n, d) = exp_num {
                                     let mut new_base = workspace.new_atom();
 
@@ -994,6 +1532,30 @@ impl<'a> AtomView<'a> {
 
                                     break 'pow_simplify;
                                 }
+                            } else if let Some(min_poly) =
+                                State::get_minimal_polynomial(v.get_symbol())
+                            {
+                                // reduce powers of an algebraic number using its minimal polynomial
+                                if let CoefficientView::Natural(n, 1) = exp_num {
+                                    if n < 0 || n >= min_poly.degree(0) as i64 {
+                                        let field = AlgebraicNumberRing::new(min_poly);
+                                        let mut a = field
+                                            .to_element(field.poly().monomial(Q.one(), vec![1]));
+                                        if n < 0 {
+                                            a = field.inv(&a);
+                                        }
+
+                                        field
+                                            .pow(&a, n.unsigned_abs())
+                                            .poly
+                                            .to_expression_with_map(
+                                                workspace,
+                                                &HashMap::default(),
+                                                out,
+                                            );
+                                        break 'pow_simplify;
+                                    }
+                                }
                             }
                         } else if let AtomView::Pow(p_base) = base_handle.as_view() {
                             // simplify x^2^3
@@ -1040,6 +1602,13 @@ impl<'a> AtomView<'a> {
                         a
                     };
 
+                    if let AtomView::Var(v) = r {
+                        if v.get_symbol() == State::I {
+                            ns.extend(workspace.new_num(Coefficient::i()).as_view());
+                            continue;
+                        }
+                    }
+
                     if let AtomView::Add(new_add) = r {
                         for c in new_add.iter() {
                             if let AtomView::Num(n) = c {
@@ -1048,7 +1617,7 @@ impl<'a> AtomView<'a> {
                                 }
                             }
 
-                            ns.extend(r);
+                            ns.extend(c);
                         }
                     } else {
                         if let AtomView::Num(n) = r {
@@ -1065,7 +1634,11 @@ impl<'a> AtomView<'a> {
                     atom_sort_buf.push(x);
                 }
 
-                atom_sort_buf.sort_by(|a, b| a.cmp_terms(b));
+                if let Some(order) = State::get_custom_term_order() {
+                    atom_sort_buf.sort_by(|a, b| order.cmp_terms(a, b));
+                } else {
+                    atom_sort_buf.sort_by(|a, b| a.cmp_terms(b));
+                }
 
                 if atom_sort_buf.is_empty() {
                     out.to_num(Coefficient::zero());
@@ -1110,6 +1683,8 @@ impl<'a> AtomView<'a> {
                             // downgrade
                             last_buf.set_from_view(&out_add.to_add_view().to_slice().get(0));
                             out.set_from_view(&last_buf.as_view());
+                        } else {
+                            out_add.set_normalized(true);
                         }
                     } else {
                         out_add.extend(v);
@@ -1120,3 +1695,591 @@ impl<'a> AtomView<'a> {
         }
     }
 }
+
+impl TermOrder {
+    /// Get the variables of the order and whether the order is graded.
+    fn get_variables(&self) -> Option<(&[Symbol], bool)> {
+        match self {
+            TermOrder::Default => None,
+            TermOrder::Lexicographic(v) => Some((v, false)),
+            TermOrder::GradedLexicographic(v) => Some((v, true)),
+        }
+    }
+
+    /// Get the integer degree of a term in the variable `s`.
+    fn degree(term: AtomView<'_>, s: Symbol) -> i64 {
+        match term {
+            AtomView::Var(v) if v.get_symbol() == s => 1,
+            AtomView::Pow(p) => {
+                if let (AtomView::Var(v), AtomView::Num(n)) = p.get_base_exp() {
+                    if let CoefficientView::Natural(e, 1) = n.get_coeff_view() {
+                        if v.get_symbol() == s {
+                            return e;
+                        }
+                    }
+                }
+                0
+            }
+            AtomView::Mul(m) => m.iter().map(|f| Self::degree(f, s)).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Compare terms in a sum, where terms with the highest degree come first.
+    pub(crate) fn cmp_terms(&self, a: &AtomView<'_>, b: &AtomView<'_>) -> Ordering {
+        let Some((vars, graded)) = self.get_variables() else {
+            return a.cmp_terms(b);
+        };
+
+        if graded {
+            let total = |t: &AtomView| vars.iter().map(|v| Self::degree(*t, *v)).sum::<i64>();
+            let c = total(b).cmp(&total(a));
+            if c != Ordering::Equal {
+                return c;
+            }
+        }
+
+        for v in vars {
+            let c = Self::degree(*b, *v).cmp(&Self::degree(*a, *v));
+            if c != Ordering::Equal {
+                return c;
+            }
+        }
+
+        a.cmp_terms(b)
+    }
+
+    /// Compare factors in a product, where the variables of the order come first.
+    pub(crate) fn cmp_factors(&self, a: &AtomView<'_>, b: &AtomView<'_>) -> Ordering {
+        let Some((vars, _)) = self.get_variables() else {
+            return a.cmp_factors(b);
+        };
+
+        let rank = |f: &AtomView| {
+            let base = if let AtomView::Pow(p) = f {
+                p.get_base()
+            } else {
+                *f
+            };
+
+            match base {
+                AtomView::Var(v) => vars
+                    .iter()
+                    .position(|s| *s == v.get_symbol())
+                    .unwrap_or(vars.len()),
+                _ => vars.len(),
+            }
+        };
+
+        rank(a).cmp(&rank(b)).then_with(|| a.cmp_factors(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256StarStar;
+
+    use crate::{
+        coefficient::Coefficient,
+        domains::{
+            rational::{Rational, Q},
+            Field, Ring,
+        },
+        representations::{Atom, AtomView, FunctionBuilder, Symbol},
+        state::{FunctionAttribute, RecycledAtom, State, TermOrder, Workspace},
+    };
+
+    fn normalize(a: &Atom) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            a.as_view().normalize(ws, &mut out);
+            out.into_inner()
+        })
+    }
+
+    /// Copy an atom and mark every subexpression as not normalized, so that
+    /// normalization processes the full expression again.
+    fn denormalize(a: AtomView) -> Atom {
+        let mut out = RecycledAtom::new();
+        match a {
+            AtomView::Num(_) | AtomView::Var(_) => out.set_from_view(&a),
+            AtomView::Fun(f) => {
+                let fun = out.to_fun(f.get_symbol());
+                for arg in f.iter() {
+                    fun.add_arg(denormalize(arg).as_view());
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                out.to_pow(denormalize(base).as_view(), denormalize(exp).as_view());
+            }
+            AtomView::Mul(m) => {
+                let mul = out.to_mul();
+                for f in m.iter() {
+                    mul.extend(denormalize(f).as_view());
+                }
+            }
+            AtomView::Add(a) => {
+                let add = out.to_add();
+                for t in a.iter() {
+                    add.extend(denormalize(t).as_view());
+                }
+            }
+        }
+        out.into_inner()
+    }
+
+    /// Generate a random unnormalized expression, together with its value if
+    /// it does not contain a function.
+    fn random_expr(
+        rng: &mut Xoshiro256StarStar,
+        depth: usize,
+        vars: &[(Symbol, Rational)],
+        f: Symbol,
+    ) -> (Atom, Option<Rational>) {
+        let kind = if depth == 0 {
+            rng.gen_range(0..2)
+        } else {
+            rng.gen_range(0..7)
+        };
+
+        match kind {
+            0 => {
+                let (s, v) = &vars[rng.gen_range(0..vars.len())];
+                (Atom::new_var(*s), Some(v.clone()))
+            }
+            1 => {
+                let r = if rng.gen_bool(0.2) {
+                    Rational::from_large(
+                        rug::Rational::from(i64::MAX) * rng.gen_range(-3..4) / rng.gen_range(1..4),
+                    )
+                } else {
+                    Rational::new(rng.gen_range(-4..5), rng.gen_range(1..4))
+                };
+                (Atom::new_num(r.clone()), Some(r))
+            }
+            2 | 3 => {
+                let mut out = RecycledAtom::new();
+                let add = out.to_add();
+                let mut value = Some(Q.zero());
+                for _ in 0..rng.gen_range(2..5) {
+                    let (a, v) = random_expr(rng, depth - 1, vars, f);
+                    add.extend(a.as_view());
+                    value = value.zip(v).map(|(x, y)| Q.add(&x, &y));
+                }
+                (out.into_inner(), value)
+            }
+            4 | 5 => {
+                let mut out = RecycledAtom::new();
+                let mul = out.to_mul();
+                let mut value = Some(Q.one());
+                for _ in 0..rng.gen_range(2..4) {
+                    let (a, v) = random_expr(rng, depth - 1, vars, f);
+                    mul.extend(a.as_view());
+                    value = value.zip(v).map(|(x, y)| Q.mul(&x, &y));
+                }
+                (out.into_inner(), value)
+            }
+            _ => {
+                if rng.gen_bool(0.2) {
+                    let (a, _) = random_expr(rng, depth - 1, vars, f);
+                    let mut out = RecycledAtom::new();
+                    out.to_fun(f).add_arg(a.as_view());
+                    return (out.into_inner(), None);
+                }
+
+                // only variables, which are non-zero, are raised to negative powers
+                let (base, value, exp) = if rng.gen_bool(0.5) {
+                    let (s, v) = &vars[rng.gen_range(0..vars.len())];
+                    (Atom::new_var(*s), Some(v.clone()), rng.gen_range(-2..4i64))
+                } else {
+                    let (a, v) = random_expr(rng, depth - 1, vars, f);
+                    (a, v, rng.gen_range(0..4i64))
+                };
+
+                let value = value.map(|v| {
+                    if exp < 0 {
+                        Q.inv(&v).pow(-exp as u64)
+                    } else {
+                        v.pow(exp as u64)
+                    }
+                });
+
+                let mut out = RecycledAtom::new();
+                out.to_pow(base.as_view(), Atom::new_num(exp).as_view());
+                (out.into_inner(), value)
+            }
+        }
+    }
+
+    #[test]
+    fn idempotence() {
+        let vars = [
+            (State::get_symbol("ni_x"), Rational::new(2, 1)),
+            (State::get_symbol("ni_y"), Rational::new(-3, 5)),
+            (State::get_symbol("ni_z"), Rational::new(7, 3)),
+        ];
+        let f = State::get_symbol("ni_f");
+        let bindings: Vec<_> = vars
+            .iter()
+            .map(|(s, v)| (*s, Coefficient::Rational(v.clone())))
+            .collect();
+
+        let mut rng = Xoshiro256StarStar::seed_from_u64(1);
+        for _ in 0..1000 {
+            let (raw, value) = random_expr(&mut rng, 3, &vars, f);
+
+            let n = normalize(&raw);
+            let n2 = normalize(&denormalize(n.as_view()));
+            assert_eq!(
+                n, n2,
+                "Normalization of {} is not idempotent: {} vs {}",
+                raw, n, n2
+            );
+
+            if let Some(v) = value {
+                assert_eq!(
+                    n.eval_to_number(&bindings),
+                    Some(Coefficient::Rational(v)),
+                    "Value of {} changed",
+                    raw
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unnormalized_rationals() {
+        let x = Atom::new_var(State::get_symbol("ni_x"));
+
+        for (num, res) in [
+            (Rational::Natural(0, 5), "ni_x"),
+            (Rational::Natural(0, -3), "ni_x"),
+            (Rational::Natural(3, -4), "ni_x-3/4"),
+            (Rational::Natural(-3, -4), "ni_x+3/4"),
+        ] {
+            let mut a = RecycledAtom::new();
+            let add = a.to_add();
+            add.extend(x.as_view());
+            add.extend(Atom::new_num(Coefficient::Rational(num)).as_view());
+
+            let n = normalize(&a);
+            assert_eq!(n, Atom::parse(res).unwrap());
+            assert_eq!(normalize(&denormalize(n.as_view())), n);
+        }
+
+        for (num, res) in [
+            (Rational::Natural(0, 5), "0"),
+            (Rational::Natural(0, -3), "0"),
+            (Rational::Natural(3, -4), "-3/4*ni_x"),
+            (Rational::Natural(3, -2), "-3/2*ni_x"),
+            (Rational::Natural(-1, -1), "ni_x"),
+        ] {
+            let mut a = RecycledAtom::new();
+            let mul = a.to_mul();
+            mul.extend(Atom::new_num(Coefficient::Rational(num)).as_view());
+            mul.extend(x.as_view());
+
+            let n = normalize(&a);
+            assert_eq!(n, Atom::parse(res).unwrap());
+            assert_eq!(normalize(&denormalize(n.as_view())), n);
+        }
+    }
+
+    #[test]
+    fn rational_roots() {
+        for (input, res) in [
+            ("8^(2/3)", "4"),
+            ("4^(-1/2)", "1/2"),
+            ("12^(1/2)", "2*3^(1/2)"),
+            ("(-12)^(1/2)", "2*(-3)^(1/2)"),
+            ("(12/25)^(1/2)", "2/5*3^(1/2)"),
+            ("(1/8)^(-1/2)", "2*2^(1/2)"),
+            ("12^(1/2)*3^(1/2)", "6"),
+            ("12^(1/2)+3^(1/2)", "3*3^(1/2)"),
+            ("(3*2^100)^(1/2)", "1125899906842624*3^(1/2)"),
+            ("1027243729^(1/2)", "1009*1009^(1/2)"),
+            ("6^(1/2)", "6^(1/2)"),
+        ] {
+            assert_eq!(
+                Atom::parse(input).unwrap(),
+                Atom::parse(res).unwrap(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn noncommutative() {
+        for name in ["nc_A", "nc_B"] {
+            State::get_symbol_with_attributes(name, vec![FunctionAttribute::NonCommutative])
+                .unwrap();
+        }
+
+        let a = Atom::parse("nc_B*nc_x*nc_A*3").unwrap();
+        assert_eq!(a.to_string(), "3*nc_x*nc_B*nc_A");
+
+        let a = Atom::parse("nc_A*nc_A*nc_B*nc_A").unwrap();
+        assert_eq!(a.to_string(), "nc_A^2*nc_B*nc_A");
+
+        let a = Atom::parse("nc_A*nc_B - nc_B*nc_A").unwrap();
+        assert_ne!(a, Atom::new_num(0));
+
+        let a = Atom::parse("(nc_A+nc_B)^2").unwrap().expand();
+        assert_eq!(a, Atom::parse("nc_A^2+nc_A*nc_B+nc_B*nc_A+nc_B^2").unwrap());
+
+        let a = Atom::parse("(nc_A*nc_B)^2").unwrap().expand();
+        assert_eq!(a.to_string(), "nc_A*nc_B*nc_A*nc_B");
+    }
+
+    #[test]
+    fn linear_arguments() {
+        let f =
+            State::get_symbol_with_attributes("lin_f", vec![FunctionAttribute::linear_in(&[0])])
+                .unwrap();
+        assert!(f.is_linear_in(0) && !f.is_linear_in(1));
+        assert_eq!(f.get_linear_arguments(), Some(vec![0]));
+
+        let a = Atom::parse("lin_f(2*x+3*y, 2*x+3*y)").unwrap();
+        assert_eq!(
+            a,
+            Atom::parse("2*lin_f(x, 2*x+3*y) + 3*lin_f(y, 2*x+3*y)").unwrap()
+        );
+
+        // commutative factors are scalars for noncommutative symbols
+        for name in ["lin_x", "lin_y"] {
+            State::get_symbol_with_attributes(name, vec![FunctionAttribute::NonCommutative])
+                .unwrap();
+        }
+        let a = Atom::parse("lin_f(a*lin_x+2*lin_y, 1)").unwrap();
+        assert_eq!(
+            a,
+            Atom::parse("a*lin_f(lin_x, 1) + 2*lin_f(lin_y, 1)").unwrap()
+        );
+
+        assert!(State::get_symbol_with_attributes(
+            "lin_g",
+            vec![
+                FunctionAttribute::Linear,
+                FunctionAttribute::linear_in(&[1])
+            ],
+        )
+        .is_err());
+        for pos in [Symbol::MAX_LINEAR_ARGUMENTS, 100] {
+            let attr = FunctionAttribute::linear_in(&[0, pos]);
+            assert!(State::get_symbol_with_attributes("lin_g", vec![attr, attr]).is_err());
+            assert!(State::get_symbol_with_attributes("lin_g", vec![attr]).is_err());
+        }
+
+        // the last linear argument can be combined with all other attributes
+        let h = State::get_symbol_with_attributes(
+            "lin_h",
+            vec![
+                FunctionAttribute::linear_in(&[0, Symbol::MAX_LINEAR_ARGUMENTS - 1]),
+                FunctionAttribute::Real,
+                FunctionAttribute::NonCommutative,
+            ],
+        )
+        .unwrap();
+        let a = FunctionBuilder::new(h).add_arg(&Atom::new_num(1)).finish();
+        let AtomView::Fun(f) = a.as_view() else {
+            unreachable!()
+        };
+        assert_eq!(f.get_symbol(), h);
+        assert!(h.is_real() && h.is_noncommutative());
+        assert!(h.is_linear_in(Symbol::MAX_LINEAR_ARGUMENTS - 1) && !h.is_linear_in(1));
+    }
+
+    #[test]
+    fn term_order() {
+        let (x, y) = (State::get_symbol("to_x"), State::get_symbol("to_y"));
+        let a = Atom::parse("to_x^2 + 1 + to_x*to_y^3 + to_y^2").unwrap();
+        let AtomView::Add(add) = a.as_view() else {
+            unreachable!()
+        };
+
+        let sorted = |order: TermOrder| {
+            let mut terms: Vec<_> = add.iter().collect();
+            terms.sort_by(|a, b| order.cmp_terms(a, b));
+            terms.iter().map(|t| t.to_string()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sorted(TermOrder::Lexicographic(vec![y, x])),
+            ["to_x*to_y^3", "to_y^2", "to_x^2", "1"]
+        );
+        assert_eq!(
+            sorted(TermOrder::GradedLexicographic(vec![x, y])),
+            ["to_x*to_y^3", "to_x^2", "to_y^2", "1"]
+        );
+
+        let m = Atom::parse("to_x*to_y^3").unwrap();
+        let AtomView::Mul(mul) = m.as_view() else {
+            unreachable!()
+        };
+        let mut factors: Vec<_> = mul.iter().collect();
+        factors.sort_by(|a, b| TermOrder::Lexicographic(vec![y, x]).cmp_factors(a, b));
+        assert_eq!(factors[0].to_string(), "to_y^3");
+    }
+
+    #[test]
+    fn conjugation() {
+        State::get_symbol_with_attributes("cj_r", vec![FunctionAttribute::Real]).unwrap();
+        State::get_symbol_with_attributes("cj_g", vec![FunctionAttribute::Real]).unwrap();
+
+        for (input, res) in [
+            ("conj(conj(cj_z))", "cj_z"),
+            ("conj(cj_r)", "cj_r"),
+            ("conj(𝑖)", "-𝑖"),
+            ("conj(cj_z + 𝑖*cj_r)", "conj(cj_z) - 𝑖*cj_r"),
+            ("conj(cj_z*cj_w^2)", "conj(cj_z)*conj(cj_w)^2"),
+            ("conj(cj_f(cj_z))", "conj(cj_f(cj_z))"),
+            ("conj(cj_g(cj_z))", "cj_g(conj(cj_z))"),
+            ("conj(exp(𝑖*cj_r))", "exp(-𝑖*cj_r)"),
+            ("conj(re(cj_z) + im(cj_z))", "re(cj_z) + im(cj_z)"),
+            ("re(cj_z + 3*cj_r)", "re(cj_z) + 3*cj_r"),
+            ("im(cj_z + 3*cj_r)", "im(cj_z)"),
+            ("re(𝑖*cj_r*cj_z)", "-cj_r*im(cj_z)"),
+            ("im(𝑖*cj_z)", "re(cj_z)"),
+            ("im(conj(cj_z))", "-im(cj_z)"),
+            ("re(2*𝑖)", "0"),
+            ("im(2*𝑖)", "2"),
+            ("re((2+3*𝑖)*cj_z)", "2*re(cj_z) - 3*im(cj_z)"),
+        ] {
+            assert_eq!(
+                Atom::parse(input).unwrap(),
+                Atom::parse(res).unwrap(),
+                "{}",
+                input
+            );
+        }
+
+        let c = Atom::new_num(Coefficient::complex(
+            Rational::from((1, 2)).into(),
+            Coefficient::from(3),
+        ));
+        let r = FunctionBuilder::new(State::CONJ).add_arg(&c).finish();
+        assert_eq!(
+            r,
+            Atom::new_num(Coefficient::complex(
+                Rational::from((1, 2)).into(),
+                Coefficient::from(-3)
+            ))
+        );
+        assert_eq!(c.as_view().im(), Atom::new_num(3));
+        assert!(Atom::parse("cj_r^2 + sin(cj_r)*𝜋")
+            .unwrap()
+            .as_view()
+            .is_real());
+    }
+
+    #[test]
+    fn large_exponent() {
+        let a = Atom::parse("le_x^(10^12)").unwrap();
+        assert_eq!(
+            &a * &Atom::parse("le_x^(10^12)").unwrap(),
+            Atom::parse("le_x^(2*10^12)").unwrap()
+        );
+        assert_eq!(
+            &a * &Atom::parse("le_x^(-(10^12))").unwrap(),
+            Atom::new_num(1)
+        );
+
+        let a = Atom::parse("le_x^(2^70)")
+            .unwrap()
+            .pow(&Atom::parse("2^70").unwrap());
+        assert_eq!(a, Atom::parse("le_x^(2^140)").unwrap());
+        assert_eq!(
+            a.as_view().validate(),
+            Ok(()),
+            "Exponent is not stored as a large number"
+        );
+
+        assert_eq!(Atom::parse("(-1)^(10^10+1)").unwrap(), Atom::new_num(-1));
+        assert_eq!(Atom::parse("(-1)^(-(10^10+1))").unwrap(), Atom::new_num(-1));
+        assert_eq!(Atom::parse("(-1)^(-(10^10))").unwrap(), Atom::new_num(1));
+        assert_eq!(
+            Atom::parse("𝑖^(2^70+1)").unwrap(),
+            Atom::parse("𝑖").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("(-𝑖)^(-(10^12)-1)").unwrap(),
+            Atom::new_num(Coefficient::i())
+        );
+        assert_eq!(
+            Atom::parse("(le_x*le_y)^(2^70)").unwrap().expand(),
+            Atom::parse("le_x^(2^70)*le_y^(2^70)").unwrap()
+        );
+
+        // powers that cannot be computed are kept
+        let a = Atom::parse("2^(10^12)").unwrap();
+        assert!(matches!(a.as_view(), AtomView::Pow(_)));
+    }
+
+    #[test]
+    fn imaginary_unit() {
+        let i = Atom::parse("𝑖").unwrap();
+        assert_eq!(i, Atom::new_num(Coefficient::i()));
+        assert_eq!(i, Atom::parse("2*𝑖/2").unwrap());
+        assert_eq!(normalize(&Atom::new_var(State::I)), i);
+        assert_eq!(Atom::parse("𝑖*𝑖").unwrap(), Atom::new_num(-1));
+        assert_eq!(
+            Atom::parse("f(𝑖)+f(2*𝑖/2)").unwrap(),
+            Atom::parse("2*f(𝑖)").unwrap()
+        );
+        assert_eq!(Atom::parse("𝑖*x - x*𝑖").unwrap(), Atom::new_num(0));
+    }
+
+    #[test]
+    fn number_field() {
+        let a = State::get_symbol("nfn_a");
+        State::set_minimal_polynomial(a, &Atom::parse("nfn_a^2 - 2").unwrap()).unwrap();
+        assert!(State::set_minimal_polynomial(a, &Atom::parse("nfn_a^2 - 3").unwrap()).is_err());
+
+        assert_eq!(Atom::parse("nfn_a^2").unwrap(), Atom::new_num(2));
+        assert_eq!(
+            Atom::parse("nfn_a^5").unwrap(),
+            Atom::parse("4*nfn_a").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("nfn_a^-3").unwrap(),
+            Atom::parse("nfn_a/4").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("3*nfn_x*nfn_a*nfn_a^3").unwrap(),
+            Atom::parse("12*nfn_x").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("nfn_x*nfn_a*nfn_a^2*nfn_y").unwrap(),
+            Atom::parse("2*nfn_a*nfn_x*nfn_y").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("(1 + nfn_a)*(1 - nfn_a)").unwrap().expand(),
+            Atom::new_num(-1)
+        );
+
+        // a root of x^3 - x - 1, whose powers reduce to sums
+        let b = State::get_symbol("nfn_b");
+        State::set_minimal_polynomial(b, &Atom::parse("nfn_b^3 - nfn_b - 1").unwrap()).unwrap();
+        assert_eq!(
+            Atom::parse("nfn_b^4").unwrap(),
+            Atom::parse("nfn_b^2 + nfn_b").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("nfn_b*nfn_b^2*nfn_x").unwrap().expand(),
+            Atom::parse("nfn_b*nfn_x + nfn_x").unwrap()
+        );
+        assert_eq!(
+            (Atom::parse("nfn_b^2").unwrap() * &Atom::parse("1/nfn_b").unwrap()).expand(),
+            Atom::parse("nfn_b").unwrap()
+        );
+
+        assert!(State::set_minimal_polynomial(
+            State::get_symbol("nfn_c"),
+            &Atom::parse("nfn_c^2 - 1").unwrap()
+        )
+        .is_err());
+    }
+}
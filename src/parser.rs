@@ -7,11 +7,11 @@ use smallvec::SmallVec;
 use smartstring::{LazyCompact, SmartString};
 
 use crate::{
-    coefficient::ConvertToRing,
+    coefficient::{Coefficient, ConvertToRing},
     domains::{integer::Integer, Ring},
     poly::{polynomial::MultivariatePolynomial, Exponent, Variable},
     representations::Atom,
-    state::{State, Workspace},
+    state::{CoefficientDomain, State, Workspace},
 };
 
 const HEX_DIGIT_MASK: [bool; 255] = [
@@ -323,16 +323,51 @@ impl Token {
         }
     }
 
-    /// Parse the token into an atom.
+    /// Parse the token into an atom, interpreting numbers in the
+    /// coefficient domain of the global state.
     pub fn to_atom(&self, workspace: &Workspace) -> Result<Atom, String> {
+        let domain = State::get_coefficient_domain();
+        self.to_atom_in_domain(workspace, domain)
+    }
+
+    /// Parse the token into an atom, interpreting numbers in the coefficient domain `domain`.
+    pub fn to_atom_in_domain(
+        &self,
+        workspace: &Workspace,
+        domain: CoefficientDomain,
+    ) -> Result<Atom, String> {
         let mut atom = Atom::default();
 
         let mut state = State::get_global_state().write().unwrap();
         self.to_atom_with_output(&mut state, workspace, &mut atom)?;
+        drop(state);
+
+        if let CoefficientDomain::FiniteField(field) = domain {
+            return atom
+                .as_view()
+                .try_to_finite_field(field)
+                .map_err(|e| e.to_string());
+        }
 
         Ok(atom)
     }
 
+    /// Check that the token, if it is a number, is not a hexadecimal, binary or octal
+    /// literal, as these are not allowed in a denominator.
+    fn check_denominator_literal(&self) -> Result<(), String> {
+        if let Token::Number(n) = self {
+            let n = n.trim_start_matches('-');
+            if n.starts_with("0x") || n.starts_with("0b") || n.starts_with("0o") {
+                return Err(format!(
+                    "Hexadecimal, binary and octal literals are not allowed in a denominator: {}",
+                    n
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Parse the token into the atom `out`.
     fn to_atom_with_output(
         &self,
@@ -348,7 +383,12 @@ impl Token {
                 Err(e) => return Err(format!("Could not parse number: {}", e)),
             },
             Token::ID(x) => {
-                out.to_var(state.get_symbol_impl(x));
+                let id = state.get_symbol_impl(x);
+                if id == State::I {
+                    out.to_num(Coefficient::i());
+                } else {
+                    out.to_var(id);
+                }
             }
             Token::Op(_, _, op, args) => match op {
                 Operator::Mul => {
@@ -403,6 +443,7 @@ impl Token {
                 }
                 Operator::Inv => {
                     debug_assert!(args.len() == 1);
+                    args[0].check_denominator_literal()?;
 
                     let mut base = workspace.new_atom();
                     args[0].to_atom_with_output(state, workspace, &mut base)?;
@@ -420,8 +461,20 @@ impl Token {
                     _ => unreachable!(),
                 };
 
+                let symbol = state.get_symbol_impl(name);
+                if let Some(arity) = state.get_arity_impl(symbol) {
+                    if arity != args.len() - 1 {
+                        return Err(format!(
+                            "Function {} takes {} argument(s), but {} were provided",
+                            name,
+                            arity,
+                            args.len() - 1
+                        ));
+                    }
+                }
+
                 let mut fun_h = workspace.new_atom();
-                let fun = fun_h.to_fun(state.get_symbol_impl(name));
+                let fun = fun_h.to_fun(symbol);
                 let mut atom = workspace.new_atom();
                 for a in args.iter().skip(1) {
                     a.to_atom_with_output(state, workspace, &mut atom)?;
@@ -537,6 +590,7 @@ impl Token {
                 }
                 Operator::Inv => {
                     debug_assert!(args.len() == 1);
+                    args[0].check_denominator_literal()?;
 
                     let mut base = workspace.new_atom();
                     args[0].to_atom_with_output_and_var_map(
@@ -594,7 +648,9 @@ impl Token {
         stack.push(Token::Start);
         let mut state = ParseState::Any;
 
-        let ops = ['\0', '^', '+', '*', '-', '(', ')', '/', ',', '[', ']'];
+        let ops = [
+            '\0', '^', '+', '*', '-', '(', ')', '/', ',', '[', ']', '{', '}',
+        ];
         let whitespace = [' ', '\t', '\n', '\r', '\\'];
         let forbidden = [';', ':', '&', '!', '%'];
 
@@ -626,7 +682,23 @@ impl Token {
                     }
                 }
                 ParseState::Number => {
-                    if c != '_' && c != ' ' && !c.is_ascii_digit() {
+                    let has_radix_prefix = id_buffer.len() > 1
+                        && matches!(&id_buffer.as_bytes()[..2], b"0x" | b"0b" | b"0o");
+                    let is_radix_prefix = id_buffer.as_str() == "0" && matches!(c, 'x' | 'b' | 'o');
+
+                    if has_radix_prefix && c == '.' {
+                        Err(format!(
+                            "Hexadecimal, binary and octal literals must be integers, found '.' at line {} and column {}",
+                            line_counter, column_counter
+                        ))?;
+                    }
+
+                    if c != '_'
+                        && c != ' '
+                        && !c.is_ascii_digit()
+                        && !is_radix_prefix
+                        && !(has_radix_prefix && c.is_ascii_alphanumeric())
+                    {
                         // drag in the neg operator
                         if let Some(Token::Op(false, true, Operator::Neg, _)) = stack.last_mut() {
                             stack.pop();
@@ -724,7 +796,21 @@ impl Token {
                             stack.push(Token::OpenParenthesis)
                         }
                     }
-                    ')' => stack.push(Token::CloseParenthesis),
+                    '{' => {
+                        if unsafe { stack.last().unwrap_unchecked() }.is_normal() {
+                            // insert multiplication: x{a,b} -> x*{a,b}
+                            stack.push(Token::Op(true, true, Operator::Mul, vec![]));
+                            extra_ops.push(c);
+                        } else {
+                            // a list {a,b} is the built-in function State::LIST, whose name
+                            // cannot be written as a function name
+                            stack.push(Token::ID(
+                                State::BUILTIN_VAR_LIST[State::LIST.get_id() as usize].into(),
+                            ));
+                            extra_ops.push('(');
+                        }
+                    }
+                    ')' | '}' => stack.push(Token::CloseParenthesis),
                     '/' => {
                         if matches!(
                             stack.last().unwrap(),
@@ -1138,3 +1224,147 @@ impl Token {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coefficient::CoefficientView,
+        domains::finite_field::{FiniteFieldCore, Zp64},
+        id::Pattern,
+        representations::{Atom, AtomView},
+        state::{CoefficientDomain, State, Workspace},
+    };
+
+    use super::Token;
+
+    #[test]
+    fn arity_enforced() {
+        let f = State::get_symbol("f_arity_enforced");
+        State::set_arity(f, 1);
+
+        assert!(Atom::parse("f_arity_enforced(x)").is_ok());
+        assert!(Atom::parse("f_arity_enforced(x,y)").is_err());
+        assert!(Atom::parse("g(f_arity_enforced(x,y))").is_err());
+    }
+
+    #[test]
+    fn arity_not_enforced() {
+        assert!(Atom::parse("f_arity_free(x)").is_ok());
+        assert!(Atom::parse("f_arity_free(x,y)").is_ok());
+    }
+
+    #[test]
+    fn parse_in_finite_field() {
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        let domain = CoefficientDomain::FiniteField(field);
+
+        let a = Workspace::get_local()
+            .with(|ws| Token::parse("3/2").unwrap().to_atom_in_domain(ws, domain))
+            .unwrap();
+
+        let f = State::get_finite_field(field);
+        if let AtomView::Num(n) = a.as_view() {
+            // 3 * 2^-1 mod 7 = 3 * 4 mod 7 = 5
+            assert_eq!(
+                n.get_coeff_view(),
+                CoefficientView::FiniteField(f.to_element(5), field)
+            );
+        } else {
+            panic!("Expected a number, got {}", a);
+        }
+
+        // exponents are not reduced
+        let a = Workspace::get_local()
+            .with(|ws| {
+                Token::parse("x_ff^8*9 + x_ff")
+                    .unwrap()
+                    .to_atom_in_domain(ws, domain)
+            })
+            .unwrap();
+        let b = Atom::parse("x_ff^8*2 + x_ff")
+            .unwrap()
+            .to_finite_field(field);
+        assert_eq!(a, b);
+
+        // a denominator that is divisible by the prime is an error
+        for s in ["1/7", "x_ff/14 + 1", "f_ff(x_ff^2/7)"] {
+            assert!(Workspace::get_local()
+                .with(|ws| Token::parse(s).unwrap().to_atom_in_domain(ws, domain))
+                .is_err());
+        }
+        assert!(Workspace::get_local()
+            .with(|ws| Token::parse("x_ff^(1/7)")
+                .unwrap()
+                .to_atom_in_domain(ws, domain))
+            .is_ok());
+    }
+
+    #[test]
+    fn radix_literals() {
+        assert_eq!(Atom::parse("0xFF").unwrap(), Atom::new_num(255));
+        assert_eq!(Atom::parse("0b1010").unwrap(), Atom::new_num(10));
+        assert_eq!(Atom::parse("0o17").unwrap(), Atom::new_num(15));
+        assert_eq!(Atom::parse("-0x10").unwrap(), Atom::new_num(-16));
+        assert_eq!(
+            Atom::parse("0x1_0000_0000").unwrap(),
+            Atom::new_num(1i64 << 32)
+        );
+        assert_eq!(
+            Atom::parse("0x1_0000_0000_0000_0000").unwrap(),
+            Atom::parse("18446744073709551616").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("0xa*rl_x").unwrap(),
+            Atom::parse("10*rl_x").unwrap()
+        );
+
+        assert!(Atom::parse("0b12").is_err());
+        assert!(Atom::parse("1/0x2").is_err());
+        assert!(Atom::parse("0x1.5").is_err());
+    }
+
+    #[test]
+    fn lists() {
+        let a = Atom::parse("{x, 2*y, {}} + x{1}").unwrap();
+        assert_eq!(a.to_string(), "{x,2*y,{}}+x*{1}");
+
+        // a user-defined function named list is not a list
+        let u = Atom::parse("list(1)").unwrap();
+        assert!(!u.as_view().is_list());
+        assert_ne!(u, Atom::parse("{1}").unwrap());
+        assert_eq!(u.to_string(), "list(1)");
+
+        // lists do not combine arithmetically
+        let a = Atom::parse("{1} + {1}").unwrap();
+        assert_eq!(a.to_string(), "{1}+{1}");
+        assert!(matches!(a.as_view(), AtomView::Add(_)));
+        assert_eq!(Atom::parse("{1}*{1}").unwrap().to_string(), "{1}*{1}");
+        assert_eq!(
+            Atom::parse("3*x*{1} - x*{1}").unwrap().to_string(),
+            "3*x*{1}-x*{1}"
+        );
+        assert_eq!(Atom::parse("{1}*{1}^2").unwrap().to_string(), "{1}*{1}^2");
+
+        let l = Atom::parse("{x, 2*y, {}}").unwrap();
+        assert!(l.as_view().is_list());
+        assert_eq!(
+            l.as_view().get_list_element(1),
+            Some(Atom::parse("2*y").unwrap().as_view())
+        );
+        assert_eq!(l.as_view().get_list_element(3), None);
+
+        // function wildcards do not match lists, but list elements can be matched
+        let p = Pattern::parse("f_(x_)").unwrap();
+        let r = p.replace_all(
+            Atom::parse("g(1) + {2}").unwrap().as_view(),
+            &Pattern::parse("x_").unwrap(),
+            None,
+            None,
+        );
+        assert_eq!(r, Atom::parse("1 + {2}").unwrap());
+
+        let p = Pattern::parse("{x_, y_, z_}").unwrap();
+        let r = p.replace_all(l.as_view(), &Pattern::parse("y_").unwrap(), None, None);
+        assert_eq!(r, Atom::parse("2*y").unwrap());
+    }
+}
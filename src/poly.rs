@@ -17,10 +17,13 @@ use smallvec::{smallvec, SmallVec};
 use smartstring::{LazyCompact, SmartString};
 
 use crate::coefficient::{Coefficient, CoefficientView, ConvertToRing};
+use crate::domains::algebraic_number::AlgebraicNumberRing;
 use crate::domains::factorized_rational_polynomial::{
     FactorizedRationalPolynomial, FromNumeratorAndFactorizedDenominator,
 };
-use crate::domains::integer::Integer;
+use crate::domains::finite_field::{FiniteFieldCore, Zp64};
+use crate::domains::integer::{Integer, IntegerRing, Z};
+use crate::domains::rational::{RationalField, Q};
 use crate::domains::rational_polynomial::{FromNumeratorAndDenominator, RationalPolynomial};
 use crate::domains::{EuclideanDomain, Ring};
 use crate::parser::{Operator, Token};
@@ -407,6 +410,106 @@ impl Atom {
         self.as_view().to_polynomial(field, var_map)
     }
 
+    /// Factor the univariate polynomial in `x` with rational coefficients into
+    /// irreducible factors and their multiplicities. The numerical content is
+    /// returned as a separate factor with multiplicity 1, if it is not 1.
+    /// A constant is therefore its only factor, and 1 has no factors.
+    ///
+    /// An error is returned if the expression is not a polynomial in `x` only.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::State};
+    /// let x = State::get_symbol("x");
+    /// let f = Atom::parse("x^2 + 2*x + 1").unwrap().factor(x).unwrap();
+    /// assert_eq!(f, vec![(Atom::parse("x+1").unwrap(), 2)]);
+    /// ```
+    pub fn factor(&self, x: Symbol) -> Result<Vec<(Atom, u32)>, String> {
+        let var_map = Arc::new(vec![Variable::Symbol(x)]);
+        let poly: MultivariatePolynomial<_, u16> = self.expand().to_polynomial(&Q, Some(var_map));
+
+        if poly.nvars() != 1 {
+            return Err(format!("{} is not a univariate polynomial in {}", self, x));
+        }
+
+        Ok(poly
+            .factor()
+            .into_iter()
+            .filter(|(f, _)| !f.is_one())
+            .map(|(f, p)| (f.to_expression(), p as u32))
+            .collect())
+    }
+
+    /// Factor the univariate polynomial in `x` over the number field `Q(a)`, where `a`
+    /// is a root of the monic, irreducible polynomial `minimal_poly` in `a`. The expression
+    /// may contain `a`, which is reduced using its minimal polynomial.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::State};
+    /// let (x, a) = (State::get_symbol("x"), State::get_symbol("a"));
+    /// let f = Atom::parse("x^2 - 2")
+    ///     .unwrap()
+    ///     .factor_in_number_field(x, a, &Atom::parse("a^2 - 2").unwrap())
+    ///     .unwrap();
+    /// assert_eq!(f.len(), 2);
+    /// assert!(f.contains(&(Atom::parse("x-a").unwrap(), 1)));
+    /// ```
+    pub fn factor_in_number_field(
+        &self,
+        x: Symbol,
+        a: Symbol,
+        minimal_poly: &Atom,
+    ) -> Result<Vec<(Atom, u32)>, String> {
+        let min_poly = minimal_poly.to_minimal_polynomial(a)?;
+
+        let var_map = Arc::new(vec![Variable::Symbol(x), Variable::Symbol(a)]);
+        let poly: MultivariatePolynomial<_, u16> = self.expand().to_polynomial(&Q, Some(var_map));
+
+        if poly.nvars() != 2 {
+            return Err(format!("{} is not a univariate polynomial in {}", self, x));
+        }
+
+        let field = AlgebraicNumberRing::new(min_poly);
+        Ok(poly
+            .to_number_field(&field)
+            .try_factor()?
+            .into_iter()
+            .filter(|(f, _)| !f.is_one())
+            .map(|(f, p)| (f.from_number_field().to_expression(), p as u32))
+            .collect())
+    }
+
+    /// Convert the expression to the minimal polynomial of the algebraic number `a`,
+    /// which must be a monic univariate polynomial in `a` that is irreducible over the rationals.
+    pub(crate) fn to_minimal_polynomial(
+        &self,
+        a: Symbol,
+    ) -> Result<MultivariatePolynomial<RationalField, u8>, String> {
+        let min_poly: MultivariatePolynomial<_, u8> = self
+            .expand()
+            .to_polynomial(&Q, Some(Arc::new(vec![Variable::Symbol(a)])));
+
+        if min_poly.nvars() != 1 || min_poly.is_constant() || !Q.is_one(&min_poly.lcoeff()) {
+            return Err(format!(
+                "{} is not a monic univariate polynomial in {}",
+                self, a
+            ));
+        }
+
+        if min_poly.factor().len() != 1 {
+            return Err(format!("{} is not irreducible", self));
+        }
+
+        Ok(min_poly)
+    }
+
+    /// Combine all fractions in the expression into a single numerator and denominator.
+    /// See [`AtomView::together`].
+    pub fn together(&self) -> Atom {
+        self.as_view().together()
+    }
+
     /// Convert the atom to a rational polynomial, optionally in the variable ordering
     /// specified by `var_map`. If new variables are encountered, they are
     /// added to the variable map. Similarly, non-rational polynomial parts are automatically
@@ -454,6 +557,38 @@ impl Atom {
 }
 
 impl<'a> AtomView<'a> {
+    /// Combine all fractions in the expression into a single numerator and denominator,
+    /// cancelling their greatest common divisor. Non-polynomial parts, such as functions,
+    /// are treated as independent variables.
+    ///
+    /// If the expression contains finite field coefficients, the fractions are combined
+    /// in that field instead of over the rationals.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let a = Atom::parse("1/x + 1/y").unwrap().together();
+    /// assert_eq!(a, Atom::parse("(x+y)/(x*y)").unwrap());
+    /// ```
+    pub fn together(&self) -> Atom {
+        let Some(fi) = self.get_finite_field() else {
+            let r: RationalPolynomial<IntegerRing, u16> = self.to_rational_polynomial(&Q, &Z, None);
+            return r.to_expression();
+        };
+
+        let field = State::get_finite_field(fi);
+        let r: RationalPolynomial<Zp64, u16> = self.to_rational_polynomial(field, field, None);
+
+        // finite field elements do not know their field, so convert to integers and back
+        let r = RationalPolynomial {
+            numerator: r.numerator.map_coeff(|c| field.to_symmetric_integer(c), Z),
+            denominator: r
+                .denominator
+                .map_coeff(|c| field.to_symmetric_integer(c), Z),
+        };
+        r.to_expression().to_finite_field(fi)
+    }
+
     /// Convert an expanded expression to a polynomial.
     fn to_polynomial_expanded<R: Ring + ConvertToRing, E: Exponent>(
         &self,
@@ -467,12 +602,7 @@ impl<'a> AtomView<'a> {
             allow_new_vars: bool,
         ) -> Result<(), &'static str> {
             match factor {
-                AtomView::Num(n) => match n.get_coeff_view() {
-                    CoefficientView::FiniteField(_, _) => {
-                        Err("Finite field not supported in conversion routine")
-                    }
-                    _ => Ok(()),
-                },
+                AtomView::Num(_) => Ok(()),
                 AtomView::Var(v) => {
                     let name = v.get_symbol();
                     if !vars.contains(&name.into()) {
@@ -526,6 +656,12 @@ impl<'a> AtomView<'a> {
                             CoefficientView::RationalPolynomial(_) => {
                                 Err("Rational polynomial not supported in conversion routine")
                             }
+                            CoefficientView::Float(_) => {
+                                Err("Float not supported in conversion routine")
+                            }
+                            CoefficientView::Complex(_) => {
+                                Err("Complex number not supported in conversion routine")
+                            }
                         },
                         _ => Err("base must be a variable"),
                     }
@@ -1682,3 +1818,160 @@ impl Token {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coefficient::Coefficient,
+        domains::{
+            algebraic_number::AlgebraicNumberRing,
+            finite_field::{FiniteFieldCore, Zp64},
+            rational::Q,
+            Field, Ring,
+        },
+        poly::polynomial::MultivariatePolynomial,
+        representations::Atom,
+        state::State,
+    };
+
+    #[test]
+    fn together() {
+        let a = Atom::parse("1/tog_x + 1/tog_y").unwrap().together();
+        assert_eq!(a, Atom::parse("(tog_x+tog_y)/(tog_x*tog_y)").unwrap());
+
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        let f = State::get_finite_field(field);
+
+        // 1/3 + 1/3 = 2 * 3^-1 in GF(7)
+        let a = Atom::parse("1/3 + tog_x/(3*tog_y)")
+            .unwrap()
+            .to_finite_field(field)
+            .together();
+        let three_inv = f.inv(&f.to_element(3));
+        assert_eq!(three_inv, f.to_element(5));
+        let expected = Atom::parse("(5*tog_x + 5*tog_y)/tog_y")
+            .unwrap()
+            .to_finite_field(field);
+        assert_eq!(a, expected);
+
+        let a = Atom::parse("1/3 + 1/3")
+            .unwrap()
+            .to_finite_field(field)
+            .together();
+        assert_eq!(
+            a,
+            Atom::new_num(Coefficient::FiniteField(
+                f.mul(&f.to_element(2), &three_inv),
+                field
+            ))
+        );
+    }
+
+    #[test]
+    fn factor() {
+        let x = State::get_symbol("fac_x");
+
+        let mut f = Atom::parse("fac_x^2 - 1").unwrap().factor(x).unwrap();
+        f.sort_by_key(|(f, _)| f.to_string());
+        assert_eq!(
+            f,
+            vec![
+                (Atom::parse("fac_x+1").unwrap(), 1),
+                (Atom::parse("fac_x-1").unwrap(), 1)
+            ]
+        );
+
+        let f = Atom::parse("fac_x^2 + 2*fac_x + 1")
+            .unwrap()
+            .factor(x)
+            .unwrap();
+        assert_eq!(f, vec![(Atom::parse("fac_x+1").unwrap(), 2)]);
+
+        let a = Atom::parse("(3*fac_x^2 + 3)*(fac_x-2)^3/2").unwrap();
+        let f = a.factor(x).unwrap();
+        let mut product = Atom::new_num(1);
+        for (f, p) in &f {
+            product = product * &f.npow(*p as i64);
+        }
+        for v in [Coefficient::from(5), Coefficient::from((-3i64, 7i64))] {
+            assert_eq!(
+                product.eval_to_number(&[(x, v.clone())]),
+                a.eval_to_number(&[(x, v)])
+            );
+        }
+        assert!(f.contains(&(Atom::parse("fac_x-2").unwrap(), 3)));
+
+        assert_eq!(
+            Atom::new_num(6).factor(x).unwrap(),
+            vec![(Atom::new_num(6), 1)]
+        );
+        assert_eq!(
+            Atom::parse("-2*fac_x").unwrap().factor(x).unwrap(),
+            vec![(Atom::parse("fac_x").unwrap(), 1), (Atom::new_num(-2), 1)]
+        );
+        assert!(Atom::new_num(1).factor(x).unwrap().is_empty());
+
+        assert!(Atom::parse("fac_x*fac_y").unwrap().factor(x).is_err());
+        assert!(Atom::parse("sin(fac_x)").unwrap().factor(x).is_err());
+    }
+
+    #[test]
+    fn factor_in_number_field() {
+        let x = State::get_symbol("nf_x");
+        let a = State::get_symbol("nf_a");
+        let sqrt2 = Atom::parse("nf_a^2 - 2").unwrap();
+
+        let mut f = Atom::parse("nf_x^2 - 2")
+            .unwrap()
+            .factor_in_number_field(x, a, &sqrt2)
+            .unwrap();
+        f.sort_by_key(|(f, _)| f.to_string());
+        assert_eq!(
+            f,
+            vec![
+                (Atom::parse("nf_x+nf_a").unwrap(), 1),
+                (Atom::parse("nf_x-nf_a").unwrap(), 1)
+            ]
+        );
+
+        // x^4 + 1 splits into two quadratics over Q(sqrt(2))
+        let e = Atom::parse("3*(nf_x^4 + 1)*(nf_x - nf_a)^2*(nf_x^2 + 3)").unwrap();
+        let f = e.factor_in_number_field(x, a, &sqrt2).unwrap();
+        assert!(f.contains(&(Atom::parse("nf_x^2+nf_a*nf_x+1").unwrap(), 1)));
+        assert!(f.contains(&(Atom::parse("nf_x^2-nf_a*nf_x+1").unwrap(), 1)));
+        assert!(f.contains(&(Atom::parse("nf_x-nf_a").unwrap(), 2)));
+        assert!(f.contains(&(Atom::parse("nf_x^2+3").unwrap(), 1)));
+        assert!(f.contains(&(Atom::new_num(3), 1)));
+        assert_eq!(f.len(), 5);
+
+        // the cube roots of unity
+        let f = Atom::parse("nf_x^3 - 1")
+            .unwrap()
+            .factor_in_number_field(x, a, &Atom::parse("nf_a^2 + nf_a + 1").unwrap())
+            .unwrap();
+        assert_eq!(f.len(), 3);
+
+        let f = Atom::parse("nf_x^3 - 2")
+            .unwrap()
+            .factor_in_number_field(x, a, &Atom::parse("nf_a^3 - 2").unwrap())
+            .unwrap();
+        assert!(f.contains(&(Atom::parse("nf_x-nf_a").unwrap(), 1)));
+        assert!(f.contains(&(Atom::parse("nf_x^2+nf_a*nf_x+nf_a^2").unwrap(), 1)));
+
+        assert!(Atom::parse("nf_x^2 - 2")
+            .unwrap()
+            .factor_in_number_field(x, a, &Atom::parse("nf_a^2 - 1").unwrap())
+            .is_err());
+
+        // multivariate polynomials are not supported
+        assert!(Atom::parse("nf_x^2 - 2*nf_y^2")
+            .unwrap()
+            .factor_in_number_field(x, a, &sqrt2)
+            .is_err());
+        let field = AlgebraicNumberRing::new(sqrt2.to_minimal_polynomial(a).unwrap());
+        let p: MultivariatePolynomial<_, u16> = Atom::parse("nf_x^2 - 2*nf_y^2")
+            .unwrap()
+            .to_polynomial(&Q, None);
+        assert!(p.to_number_field(&field).try_factor().is_err());
+    }
+}
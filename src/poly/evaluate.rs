@@ -1599,7 +1599,7 @@ impl<'a> std::fmt::Display for InstructionSetPrinter<'a> {
                             None
                         }
                     } else if let super::Variable::Symbol(i) = x {
-                        if [State::E, State::I, State::PI].contains(i) {
+                        if [State::E, State::I, State::PI, State::EULER_GAMMA].contains(i) {
                             None
                         } else {
                             Some(format!("T {}", x.to_string()))
@@ -1774,7 +1774,7 @@ impl ExpressionEvaluator {
                                 None
                             }
                         } else if let super::Variable::Symbol(i) = x {
-                            if [State::E, State::I, State::PI].contains(i) {
+                            if [State::E, State::I, State::PI, State::EULER_GAMMA].contains(i) {
                                 None
                             } else {
                                 Some(x.clone())
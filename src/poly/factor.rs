@@ -7,12 +7,13 @@ use tracing::debug;
 use crate::{
     combinatorics::CombinationIterator,
     domains::{
+        algebraic_number::AlgebraicNumberRing,
         finite_field::{
             FiniteField, FiniteFieldCore, FiniteFieldWorkspace, PrimeIteratorU64, ToFiniteField,
             Zp, Zp64,
         },
         integer::{Integer, IntegerRing, Z},
-        rational::{RationalField, Q},
+        rational::{Rational, RationalField, Q},
         EuclideanDomain, Field, Ring,
     },
     poly::gcd::LARGE_U32_PRIMES,
@@ -342,6 +343,221 @@ impl<E: Exponent> Factorize for MultivariatePolynomial<RationalField, E, LexOrde
     }
 }
 
+impl<E: Exponent> MultivariatePolynomial<AlgebraicNumberRing<RationalField>, E, LexOrder> {
+    /// Get the variable of a univariate polynomial, or `None` if the polynomial is constant.
+    /// Returns an error if the polynomial is multivariate.
+    fn number_field_univariate_var(&self) -> Result<Option<usize>, String> {
+        let Some(var) = self.last_exponents().iter().position(|x| *x > E::zero()) else {
+            return Ok(None);
+        };
+
+        if (0..self.nvars()).any(|v| v != var && self.degree(v) > E::zero()) {
+            return Err(format!(
+                "Factorization of multivariate polynomials over number fields is not supported: {}",
+                self
+            ));
+        }
+        Ok(Some(var))
+    }
+
+    /// Compute the norm `Res_α(f(x), m(α))` of a univariate polynomial in `var`, where
+    /// `m` is the minimal polynomial of the number field. The resultant is computed by evaluating
+    /// `x` at integer points and interpolating.
+    fn norm(&self, var: usize) -> MultivariatePolynomial<RationalField, E> {
+        let m: Vec<Rational> = {
+            let poly = self.field.poly();
+            let mut m = vec![Q.zero(); poly.degree(0) as usize + 1];
+            for t in poly {
+                m[t.exponents[0] as usize] = t.coefficient.clone();
+            }
+            m
+        };
+
+        let d = self.degree(var).to_u32() as usize * (m.len() - 1);
+
+        let mut values = Vec::with_capacity(d + 1);
+        for i in 0..=d {
+            let x = Q.nth(i as u64);
+            let mut f = vec![Q.zero(); m.len() - 1];
+            for t in self {
+                let xp = Q.pow(&x, t.exponents[var].to_u32() as u64);
+                for c in &t.coefficient.poly {
+                    Q.add_mul_assign(&mut f[c.exponents[0] as usize], c.coefficient, &xp);
+                }
+            }
+
+            values.push(dense_resultant(f, m.clone()));
+        }
+
+        // Newton interpolation on the points 0..=d
+        for j in 1..=d {
+            for i in (j..=d).rev() {
+                let diff = Q.sub(&values[i], &values[i - 1]);
+                values[i] = Q.div(&diff, &Q.nth(j as u64));
+            }
+        }
+
+        let mut x_exp = vec![E::zero(); self.nvars()];
+        x_exp[var] = E::one();
+        let x = MultivariatePolynomial::new(&Q, None, self.get_vars()).monomial(Q.one(), x_exp);
+
+        let mut res = x.constant(values[d].clone());
+        for i in (0..d).rev() {
+            res = &res * &(&x - &x.constant(Q.nth(i as u64))) + x.constant(values[i].clone());
+        }
+        res
+    }
+
+    /// Factor a square-free univariate polynomial in `var` using Trager's algorithm:
+    /// find a shift `s` such that the norm of `f(x - sα)` is square-free, factor the norm
+    /// over the rationals and lift every factor with a gcd over the number field.
+    fn trager_factorization(&self, var: usize) -> Vec<Self> {
+        let alpha = self
+            .field
+            .to_element(self.field.poly().monomial(Q.one(), vec![1]));
+
+        for s in (0..).map(|i: i64| if i % 2 == 0 { -i / 2 } else { (i + 1) / 2 }) {
+            let shift = self.field.mul(&self.field.nth(s.unsigned_abs()), &alpha);
+            let shift = if s < 0 { self.field.neg(&shift) } else { shift };
+
+            let g = self.shift_var(var, &self.field.neg(&shift));
+            let norm = g.norm(var);
+
+            if !norm.univariate_gcd(&norm.derivative(var)).is_constant() {
+                continue;
+            }
+
+            let norm_factors = norm.factor();
+            if norm_factors
+                .iter()
+                .filter(|(f, _)| !f.is_constant())
+                .count()
+                == 1
+            {
+                return vec![self.clone()];
+            }
+
+            let mut factors = vec![];
+            for (f, _) in norm_factors {
+                if f.is_constant() {
+                    continue;
+                }
+
+                let h = g.univariate_gcd(&f.to_number_field(&self.field));
+                if !h.is_constant() {
+                    factors.push(h.shift_var(var, &shift));
+                }
+            }
+
+            return factors;
+        }
+
+        unreachable!()
+    }
+}
+
+/// Compute the resultant of two dense univariate polynomials over the rationals,
+/// with coefficients ordered from low to high degree.
+fn dense_resultant(mut a: Vec<Rational>, mut b: Vec<Rational>) -> Rational {
+    let trim = |a: &mut Vec<Rational>| {
+        while a.last().map(RationalField::is_zero).unwrap_or(false) {
+            a.pop();
+        }
+    };
+
+    trim(&mut a);
+    trim(&mut b);
+    if a.is_empty() || b.is_empty() {
+        return Q.zero();
+    }
+
+    let mut res = Q.one();
+    loop {
+        let (m, n) = (a.len() - 1, b.len() - 1);
+        if n == 0 {
+            return Q.mul(&res, &Q.pow(&b[0], m as u64));
+        }
+
+        // compute the remainder of a divided by b
+        let lc_inv = Q.inv(&b[n]);
+        for i in (n..=m).rev() {
+            let c = Q.mul(&a[i], &lc_inv);
+            for (j, y) in b.iter().enumerate() {
+                Q.sub_mul_assign(&mut a[i - n + j], &c, y);
+            }
+        }
+        a.truncate(n);
+        trim(&mut a);
+
+        if a.is_empty() {
+            return Q.zero();
+        }
+
+        res = Q.mul(&res, &Q.pow(&b[n], (m - (a.len() - 1)) as u64));
+        if m % 2 == 1 && n % 2 == 1 {
+            res = Q.neg(&res);
+        }
+
+        std::mem::swap(&mut a, &mut b);
+    }
+}
+
+impl<E: Exponent> MultivariatePolynomial<AlgebraicNumberRing<RationalField>, E, LexOrder> {
+    /// Perform a square-free factorization of a univariate polynomial using Yun's algorithm.
+    /// Returns an error if the polynomial is multivariate.
+    pub fn try_square_free_factorization(&self) -> Result<Vec<(Self, usize)>, String> {
+        let Some(var) = self.number_field_univariate_var()? else {
+            return Ok(vec![(self.clone(), 1)]);
+        };
+
+        let c = self.lcoeff();
+        let f = self.clone().make_monic();
+
+        let mut factors = vec![];
+        let df = f.derivative(var);
+        let a = f.univariate_gcd(&df);
+        let mut b = &f / &a;
+        let mut d = &df / &a - b.derivative(var);
+        let mut i = 1;
+        while !b.is_constant() {
+            let a = b.univariate_gcd(&d);
+            b = &b / &a;
+            let c = &d / &a;
+            d = c - b.derivative(var);
+
+            if !a.is_constant() {
+                factors.push((a, i));
+            }
+            i += 1;
+        }
+
+        if !self.field.is_one(&c) {
+            factors.push((self.constant(c), 1));
+        }
+
+        Ok(factors)
+    }
+
+    /// Factor a univariate polynomial over its number field.
+    /// Returns an error if the polynomial is multivariate.
+    pub fn try_factor(&self) -> Result<Vec<(Self, usize)>, String> {
+        let Some(var) = self.number_field_univariate_var()? else {
+            return Ok(vec![(self.clone(), 1)]);
+        };
+
+        let mut factors = vec![];
+        for (f, p) in self.try_square_free_factorization()? {
+            if f.is_constant() {
+                factors.push((f, p));
+            } else {
+                factors.extend(f.trager_factorization(var).into_iter().map(|f| (f, p)));
+            }
+        }
+
+        Ok(factors)
+    }
+}
+
 impl<UField: FiniteFieldWorkspace, E: Exponent> Factorize
     for MultivariatePolynomial<FiniteField<UField>, E, LexOrder>
 where
@@ -1,6 +1,7 @@
 use std::fmt::{self, Display, Write};
 
 use colored::Colorize;
+use rug::{Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational};
 
 use crate::{
     coefficient::CoefficientView,
@@ -10,7 +11,7 @@ use crate::{
     },
     poly::{polynomial::MultivariatePolynomial, Exponent, MonomialOrder},
     representations::{
-        default::FunView, AddView, AtomView, MulView, NumView, PowView, Symbol, VarView,
+        default::FunView, AddView, Atom, AtomView, MulView, NumView, PowView, Symbol, VarView,
     },
     state::State,
     tensors::matrix::Matrix,
@@ -29,6 +30,9 @@ pub struct PrintOptions {
     pub square_brackets_for_function: bool,
     pub num_exp_as_superscript: bool,
     pub latex: bool,
+    /// The maximum number of digits of large rational numbers, which are otherwise
+    /// printed approximately. See [`State::set_max_display_digits`].
+    pub max_display_digits: Option<usize>,
 }
 
 impl PrintOptions {
@@ -46,6 +50,26 @@ impl PrintOptions {
             square_brackets_for_function: true,
             num_exp_as_superscript: false,
             latex: false,
+            max_display_digits: None,
+        }
+    }
+
+    /// Print the output in a format that can be parsed again, without colors
+    /// and with all numbers printed exactly.
+    pub fn file() -> PrintOptions {
+        Self {
+            terms_on_new_line: false,
+            color_top_level_sum: false,
+            color_builtin_functions: false,
+            print_finite_field: true,
+            symmetric_representation_for_finite_field: false,
+            explicit_rational_polynomial: false,
+            number_thousands_separator: None,
+            multiplication_operator: '*',
+            square_brackets_for_function: false,
+            num_exp_as_superscript: false,
+            latex: false,
+            max_display_digits: None,
         }
     }
 
@@ -63,6 +87,7 @@ impl PrintOptions {
             square_brackets_for_function: false,
             num_exp_as_superscript: false,
             latex: true,
+            max_display_digits: None,
         }
     }
 }
@@ -81,6 +106,7 @@ impl Default for PrintOptions {
             square_brackets_for_function: false,
             num_exp_as_superscript: false,
             latex: false,
+            max_display_digits: State::get_max_display_digits(),
         }
     }
 }
@@ -185,6 +211,92 @@ impl<'a> AtomView<'a> {
     }
 }
 
+impl Atom {
+    /// Print the expression in LaTeX format. See [`AtomView::to_latex`].
+    pub fn to_latex(&self) -> String {
+        self.as_view().to_latex()
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Print the expression in LaTeX format, without surrounding math delimiters.
+    /// Symbols are printed with the name set by [`State::set_latex_name`], if any.
+    pub fn to_latex(&self) -> String {
+        AtomPrinter::new_with_options(*self, PrintOptions::latex()).to_string()
+    }
+
+    /// Export the expression tree in the Graphviz DOT format. Operators and functions
+    /// are nodes that have an edge to each of their children.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph G {\n");
+        let mut node_count = 0;
+        self.write_dot_node(&mut out, &mut node_count);
+        out.push('}');
+        out
+    }
+
+    /// Write the node of `self` and its subtree, returning the node id.
+    fn write_dot_node(&self, out: &mut String, node_count: &mut usize) -> usize {
+        let id = *node_count;
+        *node_count += 1;
+
+        let label = match self {
+            AtomView::Num(_) => {
+                let opts = PrintOptions {
+                    color_top_level_sum: false,
+                    color_builtin_functions: false,
+                    ..PrintOptions::default()
+                };
+                AtomPrinter::new_with_options(*self, opts).to_string()
+            }
+            AtomView::Var(v) => State::get_name(v.get_symbol()).to_owned(),
+            AtomView::Fun(f) => State::get_name(f.get_symbol()).to_owned(),
+            AtomView::Pow(_) => "^".to_owned(),
+            AtomView::Mul(_) => "*".to_owned(),
+            AtomView::Add(_) => "+".to_owned(),
+        };
+
+        writeln!(
+            out,
+            "  {} [label=\"{}\"];",
+            id,
+            label.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+        .unwrap();
+
+        let mut write_child = |child: AtomView, out: &mut String| {
+            let child_id = child.write_dot_node(out, node_count);
+            writeln!(out, "  {} -> {};", id, child_id).unwrap();
+        };
+
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => {}
+            AtomView::Fun(f) => {
+                for arg in f.iter() {
+                    write_child(arg, out);
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                write_child(base, out);
+                write_child(exp, out);
+            }
+            AtomView::Mul(m) => {
+                for arg in m.iter() {
+                    write_child(arg, out);
+                }
+            }
+            AtomView::Add(a) => {
+                for arg in a.iter() {
+                    write_child(arg, out);
+                }
+            }
+        }
+
+        id
+    }
+}
+
 impl<'a> fmt::Debug for AtomView<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         self.fmt_debug(fmt)
@@ -210,13 +322,18 @@ impl<'a> FormattedPrintVar for VarView<'a> {
         let name = State::get_name(id);
 
         if opts.latex {
+            if let Some(latex_name) = State::get_latex_name(id) {
+                return f.write_str(&latex_name);
+            }
+
             match id {
                 State::E => f.write_char('e'),
                 State::PI => f.write_str("\\pi"),
+                State::EULER_GAMMA => f.write_str("\\gamma_E"),
                 State::I => f.write_char('i'),
                 _ => f.write_str(name),
             }
-        } else if name.ends_with('_') {
+        } else if id.get_wildcard_level() > 0 {
             f.write_fmt(format_args!("{}", name.cyan().italic()))
         } else if opts.color_builtin_functions && State::is_builtin(id) {
             f.write_fmt(format_args!("{}", name.purple()))
@@ -280,6 +397,8 @@ impl<'a> FormattedPrintNum for NumView<'a> {
         let is_negative = match d {
             CoefficientView::Natural(n, _) => n < 0,
             CoefficientView::Large(r) => r.is_negative(),
+            CoefficientView::Float(f) => f.is_negative(),
+            CoefficientView::Complex(c) if c.re().is_zero() => c.im().is_negative(),
             _ => false,
         };
 
@@ -322,6 +441,16 @@ impl<'a> FormattedPrintNum for NumView<'a> {
             }
             CoefficientView::Large(r) => {
                 let rat = r.to_rat().abs();
+
+                if !opts.latex {
+                    if let Some(digits) = opts.max_display_digits {
+                        let (num, den) = (rat.numer().to_string(), rat.denom().to_string());
+                        if num.len() > digits || den.len() > digits {
+                            return f.write_str(&format_approximate(&rat, &num, &den, digits));
+                        }
+                    }
+                }
+
                 if !opts.latex
                     && (opts.number_thousands_separator.is_some() || print_state.superscript)
                 {
@@ -357,7 +486,70 @@ impl<'a> FormattedPrintNum for NumView<'a> {
                     add_parentheses: false,
                 }
             )),
+            CoefficientView::Float(fl) => f.write_fmt(format_args!("{}", fl.to_float().abs())),
+            CoefficientView::Complex(c) => {
+                let i = if opts.latex { "i" } else { "𝑖" };
+                let (re, im) = (c.re(), c.im());
+                let im_abs = if im.is_one() || im.is_minus_one() {
+                    String::new()
+                } else {
+                    im.to_real_string(true)
+                };
+
+                if re.is_zero() {
+                    f.write_fmt(format_args!("{}{}", im_abs, i))
+                } else {
+                    f.write_fmt(format_args!(
+                        "({}{}{}{})",
+                        re.to_real_string(false),
+                        if im.is_negative() { '-' } else { '+' },
+                        im_abs,
+                        i
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Format the positive rational number `rat`, with numerator and denominator
+/// strings `num` and `den`, as a decimal rounded to `digits` significant digits.
+fn format_approximate(rat: &MultiPrecisionRational, num: &str, den: &str, digits: usize) -> String {
+    let scaled = |exp: i64| {
+        // compute floor(rat * 10^(digits - exp)), which has `digits + 1` digits
+        // when `exp` is the decimal exponent of `rat`
+        let shift = digits as i64 - exp;
+        let mut n = rat.numer().clone();
+        let mut d = rat.denom().clone();
+        if shift >= 0 {
+            n *= MultiPrecisionInteger::from(MultiPrecisionInteger::u_pow_u(10, shift as u32));
+        } else {
+            d *= MultiPrecisionInteger::from(MultiPrecisionInteger::u_pow_u(10, (-shift) as u32));
         }
+        n / d
+    };
+
+    // the exponent is either the difference in digit count or one less
+    let mut exp = num.len() as i64 - den.len() as i64;
+    let mut q = scaled(exp);
+    if q < MultiPrecisionInteger::from(MultiPrecisionInteger::u_pow_u(10, digits as u32)) {
+        exp -= 1;
+        q = scaled(exp);
+    }
+
+    // round half up to `digits` digits
+    q += 5;
+    q /= 10;
+    if q == MultiPrecisionInteger::from(MultiPrecisionInteger::u_pow_u(10, digits as u32)) {
+        q /= 10;
+        exp += 1;
+    }
+
+    let mantissa = q.to_string();
+    if digits == 1 {
+        format!("{}…e{}", mantissa, exp)
+    } else {
+        format!("{}.{}…e{}", &mantissa[..1], &mantissa[1..], exp)
     }
 }
 
@@ -453,14 +645,51 @@ impl<'a> FormattedPrintFn for FunView<'a> {
         let id = self.get_symbol();
         let name = State::get_name(id);
 
+        if id == State::LIST {
+            f.write_str(if opts.latex { "\\left\\{" } else { "{" })?;
+            print_state.level += 1;
+            print_state.explicit_sign = false;
+            for (i, x) in self.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                x.fmt_output(f, opts, print_state)?;
+            }
+            return f.write_str(if opts.latex { "\\right\\}" } else { "}" });
+        }
+
         if opts.latex {
-            if name == "cos" || name == "sin" || name == "exp" || name == "log" {
+            if id == State::CONJ && self.get_nargs() == 1 {
+                f.write_str("\\overline{")?;
+                print_state.level += 1;
+                print_state.explicit_sign = false;
+                self.iter()
+                    .next()
+                    .unwrap()
+                    .fmt_output(f, opts, print_state)?;
+                return f.write_char('}');
+            }
+
+            if id == State::SQRT && self.get_nargs() == 1 {
+                f.write_str("\\sqrt{")?;
+                print_state.level += 1;
+                print_state.explicit_sign = false;
+                self.iter()
+                    .next()
+                    .unwrap()
+                    .fmt_output(f, opts, print_state)?;
+                return f.write_char('}');
+            }
+
+            if let Some(latex_name) = State::get_latex_name(id) {
+                f.write_fmt(format_args!("{}\\!\\left(", latex_name))?;
+            } else if name == "cos" || name == "sin" || name == "exp" || name == "log" {
                 f.write_fmt(format_args!("\\{}\\!\\left(", name))?;
             } else {
                 f.write_fmt(format_args!("{}\\!\\left(", name))?;
             }
         } else {
-            if name.ends_with('_') {
+            if id.get_wildcard_level() > 0 {
                 f.write_fmt(format_args!("{}", name.cyan().italic()))?;
             } else {
                 // check if the function name is built in
@@ -1277,3 +1506,79 @@ impl<'a, F: Ring + Display> Display for MatrixPrinter<'a, F> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rug::{Complete, Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational};
+
+    use crate::{representations::Atom, state::State};
+
+    use super::{AtomPrinter, PrintOptions};
+
+    #[test]
+    fn to_dot() {
+        let a = Atom::parse("f(x+1)").unwrap();
+        let dot = a.as_view().to_dot();
+
+        assert!(dot.starts_with("digraph G {"));
+        assert!(dot.contains("[label=\"f\"]"));
+        assert!(dot.contains("[label=\"+\"]"));
+        assert!(dot.contains("[label=\"x\"]"));
+        assert!(dot.contains("[label=\"1\"]"));
+        assert_eq!(dot.matches("->").count(), 3);
+    }
+
+    #[test]
+    fn to_latex() {
+        let a = Atom::parse("sin(ltx_x)/2 + ltx_x^2").unwrap();
+        let latex = a.to_latex();
+
+        assert!(latex.contains("\\frac"));
+        assert!(latex.contains("\\sin"));
+        assert!(latex.contains("ltx_x^{2}"));
+
+        let b = Atom::parse("sqrt(ltx_y)").unwrap();
+        assert_eq!(b.to_latex(), "\\sqrt{ltx_y}");
+
+        State::set_latex_name(State::get_symbol("ltx_alpha"), "\\alpha");
+        let c = Atom::parse("ltx_alpha*ltx_f(ltx_alpha)").unwrap();
+        State::set_latex_name(State::get_symbol("ltx_f"), "\\mathcal{F}");
+        assert!(c.to_latex().contains("\\alpha"));
+        assert!(c
+            .to_latex()
+            .contains("\\mathcal{F}\\!\\left(\\alpha\\right)"));
+    }
+
+    #[test]
+    fn approximate_large() {
+        let fmt = |r: MultiPrecisionRational, digits| {
+            let (num, den) = (r.numer().to_string(), r.denom().to_string());
+            super::format_approximate(&r, &num, &den, digits)
+        };
+
+        let big = MultiPrecisionRational::from(MultiPrecisionInteger::u_pow_u(10, 500).complete());
+        assert_eq!(fmt(big.clone() * 2 / 3, 4), "6.667…e499");
+        assert_eq!(fmt(big.clone() * 99999 / 100000, 3), "1.00…e500");
+        assert_eq!(fmt(big.clone(), 1), "1…e500");
+        assert_eq!(fmt(MultiPrecisionRational::from(1) / big, 2), "1.0…e-500");
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let a =
+            Atom::parse("prt_f(2^200/3 + prt_x, -7^90*prt_x^(5^40/3), (1+2^70*𝑖)*prt_y)").unwrap();
+
+        let exact = AtomPrinter::new_with_options(a.as_view(), PrintOptions::file()).to_string();
+        assert_eq!(Atom::parse(&exact).unwrap(), a);
+        assert_eq!(Atom::parse(&a.to_string()).unwrap(), a);
+
+        // abbreviated numbers are approximate and cannot be parsed back
+        let opts = PrintOptions {
+            max_display_digits: Some(5),
+            ..PrintOptions::file()
+        };
+        let approx = AtomPrinter::new_with_options(a.as_view(), opts).to_string();
+        assert!(approx.contains("…e59"));
+        assert!(approx.len() < exact.len());
+    }
+}
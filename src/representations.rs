@@ -1,17 +1,23 @@
 mod coefficient;
+#[cfg(feature = "compression")]
+pub mod compressed;
+pub mod dag;
 pub mod default;
 
 use crate::{
-    coefficient::Coefficient,
+    coefficient::{Coefficient, CoefficientView},
     parser::Token,
     printer::AtomPrinter,
-    state::{RecycledAtom, Workspace},
+    state::{LinearArguments, RecycledAtom, State, Workspace},
 };
+use ahash::HashSet;
 use std::{cmp::Ordering, hash::Hash, ops::DerefMut};
+use xxhash_rust::xxh3::Xxh3;
 
+pub(crate) use self::coefficient::PackedRationalNumberReader;
 pub use self::default::{
-    Add, AddView, Fun, ListIterator, ListSlice, Mul, MulView, Num, NumView, Pow, PowView, Var,
-    VarView,
+    Add, AddCursor, AddView, Fun, ListIterator, ListSlice, Mul, MulView, Num, NumView,
+    PackedDataError, PackedDataErrorKind, Pow, PowView, Var, VarView,
 };
 use self::default::{FunView, RawAtom};
 
@@ -25,6 +31,9 @@ pub struct Symbol {
     is_symmetric: bool,
     is_antisymmetric: bool,
     is_linear: bool,
+    is_noncommutative: bool,
+    is_real: bool,
+    linear_arguments: u32,
 }
 
 impl Symbol {
@@ -37,6 +46,9 @@ impl Symbol {
             is_symmetric: false,
             is_antisymmetric: false,
             is_linear: false,
+            is_noncommutative: false,
+            is_real: false,
+            linear_arguments: 0,
         }
     }
 
@@ -48,6 +60,7 @@ impl Symbol {
         is_symmetric: bool,
         is_antisymmetric: bool,
         is_linear: bool,
+        is_noncommutative: bool,
     ) -> Self {
         Symbol {
             id,
@@ -55,9 +68,34 @@ impl Symbol {
             is_symmetric,
             is_antisymmetric,
             is_linear,
+            is_noncommutative,
+            is_real: false,
+            linear_arguments: 0,
         }
     }
 
+    /// The largest number of arguments a function can be restricted to be linear in.
+    /// The mask of linear arguments is stored in the 30 bits of the packed 64-bit
+    /// function id that are not used by the id and the function flags.
+    pub const MAX_LINEAR_ARGUMENTS: usize = 30;
+
+    /// The largest id of a symbol. The bits above it are used
+    /// to store the attributes of a function.
+    pub(crate) const MAX_ID: u32 = (1 << 31) - 1;
+
+    /// Restrict a linear function to be linear only in the arguments whose bit is set in `mask`.
+    /// A mask of 0 means that the function is linear in all arguments.
+    pub(crate) const fn with_linear_arguments(mut self, mask: u32) -> Self {
+        self.linear_arguments = mask;
+        self
+    }
+
+    /// Mark the symbol as real.
+    pub(crate) const fn with_real(mut self, is_real: bool) -> Self {
+        self.is_real = is_real;
+        self
+    }
+
     pub fn get_id(&self) -> u32 {
         self.id
     }
@@ -77,6 +115,39 @@ impl Symbol {
     pub fn is_linear(&self) -> bool {
         self.is_linear
     }
+
+    /// Returns `true` iff the function is linear in its argument at position `index`.
+    pub fn is_linear_in(&self, index: usize) -> bool {
+        self.is_linear
+            && (self.linear_arguments == 0
+                || index < Self::MAX_LINEAR_ARGUMENTS && self.linear_arguments & (1 << index) != 0)
+    }
+
+    /// Get the positions of the arguments the function is linear in, if
+    /// the function is only linear in some of its arguments.
+    pub fn get_linear_arguments(&self) -> Option<Vec<usize>> {
+        if !self.is_linear || self.linear_arguments == 0 {
+            return None;
+        }
+
+        Some(LinearArguments::from_mask(self.linear_arguments).positions())
+    }
+
+    pub(crate) fn get_linear_arguments_mask(&self) -> u32 {
+        self.linear_arguments
+    }
+
+    /// Returns `true` iff the symbol does not commute with other noncommutative
+    /// symbols in a product.
+    pub fn is_noncommutative(&self) -> bool {
+        self.is_noncommutative
+    }
+
+    /// Returns `true` iff the symbol is declared to be real. For a function, this
+    /// means that it commutes with complex conjugation.
+    pub fn is_real(&self) -> bool {
+        self.is_real
+    }
 }
 
 impl std::fmt::Debug for Symbol {
@@ -336,6 +407,532 @@ impl<'a> AtomView<'a> {
         }
     }
 
+    /// Returns `true` iff the atom is a list `{a,b,...}`.
+    pub fn is_list(&self) -> bool {
+        matches!(self, AtomView::Fun(f) if f.get_symbol() == State::LIST)
+    }
+
+    /// Get the element at position `index` of a list, or `None` if the atom
+    /// is not a list or if the index is out of range.
+    pub fn get_list_element(&self, index: usize) -> Option<AtomView<'a>> {
+        match self {
+            AtomView::Fun(f) if f.get_symbol() == State::LIST => f.iter().nth(index),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` iff the expression is known to be real. Symbols and functions
+    /// are complex, unless they are built-in constants, `re`, `im` or are declared
+    /// with [`FunctionAttribute::Real`](crate::state::FunctionAttribute::Real).
+    /// A real function is real when all its arguments are real, as are
+    /// `exp`, `sin` and `cos`.
+    pub fn is_real(&self) -> bool {
+        match self {
+            AtomView::Num(n) => matches!(
+                n.get_coeff_view(),
+                CoefficientView::Natural(..)
+                    | CoefficientView::Large(_)
+                    | CoefficientView::Float(_)
+            ),
+            AtomView::Var(v) => {
+                let s = v.get_symbol();
+                s.is_real() || [State::E, State::PI, State::EULER_GAMMA].contains(&s)
+            }
+            AtomView::Fun(f) => {
+                let s = f.get_symbol();
+                s == State::RE
+                    || s == State::IM
+                    || (s.is_real() || [State::EXP, State::SIN, State::COS].contains(&s))
+                        && f.iter().all(|a| a.is_real())
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.is_real()
+                    && matches!(exp, AtomView::Num(n) if matches!(n.get_coeff_view(), CoefficientView::Natural(_, 1)))
+            }
+            AtomView::Mul(m) => m.iter().all(|a| a.is_real()),
+            AtomView::Add(a) => a.iter().all(|a| a.is_real()),
+        }
+    }
+
+    /// Get the complex conjugate `conj(x)` of the expression.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::{FunctionAttribute, State}};
+    /// State::get_symbol_with_attributes("r", vec![FunctionAttribute::Real]).unwrap();
+    /// let a = Atom::parse("(x + 2*𝑖*r)^2").unwrap();
+    /// assert_eq!(
+    ///     a.as_view().conj(),
+    ///     Atom::parse("(conj(x) - 2*𝑖*r)^2").unwrap()
+    /// );
+    /// ```
+    pub fn conj(&self) -> Atom {
+        FunctionBuilder::new(State::CONJ).add_arg(*self).finish()
+    }
+
+    /// Get the real part `re(x)` of the expression.
+    pub fn re(&self) -> Atom {
+        FunctionBuilder::new(State::RE).add_arg(*self).finish()
+    }
+
+    /// Get the imaginary part `im(x)` of the expression.
+    pub fn im(&self) -> Atom {
+        FunctionBuilder::new(State::IM).add_arg(*self).finish()
+    }
+
+    /// Map every element of a list with `f`, or return `None` if the atom is not a list.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let a = Atom::parse("{x, x^2, f(x)}").unwrap();
+    /// let r = a.as_view().map_list(|e| e.derivative(symbolica::state::State::get_symbol("x")));
+    /// assert_eq!(r.unwrap(), Atom::parse("{1, 2*x, der(1,f(x))}").unwrap());
+    /// ```
+    pub fn map_list<F: FnMut(AtomView<'a>) -> Atom>(&self, mut f: F) -> Option<Atom> {
+        match self {
+            AtomView::Fun(l) if l.get_symbol() == State::LIST => {
+                let mut b = FunctionBuilder::new(State::LIST);
+                for e in l.iter() {
+                    b = b.add_arg(&f(e));
+                }
+                Some(b.finish())
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the subexpression at the position `path`, where every entry is the index
+    /// of an argument of a function, a factor of a product, a term of a sum, or
+    /// the base (0) or exponent (1) of a power. This is the format of the positions
+    /// returned by [`Pattern::pattern_match`](crate::id::Pattern::pattern_match).
+    ///
+    /// This function panics if the path does not exist.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let a = Atom::parse("f(x, y^2)").unwrap();
+    /// assert_eq!(a.as_view().at(&[1, 0]), Atom::parse("y").unwrap().as_view());
+    /// ```
+    pub fn at(&self, path: &[usize]) -> AtomView<'a> {
+        let mut cur = *self;
+        for &i in path {
+            let slice = match cur {
+                AtomView::Fun(f) => f.to_slice(),
+                AtomView::Pow(p) => p.to_slice(),
+                AtomView::Mul(m) => m.to_slice(),
+                AtomView::Add(a) => a.to_slice(),
+                _ => panic!("Position {:?} does not exist in {}", path, self),
+            };
+
+            if i >= slice.len() {
+                panic!("Position {:?} does not exist in {}", path, self);
+            }
+
+            cur = slice.get(i);
+        }
+        cur
+    }
+
+    /// Copy the expression, with the subexpression at `path` replaced by `rhs`.
+    /// If the subexpression is a product or sum, only the children marked in `used_flags`
+    /// are replaced. The result is not normalized.
+    fn replace_at_no_norm(
+        &self,
+        path: &[usize],
+        used_flags: &[bool],
+        rhs: AtomView,
+        ws: &Workspace,
+        out: &mut Atom,
+    ) {
+        let Some((first, rest)) = path.split_first() else {
+            match self {
+                AtomView::Mul(m) if !used_flags.is_empty() => {
+                    let out = out.to_mul();
+                    for (child, used) in m.iter().zip(used_flags) {
+                        if !used {
+                            out.extend(child);
+                        }
+                    }
+                    out.extend(rhs);
+                }
+                AtomView::Add(a) if !used_flags.is_empty() => {
+                    let out = out.to_add();
+                    for (child, used) in a.iter().zip(used_flags) {
+                        if !used {
+                            out.extend(child);
+                        }
+                    }
+                    out.extend(rhs);
+                }
+                _ => out.set_from_view(&rhs),
+            }
+            return;
+        };
+
+        let replace = |arg: AtomView| {
+            let mut oa = ws.new_atom();
+            arg.replace_at_no_norm(rest, used_flags, rhs, ws, &mut oa);
+            oa
+        };
+
+        match self {
+            AtomView::Fun(f) => {
+                let out = out.to_fun(f.get_symbol());
+                for (i, arg) in f.iter().enumerate() {
+                    if i == *first {
+                        out.add_arg(replace(arg).as_view());
+                    } else {
+                        out.add_arg(arg);
+                    }
+                }
+            }
+            AtomView::Pow(p) => {
+                let (b, e) = p.get_base_exp();
+                match first {
+                    0 => out.to_pow(replace(b).as_view(), e),
+                    1 => out.to_pow(b, replace(e).as_view()),
+                    _ => panic!("Position {:?} does not exist in {}", path, self),
+                };
+            }
+            AtomView::Mul(m) => {
+                let out = out.to_mul();
+                for (i, arg) in m.iter().enumerate() {
+                    if i == *first {
+                        out.extend(replace(arg).as_view());
+                    } else {
+                        out.extend(arg);
+                    }
+                }
+            }
+            AtomView::Add(a) => {
+                let out = out.to_add();
+                for (i, arg) in a.iter().enumerate() {
+                    if i == *first {
+                        out.extend(replace(arg).as_view());
+                    } else {
+                        out.extend(arg);
+                    }
+                }
+            }
+            _ => panic!("Position {:?} does not exist in {}", path, self),
+        }
+    }
+
+    /// Get all distinct variables in the expression, in order of first appearance.
+    /// Function names, wildcards and the built-in constants are not included.
+    pub fn free_variables(&self) -> Vec<Symbol> {
+        let mut out = vec![];
+        self.free_variables_impl(&mut out);
+        out
+    }
+
+    fn free_variables_impl(&self, out: &mut Vec<Symbol>) {
+        match self {
+            AtomView::Num(_) => {}
+            AtomView::Var(v) => {
+                let s = v.get_symbol();
+                if s.get_wildcard_level() == 0
+                    && ![State::E, State::PI, State::I, State::EULER_GAMMA].contains(&s)
+                    && !out.contains(&s)
+                {
+                    out.push(s);
+                }
+            }
+            AtomView::Fun(f) => {
+                for arg in f.iter() {
+                    arg.free_variables_impl(out);
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.free_variables_impl(out);
+                exp.free_variables_impl(out);
+            }
+            AtomView::Mul(m) => {
+                for child in m.iter() {
+                    child.free_variables_impl(out);
+                }
+            }
+            AtomView::Add(a) => {
+                for child in a.iter() {
+                    child.free_variables_impl(out);
+                }
+            }
+        }
+    }
+
+    /// Get all distinct wildcards in the expression, including wildcard function names,
+    /// in order of first appearance.
+    pub fn wildcards(&self) -> Vec<Symbol> {
+        let mut out = vec![];
+        self.wildcards_impl(&mut out);
+        out
+    }
+
+    fn wildcards_impl(&self, out: &mut Vec<Symbol>) {
+        let mut add = |s: Symbol| {
+            if s.get_wildcard_level() > 0 && !out.contains(&s) {
+                out.push(s);
+            }
+        };
+
+        match self {
+            AtomView::Num(_) => {}
+            AtomView::Var(v) => add(v.get_symbol()),
+            AtomView::Fun(f) => {
+                add(f.get_symbol());
+                for arg in f.iter() {
+                    arg.wildcards_impl(out);
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.wildcards_impl(out);
+                exp.wildcards_impl(out);
+            }
+            AtomView::Mul(m) => {
+                for child in m.iter() {
+                    child.wildcards_impl(out);
+                }
+            }
+            AtomView::Add(a) => {
+                for child in a.iter() {
+                    child.wildcards_impl(out);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` iff the expression has no free variables.
+    /// See [`AtomView::free_variables`].
+    pub fn is_constant(&self) -> bool {
+        match self {
+            AtomView::Num(_) => true,
+            AtomView::Var(v) => {
+                let s = v.get_symbol();
+                s.get_wildcard_level() > 0
+                    || [State::E, State::PI, State::I, State::EULER_GAMMA].contains(&s)
+            }
+            AtomView::Fun(f) => f.iter().all(|arg| arg.is_constant()),
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.is_constant() && exp.is_constant()
+            }
+            AtomView::Mul(m) => m.iter().all(|child| child.is_constant()),
+            AtomView::Add(a) => a.iter().all(|child| child.is_constant()),
+        }
+    }
+
+    /// Returns `true` iff the symbol `s` appears in the expression,
+    /// either as a variable or as a function name.
+    pub fn contains_symbol(&self, s: Symbol) -> bool {
+        match self {
+            AtomView::Num(_) => false,
+            AtomView::Var(v) => v.get_symbol().get_id() == s.get_id(),
+            AtomView::Fun(f) => {
+                f.get_symbol().get_id() == s.get_id() || f.iter().any(|arg| arg.contains_symbol(s))
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.contains_symbol(s) || exp.contains_symbol(s)
+            }
+            AtomView::Mul(m) => m.iter().any(|child| child.contains_symbol(s)),
+            AtomView::Add(a) => a.iter().any(|child| child.contains_symbol(s)),
+        }
+    }
+
+    /// Returns `true` iff `self` and `other` are equal after normalization,
+    /// comparing numbers by value instead of by representation.
+    pub fn value_eq(&self, other: AtomView<'_>) -> bool {
+        if *self == other {
+            return true;
+        }
+
+        Workspace::get_local().with(|ws| {
+            let mut a = ws.new_atom();
+            self.normalize(ws, &mut a);
+            let mut b = ws.new_atom();
+            other.normalize(ws, &mut b);
+            a.as_view().value_eq_impl(b.as_view())
+        })
+    }
+
+    fn value_eq_impl(&self, other: AtomView<'_>) -> bool {
+        match (self, other) {
+            (AtomView::Num(n1), AtomView::Num(n2)) => {
+                n1.get_coeff_view().normalize() == n2.get_coeff_view().normalize()
+            }
+            (AtomView::Var(v1), AtomView::Var(v2)) => v1.get_symbol() == v2.get_symbol(),
+            (AtomView::Fun(f1), AtomView::Fun(f2)) => {
+                f1.get_symbol() == f2.get_symbol()
+                    && f1.get_nargs() == f2.get_nargs()
+                    && f1.iter().zip(f2.iter()).all(|(a, b)| a.value_eq_impl(b))
+            }
+            (AtomView::Pow(p1), AtomView::Pow(p2)) => {
+                let (b1, e1) = p1.get_base_exp();
+                let (b2, e2) = p2.get_base_exp();
+                b1.value_eq_impl(b2) && e1.value_eq_impl(e2)
+            }
+            (AtomView::Mul(m1), AtomView::Mul(m2)) => {
+                m1.get_nargs() == m2.get_nargs()
+                    && m1.iter().zip(m2.iter()).all(|(a, b)| a.value_eq_impl(b))
+            }
+            (AtomView::Add(a1), AtomView::Add(a2)) => {
+                a1.get_nargs() == a2.get_nargs()
+                    && a1.iter().zip(a2.iter()).all(|(a, b)| a.value_eq_impl(b))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` iff `self` and `other` are equal up to the order of the terms
+    /// in sums, factors in products and arguments of symmetric functions. In contrast to
+    /// [`AtomView::value_eq`], neither expression is normalized, so that the comparison
+    /// can stop at the first mismatch.
+    pub fn equal_unordered(&self, other: AtomView<'_>) -> bool {
+        if *self == other {
+            return true;
+        }
+
+        match (self, other) {
+            (AtomView::Num(n1), AtomView::Num(n2)) => {
+                n1.get_coeff_view().normalize() == n2.get_coeff_view().normalize()
+            }
+            (AtomView::Var(v1), AtomView::Var(v2)) => v1.get_symbol() == v2.get_symbol(),
+            (AtomView::Fun(f1), AtomView::Fun(f2)) => {
+                if f1.get_symbol() != f2.get_symbol() || f1.get_nargs() != f2.get_nargs() {
+                    return false;
+                }
+
+                if f1.get_symbol().is_symmetric() {
+                    Self::equal_unordered_multiset(f1.iter().collect(), f2.iter().collect())
+                } else {
+                    f1.iter().zip(f2.iter()).all(|(a, b)| a.equal_unordered(b))
+                }
+            }
+            (AtomView::Pow(p1), AtomView::Pow(p2)) => {
+                let (b1, e1) = p1.get_base_exp();
+                let (b2, e2) = p2.get_base_exp();
+                b1.equal_unordered(b2) && e1.equal_unordered(e2)
+            }
+            (AtomView::Mul(m1), AtomView::Mul(m2)) => {
+                m1.get_nargs() == m2.get_nargs()
+                    && Self::equal_unordered_multiset(m1.iter().collect(), m2.iter().collect())
+            }
+            (AtomView::Add(a1), AtomView::Add(a2)) => {
+                a1.get_nargs() == a2.get_nargs()
+                    && Self::equal_unordered_multiset(a1.iter().collect(), a2.iter().collect())
+            }
+            _ => false,
+        }
+    }
+
+    /// Check if two lists of atoms of the same length are equal as multisets.
+    fn equal_unordered_multiset(mut l1: Vec<AtomView<'_>>, mut l2: Vec<AtomView<'_>>) -> bool {
+        // in the common case, sorting in the canonical order pairs up the equal atoms
+        l1.sort_by(|a, b| a.cmp(b));
+        l2.sort_by(|a, b| a.cmp(b));
+
+        let mut unmatched = vec![];
+        for (i, (a, b)) in l1.iter().zip(&l2).enumerate() {
+            if !a.equal_unordered(*b) {
+                unmatched.push(i);
+            }
+        }
+
+        // atoms that are equal up to ordering may sort differently, so
+        // pair the remaining atoms by search
+        let mut rest: Vec<_> = unmatched.iter().map(|i| l2[*i]).collect();
+        for i in unmatched {
+            match rest.iter().position(|b| l1[i].equal_unordered(*b)) {
+                Some(p) => {
+                    rest.swap_remove(p);
+                }
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Compute statistics of the expression in a single pass.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let a = Atom::parse("x*f(y, 2) + 3^100 + x").unwrap();
+    /// let stats = a.as_view().stats();
+    /// assert_eq!(stats.n_terms, 3);
+    /// assert_eq!((stats.n_symbols, stats.n_functions), (2, 1));
+    /// assert_eq!(stats.max_depth, 4);
+    /// ```
+    pub fn stats(&self) -> AtomStats {
+        let mut stats = AtomStats {
+            n_terms: match self {
+                AtomView::Add(a) => a.get_nargs(),
+                _ => 1,
+            },
+            byte_size: self.get_byte_size(),
+            ..Default::default()
+        };
+
+        let mut symbols = HashSet::default();
+        let mut functions = HashSet::default();
+        stats.max_depth = self.stats_impl(&mut stats, &mut symbols, &mut functions);
+        stats.n_symbols = symbols.len();
+        stats.n_functions = functions.len();
+        stats
+    }
+
+    /// Collect statistics of the subexpression and return its depth.
+    fn stats_impl(
+        &self,
+        stats: &mut AtomStats,
+        symbols: &mut HashSet<Symbol>,
+        functions: &mut HashSet<Symbol>,
+    ) -> usize {
+        let mut depth = 0;
+        match self {
+            AtomView::Num(n) => {
+                let bucket = (usize::BITS - 1 - n.get_byte_size().leading_zeros()) as usize;
+                if stats.coefficient_size_histogram.len() <= bucket {
+                    stats.coefficient_size_histogram.resize(bucket + 1, 0);
+                }
+                stats.coefficient_size_histogram[bucket] += 1;
+            }
+            AtomView::Var(v) => {
+                symbols.insert(v.get_symbol());
+            }
+            AtomView::Fun(f) => {
+                functions.insert(f.get_symbol());
+                for arg in f.iter() {
+                    depth = depth.max(arg.stats_impl(stats, symbols, functions));
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                depth = base
+                    .stats_impl(stats, symbols, functions)
+                    .max(exp.stats_impl(stats, symbols, functions));
+            }
+            AtomView::Mul(m) => {
+                for child in m.iter() {
+                    depth = depth.max(child.stats_impl(stats, symbols, functions));
+                }
+            }
+            AtomView::Add(a) => {
+                for child in a.iter() {
+                    depth = depth.max(child.stats_impl(stats, symbols, functions));
+                }
+            }
+        }
+
+        depth + 1
+    }
+
     pub fn get_byte_size(&self) -> usize {
         match self {
             AtomView::Num(n) => n.get_byte_size(),
@@ -346,6 +943,124 @@ impl<'a> AtomView<'a> {
             AtomView::Add(a) => a.get_byte_size(),
         }
     }
+
+    /// Compute a 64-bit hash of the structure of the expression, using xxh3.
+    ///
+    /// Unlike the [`Hash`] implementation, the hash does not depend on
+    /// normalization flags or on the identifiers of symbols, but only on
+    /// their names. Since the order of terms, of commuting factors and of the arguments
+    /// of symmetric functions depends on the identifiers, they are hashed independently
+    /// of their order. As a result, equal expressions have the same hash in any session.
+    /// The exceptions are finite field coefficients, that refer to the field by its index,
+    /// and antisymmetric and cyclesymmetric functions, whose normal form depends on the
+    /// identifiers.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let a = Atom::parse("f(x)*(1+y)^2").unwrap();
+    /// let b = Atom::parse("(y+1)^2*f(x)").unwrap();
+    /// assert_eq!(a.as_view().structural_hash(), b.as_view().structural_hash());
+    /// ```
+    pub fn structural_hash(&self) -> u64 {
+        let mut h = Xxh3::new();
+        self.structural_hash_impl(&mut h);
+        h.digest()
+    }
+
+    /// Compute a 128-bit hash of the structure of the expression.
+    /// See [`AtomView::structural_hash`].
+    pub fn structural_hash_128(&self) -> u128 {
+        let mut h = Xxh3::new();
+        self.structural_hash_impl(&mut h);
+        h.digest128()
+    }
+
+    fn structural_hash_impl(&self, h: &mut Xxh3) {
+        let hash_symbol = |h: &mut Xxh3, s: Symbol| {
+            let name = State::get_name(s);
+            h.update(&(name.len() as u64).to_le_bytes());
+            h.update(name.as_bytes());
+            h.update(&[s.get_wildcard_level()]);
+        };
+
+        match self {
+            AtomView::Num(n) => {
+                h.update(&[0]);
+                if let CoefficientView::RationalPolynomial(p) = n.get_coeff_view() {
+                    // the packed form contains pointers
+                    h.update(p.to_string().as_bytes());
+                } else {
+                    h.update(self.get_data());
+                }
+            }
+            AtomView::Var(v) => {
+                h.update(&[1]);
+                hash_symbol(h, v.get_symbol());
+            }
+            AtomView::Fun(f) => {
+                h.update(&[2]);
+                hash_symbol(h, f.get_symbol());
+                h.update(&(f.get_nargs() as u64).to_le_bytes());
+                if f.get_symbol().is_symmetric() {
+                    Self::structural_hash_unordered(h, f.iter());
+                } else {
+                    for arg in f.iter() {
+                        arg.structural_hash_impl(h);
+                    }
+                }
+            }
+            AtomView::Pow(p) => {
+                h.update(&[3]);
+                let (base, exp) = p.get_base_exp();
+                base.structural_hash_impl(h);
+                exp.structural_hash_impl(h);
+            }
+            AtomView::Mul(m) => {
+                h.update(&[4]);
+                h.update(&(m.get_nargs() as u64).to_le_bytes());
+                // noncommutative factors keep their relative order
+                Self::structural_hash_unordered(
+                    h,
+                    m.iter().filter(|x| !x.is_noncommutative_factor()),
+                );
+                for child in m.iter().filter(|x| x.is_noncommutative_factor()) {
+                    child.structural_hash_impl(h);
+                }
+            }
+            AtomView::Add(a) => {
+                h.update(&[5]);
+                h.update(&(a.get_nargs() as u64).to_le_bytes());
+                Self::structural_hash_unordered(h, a.iter());
+            }
+        }
+    }
+
+    /// Hash the children independently of their order, by sorting their hashes.
+    fn structural_hash_unordered<'b>(h: &mut Xxh3, children: impl Iterator<Item = AtomView<'b>>) {
+        let mut hashes: Vec<_> = children.map(|c| c.structural_hash_128()).collect();
+        hashes.sort_unstable();
+        for c in hashes {
+            h.update(&c.to_le_bytes());
+        }
+    }
+}
+
+/// Statistics about the size and shape of an expression, computed with [`AtomView::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AtomStats {
+    /// The number of terms, which is 1 if the expression is not a sum.
+    pub n_terms: usize,
+    /// The size in bytes of the packed representation.
+    pub byte_size: usize,
+    /// The maximal nesting depth, where an expression without subexpressions has depth 1.
+    pub max_depth: usize,
+    /// The number of distinct variables.
+    pub n_symbols: usize,
+    /// The number of distinct function names.
+    pub n_functions: usize,
+    /// The number of numbers whose packed size in bytes is in `[2^i, 2^(i+1))` for every `i`.
+    pub coefficient_size_histogram: Vec<usize>,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -430,6 +1145,50 @@ impl Atom {
         Num::new(num.into()).into()
     }
 
+    /// Create a number. This is a shorthand for [`Atom::new_num`].
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let x = Atom::var("x");
+    /// let y = Atom::var("y");
+    /// let e = &x + &y * Atom::num(3) - Atom::fun("f", [&x, &y]).npow(2);
+    /// assert_eq!(e, Atom::parse("x + 3*y - f(x,y)^2").unwrap());
+    /// ```
+    #[inline]
+    pub fn num<T: Into<Coefficient>>(num: T) -> Atom {
+        Atom::new_num(num)
+    }
+
+    /// Create a variable with the given name, registering the name
+    /// in the global state if needed.
+    pub fn var<S: AsRef<str>>(name: S) -> Atom {
+        Atom::new_var(State::get_symbol(name))
+    }
+
+    /// Create the list `{elements}`, which is the built-in function [`State::LIST`].
+    /// See [`State::LIST`] for how lists differ from other functions.
+    pub fn list<'a, T: AsAtomView<'a>, I: IntoIterator<Item = T>>(elements: I) -> Atom {
+        elements
+            .into_iter()
+            .fold(FunctionBuilder::new(State::LIST), |f, a| f.add_arg(a))
+            .finish()
+    }
+
+    /// Create the function `name` with arguments `args`, registering the name
+    /// in the global state if needed. Use [`FunctionBuilder`] for functions
+    /// with attributes.
+    pub fn fun<'a, S: AsRef<str>, T: AsAtomView<'a>, I: IntoIterator<Item = T>>(
+        name: S,
+        args: I,
+    ) -> Atom {
+        args.into_iter()
+            .fold(FunctionBuilder::new(State::get_symbol(name)), |f, a| {
+                f.add_arg(a)
+            })
+            .finish()
+    }
+
     #[inline]
     pub fn to_num(&mut self, coeff: Coefficient) -> &mut Num {
         let buffer = std::mem::replace(self, Atom::Empty).into_raw();
@@ -535,6 +1294,46 @@ impl Atom {
         }
     }
 
+    /// Get a cursor that edits the terms of a sum in place, or `None`
+    /// if the atom is not a sum. See [`AddCursor`].
+    pub fn term_cursor(&mut self) -> Option<AddCursor<'_>> {
+        match self {
+            Atom::Add(a) => Some(a.cursor()),
+            _ => None,
+        }
+    }
+
+    /// Get the subexpression at the position `path`. See [`AtomView::at`].
+    pub fn at(&self, path: &[usize]) -> AtomView<'_> {
+        self.as_view().at(path)
+    }
+
+    /// Replace the subexpression at the position `path` by `replacement` and normalize
+    /// the result. The path and the used flags have the format of the position and flags
+    /// returned by [`Pattern::pattern_match`](crate::id::Pattern::pattern_match), so that
+    /// a single match can be replaced without calling `replace_all`. If the subexpression
+    /// is a product or sum, only the factors or terms marked as used are replaced,
+    /// as a pattern may match only part of them. Empty used flags replace the entire
+    /// subexpression.
+    ///
+    /// This function panics if the path does not exist.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let mut a = Atom::parse("f(x, y^2)").unwrap();
+    /// a.replace_at(&[1, 0], &[], Atom::parse("x+1").unwrap().as_view());
+    /// assert_eq!(a, Atom::parse("f(x, (x+1)^2)").unwrap());
+    /// ```
+    pub fn replace_at(&mut self, path: &[usize], used_flags: &[bool], replacement: AtomView) {
+        Workspace::get_local().with(|ws| {
+            let mut a = ws.new_atom();
+            self.as_view()
+                .replace_at_no_norm(path, used_flags, replacement, ws, &mut a);
+            a.as_view().normalize(ws, self);
+        });
+    }
+
     /// Get the symbol of a variable or function.
     #[inline(always)]
     pub fn get_symbol(&self) -> Option<Symbol> {
@@ -545,6 +1344,51 @@ impl Atom {
         }
     }
 
+    /// Get all distinct variables in the expression, in order of first appearance.
+    /// Function names, wildcards and the built-in constants are not included.
+    pub fn free_variables(&self) -> Vec<Symbol> {
+        self.as_view().free_variables()
+    }
+
+    /// Returns `true` iff the expression has no free variables.
+    pub fn is_constant(&self) -> bool {
+        self.as_view().is_constant()
+    }
+
+    /// Compute statistics of the expression. See [`AtomView::stats`].
+    pub fn stats(&self) -> AtomStats {
+        self.as_view().stats()
+    }
+
+    /// Compute a 64-bit hash of the structure of the expression. See [`AtomView::structural_hash`].
+    pub fn structural_hash(&self) -> u64 {
+        self.as_view().structural_hash()
+    }
+
+    /// Compute a 128-bit hash of the structure of the expression. See [`AtomView::structural_hash`].
+    pub fn structural_hash_128(&self) -> u128 {
+        self.as_view().structural_hash_128()
+    }
+
+    /// Get all distinct wildcards in the expression, including wildcard function names,
+    /// in order of first appearance.
+    pub fn wildcards(&self) -> Vec<Symbol> {
+        self.as_view().wildcards()
+    }
+
+    /// Returns `true` iff `self` and `other` are equal after normalization.
+    /// See [`AtomView::value_eq`].
+    pub fn value_eq(&self, other: &Atom) -> bool {
+        self.as_view().value_eq(other.as_view())
+    }
+
+    /// Returns `true` iff `self` and `other` are equal up to the order of the terms
+    /// in sums, factors in products and arguments of symmetric functions.
+    /// See [`AtomView::equal_unordered`].
+    pub fn equal_unordered(&self, other: &Atom) -> bool {
+        self.as_view().equal_unordered(other.as_view())
+    }
+
     #[inline(always)]
     pub(crate) fn set_normalized(&mut self, normalized: bool) {
         match self {
@@ -860,6 +1704,38 @@ impl std::ops::Neg for Atom {
     }
 }
 
+impl std::ops::Add<Atom> for Atom {
+    type Output = Atom;
+
+    fn add(self, rhs: Atom) -> Atom {
+        self + &rhs
+    }
+}
+
+impl std::ops::Sub<Atom> for Atom {
+    type Output = Atom;
+
+    fn sub(self, rhs: Atom) -> Atom {
+        self - &rhs
+    }
+}
+
+impl std::ops::Mul<Atom> for Atom {
+    type Output = Atom;
+
+    fn mul(self, rhs: Atom) -> Atom {
+        self * &rhs
+    }
+}
+
+impl std::ops::Div<Atom> for Atom {
+    type Output = Atom;
+
+    fn div(self, rhs: Atom) -> Atom {
+        self / &rhs
+    }
+}
+
 impl std::ops::Add<AtomView<'_>> for AtomView<'_> {
     type Output = Atom;
 
@@ -1037,3 +1913,371 @@ impl<T: Into<Coefficient>> std::ops::Div<T> for Atom {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coefficient::Coefficient,
+        domains::finite_field::{FiniteFieldCore, FiniteFieldElement, Zp64},
+        id::{Condition, MatchSettings, Pattern},
+        representations::{AtomView, PackedDataErrorKind},
+        state::{State, Workspace},
+    };
+
+    use super::{Atom, Symbol};
+
+    #[test]
+    fn free_variables() {
+        let a = Atom::parse("fv_a*fv_x + 3").unwrap();
+        assert_eq!(
+            a.free_variables(),
+            vec![State::get_symbol("fv_a"), State::get_symbol("fv_x")]
+        );
+        assert!(!a.is_constant());
+
+        let a = Atom::parse("sin(fv_x)").unwrap();
+        assert_eq!(a.free_variables(), vec![State::get_symbol("fv_x")]);
+
+        let a = Atom::parse("5").unwrap();
+        assert!(a.free_variables().is_empty());
+        assert!(a.is_constant());
+
+        let a = Atom::parse("fv_f(2, 𝜋)^fv_x_").unwrap();
+        assert!(a.is_constant());
+    }
+
+    #[test]
+    fn builders() {
+        let x = Atom::var("bld_x");
+        let y = Atom::var("bld_y");
+        let args = vec![x.clone(), Atom::num((1, 2))];
+
+        let e = (&x + &y) * Atom::fun("bld_f", &args) - x.clone() / y.clone() + -x.npow(2);
+        assert_eq!(
+            e,
+            Atom::parse("(bld_x+bld_y)*bld_f(bld_x,1/2) - bld_x/bld_y - bld_x^2").unwrap()
+        );
+        assert_eq!(
+            Atom::fun("bld_g", Vec::<Atom>::new().iter()),
+            Atom::parse("bld_g()").unwrap()
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let a = Atom::parse("st_x^2*st_f(st_y, 1/3) + 2^200*st_x + 5").unwrap();
+        let stats = a.stats();
+        assert_eq!(stats.n_terms, 3);
+        assert_eq!(stats.byte_size, a.as_view().get_byte_size());
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!((stats.n_symbols, stats.n_functions), (2, 1));
+        assert_eq!(stats.coefficient_size_histogram.iter().sum::<usize>(), 4);
+        assert!(stats.coefficient_size_histogram.len() >= 5);
+
+        let stats = Atom::parse("st_x").unwrap().stats();
+        assert_eq!((stats.n_terms, stats.max_depth), (1, 1));
+    }
+
+    #[test]
+    fn value_eq() {
+        let x = Atom::new_var(State::get_symbol("veq_x"));
+        let two = Atom::new_num(2);
+
+        // an unnormalized product
+        let mut a = Atom::default();
+        let m = a.to_mul();
+        m.extend(x.as_view());
+        m.extend(two.as_view());
+
+        let b = Atom::parse("2*veq_x").unwrap();
+        assert_ne!(a, b);
+        assert!(a.value_eq(&b));
+        assert!(!a.value_eq(&Atom::parse("3*veq_x").unwrap()));
+
+        let a = Atom::parse("veq_x/2").unwrap();
+        let b = Atom::parse("(1/2)*veq_x").unwrap();
+        assert!(a.value_eq(&b));
+    }
+
+    #[test]
+    fn equal_unordered() {
+        let p = |s: &str| Atom::parse(s).unwrap();
+        let add = |terms: &[&Atom]| {
+            let mut a = Atom::default();
+            let add = a.to_add();
+            for t in terms {
+                add.extend(t.as_view());
+            }
+            a
+        };
+        let mul = |factors: &[&Atom]| {
+            let mut a = Atom::default();
+            let mul = a.to_mul();
+            for f in factors {
+                mul.extend(f.as_view());
+            }
+            a
+        };
+
+        let (a, b, c) = (p("eu_a"), p("eu_b"), p("eu_c"));
+        let (x, y) = (p("eu_x"), p("eu_y"));
+
+        assert!(add(&[&a, &b, &c]).equal_unordered(&add(&[&c, &a, &b])));
+        assert!(mul(&[&x, &y]).equal_unordered(&mul(&[&y, &x])));
+        assert!(!add(&[&a, &b]).equal_unordered(&add(&[&a, &c])));
+        assert!(!add(&[&a, &a, &b]).equal_unordered(&add(&[&a, &b, &b])));
+
+        // nested sums that only agree up to ordering
+        let (ab, ba) = (add(&[&a, &b]), add(&[&b, &a]));
+        assert!(mul(&[&ab, &x, &ba]).equal_unordered(&mul(&[&x, &ba, &ab])));
+        assert!(!mul(&[&ab, &x]).equal_unordered(&mul(&[&ab, &y])));
+    }
+
+    #[test]
+    fn term_cursor() {
+        let sum = |r: &mut dyn Iterator<Item = usize>| {
+            Atom::parse(
+                &r.map(|i| format!("tc_x^{}", i))
+                    .collect::<Vec<_>>()
+                    .join("+"),
+            )
+            .unwrap()
+        };
+
+        // the number of terms crosses the boundary of the packed size
+        let mut a = sum(&mut (2..=300));
+        let mut c = a.term_cursor().unwrap();
+        while let Some(t) = c.current() {
+            let AtomView::Pow(p) = t else { unreachable!() };
+            if p.get_exp() == Atom::new_num(50).as_view() {
+                c.rescale(&Coefficient::from(-2));
+                c.advance();
+            } else if p.get_exp() > Atom::new_num(100).as_view() {
+                c.remove();
+            } else {
+                c.advance();
+            }
+        }
+        assert_eq!(c.get_nargs(), 99);
+        assert_eq!(a, sum(&mut (2..=100)) - &Atom::parse("3*tc_x^50").unwrap());
+
+        let mut c = a.term_cursor().unwrap();
+        c.advance();
+        c.replace(Atom::parse("tc_x").unwrap().as_view());
+        c.rewind();
+        while c.get_nargs() > 2 {
+            c.remove();
+        }
+        let mut out = Atom::new();
+        Workspace::get_local().with(|ws| a.as_view().normalize(ws, &mut out));
+        assert_eq!(out, Atom::parse("tc_x^99+tc_x^100").unwrap());
+
+        let mut c = out.term_cursor().unwrap();
+        c.rescale(&Coefficient::zero());
+        let mut out2 = Atom::new();
+        Workspace::get_local().with(|ws| out.as_view().normalize(ws, &mut out2));
+        assert_eq!(out2, Atom::parse("tc_x^100").unwrap());
+    }
+
+    #[test]
+    fn structural_hash() {
+        let a = Atom::parse("sh_f(sh_x, 2/3)*(sh_x + sh_y)^2 + 12345678901234567890").unwrap();
+        let b = Atom::parse("12345678901234567890 + (sh_y + sh_x)^2*sh_f(sh_x, 4/6)").unwrap();
+        assert_eq!(a.structural_hash(), b.structural_hash());
+        assert_eq!(a.structural_hash_128(), b.structural_hash_128());
+
+        let c = Atom::parse("sh_f(sh_x, 2/3)*(sh_x + sh_y)^3 + 12345678901234567890").unwrap();
+        assert_ne!(a.structural_hash(), c.structural_hash());
+
+        // the hash depends on the name of a symbol, not on its identifier
+        assert_eq!(
+            Atom::parse("sh_f(sh_x)").unwrap().structural_hash(),
+            2785162853298673454
+        );
+
+        // the stored order of terms and factors depends on the order in which the
+        // symbols are defined, which may differ between sessions
+        State::get_symbol("sh_hb");
+        State::get_symbol("sh_ha");
+        let ha = Atom::parse("sh_ha").unwrap();
+        let hb = Atom::parse("sh_hb").unwrap();
+        let mut ab = Atom::new();
+        let o = ab.to_add();
+        o.extend(ha.as_view());
+        o.extend(hb.as_view());
+        let mut ba = Atom::new();
+        let o = ba.to_add();
+        o.extend(hb.as_view());
+        o.extend(ha.as_view());
+        assert_eq!(ab.structural_hash(), ba.structural_hash());
+        assert_eq!(
+            Atom::parse("sh_ha + sh_hb").unwrap().structural_hash(),
+            ab.structural_hash()
+        );
+        assert_eq!(ab.structural_hash(), 5943467902989055654);
+        let mut ab = Atom::new();
+        let o = ab.to_mul();
+        o.extend(ha.as_view());
+        o.extend(hb.as_view());
+        let mut ba = Atom::new();
+        let o = ba.to_mul();
+        o.extend(hb.as_view());
+        o.extend(ha.as_view());
+        assert_eq!(ab.structural_hash(), ba.structural_hash());
+        assert_ne!(
+            Atom::parse("sh_ha + sh_hb").unwrap().structural_hash(),
+            ab.structural_hash()
+        );
+
+        // rational polynomial coefficients are hashed by value
+        let vars = std::sync::Arc::new(vec![State::get_symbol("sh_y").into()]);
+        let r1 = Atom::parse("sh_x/(1+sh_y)")
+            .unwrap()
+            .set_coefficient_ring(&vars);
+        let r2 = Atom::parse("sh_x/(1+sh_y)")
+            .unwrap()
+            .set_coefficient_ring(&vars);
+        assert_eq!(r1.structural_hash(), r2.structural_hash());
+    }
+
+    #[test]
+    fn replace_at() {
+        let mut a = Atom::parse("pa_f(pa_x, pa_g(pa_x)^2) + pa_x").unwrap();
+        let pat = Pattern::parse("pa_g(pa_x)").unwrap();
+        let conditions = Condition::default();
+        let settings = MatchSettings::default();
+        let (path, used_flags) = {
+            let mut it = pat.pattern_match(a.as_view(), &conditions, &settings);
+            let (path, used_flags, ..) = it.next().unwrap();
+            (path.to_vec(), used_flags)
+        };
+
+        assert_eq!(a.at(&path), Atom::parse("pa_g(pa_x)").unwrap().as_view());
+        a.replace_at(
+            &path,
+            &used_flags,
+            Atom::parse("pa_y + 1").unwrap().as_view(),
+        );
+        assert_eq!(a, Atom::parse("pa_f(pa_x, (pa_y + 1)^2) + pa_x").unwrap());
+
+        let mut a = Atom::parse("pa_x*pa_y^2").unwrap();
+        let path = [1, 1];
+        assert_eq!(a.at(&path), Atom::new_num(2).as_view());
+        a.replace_at(&path, &[], Atom::parse("-1").unwrap().as_view());
+        assert_eq!(a, Atom::parse("pa_x/pa_y").unwrap());
+
+        // a partial match of a product only replaces the matched factors
+        let mut a = Atom::parse("pa_f(2*pa_x*pa_y*pa_z)").unwrap();
+        let pat = Pattern::parse("pa_x*pa_y").unwrap();
+        let rhs = Atom::parse("pa_w").unwrap();
+        let (path, used_flags) = {
+            let mut it = pat.pattern_match(a.as_view(), &conditions, &settings);
+            let (path, used_flags, ..) = it.next().unwrap();
+            (path.to_vec(), used_flags)
+        };
+        let (r, _) = pat.replace_first(a.as_view(), &rhs.as_view().into_pattern(), None, None);
+        a.replace_at(&path, &used_flags, rhs.as_view());
+        assert_eq!(a, Atom::parse("pa_f(2*pa_z*pa_w)").unwrap());
+        assert_eq!(a, r);
+    }
+
+    #[test]
+    fn checked_view() {
+        let a = Atom::parse("cv_f(cv_x, 1/3, 2^70)^2 + 3*cv_x").unwrap();
+        let data = a.as_view().get_data().to_vec();
+        assert_eq!(AtomView::from_checked(&data).unwrap(), a.as_view());
+        assert!(a.as_view().validate().is_ok());
+
+        for i in 0..data.len() {
+            assert_eq!(
+                AtomView::from_checked(&data[..i]).unwrap_err().kind,
+                PackedDataErrorKind::UnexpectedEnd
+            );
+        }
+
+        // deeply nested data returns an error instead of overflowing the stack
+        let pow = Atom::parse("cv_x^cv_y").unwrap();
+        let deep = vec![pow.as_view().get_data()[0]; 1 << 20];
+        assert_eq!(
+            AtomView::from_checked(&deep).unwrap_err().kind,
+            PackedDataErrorKind::UnexpectedEnd
+        );
+
+        let mut extra = data.clone();
+        extra.push(0);
+        let err = AtomView::from_checked(&extra).unwrap_err();
+        assert_eq!(err.kind, PackedDataErrorKind::TrailingData);
+        assert_eq!(err.offset, data.len());
+
+        let mut bad = data.clone();
+        bad[0] = 0;
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::UnknownAtomType(0)
+        );
+
+        // a sum whose stored size is larger than its arguments
+        let mut bad = data.clone();
+        bad[1] += 1;
+        bad.push(0);
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::SizeMismatch
+        );
+
+        let n = Atom::parse("1/3").unwrap();
+        let mut bad = n.as_view().get_data().to_vec();
+        *bad.last_mut().unwrap() = 0;
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::ZeroDenominator
+        );
+
+        // functions without arguments
+        let a = Atom::parse("cv_f() + der()").unwrap();
+        assert!(a.as_view().validate().is_ok());
+
+        // large fractions must have a non-zero denominator and be reduced
+        let n = Atom::parse("2^70/3").unwrap();
+        let mut bad = n.as_view().get_data().to_vec();
+        *bad.last_mut().unwrap() = 0;
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::ZeroDenominator
+        );
+        *bad.last_mut().unwrap() = 2;
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::NotNormalized
+        );
+
+        // symbols and finite fields must be defined in the state
+        let mut bad = Atom::new_var(State::get_symbol("cv_x"))
+            .as_view()
+            .get_data()
+            .to_vec();
+        bad.truncate(1);
+        bad.extend([4, 255, 255, 255, 127, 0, 0, 0, 0]);
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::UnknownSymbol(Symbol::MAX_ID)
+        );
+
+        let field = State::get_or_insert_finite_field(Zp64::new(17));
+        let n = Atom::new_num(Coefficient::FiniteField(FiniteFieldElement(16), field));
+        let data = n.as_view().get_data().to_vec();
+        assert!(n.as_view().validate().is_ok());
+        let mut bad = data.clone();
+        bad[2] = 17;
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::NotNormalized
+        );
+        let mut bad = data.clone();
+        *bad.last_mut().unwrap() = 255;
+        assert_eq!(
+            AtomView::from_checked(&bad).unwrap_err().kind,
+            PackedDataErrorKind::UnknownFiniteField(255)
+        );
+    }
+}
@@ -1,13 +1,18 @@
 use bytes::{Buf, BufMut};
-use rug::integer::Order;
+use rug::{integer::Order, Integer as MultiPrecisionInteger};
 
 use crate::{
-    coefficient::{Coefficient, CoefficientView, SerializedRational},
+    coefficient::{
+        Coefficient, CoefficientView, SerializedComplex, SerializedFloat, SerializedRational,
+    },
     domains::{
-        finite_field::FiniteFieldElement, integer::IntegerRing, rational::Rational,
+        finite_field::{FiniteFieldCore, FiniteFieldElement},
+        integer::IntegerRing,
+        rational::Rational,
         rational_polynomial::RationalPolynomial,
     },
-    state::FiniteFieldIndex,
+    representations::{PackedDataError, PackedDataErrorKind},
+    state::{FiniteFieldIndex, State},
     utils,
 };
 
@@ -16,8 +21,10 @@ const U16_NUM: u8 = 0b00000010;
 const U32_NUM: u8 = 0b00000011;
 const U64_NUM: u8 = 0b00000100;
 const FIN_NUM: u8 = 0b00000101;
+const FLOAT_NUM: u8 = 0b00000110;
 const ARB_NUM: u8 = 0b00000111;
 const RAT_POLY: u8 = 0b00001000;
+const COMPLEX_NUM: u8 = 0b00001001;
 const U8_DEN: u8 = 0b00010000;
 const U16_DEN: u8 = 0b00100000;
 const U32_DEN: u8 = 0b00110000;
@@ -26,6 +33,8 @@ const ARB_DEN: u8 = 0b01110000;
 const NUM_MASK: u8 = 0b00001111;
 const DEN_MASK: u8 = 0b01110000;
 const SIGN: u8 = 0b10000000;
+/// Set on a finite field tag if the field index is stored as a `u32` instead of a `u8`.
+const FIN_WIDE_INDEX: u8 = SIGN;
 
 #[inline(always)]
 fn get_size_of_natural(num_type: u8) -> u8 {
@@ -39,6 +48,220 @@ fn get_size_of_natural(num_type: u8) -> u8 {
     }
 }
 
+/// Get the tag of a finite field element. The lower bits are [`FIN_NUM`], the
+/// denominator bits store the byte size of the element and [`FIN_WIDE_INDEX`]
+/// indicates the size of the field index.
+#[inline(always)]
+fn get_finite_field_tag(num: u64, index: usize) -> u8 {
+    let size = if num <= u8::MAX as u64 {
+        U8_DEN
+    } else if num <= u16::MAX as u64 {
+        U16_DEN
+    } else if num <= u32::MAX as u64 {
+        U32_DEN
+    } else {
+        U64_DEN
+    };
+
+    if index <= u8::MAX as usize {
+        FIN_NUM | size
+    } else {
+        FIN_NUM | size | FIN_WIDE_INDEX
+    }
+}
+
+/// Get the number of bytes of a packed finite field element with tag `tag`, including the tag.
+#[inline(always)]
+fn get_finite_field_packed_size(tag: u8) -> u8 {
+    let index_size = if tag & FIN_WIDE_INDEX != 0 { 4 } else { 1 };
+    1 + get_size_of_natural((tag & DEN_MASK) >> 4) + index_size
+}
+
+/// Write a finite field element `num` of the field with index `index` behind a single tag.
+#[inline(always)]
+fn write_finite_field_packed<B: BufMut>(num: u64, index: usize, dest: &mut B) {
+    let tag = get_finite_field_tag(num, index);
+    dest.put_u8(tag);
+
+    match tag & DEN_MASK {
+        U8_DEN => dest.put_u8(num as u8),
+        U16_DEN => dest.put_u16_le(num as u16),
+        U32_DEN => dest.put_u32_le(num as u32),
+        _ => dest.put_u64_le(num),
+    }
+
+    if tag & FIN_WIDE_INDEX != 0 {
+        dest.put_u32_le(u32::try_from(index).expect("Finite field index too large"));
+    } else {
+        dest.put_u8(index as u8);
+    }
+}
+
+/// Read a finite field element and its field index.
+#[inline(always)]
+fn read_finite_field_packed(mut source: &[u8]) -> (u64, usize, &[u8]) {
+    let tag = source.get_u8();
+
+    let num = match tag & DEN_MASK {
+        U8_DEN => source.get_u8() as u64,
+        U16_DEN => source.get_u16_le() as u64,
+        U32_DEN => source.get_u32_le() as u64,
+        U64_DEN => source.get_u64_le(),
+        x => unreachable!("Unsupported finite field element size {}", x),
+    };
+
+    let index = if tag & FIN_WIDE_INDEX != 0 {
+        source.get_u32_le() as usize
+    } else {
+        source.get_u8() as usize
+    };
+
+    (num, index, source)
+}
+
+/// Check the packed pair of machine-size integers at position `pos` of `data` and return the
+/// absolute value of the first, the second and the position after the pair. Unlike
+/// [`check_packed_frac`], the second integer may be zero.
+pub(crate) fn check_packed_pair(
+    data: &[u8],
+    pos: usize,
+) -> Result<(u64, u64, usize), PackedDataError> {
+    let Some(&tag) = data.get(pos) else {
+        return Err(PackedDataError::new(
+            pos,
+            PackedDataErrorKind::UnexpectedEnd,
+        ));
+    };
+
+    let (num_type, den_type) = (tag & NUM_MASK, (tag & DEN_MASK) >> 4);
+    if !(U8_NUM..=U64_NUM).contains(&num_type) || den_type > U64_NUM {
+        return Err(PackedDataError::new(
+            pos,
+            PackedDataErrorKind::UnknownNumberType(tag),
+        ));
+    }
+
+    let end =
+        pos + 1 + get_size_of_natural(num_type) as usize + get_size_of_natural(den_type) as usize;
+    if end > data.len() {
+        return Err(PackedDataError::new(
+            data.len(),
+            PackedDataErrorKind::UnexpectedEnd,
+        ));
+    }
+
+    let (num, den, _) = data[pos..].get_frac_u64();
+    Ok((num, den, end))
+}
+
+/// Check the packed machine-size fraction at position `pos` of `data` and return the absolute
+/// value of its numerator, its denominator and the position after the fraction.
+pub(crate) fn check_packed_frac(
+    data: &[u8],
+    pos: usize,
+) -> Result<(u64, u64, usize), PackedDataError> {
+    let (num, den, end) = check_packed_pair(data, pos)?;
+    if den == 0 {
+        return Err(PackedDataError::new(
+            pos,
+            PackedDataErrorKind::ZeroDenominator,
+        ));
+    }
+
+    Ok((num, den, end))
+}
+
+/// Check the packed coefficient at position `pos` of `data` and return the position after it.
+pub(crate) fn check_packed_coefficient(data: &[u8], pos: usize) -> Result<usize, PackedDataError> {
+    let Some(&tag) = data.get(pos) else {
+        return Err(PackedDataError::new(
+            pos,
+            PackedDataErrorKind::UnexpectedEnd,
+        ));
+    };
+
+    let check_end = |end: usize| {
+        if end > data.len() {
+            Err(PackedDataError::new(
+                data.len(),
+                PackedDataErrorKind::UnexpectedEnd,
+            ))
+        } else {
+            Ok(end)
+        }
+    };
+
+    match tag & NUM_MASK {
+        U8_NUM..=U64_NUM => check_packed_frac(data, pos).map(|(_, _, end)| end),
+        ARB_NUM => {
+            let (num_len, den_len, start) = check_packed_pair(data, pos + 1)?;
+            let (num_len, den_len) = (num_len as usize, den_len as usize);
+            let end = check_end(start + num_len + den_len)?;
+
+            let (num, den) = (&data[start..start + num_len], &data[start + num_len..end]);
+            if den.iter().all(|x| *x == 0) {
+                return Err(PackedDataError::new(
+                    pos,
+                    PackedDataErrorKind::ZeroDenominator,
+                ));
+            }
+
+            // the digits may not have leading zeros and the fraction must be reduced
+            if num.last().map(|x| *x == 0).unwrap_or(true)
+                || den.last() == Some(&0)
+                || MultiPrecisionInteger::from_digits(num, Order::Lsf)
+                    .gcd(&MultiPrecisionInteger::from_digits(den, Order::Lsf))
+                    != 1
+            {
+                return Err(PackedDataError::new(
+                    pos,
+                    PackedDataErrorKind::NotNormalized,
+                ));
+            }
+
+            Ok(end)
+        }
+        FLOAT_NUM => {
+            // the digit count is allowed to be zero
+            let (_, num_len, p) = check_packed_pair(data, pos + 1)?;
+            let (_, _, start) = check_packed_frac(data, p)?;
+            check_end(start + num_len as usize)
+        }
+        FIN_NUM if (U8_NUM..=U64_NUM).contains(&((tag & DEN_MASK) >> 4)) => {
+            let end = check_end(pos + get_finite_field_packed_size(tag) as usize)?;
+
+            let (num, index, _) = read_finite_field_packed(&data[pos..]);
+            let Some(field) = State::try_get_finite_field(FiniteFieldIndex(index)) else {
+                return Err(PackedDataError::new(
+                    pos,
+                    PackedDataErrorKind::UnknownFiniteField(index),
+                ));
+            };
+
+            if num >= field.get_prime() {
+                return Err(PackedDataError::new(
+                    pos,
+                    PackedDataErrorKind::NotNormalized,
+                ));
+            }
+
+            Ok(end)
+        }
+        COMPLEX_NUM if tag == COMPLEX_NUM => {
+            let p = check_packed_coefficient(data, pos + 1)?;
+            check_packed_coefficient(data, p)
+        }
+        RAT_POLY if tag == RAT_POLY => Err(PackedDataError::new(
+            pos,
+            PackedDataErrorKind::RationalPolynomial,
+        )),
+        _ => Err(PackedDataError::new(
+            pos,
+            PackedDataErrorKind::UnknownNumberType(tag),
+        )),
+    }
+}
+
 /// A generalized rational number. The first byte indicates the sign, size and type of the numerator and denominator.
 /// The highest four bits give the byte size of the numerator and the lower bits of the denominator.
 pub trait PackedRationalNumberWriter {
@@ -56,6 +279,8 @@ impl PackedRationalNumberWriter for Coefficient {
             Coefficient::Rational(r) => match r {
                 Rational::Natural(num, den) => (*num, *den).write_packed(dest),
                 Rational::Large(r) => {
+                    // the numerator and denominator are stored as their byte sizes followed by
+                    // their little-endian digits, so that the packed number is self-contained
                     dest.put_u8(ARB_NUM | ARB_DEN);
 
                     let num_digits = r.numer().significant_digits::<u8>();
@@ -74,9 +299,31 @@ impl PackedRationalNumberWriter for Coefficient {
                         .write_digits(&mut dest[old_len + num_digits..], Order::Lsf);
                 }
             },
-            Coefficient::FiniteField(num, f) => {
-                dest.put_u8(FIN_NUM);
-                (num.0, f.0 as u64).write_packed(dest); // this adds an extra tag
+            Coefficient::FiniteField(num, f) => write_finite_field_packed(num.0, f.0, dest),
+            Coefficient::Float(f) => {
+                // the float is stored as `mantissa * 2^exp` with its precision, followed by
+                // the signed number of digits of the mantissa, which may be zero
+                dest.put_u8(FLOAT_NUM);
+
+                let (mantissa, exp) = f.to_parts();
+                let digits = mantissa.significant_digits::<u8>() as i64;
+
+                if mantissa < 0 {
+                    (f.prec() as i64, -digits).write_packed(dest);
+                } else {
+                    (f.prec() as i64, digits).write_packed(dest);
+                }
+                (exp, 1).write_packed(dest);
+
+                let old_len = dest.len();
+                dest.resize(old_len + digits as usize, 0);
+                mantissa.write_digits(&mut dest[old_len..], Order::Lsf);
+            }
+            Coefficient::Complex(c) => {
+                // the real part is followed by the imaginary part
+                dest.put_u8(COMPLEX_NUM);
+                c.0.write_packed(dest);
+                c.1.write_packed(dest);
             }
             Coefficient::RationalPolynomial(p) => {
                 dest.put_u8(RAT_POLY);
@@ -95,15 +342,21 @@ impl PackedRationalNumberWriter for Coefficient {
         match self {
             Coefficient::Rational(r) => match r {
                 Rational::Natural(num, den) => (*num, *den).write_packed_fixed(dest),
-                Rational::Large(_) => todo!("Writing large packed rational not implemented"),
+                Rational::Large(_) => {
+                    let mut buf = Vec::with_capacity(dest.len());
+                    self.write_packed(&mut buf);
+                    dest[..buf.len()].copy_from_slice(&buf);
+                }
             },
             Coefficient::RationalPolynomial(_) => {
                 todo!("Writing packed rational polynomial not implemented")
             }
-            Coefficient::FiniteField(num, f) => {
-                dest.put_u8(FIN_NUM);
-                (num.0, f.0 as u64).write_packed_fixed(dest);
+            Coefficient::Float(_) | Coefficient::Complex(_) => {
+                let mut buf = Vec::with_capacity(dest.len());
+                self.write_packed(&mut buf);
+                dest[..buf.len()].copy_from_slice(&buf);
             }
+            Coefficient::FiniteField(num, f) => write_finite_field_packed(num.0, f.0, &mut dest),
         }
     }
 
@@ -117,10 +370,20 @@ impl PackedRationalNumberWriter for Coefficient {
                     1 + (n, d).get_packed_size() + n as u64 + d as u64
                 }
             },
-            Coefficient::FiniteField(m, i) => 2 + (m.0, i.0 as u64).get_packed_size(),
+            Coefficient::FiniteField(m, i) => {
+                get_finite_field_packed_size(get_finite_field_tag(m.0, i.0)) as u64
+            }
             Coefficient::RationalPolynomial(_) => {
                 1 + std::mem::size_of::<RationalPolynomial<IntegerRing, u16>>() as u64
             }
+            Coefficient::Complex(c) => 1 + c.0.get_packed_size() + c.1.get_packed_size(),
+            Coefficient::Float(f) => {
+                let (mantissa, exp) = f.to_parts();
+                let digits = mantissa.significant_digits::<u8>() as i64;
+                1 + (f.prec() as i64, digits).get_packed_size()
+                    + (exp, 1).get_packed_size()
+                    + digits as u64
+            }
         }
     }
 }
@@ -162,14 +425,30 @@ impl PackedRationalNumberReader for [u8] {
                 }),
                 &source[num_len + den_len..],
             )
+        } else if disc == COMPLEX_NUM {
+            (
+                CoefficientView::Complex(SerializedComplex { data: source }),
+                source.skip_rational().skip_rational(),
+            )
+        } else if (disc & NUM_MASK) == FLOAT_NUM {
+            let (prec, num_len, exp);
+            (prec, num_len, source) = source.get_frac_i64();
+            (exp, _, source) = source.get_frac_i64();
+            let digits = num_len as usize;
+
+            (
+                CoefficientView::Float(SerializedFloat {
+                    is_negative: prec < 0,
+                    prec: prec.unsigned_abs() as u32,
+                    exp,
+                    digits: &source[..digits],
+                }),
+                &source[digits..],
+            )
         } else if (disc & NUM_MASK) == FIN_NUM {
-            let (num, fi);
-            (num, fi, source) = source.get_frac_u64();
+            let (num, fi, source) = read_finite_field_packed(self);
             (
-                CoefficientView::FiniteField(
-                    FiniteFieldElement(num),
-                    FiniteFieldIndex(fi as usize),
-                ),
+                CoefficientView::FiniteField(FiniteFieldElement(num), FiniteFieldIndex(fi)),
                 source,
             )
         } else {
@@ -296,7 +575,8 @@ impl PackedRationalNumberReader for [u8] {
         };
 
         if disc & SIGN != 0 {
-            (-num, den, source)
+            // the magnitude of i64::MIN is read as i64::MIN, which is already the correct value
+            (num.wrapping_neg(), den, source)
         } else {
             (num, den, source)
         }
@@ -319,10 +599,15 @@ impl PackedRationalNumberReader for [u8] {
             dest.advance(std::mem::size_of::<RationalPolynomial<IntegerRing, u16>>());
             dest
         } else if v_num == FIN_NUM {
-            let var_size = dest.get_u8();
-            let size = get_size_of_natural(var_size & NUM_MASK)
-                + get_size_of_natural((var_size & DEN_MASK) >> 4);
-            dest.advance(size as usize);
+            dest.advance(get_finite_field_packed_size(var_size) as usize - 1);
+            dest
+        } else if v_num == COMPLEX_NUM {
+            dest.skip_rational().skip_rational()
+        } else if v_num == FLOAT_NUM {
+            let num_size;
+            (_, num_size, dest) = dest.get_frac_i64();
+            (_, _, dest) = dest.get_frac_i64();
+            dest.advance(num_size as usize);
             dest
         } else {
             let size = get_size_of_natural(v_num) + get_size_of_natural((var_size & DEN_MASK) >> 4);
@@ -343,33 +628,48 @@ impl PackedRationalNumberReader for [u8] {
     }
 }
 
+/// Get the absolute value of the numerator and denominator of a fraction and
+/// its sign, such that the sign is canonical even if the fraction is not normalized,
+/// for example for `-1/-2` or `0/-1`.
+#[inline(always)]
+fn canonical_sign_abs((num, den): (i64, i64)) -> (u64, u64, bool) {
+    if num == 0 {
+        (0, 1, false)
+    } else {
+        (
+            num.unsigned_abs(),
+            den.unsigned_abs(),
+            (num < 0) != (den < 0),
+        )
+    }
+}
+
 impl PackedRationalNumberWriter for (i64, i64) {
     #[inline(always)]
     fn write_packed(&self, dest: &mut Vec<u8>) {
         let p = dest.len();
 
-        let num_u64 = self.0.unsigned_abs();
-        let den_u64 = self.1.unsigned_abs();
+        let (num_u64, den_u64, negative) = canonical_sign_abs(*self);
         (num_u64, den_u64).write_packed(dest);
 
-        if self.0 >= 0 && self.1 < 0 || self.0 < 0 && self.1 >= 0 {
+        if negative {
             dest[p] |= SIGN;
         }
     }
 
     #[inline(always)]
     fn write_packed_fixed(&self, dest: &mut [u8]) {
-        let num_u64 = self.0.unsigned_abs();
-        let den_u64 = self.1.unsigned_abs();
+        let (num_u64, den_u64, negative) = canonical_sign_abs(*self);
         (num_u64, den_u64).write_packed_fixed(dest);
 
-        if self.0 >= 0 && self.1 < 0 || self.0 < 0 && self.1 >= 0 {
+        if negative {
             dest[0] |= SIGN;
         }
     }
 
     fn get_packed_size(&self) -> u64 {
-        (self.0 as u64, self.1 as u64).get_packed_size()
+        let (num_u64, den_u64, _) = canonical_sign_abs(*self);
+        (num_u64, den_u64).get_packed_size()
     }
 }
 
@@ -468,3 +768,153 @@ impl PackedRationalNumberWriter for (u64, u64) {
         size as u64
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rug::{Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational};
+
+    use crate::{
+        coefficient::{Coefficient, CoefficientView},
+        domains::{finite_field::FiniteFieldElement, float::Float},
+        state::FiniteFieldIndex,
+    };
+
+    use super::{PackedRationalNumberReader, PackedRationalNumberWriter};
+
+    #[test]
+    fn packed_sign() {
+        for (r, res) in [
+            ((-1i64, -2i64), (1, 2)),
+            ((1, -2), (-1, 2)),
+            ((-1, 2), (-1, 2)),
+            ((0, -1), (0, 1)),
+        ] {
+            let mut dest = vec![];
+            (r.0, r.1).write_packed(&mut dest);
+            assert_eq!(
+                dest.get_coeff_view().0,
+                CoefficientView::Natural(res.0, res.1)
+            );
+
+            let mut dest_norm = vec![];
+            (res.0, res.1).write_packed(&mut dest_norm);
+            assert_eq!(dest, dest_norm);
+        }
+    }
+
+    #[test]
+    fn packed_finite_field() {
+        for (num, index) in [(0, 0), (3, 1), (70000, 2), (u64::MAX - 1, 300)] {
+            let c = Coefficient::FiniteField(FiniteFieldElement(num), FiniteFieldIndex(index));
+
+            let mut dest = vec![];
+            c.write_packed(&mut dest);
+            assert_eq!(dest.len() as u64, c.get_packed_size());
+
+            let mut fixed = vec![0; dest.len()];
+            c.write_packed_fixed(&mut fixed);
+            assert_eq!(dest, fixed);
+
+            // append another number and skip over the finite field element
+            (-5i64, 3i64).write_packed(&mut dest);
+
+            let (view, rest) = dest.get_coeff_view();
+            assert_eq!(
+                view,
+                CoefficientView::FiniteField(FiniteFieldElement(num), FiniteFieldIndex(index))
+            );
+            assert_eq!(rest.get_coeff_view().0, CoefficientView::Natural(-5, 3));
+
+            let rest = dest.skip_rational();
+            assert_eq!(rest.get_coeff_view().0, CoefficientView::Natural(-5, 3));
+        }
+    }
+
+    #[test]
+    fn packed_large() {
+        let r: MultiPrecisionRational = "-123456789012345678901234567890/98765432109876543210"
+            .parse()
+            .unwrap();
+        let c = Coefficient::from(r.clone());
+
+        let mut dest = vec![];
+        c.write_packed(&mut dest);
+        assert_eq!(dest.len() as u64, c.get_packed_size());
+
+        let mut fixed = vec![0; dest.len()];
+        c.write_packed_fixed(&mut fixed);
+        assert_eq!(dest, fixed);
+
+        // the bytes are self-contained and can be read back after the original is dropped
+        drop(c);
+        (7i64, 1i64).write_packed(&mut dest);
+        let stored = dest.clone();
+        drop(dest);
+
+        let (view, rest) = stored.get_coeff_view();
+        assert_eq!(view.to_owned(), Coefficient::from(r));
+        assert_eq!(rest.get_coeff_view().0, CoefficientView::Natural(7, 1));
+        assert_eq!(
+            stored.skip_rational().get_coeff_view().0,
+            CoefficientView::Natural(7, 1)
+        );
+    }
+
+    #[test]
+    fn packed_float() {
+        let f = Float::from_rational(&(-1, 3).into(), 200).mul(&Float::from_parts(
+            MultiPrecisionInteger::from(1),
+            -1000,
+            200,
+        ));
+        let c = Coefficient::Float(f.clone());
+
+        let mut dest = vec![];
+        c.write_packed(&mut dest);
+        assert_eq!(dest.len() as u64, c.get_packed_size());
+
+        let mut fixed = vec![0; dest.len()];
+        c.write_packed_fixed(&mut fixed);
+        assert_eq!(dest, fixed);
+
+        let (view, _) = fixed.get_coeff_view();
+        assert_eq!(view.to_owned(), c);
+        let CoefficientView::Float(g) = view else {
+            panic!("Expected a float");
+        };
+        assert_eq!(g.to_float().prec(), 200);
+
+        // floats that are not finite are stored with a zero mantissa
+        let inf = Float::from_parts(MultiPrecisionInteger::from(1), 100, 100).exp();
+        let neg_inf = inf.mul(&Float::from_f64(-1., 100));
+        let nan = inf.mul(&Float::zero(100));
+        for (f, exp) in [(inf, 1), (neg_inf, -1), (nan, 2)] {
+            let c = Coefficient::Float(f);
+            let mut dest = vec![];
+            c.write_packed(&mut dest);
+            assert_eq!(dest.len() as u64, c.get_packed_size());
+
+            let CoefficientView::Float(g) = dest.get_coeff_view().0 else {
+                panic!("Expected a float");
+            };
+            let g = g.to_float();
+            assert!(!g.is_finite());
+            assert_eq!(g.prec(), 100);
+            assert_eq!(g.to_parts(), (MultiPrecisionInteger::new(), exp));
+        }
+    }
+
+    #[test]
+    fn packed_complex() {
+        let c = Coefficient::complex((1, 3).into(), (-2).into());
+
+        let mut dest = vec![];
+        c.write_packed(&mut dest);
+        assert_eq!(dest.len() as u64, c.get_packed_size());
+
+        let mut fixed = vec![0; dest.len()];
+        c.write_packed_fixed(&mut fixed);
+        assert_eq!(dest, fixed);
+        assert_eq!(fixed.get_coeff_view().0.to_owned(), c);
+    }
+}
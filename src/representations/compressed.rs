@@ -0,0 +1,281 @@
+use crate::{
+    representations::{Atom, AtomView},
+    state::Workspace,
+};
+
+/// The terms of a sum, stored as packed atoms in blocks that are compressed with zstd.
+/// Large sums of similar terms, such as those with many coefficients of similar size,
+/// take up a fraction of the memory of an [`Atom`].
+///
+/// A block is compressed when it is full and is decompressed one at a time
+/// when the terms are iterated over. The terms are kept in the order in which
+/// they are added and equal terms are not merged.
+///
+/// The compression is not transparent: `CompressedTerms` is a separate container,
+/// and an [`AtomView`] always refers to uncompressed data, so that the many functions
+/// that read packed atoms do not have to handle a compressed form. The terms are
+/// stored in the packed format, without a separate varint encoding of exponents,
+/// as small numbers already take the smallest of 1, 2, 4 or 8 bytes.
+///
+/// Example:
+/// ```
+/// # use symbolica::representations::{Atom, compressed::CompressedTerms};
+/// let a = Atom::parse("x^2 + 2*x*y + y^2").unwrap();
+/// let mut c = CompressedTerms::new();
+/// c.push(a.as_view());
+/// assert_eq!(c.len(), 3);
+/// assert_eq!(c.to_expression(), a);
+/// ```
+pub struct CompressedTerms {
+    blocks: Vec<CompressedBlock>,
+    buffer: Vec<u8>,
+    nterms: usize,
+    block_size: usize,
+    level: i32,
+}
+
+/// A compressed block of terms, where every term is prefixed by its length as a varint.
+struct CompressedBlock {
+    data: Box<[u8]>,
+    size: usize,
+}
+
+impl Default for CompressedTerms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompressedTerms {
+    /// The default number of uncompressed bytes in a block.
+    pub const DEFAULT_BLOCK_SIZE: usize = 1 << 16;
+
+    /// Create an empty list of terms with the default block size and compression level.
+    pub fn new() -> CompressedTerms {
+        Self::with_settings(Self::DEFAULT_BLOCK_SIZE, zstd::DEFAULT_COMPRESSION_LEVEL)
+    }
+
+    /// Create an empty list of terms that are compressed in blocks of at
+    /// least `block_size` bytes, with the zstd compression level `level`.
+    /// Larger blocks compress better, but make the iteration less lazy.
+    pub fn with_settings(block_size: usize, level: i32) -> CompressedTerms {
+        CompressedTerms {
+            blocks: vec![],
+            buffer: vec![],
+            nterms: 0,
+            block_size,
+            level,
+        }
+    }
+
+    /// Add the terms of `a`, or `a` itself if it is not a sum.
+    pub fn push(&mut self, a: AtomView) {
+        if let AtomView::Add(add) = a {
+            for t in add.iter() {
+                self.push_term(t);
+            }
+        } else {
+            self.push_term(a);
+        }
+    }
+
+    fn push_term(&mut self, t: AtomView) {
+        let data = t.get_data();
+        write_varint(data.len(), &mut self.buffer);
+        self.buffer.extend_from_slice(data);
+        self.nterms += 1;
+
+        if self.buffer.len() >= self.block_size {
+            self.compress_buffer();
+        }
+    }
+
+    fn compress_buffer(&mut self) {
+        let data = zstd::bulk::compress(&self.buffer, self.level)
+            .unwrap_or_else(|e| panic!("Could not compress terms: {}", e));
+
+        self.blocks.push(CompressedBlock {
+            data: data.into_boxed_slice(),
+            size: self.buffer.len(),
+        });
+        self.buffer.clear();
+    }
+
+    /// The number of terms.
+    pub fn len(&self) -> usize {
+        self.nterms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nterms == 0
+    }
+
+    /// Get the number of bytes used to store the terms, including the block
+    /// that is not compressed yet.
+    pub fn get_byte_size(&self) -> usize {
+        self.blocks.iter().map(|b| b.data.len()).sum::<usize>() + self.buffer.len()
+    }
+
+    /// Call `f` on every term, without copying the terms out of the decompressed blocks.
+    pub fn for_each<F: FnMut(AtomView)>(&self, mut f: F) {
+        let mut data = vec![];
+        for b in &self.blocks {
+            b.decompress_into(&mut data);
+            for_each_in_block(&data, &mut f);
+        }
+
+        for_each_in_block(&self.buffer, &mut f);
+    }
+
+    /// Iterate over the terms, decompressing one block at a time.
+    pub fn iter(&self) -> CompressedTermIterator<'_> {
+        CompressedTermIterator {
+            terms: self,
+            block: 0,
+            data: vec![],
+            pos: 0,
+            in_buffer: false,
+        }
+    }
+
+    /// Add all terms into a single expression.
+    pub fn to_expression(&self) -> Atom {
+        let mut add = Atom::new();
+        let a = add.to_add();
+        self.for_each(|t| {
+            a.extend(t);
+        });
+
+        let mut out = Atom::new();
+        Workspace::get_local().with(|ws| add.as_view().normalize(ws, &mut out));
+        out
+    }
+}
+
+impl CompressedBlock {
+    fn decompress_into(&self, out: &mut Vec<u8>) {
+        out.clear();
+        out.reserve(self.size);
+        zstd::bulk::Decompressor::new()
+            .and_then(|mut d| d.decompress_to_buffer(&self.data, out))
+            .unwrap_or_else(|e| panic!("Could not decompress terms: {}", e));
+    }
+}
+
+fn for_each_in_block<F: FnMut(AtomView)>(mut data: &[u8], f: &mut F) {
+    while !data.is_empty() {
+        let len = read_varint(&mut data);
+        f(AtomView::from(&data[..len]));
+        data = &data[len..];
+    }
+}
+
+fn write_varint(mut n: usize, out: &mut Vec<u8>) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn read_varint(data: &mut &[u8]) -> usize {
+    let mut n = 0;
+    let mut shift = 0;
+    loop {
+        let b = data[0];
+        *data = &data[1..];
+        n |= ((b & 0x7f) as usize) << shift;
+        if b < 0x80 {
+            return n;
+        }
+        shift += 7;
+    }
+}
+
+/// An iterator over the terms of [`CompressedTerms`].
+pub struct CompressedTermIterator<'a> {
+    terms: &'a CompressedTerms,
+    block: usize,
+    data: Vec<u8>,
+    pos: usize,
+    in_buffer: bool,
+}
+
+impl<'a> Iterator for CompressedTermIterator<'a> {
+    type Item = Atom;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let data = if self.in_buffer {
+                &self.terms.buffer[..]
+            } else {
+                &self.data[..]
+            };
+
+            if self.pos < data.len() {
+                let mut rest = &data[self.pos..];
+                let len = read_varint(&mut rest);
+                self.pos = data.len() - rest.len() + len;
+                return Some(AtomView::from(&rest[..len]).to_owned());
+            }
+
+            if self.in_buffer {
+                return None;
+            }
+
+            self.pos = 0;
+            if let Some(b) = self.terms.blocks.get(self.block) {
+                b.decompress_into(&mut self.data);
+                self.block += 1;
+            } else {
+                self.in_buffer = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        representations::{Atom, FunctionBuilder},
+        state::State,
+    };
+
+    use super::CompressedTerms;
+
+    #[test]
+    fn compressed_terms() {
+        let f = State::get_symbol("ct_f");
+        let x = Atom::parse("ct_x").unwrap();
+        let mut c = CompressedTerms::with_settings(1000, 3);
+        let mut uncompressed_size = 0;
+        for i in 0..5000 {
+            let t = FunctionBuilder::new(f)
+                .add_arg(&Atom::new_num(i))
+                .finish()
+                .pow(&x)
+                * &Atom::parse("123456789123456789/987654321987654321").unwrap();
+            uncompressed_size += t.as_view().get_byte_size();
+            c.push(t.as_view());
+        }
+
+        assert_eq!(c.len(), 5000);
+        assert!(c.blocks.len() > 1 && !c.buffer.is_empty());
+        assert!(c.get_byte_size() < uncompressed_size / 2);
+
+        let mut count = 0;
+        c.for_each(|_| count += 1);
+        assert_eq!(count, 5000);
+
+        for (i, t) in c.iter().enumerate() {
+            let r = FunctionBuilder::new(f)
+                .add_arg(&Atom::new_num(i as i64))
+                .finish()
+                .pow(&x)
+                * &Atom::parse("123456789123456789/987654321987654321").unwrap();
+            assert_eq!(t, r);
+        }
+        assert_eq!(c.iter().count(), 5000);
+        assert_eq!(CompressedTerms::new().iter().count(), 0);
+    }
+}
@@ -0,0 +1,234 @@
+use ahash::RandomState;
+use hashbrown::HashTable;
+
+use crate::{
+    coefficient::Coefficient,
+    representations::{Atom, AtomView, Symbol},
+    state::Workspace,
+};
+
+/// The index of a node in an [`AtomDag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeIndex(usize);
+
+impl NodeIndex {
+    /// Get the position of the node in the DAG.
+    pub fn get_id(&self) -> usize {
+        self.0
+    }
+}
+
+/// A node in an [`AtomDag`], whose children are references to other nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DagNode {
+    Num(Coefficient),
+    Var(Symbol),
+    Fun(Symbol, Vec<NodeIndex>),
+    /// A base and an exponent.
+    Pow([NodeIndex; 2]),
+    Mul(Vec<NodeIndex>),
+    Add(Vec<NodeIndex>),
+}
+
+impl DagNode {
+    /// Get the children of the node.
+    pub fn children(&self) -> &[NodeIndex] {
+        match self {
+            DagNode::Num(_) | DagNode::Var(_) => &[],
+            DagNode::Fun(_, args) | DagNode::Mul(args) | DagNode::Add(args) => args,
+            DagNode::Pow(p) => p,
+        }
+    }
+}
+
+/// A hash-consed store of expressions, in which identical subexpressions are
+/// stored only once. Expressions with a lot of sharing, such as nested derivatives,
+/// take up far less memory than in the linear representation of an [`Atom`].
+///
+/// Nodes are never removed, and a node only refers to nodes that were added before it.
+///
+/// Example:
+/// ```
+/// # use symbolica::representations::{Atom, dag::AtomDag};
+/// let a = Atom::parse("f(x+y)*(x+y)^2").unwrap();
+/// let mut dag = AtomDag::new();
+/// let root = dag.insert(a.as_view());
+/// assert_eq!(dag.len(), 7);
+/// assert_eq!(dag.to_atom(root), a);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AtomDag {
+    nodes: Vec<DagNode>,
+    /// The indices of all nodes, hashed by the node they refer to,
+    /// so that every node is stored only once.
+    index: HashTable<NodeIndex>,
+    hasher: RandomState,
+}
+
+impl AtomDag {
+    /// Create an empty DAG.
+    pub fn new() -> AtomDag {
+        AtomDag::default()
+    }
+
+    /// The number of distinct nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Get the node with index `i`.
+    pub fn get(&self, i: NodeIndex) -> &DagNode {
+        &self.nodes[i.0]
+    }
+
+    /// Add a node and return its index. If the node is already present,
+    /// the index of the existing node is returned.
+    ///
+    /// The node is not normalized, which happens when it is converted to an atom
+    /// with [`AtomDag::to_atom`]. This function panics when a child is not in the DAG.
+    pub fn insert_node(&mut self, node: DagNode) -> NodeIndex {
+        let hash = self.hasher.hash_one(&node);
+        if let Some(i) = self.index.find(hash, |i| self.nodes[i.0] == node) {
+            return *i;
+        }
+
+        assert!(
+            node.children().iter().all(|c| c.0 < self.nodes.len()),
+            "Child node is not in the DAG"
+        );
+
+        let i = NodeIndex(self.nodes.len());
+        self.nodes.push(node);
+
+        let (nodes, hasher) = (&self.nodes, &self.hasher);
+        self.index
+            .insert_unique(hash, i, |i| hasher.hash_one(&nodes[i.0]));
+        i
+    }
+
+    /// Add the expression `a` and return the index of its root node.
+    pub fn insert(&mut self, a: AtomView) -> NodeIndex {
+        let node = match a {
+            AtomView::Num(n) => DagNode::Num(n.get_coeff_view().to_owned()),
+            AtomView::Var(v) => DagNode::Var(v.get_symbol()),
+            AtomView::Fun(f) => {
+                DagNode::Fun(f.get_symbol(), f.iter().map(|x| self.insert(x)).collect())
+            }
+            AtomView::Pow(p) => {
+                let (b, e) = p.get_base_exp();
+                DagNode::Pow([self.insert(b), self.insert(e)])
+            }
+            AtomView::Mul(m) => DagNode::Mul(m.iter().map(|x| self.insert(x)).collect()),
+            AtomView::Add(a) => DagNode::Add(a.iter().map(|x| self.insert(x)).collect()),
+        };
+
+        self.insert_node(node)
+    }
+
+    /// The number of nodes of the expression with root `root` in the linear representation,
+    /// which counts shared subexpressions once for every occurrence. The count saturates
+    /// at `u64::MAX`.
+    pub fn tree_size(&self, root: NodeIndex) -> u64 {
+        let mut sizes: Vec<u64> = Vec::with_capacity(root.0 + 1);
+        for n in &self.nodes[..=root.0] {
+            let s = n
+                .children()
+                .iter()
+                .fold(1u64, |acc, c| acc.saturating_add(sizes[c.0]));
+            sizes.push(s);
+        }
+        sizes[root.0]
+    }
+
+    /// Convert the expression with root `root` to its linear representation.
+    /// Every subexpression is normalized once.
+    pub fn to_atom(&self, root: NodeIndex) -> Atom {
+        let mut cache = vec![None; root.0 + 1];
+        Workspace::get_local().with(|ws| self.to_atom_impl(root, ws, &mut cache));
+        cache[root.0].take().unwrap()
+    }
+
+    fn to_atom_impl(&self, i: NodeIndex, ws: &Workspace, cache: &mut [Option<Atom>]) {
+        if cache[i.0].is_some() {
+            return;
+        }
+
+        for c in self.nodes[i.0].children() {
+            self.to_atom_impl(*c, ws, cache);
+        }
+
+        let mut a = ws.new_atom();
+        match &self.nodes[i.0] {
+            DagNode::Num(n) => {
+                a.to_num(n.clone());
+            }
+            DagNode::Var(s) => {
+                a.to_var(*s);
+            }
+            DagNode::Fun(s, args) => {
+                let f = a.to_fun(*s);
+                for c in args {
+                    f.add_arg(cache[c.0].as_ref().unwrap().as_view());
+                }
+            }
+            DagNode::Pow([b, e]) => {
+                a.to_pow(
+                    cache[b.0].as_ref().unwrap().as_view(),
+                    cache[e.0].as_ref().unwrap().as_view(),
+                );
+            }
+            DagNode::Mul(args) => {
+                let m = a.to_mul();
+                for c in args {
+                    m.extend(cache[c.0].as_ref().unwrap().as_view());
+                }
+            }
+            DagNode::Add(args) => {
+                let add = a.to_add();
+                for c in args {
+                    add.extend(cache[c.0].as_ref().unwrap().as_view());
+                }
+            }
+        }
+
+        let mut out = Atom::new();
+        a.as_view().normalize(ws, &mut out);
+        cache[i.0] = Some(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{representations::Atom, state::State};
+
+    use super::{AtomDag, DagNode};
+
+    #[test]
+    fn sharing() {
+        let mut dag = AtomDag::new();
+        let x = dag.insert_node(DagNode::Var(State::get_symbol("dag_x")));
+        let mut cur = x;
+        for _ in 0..40 {
+            let f = dag.insert_node(DagNode::Fun(State::get_symbol("dag_f"), vec![cur]));
+            cur = dag.insert_node(DagNode::Mul(vec![f, f]));
+        }
+
+        assert_eq!(dag.len(), 81);
+        assert_eq!(dag.tree_size(cur), 4 * (1 << 40) - 3);
+
+        let a = Atom::parse("dag_f(dag_f(dag_x)^2)^2*(1+dag_x)").unwrap();
+        let mut dag = AtomDag::new();
+        let root = dag.insert(a.as_view());
+        assert_eq!(dag.to_atom(root), a);
+        assert_eq!(dag.insert(a.as_view()), root);
+
+        // unnormalized nodes are normalized on conversion
+        let x = dag.insert(Atom::parse("dag_x").unwrap().as_view());
+        let add = dag.insert_node(DagNode::Add(vec![x, x]));
+        assert_eq!(dag.to_atom(add), Atom::parse("2*dag_x").unwrap());
+    }
+}
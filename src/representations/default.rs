@@ -2,10 +2,16 @@ use byteorder::{LittleEndian, WriteBytesExt};
 use bytes::{Buf, BufMut};
 use std::cmp::Ordering;
 
-use crate::coefficient::{Coefficient, CoefficientView};
+use crate::{
+    coefficient::{Coefficient, CoefficientView},
+    state::State,
+};
 
 use super::{
-    coefficient::{PackedRationalNumberReader, PackedRationalNumberWriter},
+    coefficient::{
+        check_packed_coefficient, check_packed_frac, check_packed_pair, PackedRationalNumberReader,
+        PackedRationalNumberWriter,
+    },
     AtomView, SliceType, Symbol,
 };
 
@@ -21,9 +27,16 @@ const VAR_WILDCARD_LEVEL_MASK: u8 = 0b00011000;
 const VAR_WILDCARD_LEVEL_1: u8 = 0b00001000;
 const VAR_WILDCARD_LEVEL_2: u8 = 0b00010000;
 const VAR_WILDCARD_LEVEL_3: u8 = 0b00011000;
+const VAR_NONCOMMUTATIVE_FLAG: u8 = 0b00100000;
+const VAR_REAL_FLAG: u8 = 0b01000000;
 const FUN_SYMMETRIC_FLAG: u8 = 0b00100000;
 const FUN_LINEAR_FLAG: u8 = 0b01000000;
+const FUN_ID_MASK: u64 = Symbol::MAX_ID as u64; // the symbol id in the function id
+const FUN_REAL_FLAG: u64 = 1 << 31; // stored in the function id
 const FUN_ANTISYMMETRIC_FLAG: u64 = 1 << 32; // stored in the function id
+const FUN_NONCOMMUTATIVE_FLAG: u64 = 1 << 33; // stored in the function id
+const FUN_LINEAR_ARGUMENTS_SHIFT: u64 = 34; // mask of linear arguments stored in the function id
+const _: () = assert!(FUN_LINEAR_ARGUMENTS_SHIFT as usize + Symbol::MAX_LINEAR_ARGUMENTS == 64);
 const MUL_HAS_COEFF_FLAG: u8 = 0b01000000;
 
 pub type RawAtom = Vec<u8>;
@@ -121,16 +134,30 @@ pub struct Var {
 }
 
 impl Var {
+    #[inline(always)]
+    fn get_flags(symbol: Symbol) -> u8 {
+        let mut flags = match symbol.wildcard_level {
+            0 => VAR_ID,
+            1 => VAR_ID | VAR_WILDCARD_LEVEL_1,
+            2 => VAR_ID | VAR_WILDCARD_LEVEL_2,
+            _ => VAR_ID | VAR_WILDCARD_LEVEL_3,
+        };
+
+        if symbol.is_noncommutative {
+            flags |= VAR_NONCOMMUTATIVE_FLAG;
+        }
+        if symbol.is_real {
+            flags |= VAR_REAL_FLAG;
+        }
+
+        flags
+    }
+
     #[inline]
     pub fn new(symbol: Symbol) -> Var {
         let mut buffer = Vec::new();
 
-        match symbol.wildcard_level {
-            0 => buffer.put_u8(VAR_ID),
-            1 => buffer.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_1),
-            2 => buffer.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_2),
-            _ => buffer.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_3),
-        }
+        buffer.put_u8(Var::get_flags(symbol));
 
         (symbol.id as u64, 1).write_packed(&mut buffer);
         Var { data: buffer }
@@ -140,12 +167,7 @@ impl Var {
     pub fn new_into(symbol: Symbol, mut buffer: RawAtom) -> Var {
         buffer.clear();
 
-        match symbol.wildcard_level {
-            0 => buffer.put_u8(VAR_ID),
-            1 => buffer.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_1),
-            2 => buffer.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_2),
-            _ => buffer.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_3),
-        }
+        buffer.put_u8(Var::get_flags(symbol));
 
         (symbol.id as u64, 1).write_packed(&mut buffer);
         Var { data: buffer }
@@ -162,12 +184,7 @@ impl Var {
     pub fn set_from_symbol(&mut self, id: Symbol) {
         self.data.clear();
 
-        match id.wildcard_level {
-            0 => self.data.put_u8(VAR_ID),
-            1 => self.data.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_1),
-            2 => self.data.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_2),
-            _ => self.data.put_u8(VAR_ID | VAR_WILDCARD_LEVEL_3),
-        }
+        self.data.put_u8(Var::get_flags(id));
 
         (id.id as u64, 1).write_packed(&mut self.data);
     }
@@ -244,11 +261,17 @@ impl Fun {
 
         let buf_pos = self.data.len();
 
-        let id = if symbol.is_antisymmetric {
-            symbol.id as u64 | FUN_ANTISYMMETRIC_FLAG
-        } else {
-            symbol.id as u64
-        };
+        let mut id = symbol.id as u64;
+        if symbol.is_antisymmetric {
+            id |= FUN_ANTISYMMETRIC_FLAG;
+        }
+        if symbol.is_noncommutative {
+            id |= FUN_NONCOMMUTATIVE_FLAG;
+        }
+        if symbol.is_real {
+            id |= FUN_REAL_FLAG;
+        }
+        id |= (symbol.linear_arguments as u64) << FUN_LINEAR_ARGUMENTS_SHIFT;
 
         (id, 0).write_packed(&mut self.data);
 
@@ -705,6 +728,172 @@ impl Add {
     }
 }
 
+impl Add {
+    /// Get a cursor that edits the terms of the sum in place. See [`AddCursor`].
+    pub fn cursor(&mut self) -> AddCursor<'_> {
+        let mut c = &self.data[1 + 4..];
+        (_, _, c) = c.get_frac_u64();
+        let pos = self.data.len() - c.len();
+
+        AddCursor {
+            add: self,
+            pos,
+            index: 0,
+        }
+    }
+
+    /// Set the number of terms to `n_args`, returning the change in size of the header.
+    fn set_nargs(&mut self, n_args: u64) -> isize {
+        let c = &self.data[1 + 4..];
+        let old_size = c.len() - c.get_frac_u64().2.len();
+        let new_size = (n_args, 1).get_packed_size() as usize;
+
+        match new_size.cmp(&old_size) {
+            Ordering::Equal => {}
+            Ordering::Less => {
+                self.data.copy_within(1 + 4 + old_size.., 1 + 4 + new_size);
+                self.data.truncate(self.data.len() - old_size + new_size);
+            }
+            Ordering::Greater => {
+                let old_len = self.data.len();
+                self.data.resize(old_len + new_size - old_size, 0);
+                self.data
+                    .copy_within(1 + 4 + old_size..old_len, 1 + 4 + new_size);
+            }
+        }
+
+        (n_args, 1).write_packed_fixed(&mut self.data[1 + 4..1 + 4 + new_size]);
+        new_size as isize - old_size as isize
+    }
+
+    fn update_size(&mut self) {
+        let size = self.data.len() - 1 - 4;
+        assert!(size < u32::MAX as usize, "Term too large");
+        (&mut self.data[1..]).put_u32_le(size as u32);
+    }
+}
+
+/// A cursor over the terms of an [`Add`], that removes, rescales and replaces terms
+/// without rebuilding the sum. An edit only moves the bytes of the terms that follow
+/// the current term.
+///
+/// Removing terms and rescaling them with a non-zero factor keeps a normalized sum
+/// normalized, as long as more than one term remains. Replacing a term marks the sum as
+/// not normalized, so that it should be normalized after editing.
+///
+/// Example:
+/// ```
+/// # use symbolica::{coefficient::Coefficient, representations::{Atom, AtomView}};
+/// let mut a = Atom::parse("x + 2*y + z").unwrap();
+/// let mut c = a.term_cursor().unwrap();
+/// while let Some(t) = c.current() {
+///     if t == Atom::parse("z").unwrap().as_view() {
+///         c.remove();
+///     } else {
+///         c.rescale(&Coefficient::from(3));
+///         c.advance();
+///     }
+/// }
+/// assert_eq!(a, Atom::parse("3*x + 6*y").unwrap());
+/// ```
+pub struct AddCursor<'a> {
+    add: &'a mut Add,
+    pos: usize,
+    index: usize,
+}
+
+impl<'a> AddCursor<'a> {
+    /// The number of terms of the sum.
+    pub fn get_nargs(&self) -> usize {
+        self.add.get_nargs()
+    }
+
+    /// The position of the current term.
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the current term, or `None` if the cursor is past the last term.
+    pub fn current(&self) -> Option<AtomView<'_>> {
+        if self.index >= self.add.get_nargs() {
+            return None;
+        }
+
+        ListIterator {
+            data: &self.add.data[self.pos..],
+            length: 1,
+        }
+        .next()
+    }
+
+    /// Move to the next term, returning `false` if the cursor is past the last term.
+    pub fn advance(&mut self) -> bool {
+        if let Some(t) = self.current() {
+            self.pos += t.get_data().len();
+            self.index += 1;
+        }
+
+        self.index < self.add.get_nargs()
+    }
+
+    /// Move back to the first term.
+    pub fn rewind(&mut self) {
+        let c = &self.add.data[1 + 4..];
+        self.pos = self.add.data.len() - c.get_frac_u64().2.len();
+        self.index = 0;
+    }
+
+    /// Remove the current term. The cursor moves to the next term.
+    pub fn remove(&mut self) {
+        let Some(t) = self.current() else {
+            panic!("Cursor is past the last term");
+        };
+        let len = t.get_data().len();
+
+        let n_args = self.add.get_nargs() as u64 - 1;
+        self.add.data.drain(self.pos..self.pos + len);
+        self.pos = (self.pos as isize + self.add.set_nargs(n_args)) as usize;
+        self.add.update_size();
+
+        if n_args < 2 {
+            self.add.set_normalized(false);
+        }
+    }
+
+    /// Replace the current term by `term`, which is inserted as a single term.
+    /// The sum is marked as not normalized.
+    pub fn replace(&mut self, term: AtomView<'_>) {
+        let Some(t) = self.current() else {
+            panic!("Cursor is past the last term");
+        };
+        let len = t.get_data().len();
+
+        self.add
+            .data
+            .splice(self.pos..self.pos + len, term.get_data().iter().cloned());
+        self.add.update_size();
+        self.add.set_normalized(false);
+    }
+
+    /// Multiply the coefficient of the current term by `factor`. If `factor` is zero,
+    /// the term is removed and the cursor moves to the next term.
+    pub fn rescale(&mut self, factor: &Coefficient) {
+        if factor.is_zero() {
+            self.remove();
+            return;
+        }
+
+        let Some(t) = self.current() else {
+            panic!("Cursor is past the last term");
+        };
+
+        let new_term = t.to_owned() * &super::Atom::new_num(factor.clone());
+        let normalized = self.add.to_add_view().is_normalized();
+        self.replace(new_term.as_view());
+        self.add.set_normalized(normalized);
+    }
+}
+
 impl<'a> VarView<'a> {
     #[inline]
     pub fn to_owned(&self) -> Var {
@@ -725,10 +914,15 @@ impl<'a> VarView<'a> {
 
     #[inline(always)]
     pub fn get_symbol(&self) -> Symbol {
-        Symbol::init_var(
+        Symbol::init_fn(
             self.data[1..].get_frac_i64().0 as u32,
             self.get_wildcard_level(),
+            false,
+            false,
+            false,
+            self.data[0] & VAR_NONCOMMUTATIVE_FLAG != 0,
         )
+        .with_real(self.data[0] & VAR_REAL_FLAG != 0)
     }
 
     #[inline(always)]
@@ -794,12 +988,15 @@ impl<'a> FunView<'a> {
         let id = self.data[1 + 4..].get_frac_u64().0;
 
         Symbol::init_fn(
-            id as u32,
+            (id & FUN_ID_MASK) as u32,
             self.get_wildcard_level(),
             self.is_symmetric(),
             id & FUN_ANTISYMMETRIC_FLAG != 0,
             self.is_linear(),
+            id & FUN_NONCOMMUTATIVE_FLAG != 0,
         )
+        .with_real(id & FUN_REAL_FLAG != 0)
+        .with_linear_arguments((id >> FUN_LINEAR_ARGUMENTS_SHIFT) as u32)
     }
 
     #[inline(always)]
@@ -1173,7 +1370,234 @@ impl<'a> AddView<'a> {
     }
 }
 
+/// An error in the packed data of an atom. See [`AtomView::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedDataError {
+    /// The position of the error in the data.
+    pub offset: usize,
+    pub kind: PackedDataErrorKind,
+}
+
+/// The kind of a [`PackedDataError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackedDataErrorKind {
+    /// The data ends before the atom is complete.
+    UnexpectedEnd,
+    /// The tag of an atom is unknown.
+    UnknownAtomType(u8),
+    /// The tag of a number is unknown.
+    UnknownNumberType(u8),
+    /// A number has a denominator of zero.
+    ZeroDenominator,
+    /// The stored byte size of a function, product or sum does not match its arguments.
+    SizeMismatch,
+    /// The data continues after the end of the atom.
+    TrailingData,
+    /// The atom contains a rational polynomial coefficient, which is stored as a
+    /// pointer and can therefore not be checked.
+    RationalPolynomial,
+    /// A number is not in its canonical form, for example a fraction that is not reduced.
+    NotNormalized,
+    /// A symbol with the given identifier is not defined in the state.
+    UnknownSymbol(u32),
+    /// A finite field with the given index is not defined in the state.
+    UnknownFiniteField(usize),
+}
+
+impl PackedDataError {
+    pub(crate) fn new(offset: usize, kind: PackedDataErrorKind) -> PackedDataError {
+        PackedDataError { offset, kind }
+    }
+}
+
+impl std::fmt::Display for PackedDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            PackedDataErrorKind::UnexpectedEnd => write!(f, "Unexpected end of data")?,
+            PackedDataErrorKind::UnknownAtomType(t) => write!(f, "Unknown atom type {}", t)?,
+            PackedDataErrorKind::UnknownNumberType(t) => write!(f, "Unknown number type {}", t)?,
+            PackedDataErrorKind::ZeroDenominator => write!(f, "Denominator is zero")?,
+            PackedDataErrorKind::SizeMismatch => {
+                write!(f, "Stored size does not match the arguments")?
+            }
+            PackedDataErrorKind::TrailingData => write!(f, "Data after the end of the atom")?,
+            PackedDataErrorKind::RationalPolynomial => {
+                write!(f, "Rational polynomial coefficients cannot be checked")?
+            }
+            PackedDataErrorKind::NotNormalized => write!(f, "Number is not normalized")?,
+            PackedDataErrorKind::UnknownSymbol(id) => write!(f, "Unknown symbol {}", id)?,
+            PackedDataErrorKind::UnknownFiniteField(i) => write!(f, "Unknown finite field {}", i)?,
+        }
+
+        write!(f, " at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for PackedDataError {}
+
+/// Check that the symbol identifier `id` of the atom at position `pos` is defined.
+fn check_symbol(id: u64, pos: usize) -> Result<(), PackedDataError> {
+    if id > Symbol::MAX_ID as u64 || !State::symbol_exists(id as u32) {
+        return Err(PackedDataError::new(
+            pos,
+            PackedDataErrorKind::UnknownSymbol(id as u32),
+        ));
+    }
+    Ok(())
+}
+
+/// An atom with children whose check is in progress.
+struct PackedAtomFrame {
+    /// The bound on the data of the parent.
+    bound: usize,
+    /// The end of the atom, if its size is stored.
+    end: Option<usize>,
+    /// The number of children that have not been checked.
+    remaining: usize,
+}
+
+/// Check the atom at position `pos` of `data` and return the position after it.
+/// The atoms are traversed with an explicit stack, so that deeply nested data
+/// cannot overflow the call stack.
+fn check_packed_atom(data: &[u8], pos: usize) -> Result<usize, PackedDataError> {
+    let mut stack: Vec<PackedAtomFrame> = vec![];
+    let mut bound = data.len();
+    let mut pos = pos;
+
+    loop {
+        // the children of a function, product or sum may not exceed its stored size
+        let data = &data[..bound];
+        let Some(&tag) = data.get(pos) else {
+            return Err(PackedDataError::new(
+                pos,
+                PackedDataErrorKind::UnexpectedEnd,
+            ));
+        };
+
+        match tag & TYPE_MASK {
+            NUM_ID => pos = check_packed_coefficient(data, pos + 1)?,
+            VAR_ID => {
+                let (id, _, end) = check_packed_frac(data, pos + 1)?;
+                check_symbol(id, pos)?;
+                pos = end;
+            }
+            POW_ID => {
+                stack.push(PackedAtomFrame {
+                    bound,
+                    end: None,
+                    remaining: 2,
+                });
+                pos += 1;
+                continue;
+            }
+            FUN_ID | MUL_ID | ADD_ID => {
+                if pos + 5 > data.len() {
+                    return Err(PackedDataError::new(
+                        data.len(),
+                        PackedDataErrorKind::UnexpectedEnd,
+                    ));
+                }
+
+                let size = (&data[pos + 1..]).get_u32_le() as usize;
+                let end = pos + 5 + size;
+                if end > data.len() {
+                    return Err(PackedDataError::new(
+                        data.len(),
+                        PackedDataErrorKind::UnexpectedEnd,
+                    ));
+                }
+
+                // a function stores its id and number of arguments, which may be zero
+                let (a, b, p) = check_packed_pair(&data[..end], pos + 5)?;
+                let n_args = if tag & TYPE_MASK == FUN_ID {
+                    check_symbol(a & FUN_ID_MASK, pos)?;
+                    b
+                } else {
+                    a
+                };
+
+                if n_args > 0 {
+                    stack.push(PackedAtomFrame {
+                        bound,
+                        end: Some(end),
+                        remaining: n_args as usize,
+                    });
+                    bound = end;
+                    pos = p;
+                    continue;
+                }
+
+                if p != end {
+                    return Err(PackedDataError::new(p, PackedDataErrorKind::SizeMismatch));
+                }
+                pos = end;
+            }
+            _ => {
+                return Err(PackedDataError::new(
+                    pos,
+                    PackedDataErrorKind::UnknownAtomType(tag),
+                ))
+            }
+        }
+
+        // the atom is complete: finish the parents whose last child it is
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                return Ok(pos);
+            };
+
+            frame.remaining -= 1;
+            if frame.remaining > 0 {
+                break;
+            }
+
+            if let Some(end) = frame.end {
+                if pos != end {
+                    return Err(PackedDataError::new(pos, PackedDataErrorKind::SizeMismatch));
+                }
+            }
+            bound = frame.bound;
+            stack.pop();
+        }
+    }
+}
+
 impl<'a> AtomView<'a> {
+    /// Create a view of the packed atom `source`, after checking that the data is well-formed.
+    /// Use this instead of [`AtomView::from`] for data from an untrusted source,
+    /// such as a file.
+    ///
+    /// The packed data depends on the state in which it was created, as symbols are stored
+    /// by their identifier. Use [`Atom::save`](crate::representations::Atom::save)
+    /// to store expressions on disk in a format that is independent of the state.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::{Atom, AtomView, PackedDataErrorKind};
+    /// let a = Atom::parse("f(x, 1/3)").unwrap();
+    /// let data = a.as_view().get_data().to_vec();
+    /// assert_eq!(AtomView::from_checked(&data).unwrap(), a.as_view());
+    ///
+    /// let err = AtomView::from_checked(&data[..data.len() - 1]).unwrap_err();
+    /// assert_eq!(err.kind, PackedDataErrorKind::UnexpectedEnd);
+    /// ```
+    pub fn from_checked(source: &'a [u8]) -> Result<AtomView<'a>, PackedDataError> {
+        let end = check_packed_atom(source, 0)?;
+        if end != source.len() {
+            return Err(PackedDataError::new(end, PackedDataErrorKind::TrailingData));
+        }
+
+        Ok(AtomView::from(source))
+    }
+
+    /// Check that the packed data of the atom is well-formed, that its numbers are
+    /// normalized and that its symbols and finite fields are defined. This is guaranteed
+    /// for atoms that are created by Symbolica, but not for views of external data
+    /// created with [`AtomView::from`].
+    pub fn validate(&self) -> Result<(), PackedDataError> {
+        AtomView::from_checked(self.get_data()).map(|_| ())
+    }
+
     pub fn from(source: &'a [u8]) -> AtomView<'a> {
         match source[0] & TYPE_MASK {
             VAR_ID => AtomView::Var(VarView { data: source }),
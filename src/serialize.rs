@@ -0,0 +1,558 @@
+use std::sync::Arc;
+
+use rug::Integer as MultiPrecisionInteger;
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    coefficient::{Coefficient, CoefficientView},
+    domains::{
+        finite_field::{FiniteFieldCore, Zp64},
+        float::Float,
+        integer::{Integer, IntegerRing},
+        rational::Rational,
+        rational_polynomial::RationalPolynomial,
+    },
+    id::{Pattern, PatternRestriction},
+    poly::{polynomial::MultivariatePolynomial, Variable},
+    representations::{Atom, AtomView, Symbol},
+    state::{FunctionAttribute, LinearArguments, State, Workspace},
+};
+
+/// A symbol is stored by its name, wildcard level and attributes, so that it
+/// can be resolved in a state with a different symbol table.
+#[derive(Serialize, Deserialize)]
+struct SymbolData {
+    name: String,
+    wildcard_level: u8,
+    attributes: Vec<FunctionAttribute>,
+}
+
+impl From<Symbol> for SymbolData {
+    fn from(s: Symbol) -> Self {
+        let mut attributes = vec![];
+        if s.is_symmetric() {
+            attributes.push(FunctionAttribute::Symmetric);
+        }
+        if s.is_antisymmetric() {
+            attributes.push(FunctionAttribute::Antisymmetric);
+        }
+        if s.get_linear_arguments_mask() != 0 {
+            attributes.push(FunctionAttribute::LinearIn(LinearArguments::from_mask(
+                s.get_linear_arguments_mask(),
+            )));
+        } else if s.is_linear() {
+            attributes.push(FunctionAttribute::Linear);
+        }
+        if s.is_noncommutative() {
+            attributes.push(FunctionAttribute::NonCommutative);
+        }
+        if s.is_real() {
+            attributes.push(FunctionAttribute::Real);
+        }
+
+        SymbolData {
+            name: State::get_name(s).to_string(),
+            wildcard_level: s.get_wildcard_level(),
+            attributes,
+        }
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SymbolData::from(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = SymbolData::deserialize(deserializer)?;
+        State::import_symbol(&s.name, s.wildcard_level, &s.attributes).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum VariableData {
+    Symbol(Symbol),
+    Temporary(usize),
+    Array(Symbol, usize),
+    Function(Symbol, Atom),
+    Other(Atom),
+}
+
+/// A polynomial with integer coefficients, stored as a list of monomials.
+type PolynomialData = Vec<(String, Vec<u16>)>;
+
+/// Numbers are stored in decimal notation and finite field elements
+/// by the prime of their field, so that the field can be registered on load.
+#[derive(Serialize, Deserialize)]
+enum CoefficientData {
+    Rational(String, String),
+    FiniteField(u64, u64),
+    RationalPolynomial(Vec<VariableData>, PolynomialData, PolynomialData),
+    Float(String, i64, u32),
+    Complex(Box<(Coefficient, Coefficient)>),
+}
+
+fn polynomial_to_data(p: &MultivariatePolynomial<IntegerRing, u16>) -> PolynomialData {
+    (0..p.nterms())
+        .map(|i| (p.coefficients[i].to_string(), p.exponents(i).to_vec()))
+        .collect()
+}
+
+fn polynomial_from_data(
+    data: PolynomialData,
+    variables: &Arc<Vec<Variable>>,
+) -> Result<MultivariatePolynomial<IntegerRing, u16>, String> {
+    let mut p = MultivariatePolynomial::new(&IntegerRing::new(), None, variables.clone());
+    for (c, e) in data {
+        if e.len() != variables.len() {
+            return Err("Monomial does not match the number of variables".into());
+        }
+
+        let c: Integer = c
+            .parse()
+            .map_err(|_| format!("Invalid integer coefficient {}", c))?;
+        p.append_monomial(c, &e);
+    }
+    Ok(p)
+}
+
+impl Serialize for Coefficient {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = match self {
+            Coefficient::Rational(r) => {
+                CoefficientData::Rational(r.numerator().to_string(), r.denominator().to_string())
+            }
+            Coefficient::FiniteField(n, fi) => {
+                let f = State::get_finite_field(*fi);
+                CoefficientData::FiniteField(f.from_element(n), f.get_prime())
+            }
+            Coefficient::RationalPolynomial(r) => {
+                let variables = r
+                    .numerator
+                    .variables
+                    .iter()
+                    .map(|v| match v {
+                        Variable::Symbol(s) => VariableData::Symbol(*s),
+                        Variable::Temporary(t) => VariableData::Temporary(*t),
+                        Variable::Array(s, i) => VariableData::Array(*s, *i),
+                        Variable::Function(s, a) => VariableData::Function(*s, a.as_ref().clone()),
+                        Variable::Other(a) => VariableData::Other(a.as_ref().clone()),
+                    })
+                    .collect();
+
+                CoefficientData::RationalPolynomial(
+                    variables,
+                    polynomial_to_data(&r.numerator),
+                    polynomial_to_data(&r.denominator),
+                )
+            }
+            Coefficient::Float(f) => {
+                let (m, e) = f.to_parts();
+                CoefficientData::Float(m.to_string(), e, f.prec())
+            }
+            Coefficient::Complex(c) => CoefficientData::Complex(c.clone()),
+        };
+
+        data.serialize(serializer)
+    }
+}
+
+impl CoefficientData {
+    fn into_coefficient(self) -> Result<Coefficient, String> {
+        Ok(match self {
+            CoefficientData::Rational(n, d) => {
+                let n: Integer = n.parse().map_err(|_| format!("Invalid numerator {}", n))?;
+                let d: Integer = d
+                    .parse()
+                    .map_err(|_| format!("Invalid denominator {}", d))?;
+                if d.is_zero() {
+                    return Err("Denominator of a rational number is zero".into());
+                }
+                Coefficient::Rational(Rational::from((n, d)))
+            }
+            CoefficientData::FiniteField(n, p) => {
+                // the finite field only supports odd primes
+                if p < 3 || p % 2 == 0 {
+                    return Err(format!("Invalid prime {}", p));
+                }
+                let fi = State::get_or_insert_finite_field(Zp64::new(p));
+                let f = State::get_finite_field(fi);
+                Coefficient::FiniteField(f.to_element(n % p), fi)
+            }
+            CoefficientData::RationalPolynomial(vars, num, den) => {
+                let variables: Arc<Vec<_>> = Arc::new(
+                    vars.into_iter()
+                        .map(|v| match v {
+                            VariableData::Symbol(s) => Variable::Symbol(s),
+                            VariableData::Temporary(t) => Variable::Temporary(t),
+                            VariableData::Array(s, i) => Variable::Array(s, i),
+                            VariableData::Function(s, a) => Variable::Function(s, Arc::new(a)),
+                            VariableData::Other(a) => Variable::Other(Arc::new(a)),
+                        })
+                        .collect(),
+                );
+
+                let numerator = polynomial_from_data(num, &variables)?;
+                let denominator = polynomial_from_data(den, &variables)?;
+                if denominator.is_zero() {
+                    return Err("Denominator of a rational polynomial is zero".into());
+                }
+
+                Coefficient::RationalPolynomial(RationalPolynomial {
+                    numerator,
+                    denominator,
+                })
+            }
+            CoefficientData::Float(m, e, prec) => {
+                let m: MultiPrecisionInteger = m
+                    .parse()
+                    .map_err(|_| format!("Invalid float mantissa {}", m))?;
+                if prec == 0 {
+                    return Err("The precision of a float must be positive".into());
+                }
+                Coefficient::Float(Float::from_parts(m, e, prec))
+            }
+            CoefficientData::Complex(c) => {
+                let (re, im) = *c;
+                Coefficient::complex(re, im)
+            }
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Coefficient {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        CoefficientData::deserialize(deserializer)?
+            .into_coefficient()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl<'a> Serialize for CoefficientView<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_owned().serialize(serializer)
+    }
+}
+
+/// The tree of an atom. Symbols are stored by name, so the order of the
+/// arguments is restored by normalizing the atom on load.
+#[derive(Serialize, Deserialize)]
+enum AtomData {
+    Num(Coefficient),
+    Var(Symbol),
+    Fun(Symbol, Vec<AtomData>),
+    Pow(Box<(AtomData, AtomData)>),
+    Mul(Vec<AtomData>),
+    Add(Vec<AtomData>),
+}
+
+impl<'a> From<AtomView<'a>> for AtomData {
+    fn from(a: AtomView<'a>) -> Self {
+        match a {
+            AtomView::Num(n) => AtomData::Num(n.get_coeff_view().to_owned()),
+            AtomView::Var(v) => AtomData::Var(v.get_symbol()),
+            AtomView::Fun(f) => AtomData::Fun(f.get_symbol(), f.iter().map(Into::into).collect()),
+            AtomView::Pow(p) => {
+                let (b, e) = p.get_base_exp();
+                AtomData::Pow(Box::new((b.into(), e.into())))
+            }
+            AtomView::Mul(m) => AtomData::Mul(m.iter().map(Into::into).collect()),
+            AtomView::Add(a) => AtomData::Add(a.iter().map(Into::into).collect()),
+        }
+    }
+}
+
+impl AtomData {
+    fn to_atom_impl(&self, ws: &Workspace, out: &mut Atom) {
+        match self {
+            AtomData::Num(n) => {
+                out.to_num(n.clone());
+            }
+            AtomData::Var(s) => {
+                out.to_var(*s);
+            }
+            AtomData::Fun(s, args) => {
+                let mut f = ws.new_atom();
+                let fun = f.to_fun(*s);
+                for arg in args {
+                    let mut arg_h = ws.new_atom();
+                    arg.to_atom_impl(ws, &mut arg_h);
+                    fun.add_arg(arg_h.as_view());
+                }
+                f.as_view().normalize(ws, out);
+            }
+            AtomData::Pow(p) => {
+                let mut base = ws.new_atom();
+                p.0.to_atom_impl(ws, &mut base);
+                let mut exp = ws.new_atom();
+                p.1.to_atom_impl(ws, &mut exp);
+
+                let mut pow_h = ws.new_atom();
+                pow_h.to_pow(base.as_view(), exp.as_view());
+                pow_h.as_view().normalize(ws, out);
+            }
+            AtomData::Mul(args) => {
+                let mut mul_h = ws.new_atom();
+                let mul = mul_h.to_mul();
+                for arg in args {
+                    let mut arg_h = ws.new_atom();
+                    arg.to_atom_impl(ws, &mut arg_h);
+                    mul.extend(arg_h.as_view());
+                }
+                mul_h.as_view().normalize(ws, out);
+            }
+            AtomData::Add(args) => {
+                let mut add_h = ws.new_atom();
+                let add = add_h.to_add();
+                for arg in args {
+                    let mut arg_h = ws.new_atom();
+                    arg.to_atom_impl(ws, &mut arg_h);
+                    add.extend(arg_h.as_view());
+                }
+                add_h.as_view().normalize(ws, out);
+            }
+        }
+    }
+
+    fn to_atom(&self) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = Atom::new();
+            self.to_atom_impl(ws, &mut out);
+            out
+        })
+    }
+}
+
+impl<'a> Serialize for AtomView<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        AtomData::from(*self).serialize(serializer)
+    }
+}
+
+impl Serialize for Atom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_view().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Atom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(AtomData::deserialize(deserializer)?.to_atom())
+    }
+}
+
+/// A pattern without transformers.
+#[derive(Serialize, Deserialize)]
+enum PatternData {
+    Literal(Atom),
+    Wildcard(Symbol),
+    Fn(Symbol, Vec<PatternData>),
+    Pow(Box<(PatternData, PatternData)>),
+    Mul(Vec<PatternData>),
+    Add(Vec<PatternData>),
+}
+
+impl TryFrom<&Pattern> for PatternData {
+    type Error = &'static str;
+
+    fn try_from(p: &Pattern) -> Result<Self, Self::Error> {
+        let convert = |args: &[Pattern]| {
+            args.iter()
+                .map(PatternData::try_from)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(match p {
+            Pattern::Literal(a) => PatternData::Literal(a.clone()),
+            Pattern::Wildcard(s) => PatternData::Wildcard(*s),
+            Pattern::Fn(s, args) => PatternData::Fn(*s, convert(args)?),
+            Pattern::Pow(p) => {
+                PatternData::Pow(Box::new(((&p[0]).try_into()?, (&p[1]).try_into()?)))
+            }
+            Pattern::Mul(args) => PatternData::Mul(convert(args)?),
+            Pattern::Add(args) => PatternData::Add(convert(args)?),
+            Pattern::Transformer(_) => Err("Cannot serialize a pattern with transformers")?,
+        })
+    }
+}
+
+impl From<PatternData> for Pattern {
+    fn from(p: PatternData) -> Self {
+        let convert = |args: Vec<PatternData>| args.into_iter().map(Into::into).collect();
+
+        match p {
+            PatternData::Literal(a) => Pattern::Literal(a),
+            PatternData::Wildcard(s) => Pattern::Wildcard(s),
+            PatternData::Fn(s, args) => Pattern::Fn(s, convert(args)),
+            PatternData::Pow(p) => Pattern::Pow(Box::new([p.0.into(), p.1.into()])),
+            PatternData::Mul(args) => Pattern::Mul(convert(args)),
+            PatternData::Add(args) => Pattern::Add(convert(args)),
+        }
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PatternData::try_from(self)
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(PatternData::deserialize(deserializer)?.into())
+    }
+}
+
+/// A pattern restriction without user-defined functions.
+#[derive(Serialize, Deserialize)]
+enum PatternRestrictionData {
+    Length(usize, Option<usize>),
+    IsAtomType(crate::id::AtomType),
+    IsLiteralWildcard(Symbol),
+    Ground,
+    OneOf(Vec<Atom>),
+    NotGreedy,
+}
+
+impl Serialize for PatternRestriction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = match self {
+            PatternRestriction::Length(min, max) => PatternRestrictionData::Length(*min, *max),
+            PatternRestriction::IsAtomType(t) => PatternRestrictionData::IsAtomType(*t),
+            PatternRestriction::IsLiteralWildcard(s) => {
+                PatternRestrictionData::IsLiteralWildcard(*s)
+            }
+            PatternRestriction::Ground => PatternRestrictionData::Ground,
+            PatternRestriction::OneOf(a) => PatternRestrictionData::OneOf(a.clone()),
+            PatternRestriction::NotGreedy => PatternRestrictionData::NotGreedy,
+            PatternRestriction::Filter(_) | PatternRestriction::Cmp(_, _) => Err(
+                S::Error::custom("Cannot serialize a restriction with a user-defined function"),
+            )?,
+        };
+
+        data.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PatternRestriction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match PatternRestrictionData::deserialize(deserializer)? {
+            PatternRestrictionData::Length(min, max) => PatternRestriction::Length(min, max),
+            PatternRestrictionData::IsAtomType(t) => PatternRestriction::IsAtomType(t),
+            PatternRestrictionData::IsLiteralWildcard(s) => {
+                PatternRestriction::IsLiteralWildcard(s)
+            }
+            PatternRestrictionData::Ground => PatternRestriction::Ground,
+            PatternRestrictionData::OneOf(a) => PatternRestriction::OneOf(a),
+            PatternRestrictionData::NotGreedy => PatternRestriction::NotGreedy,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coefficient::Coefficient,
+        domains::{
+            finite_field::{FiniteFieldCore, Zp64},
+            float::Float,
+        },
+        id::{AtomType, Condition, Pattern, PatternRestriction, WildcardAndRestriction},
+        representations::{Atom, FunctionBuilder, Symbol},
+        state::{FunctionAttribute, State},
+    };
+
+    #[test]
+    fn atom_roundtrip() {
+        let f =
+            State::get_symbol_with_attributes("ser_f", vec![FunctionAttribute::Symmetric]).unwrap();
+        let a = Atom::parse("ser_f(ser_y, ser_x)^(3/7) + 123456789012345678901234567890*ser_x")
+            .unwrap();
+        let bytes = bincode::serialize(&a).unwrap();
+        let b: Atom = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(a, b);
+
+        let fi = State::get_or_insert_finite_field(Zp64::new(17));
+        let ff = State::get_finite_field(fi);
+        let c = FunctionBuilder::new(f)
+            .add_arg(&Atom::new_num(Coefficient::FiniteField(
+                ff.to_element(5),
+                fi,
+            )))
+            .add_arg(&Atom::new_num(Coefficient::Float(Float::from_f64(
+                0.1, 100,
+            ))))
+            .add_arg(&Atom::new_num(Coefficient::complex(
+                1.into(),
+                (2, 3).into(),
+            )))
+            .finish();
+        let bytes = bincode::serialize(&c).unwrap();
+        let d: Atom = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(c, d);
+
+        let e = Atom::parse("ser_x*ser_z/(1+ser_y)")
+            .unwrap()
+            .set_coefficient_ring(&std::sync::Arc::new(vec![
+                State::get_symbol("ser_y").into(),
+                State::get_symbol("ser_z").into(),
+            ]));
+        let bytes = bincode::serialize(&e).unwrap();
+        let g: Atom = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(e.to_string(), g.to_string());
+
+        let bytes = bincode::serialize(&f).unwrap();
+        let f2: Symbol = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(f, f2);
+    }
+
+    #[test]
+    fn invalid_prime() {
+        for p in [0, 1, 2, 4] {
+            let bytes = bincode::serialize(&super::CoefficientData::FiniteField(1, p)).unwrap();
+            assert!(bincode::deserialize::<Coefficient>(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn symbol_remapping() {
+        // a symbol exported from another state is registered by name
+        let bytes =
+            bincode::serialize(&("ser_remap", 0u8, vec![FunctionAttribute::Linear])).unwrap();
+        let s: Symbol = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(State::get_name(s), "ser_remap");
+        assert!(s.is_linear());
+
+        let bytes =
+            bincode::serialize(&("ser_remap", 0u8, Vec::<FunctionAttribute>::new())).unwrap();
+        assert!(bincode::deserialize::<Symbol>(&bytes).is_err());
+    }
+
+    #[test]
+    fn pattern_roundtrip() {
+        let p = Pattern::parse("ser_f(ser_x_, ser_y__)*ser_x_^2").unwrap();
+        let bytes = bincode::serialize(&p).unwrap();
+        let q: Pattern = bincode::deserialize(&bytes).unwrap();
+        let expr = Atom::parse("ser_g(1,2,3)*ser_f(4,5,6)*4^2").unwrap();
+        let rhs = Pattern::parse("ser_y__").unwrap();
+        assert_eq!(
+            p.replace_all(expr.as_view(), &rhs, None, None),
+            q.replace_all(expr.as_view(), &rhs, None, None)
+        );
+
+        let x = State::get_symbol("ser_x_");
+        let cond: Condition<WildcardAndRestriction> =
+            Condition::from((x, PatternRestriction::IsAtomType(AtomType::Num)))
+                & !Condition::from((x, PatternRestriction::Length(1, Some(2))));
+        let bytes = bincode::serialize(&cond).unwrap();
+        let _: Condition<WildcardAndRestriction> = bincode::deserialize(&bytes).unwrap();
+
+        let filter: Condition<WildcardAndRestriction> =
+            (x, PatternRestriction::Filter(Box::new(|_| true))).into();
+        assert!(bincode::serialize(&filter).is_err());
+    }
+}
@@ -1,11 +1,12 @@
 use std::hash::Hash;
 use std::mem::ManuallyDrop;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::RwLock;
 use std::thread::LocalKey;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::hash_map::Entry,
+    marker::PhantomData,
     ops::{Deref, DerefMut},
 };
 
@@ -14,28 +15,142 @@ use append_only_vec::AppendOnlyVec;
 use once_cell::sync::Lazy;
 use smartstring::alias::String;
 
-use crate::domains::finite_field::Zp64;
+use crate::domains::finite_field::{ExtensionField, Zp64};
 use crate::{
     coefficient::Coefficient,
-    domains::finite_field::FiniteFieldCore,
+    dimension::Dimension,
+    domains::{finite_field::FiniteFieldCore, rational::RationalField},
+    poly::polynomial::MultivariatePolynomial,
     representations::{Atom, Symbol},
     LicenseManager, LICENSE_MANAGER,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct FiniteFieldIndex(pub(crate) usize);
 
+/// The index of a registered finite field extension `GF(p^n)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionFieldIndex(pub(crate) usize);
+
+/// A map from the finite field indices of an exported state, created by
+/// [`State::dump_fields`], to the indices of the same fields in the current state.
+#[derive(Debug, Clone, Default)]
+pub struct FieldRemap {
+    map: HashMap<usize, FiniteFieldIndex>,
+}
+
+impl FieldRemap {
+    /// Get the index in the current state of the exported field index `fi`.
+    pub fn get(&self, fi: FiniteFieldIndex) -> Option<FiniteFieldIndex> {
+        self.map.get(&fi.0).cloned()
+    }
+
+    /// Returns `true` iff no index changes.
+    pub fn is_identity(&self) -> bool {
+        self.map.iter().all(|(k, v)| *k == v.0)
+    }
+}
+
+/// The domain in which numeric literals are interpreted when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoefficientDomain {
+    /// Numbers are rationals.
+    #[default]
+    Rational,
+    /// Numbers are immediately reduced into the given finite field.
+    FiniteField(FiniteFieldIndex),
+}
+
 #[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serialization",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 pub enum FunctionAttribute {
     Symmetric,
     Antisymmetric,
     Linear,
+    /// The function is linear only in the given arguments.
+    /// Create it with [`FunctionAttribute::linear_in`].
+    LinearIn(LinearArguments),
+    /// The symbol does not commute with other noncommutative symbols, so that
+    /// the order of noncommutative factors in a product is preserved. This
+    /// attribute can also be given to variables.
+    NonCommutative,
+    /// The symbol is real, so that it is invariant under complex conjugation.
+    /// A real function commutes with conjugation, i.e. `conj(f(x)) = f(conj(x))`.
+    Real,
+}
+
+/// The positions of the arguments a function is linear in,
+/// stored as a mask in which bit `i` is set for argument `i`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serialization",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct LinearArguments(u32);
+
+impl LinearArguments {
+    /// The bit that is set for positions that are not smaller than
+    /// [`Symbol::MAX_LINEAR_ARGUMENTS`], which are rejected when the symbol is defined.
+    const OUT_OF_RANGE: u32 = 1 << 31;
+
+    pub(crate) fn from_mask(mask: u32) -> LinearArguments {
+        LinearArguments(mask)
+    }
+
+    pub(crate) fn get_mask(&self) -> u32 {
+        self.0
+    }
+
+    /// Get the positions of the arguments.
+    pub fn positions(&self) -> Vec<usize> {
+        (0..Symbol::MAX_LINEAR_ARGUMENTS)
+            .filter(|i| self.0 & (1 << i) != 0)
+            .collect()
+    }
+}
+
+impl FunctionAttribute {
+    /// Create a [`FunctionAttribute::LinearIn`] attribute from the positions of
+    /// the arguments the function is linear in. Positions that are not smaller
+    /// than [`Symbol::MAX_LINEAR_ARGUMENTS`] are rejected when the symbol is defined.
+    pub fn linear_in(args: &[usize]) -> FunctionAttribute {
+        let mut mask = 0;
+        for &i in args {
+            mask |= if i < Symbol::MAX_LINEAR_ARGUMENTS {
+                1 << i
+            } else {
+                LinearArguments::OUT_OF_RANGE
+            };
+        }
+        FunctionAttribute::LinearIn(LinearArguments(mask))
+    }
 }
 
 static STATE: Lazy<RwLock<State>> = Lazy::new(|| RwLock::new(State::new()));
 static ID_TO_STR: AppendOnlyVec<String> = AppendOnlyVec::<String>::new();
 static FINITE_FIELDS: AppendOnlyVec<Zp64> = AppendOnlyVec::<Zp64>::new();
+static EXTENSION_FIELDS: AppendOnlyVec<ExtensionField> = AppendOnlyVec::<ExtensionField>::new();
 static SYMBOL_OFFSET: AtomicUsize = AtomicUsize::new(0);
+/// Incremented whenever existing symbols may be invalidated, i.e., on a reset or restore.
+static SYMBOL_GENERATION: AtomicUsize = AtomicUsize::new(0);
+/// The term order is not stored in the state, since the state may be locked during normalization.
+static TERM_ORDER: RwLock<TermOrder> = RwLock::new(TermOrder::Default);
+static CUSTOM_TERM_ORDER: AtomicBool = AtomicBool::new(false);
+/// The minimal polynomials of symbols that represent algebraic numbers, which are not stored
+/// in the state for the same reason as the term order.
+static MINIMAL_POLYNOMIALS: RwLock<Vec<(Symbol, MultivariatePolynomial<RationalField, u8>)>> =
+    RwLock::new(Vec::new());
+static HAS_MINIMAL_POLYNOMIALS: AtomicBool = AtomicBool::new(false);
+/// Set when the first noncommutative symbol is defined, so that products can skip the
+/// search for noncommutative factors before that. It is never cleared, since existing
+/// atoms may still contain noncommutative symbols after a reset or restore.
+static HAS_NONCOMMUTATIVE_SYMBOLS: AtomicBool = AtomicBool::new(false);
+/// The maximum number of digits of printed large numbers, or 0 if they are printed exactly.
+/// It is not stored in the state, so that printing does not need to lock it.
+static MAX_DISPLAY_DIGITS: AtomicUsize = AtomicUsize::new(0);
 
 thread_local!(
     /// A thread-local workspace, that stores recyclable atoms. By making it const and
@@ -45,9 +160,104 @@ thread_local!(
     static WORKSPACE: ManuallyDrop<Workspace> = const { ManuallyDrop::new(Workspace::new()) }
 );
 
+/// The convention for the order of terms in a sum and of factors in a product,
+/// set with [`State::set_term_order`]. Ties are broken by the default order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TermOrder {
+    /// The default order, which depends on the order in which symbols were defined.
+    #[default]
+    Default,
+    /// Order terms by their degree in the first variable, highest first, then by their degree
+    /// in the second variable, etc. The variables are placed in front of other factors
+    /// in a product, in the given order.
+    Lexicographic(Vec<Symbol>),
+    /// Order terms by their total degree in the variables, highest first, and then lexicographically.
+    /// The variables are placed in front of other factors in a product, in the given order.
+    GradedLexicographic(Vec<Symbol>),
+}
+
+/// The scheme that determines which newly defined symbols are wildcards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WildcardScheme {
+    /// Symbols whose name ends in `_` are wildcards, with a level equal
+    /// to the number of trailing underscores.
+    #[default]
+    Suffix,
+    /// Only symbols registered with [`State::get_wildcard`] are wildcards.
+    Explicit,
+}
+
+/// A copy of the symbol table and settings of the global state, created with [`State::snapshot`].
+/// The finite field and extension field tables are not copied, as they are append-only.
+#[derive(Clone)]
+pub struct StateSnapshot {
+    str_to_id: HashMap<String, Symbol>,
+    function_arity: HashMap<u32, usize>,
+    latex_names: HashMap<u32, String>,
+    derivative_rules: HashMap<u32, (Vec<Symbol>, Vec<Atom>)>,
+    dimensions: HashMap<u32, Dimension>,
+    coefficient_domain: CoefficientDomain,
+    wildcard_scheme: WildcardScheme,
+    max_display_digits: Option<usize>,
+    term_order: TermOrder,
+    minimal_polynomials: Vec<(Symbol, MultivariatePolynomial<RationalField, u8>)>,
+    symbol_offset: usize,
+}
+
+/// A handle to the global state for single-threaded use, created with [`State::local`].
+/// Symbol lookups are cached in the handle, which is invalidated when the
+/// global state is reset or restored.
+pub struct LocalState {
+    symbols: RefCell<HashMap<String, Symbol>>,
+    generation: Cell<usize>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl LocalState {
+    /// Get the symbol for a certain name if the name is already registered,
+    /// else register it and return a new symbol without attributes.
+    /// See [`State::get_symbol`].
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::state::State;
+    /// let local = State::local();
+    /// assert_eq!(local.get_symbol("x"), State::get_symbol("x"));
+    ///
+    /// let snapshot = State::snapshot();
+    /// local.get_symbol("y");
+    /// State::restore(snapshot);
+    /// assert!(State::find_symbol("y").is_none());
+    /// local.get_symbol("y");
+    /// assert!(State::find_symbol("y").is_some());
+    /// ```
+    pub fn get_symbol<S: AsRef<str>>(&self, name: S) -> Symbol {
+        let generation = SYMBOL_GENERATION.load(Ordering::Relaxed);
+        let mut symbols = self.symbols.borrow_mut();
+        if self.generation.get() != generation {
+            symbols.clear();
+            self.generation.set(generation);
+        }
+
+        if let Some(s) = symbols.get(name.as_ref()) {
+            return *s;
+        }
+
+        let s = State::get_symbol(name.as_ref());
+        symbols.insert(name.as_ref().into(), s);
+        s
+    }
+}
+
 /// A global state, that stores mappings from variable and function names to ids.
 pub struct State {
     str_to_id: HashMap<String, Symbol>,
+    function_arity: HashMap<u32, usize>,
+    latex_names: HashMap<u32, String>,
+    derivative_rules: HashMap<u32, (Vec<Symbol>, Vec<Atom>)>,
+    dimensions: HashMap<u32, Dimension>,
+    coefficient_domain: CoefficientDomain,
+    wildcard_scheme: WildcardScheme,
 }
 
 impl Default for State {
@@ -57,20 +267,52 @@ impl Default for State {
 }
 
 impl State {
-    pub const ARG: Symbol = Symbol::init_fn(0, 0, false, false, false);
-    pub const COEFF: Symbol = Symbol::init_fn(1, 0, false, false, false);
-    pub const EXP: Symbol = Symbol::init_fn(2, 0, false, false, false);
-    pub const LOG: Symbol = Symbol::init_fn(3, 0, false, false, false);
-    pub const SIN: Symbol = Symbol::init_fn(4, 0, false, false, false);
-    pub const COS: Symbol = Symbol::init_fn(5, 0, false, false, false);
-    pub const SQRT: Symbol = Symbol::init_fn(6, 0, false, false, false);
-    pub const DERIVATIVE: Symbol = Symbol::init_fn(7, 0, false, false, false);
+    pub const ARG: Symbol = Symbol::init_fn(0, 0, false, false, false, false);
+    pub const COEFF: Symbol = Symbol::init_fn(1, 0, false, false, false, false);
+    pub const EXP: Symbol = Symbol::init_fn(2, 0, false, false, false, false);
+    pub const LOG: Symbol = Symbol::init_fn(3, 0, false, false, false, false);
+    pub const SIN: Symbol = Symbol::init_fn(4, 0, false, false, false, false);
+    pub const COS: Symbol = Symbol::init_fn(5, 0, false, false, false, false);
+    pub const SQRT: Symbol = Symbol::init_fn(6, 0, false, false, false, false);
+    pub const DERIVATIVE: Symbol = Symbol::init_fn(7, 0, false, false, false, false);
     pub const E: Symbol = Symbol::init_var(8, 0);
     pub const I: Symbol = Symbol::init_var(9, 0);
     pub const PI: Symbol = Symbol::init_var(10, 0);
+    pub const PIECEWISE: Symbol = Symbol::init_fn(11, 0, false, false, false, false);
+    pub const EULER_GAMMA: Symbol = Symbol::init_var(12, 0);
+    /// The built-in list function, that is parsed and printed as `{a, b, c}`. Its
+    /// name `{}` cannot be written as a function name.
+    ///
+    /// Lists are functions and not a separate atom type, so all other code, such as
+    /// pattern matching of arguments, polynomial conversion and derivatives, treats
+    /// a list as an ordinary function. Use [`AtomView::map_list`](crate::representations::AtomView::map_list)
+    /// to apply an operation, such as a derivative, to every element. Lists only differ
+    /// from other functions in that:
+    /// - a wildcard function name does not match a list;
+    /// - equal lists are not combined into a power in a product or into a multiple in a sum.
+    pub const LIST: Symbol = Symbol::init_fn(13, 0, false, false, false, false);
+    pub const CONJ: Symbol = Symbol::init_fn(14, 0, false, false, false, false);
+    pub const RE: Symbol = Symbol::init_fn(15, 0, false, false, false, false);
+    pub const IM: Symbol = Symbol::init_fn(16, 0, false, false, false, false);
 
-    pub const BUILTIN_VAR_LIST: [&'static str; 11] = [
-        "arg", "coeff", "exp", "log", "sin", "cos", "sqrt", "der", "𝑒", "𝑖", "𝜋",
+    pub const BUILTIN_VAR_LIST: [&'static str; 17] = [
+        "arg",
+        "coeff",
+        "exp",
+        "log",
+        "sin",
+        "cos",
+        "sqrt",
+        "der",
+        "𝑒",
+        "𝑖",
+        "𝜋",
+        "piecewise",
+        "𝛾",
+        "{}",
+        "conj",
+        "re",
+        "im",
     ];
 
     fn new() -> State {
@@ -78,6 +320,12 @@ impl State {
 
         let mut state = State {
             str_to_id: HashMap::new(),
+            function_arity: HashMap::new(),
+            latex_names: HashMap::new(),
+            derivative_rules: HashMap::new(),
+            dimensions: HashMap::new(),
+            coefficient_domain: CoefficientDomain::Rational,
+            wildcard_scheme: WildcardScheme::Suffix,
         };
 
         for x in Self::BUILTIN_VAR_LIST {
@@ -106,13 +354,104 @@ impl State {
         let mut state = STATE.write().unwrap();
 
         state.str_to_id.clear();
+        state.function_arity.clear();
+        state.latex_names.clear();
+        state.derivative_rules.clear();
+        state.dimensions.clear();
+        state.coefficient_domain = CoefficientDomain::Rational;
+        state.wildcard_scheme = WildcardScheme::Suffix;
+        MAX_DISPLAY_DIGITS.store(0, Ordering::Relaxed);
+        Self::set_term_order(TermOrder::Default);
+        Self::set_minimal_polynomials(vec![]);
         SYMBOL_OFFSET.store(ID_TO_STR.len(), Ordering::Relaxed);
+        SYMBOL_GENERATION.fetch_add(1, Ordering::Relaxed);
 
         for x in Self::BUILTIN_VAR_LIST {
             state.get_symbol_impl(x);
         }
     }
 
+    /// Take a snapshot of the symbol table and settings of the global state,
+    /// so that they can be rolled back with [`State::restore`].
+    pub fn snapshot() -> StateSnapshot {
+        let state = STATE.read().unwrap();
+
+        StateSnapshot {
+            str_to_id: state.str_to_id.clone(),
+            function_arity: state.function_arity.clone(),
+            latex_names: state.latex_names.clone(),
+            derivative_rules: state.derivative_rules.clone(),
+            dimensions: state.dimensions.clone(),
+            coefficient_domain: state.coefficient_domain,
+            wildcard_scheme: state.wildcard_scheme,
+            max_display_digits: Self::get_max_display_digits(),
+            term_order: Self::get_term_order(),
+            minimal_polynomials: MINIMAL_POLYNOMIALS.read().unwrap().clone(),
+            symbol_offset: SYMBOL_OFFSET.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Restore the symbol table and settings of the global state to those in `snapshot`.
+    /// Symbols that were defined after the snapshot are no longer found by name and
+    /// are given a new identifier when they are defined again. Their names remain
+    /// accessible, so that existing atoms that contain them can still be printed.
+    ///
+    /// Since the state is global, this also rolls back symbols defined by other threads
+    /// after the snapshot was taken.
+    ///
+    /// Finite fields and extension fields are not rolled back: atoms refer to them by
+    /// their index, so their tables are append-only and fields registered after the
+    /// snapshot stay valid.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{domains::finite_field::{FiniteFieldCore, Zp64}, state::State};
+    /// State::get_symbol("x");
+    /// let snapshot = State::snapshot();
+    /// State::get_symbol("y");
+    /// let fi = State::get_or_insert_finite_field(Zp64::new(101));
+    /// State::restore(snapshot);
+    /// assert!(State::find_symbol("x").is_some());
+    /// assert!(State::find_symbol("y").is_none());
+    /// assert_eq!(State::get_finite_field(fi).get_prime(), 101);
+    /// ```
+    pub fn restore(snapshot: StateSnapshot) {
+        let mut state = STATE.write().unwrap();
+
+        if snapshot.symbol_offset != SYMBOL_OFFSET.load(Ordering::Relaxed) {
+            panic!("Cannot restore a snapshot that was taken before the state was reset");
+        }
+
+        state.str_to_id = snapshot.str_to_id;
+        state.function_arity = snapshot.function_arity;
+        state.latex_names = snapshot.latex_names;
+        state.derivative_rules = snapshot.derivative_rules;
+        state.dimensions = snapshot.dimensions;
+        state.coefficient_domain = snapshot.coefficient_domain;
+        state.wildcard_scheme = snapshot.wildcard_scheme;
+        SYMBOL_GENERATION.fetch_add(1, Ordering::Relaxed);
+        MAX_DISPLAY_DIGITS.store(snapshot.max_display_digits.unwrap_or(0), Ordering::Relaxed);
+        Self::set_term_order(snapshot.term_order);
+        Self::set_minimal_polynomials(snapshot.minimal_polynomials);
+    }
+
+    /// Get a handle for single-threaded use, that caches symbol lookups
+    /// so that repeated lookups do not need to lock the global state.
+    /// The handle cannot be sent to or shared with other threads:
+    ///
+    /// ```compile_fail
+    /// # use symbolica::state::State;
+    /// let local = State::local();
+    /// std::thread::spawn(move || local.get_symbol("x"));
+    /// ```
+    pub fn local() -> LocalState {
+        LocalState {
+            symbols: RefCell::new(HashMap::new()),
+            generation: Cell::new(SYMBOL_GENERATION.load(Ordering::Relaxed)),
+            _not_send: PhantomData,
+        }
+    }
+
     /// Iterate over all defined symbols.
     pub fn symbol_iter() -> impl Iterator<Item = &'static str> {
         ID_TO_STR
@@ -134,15 +473,41 @@ impl State {
         STATE.write().unwrap().get_symbol_impl(name.as_ref())
     }
 
+    /// Get the symbol for a certain name if the name is registered.
+    pub fn find_symbol<S: AsRef<str>>(name: S) -> Option<Symbol> {
+        STATE.read().unwrap().str_to_id.get(name.as_ref()).cloned()
+    }
+
     pub(crate) fn get_symbol_impl(&mut self, name: &str) -> Symbol {
+        let wildcard_level = self.get_wildcard_level_impl(name);
+        self.get_symbol_with_level_impl(name, wildcard_level)
+    }
+
+    fn get_symbol_with_level_impl(&mut self, name: &str, wildcard_level: u8) -> Symbol {
         match self.str_to_id.entry(name.into()) {
             Entry::Occupied(o) => *o.get(),
             Entry::Vacant(v) => {
                 let offset = SYMBOL_OFFSET.load(Ordering::Relaxed);
-                if ID_TO_STR.len() - offset == u32::MAX as usize - 1 {
+                if ID_TO_STR.len() - offset > Symbol::MAX_ID as usize {
                     panic!("Too many variables defined");
                 }
 
+                // there is no synchronization issue since only one thread can insert at a time
+                // as the state itself is behind a mutex
+                let id = ID_TO_STR.push(name.into()) - offset;
+
+                let new_symbol = Symbol::init_var(id as u32, wildcard_level);
+                v.insert(new_symbol);
+                new_symbol
+            }
+        }
+    }
+
+    /// Get the wildcard level of a new symbol with the given name,
+    /// according to the current wildcard scheme.
+    fn get_wildcard_level_impl(&self, name: &str) -> u8 {
+        match self.wildcard_scheme {
+            WildcardScheme::Suffix => {
                 let mut wildcard_level = 0;
                 for x in name.chars().rev() {
                     if x != '_' {
@@ -150,18 +515,76 @@ impl State {
                     }
                     wildcard_level += 1;
                 }
+                wildcard_level
+            }
+            WildcardScheme::Explicit => 0,
+        }
+    }
 
-                // there is no synchronization issue since only one thread can insert at a time
-                // as the state itself is behind a mutex
-                let id = ID_TO_STR.push(name.into()) - offset;
+    /// Get the wildcard with a certain name and wildcard level `level`, registering
+    /// it if it does not exist yet. A level of 1 matches a single subexpression, a
+    /// level of 2 a sequence of at least one subexpression and a level of 3 a possibly
+    /// empty sequence. This is the way to define wildcards when the wildcard scheme
+    /// is [`WildcardScheme::Explicit`].
+    ///
+    /// This function will return an error when the name is already registered with
+    /// a different wildcard level.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::state::State;
+    /// assert_eq!(State::get_wildcard("x", 2).unwrap().get_wildcard_level(), 2);
+    /// State::get_symbol("y");
+    /// assert!(State::get_wildcard("y", 1).is_err());
+    /// ```
+    pub fn get_wildcard<S: AsRef<str>>(name: S, level: u8) -> Result<Symbol, String> {
+        if level == 0 {
+            return Err("The wildcard level must be positive".into());
+        }
 
-                let new_symbol = Symbol::init_var(id as u32, wildcard_level);
-                v.insert(new_symbol);
-                new_symbol
-            }
+        let s = STATE
+            .write()
+            .unwrap()
+            .get_symbol_with_level_impl(name.as_ref(), level);
+
+        if s.get_wildcard_level() != level {
+            Err(format!(
+                "Symbol {} is already defined with wildcard level {}",
+                name.as_ref(),
+                s.get_wildcard_level()
+            )
+            .into())
+        } else {
+            Ok(s)
         }
     }
 
+    /// Set the scheme that determines which newly defined symbols are wildcards.
+    /// Symbols that are already defined keep their wildcard level.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{id::Pattern, representations::Atom, state::{State, WildcardScheme}};
+    /// State::set_wildcard_scheme(WildcardScheme::Explicit);
+    /// assert_eq!(State::get_symbol("n_").get_wildcard_level(), 0);
+    /// State::get_wildcard("x", 1).unwrap();
+    ///
+    /// let expr = Atom::parse("f(y, n_) + f(y, z)").unwrap();
+    /// let pat = Pattern::parse("f(x, n_)").unwrap();
+    /// let rhs = Pattern::parse("x^2").unwrap();
+    /// let r = pat.replace_all(expr.as_view(), &rhs, None, None);
+    /// assert_eq!(r, Atom::parse("y^2 + f(y, z)").unwrap());
+    /// State::set_wildcard_scheme(WildcardScheme::Suffix);
+    /// ```
+    pub fn set_wildcard_scheme(scheme: WildcardScheme) {
+        STATE.write().unwrap().wildcard_scheme = scheme;
+    }
+
+    /// Get the scheme that determines which newly defined symbols are wildcards.
+    pub fn get_wildcard_scheme() -> WildcardScheme {
+        STATE.read().unwrap().wildcard_scheme
+    }
+
     /// Get the symbol for a certain name if the name is already registered,
     /// else register it and return a new symbol with the given attributes.
     ///
@@ -182,6 +605,9 @@ impl State {
         name: &str,
         attributes: Vec<FunctionAttribute>,
     ) -> Result<Symbol, String> {
+        let wildcard_level = self.get_wildcard_level_impl(name);
+        let (is_linear, linear_arguments) = Self::get_linear_arguments(name, &attributes)?;
+
         match self.str_to_id.entry(name.into()) {
             Entry::Occupied(o) => {
                 let r = *o.get();
@@ -191,8 +617,11 @@ impl State {
                     r.get_wildcard_level(),
                     attributes.contains(&FunctionAttribute::Symmetric),
                     attributes.contains(&FunctionAttribute::Antisymmetric),
-                    attributes.contains(&FunctionAttribute::Linear),
-                );
+                    is_linear,
+                    attributes.contains(&FunctionAttribute::NonCommutative),
+                )
+                .with_real(attributes.contains(&FunctionAttribute::Real))
+                .with_linear_arguments(linear_arguments);
 
                 if r == new_id {
                     Ok(r)
@@ -202,7 +631,7 @@ impl State {
             }
             Entry::Vacant(v) => {
                 let offset = SYMBOL_OFFSET.load(Ordering::Relaxed);
-                if ID_TO_STR.len() - offset == u32::MAX as usize - 1 {
+                if ID_TO_STR.len() - offset > Symbol::MAX_ID as usize {
                     panic!("Too many variables defined");
                 }
 
@@ -210,21 +639,20 @@ impl State {
                 // as the state itself is behind a mutex
                 let id = ID_TO_STR.push(name.into()) - offset;
 
-                let mut wildcard_level = 0;
-                for x in name.chars().rev() {
-                    if x != '_' {
-                        break;
-                    }
-                    wildcard_level += 1;
-                }
-
                 let new_symbol = Symbol::init_fn(
                     id as u32,
                     wildcard_level,
                     attributes.contains(&FunctionAttribute::Symmetric),
                     attributes.contains(&FunctionAttribute::Antisymmetric),
-                    attributes.contains(&FunctionAttribute::Linear),
-                );
+                    is_linear,
+                    attributes.contains(&FunctionAttribute::NonCommutative),
+                )
+                .with_real(attributes.contains(&FunctionAttribute::Real))
+                .with_linear_arguments(linear_arguments);
+
+                if new_symbol.is_noncommutative() {
+                    HAS_NONCOMMUTATIVE_SYMBOLS.store(true, Ordering::Relaxed);
+                }
 
                 v.insert(new_symbol);
 
@@ -233,15 +661,459 @@ impl State {
         }
     }
 
+    /// Get whether a function with the given attributes is linear, and the mask of
+    /// the arguments it is linear in, where 0 means all arguments.
+    fn get_linear_arguments(
+        name: &str,
+        attributes: &[FunctionAttribute],
+    ) -> Result<(bool, u32), String> {
+        let mut mask = 0;
+        for a in attributes {
+            if let FunctionAttribute::LinearIn(args) = a {
+                let args = args.get_mask();
+                if attributes.contains(&FunctionAttribute::Linear) || mask != 0 || args == 0 {
+                    return Err(
+                        format!("Function {} has conflicting linearity attributes", name).into(),
+                    );
+                }
+
+                if args >> Symbol::MAX_LINEAR_ARGUMENTS != 0 {
+                    return Err(format!(
+                        "Function {} can only be linear in its first {} arguments",
+                        name,
+                        Symbol::MAX_LINEAR_ARGUMENTS
+                    )
+                    .into());
+                }
+                mask = args;
+            }
+        }
+
+        Ok((
+            mask != 0 || attributes.contains(&FunctionAttribute::Linear),
+            mask,
+        ))
+    }
+
+    /// Get the symbol with the given name, wildcard level and attributes, registering
+    /// it if needed. This is used to resolve symbols that are exported from another state.
+    ///
+    /// This function will return an error when the symbol is already registered with
+    /// a different wildcard level or different attributes.
+    pub(crate) fn import_symbol(
+        name: &str,
+        wildcard_level: u8,
+        attributes: &[FunctionAttribute],
+    ) -> Result<Symbol, String> {
+        let s = if !attributes.is_empty() {
+            State::get_symbol_with_attributes(name, attributes.to_vec())?
+        } else if wildcard_level > 0 {
+            State::get_wildcard(name, wildcard_level)?
+        } else {
+            State::get_symbol(name)
+        };
+
+        if s.get_wildcard_level() != wildcard_level
+            || s.is_symmetric() != attributes.contains(&FunctionAttribute::Symmetric)
+            || s.is_antisymmetric() != attributes.contains(&FunctionAttribute::Antisymmetric)
+            || Some((s.is_linear(), s.get_linear_arguments_mask()))
+                != Self::get_linear_arguments(name, attributes).ok()
+            || s.is_noncommutative() != attributes.contains(&FunctionAttribute::NonCommutative)
+            || s.is_real() != attributes.contains(&FunctionAttribute::Real)
+        {
+            Err(format!(
+                "Symbol {} is already defined with different attributes or wildcard level",
+                name
+            )
+            .into())
+        } else {
+            Ok(s)
+        }
+    }
+
+    /// Register the number of arguments `arity` that the function `f` takes. Parsing
+    /// a call to `f` with a different number of arguments will result in an error.
+    ///
+    /// Arity checking is opt-in: functions without a registered arity accept any
+    /// number of arguments.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::State};
+    /// let f = State::get_symbol("f");
+    /// State::set_arity(f, 1);
+    /// assert!(Atom::parse("f(x)").is_ok());
+    /// assert!(Atom::parse("f(x,y)").is_err());
+    /// ```
+    pub fn set_arity(f: Symbol, arity: usize) {
+        STATE
+            .write()
+            .unwrap()
+            .function_arity
+            .insert(f.get_id(), arity);
+    }
+
+    /// Get the registered arity of the function `f`, if any.
+    pub fn get_arity(f: Symbol) -> Option<usize> {
+        STATE.read().unwrap().get_arity_impl(f)
+    }
+
+    pub(crate) fn get_arity_impl(&self, f: Symbol) -> Option<usize> {
+        self.function_arity.get(&f.get_id()).cloned()
+    }
+
+    /// Set the name that is used for the symbol `s` in LaTeX output,
+    /// for example `\alpha` for a symbol `alpha`.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::State};
+    /// State::set_latex_name(State::get_symbol("alpha"), "\\alpha");
+    /// assert_eq!(Atom::parse("alpha^2").unwrap().to_latex(), "\\alpha^{2}");
+    /// ```
+    pub fn set_latex_name(s: Symbol, name: &str) {
+        STATE
+            .write()
+            .unwrap()
+            .latex_names
+            .insert(s.get_id(), name.into());
+    }
+
+    /// Get the name that is used for the symbol `s` in LaTeX output, if one was set.
+    pub fn get_latex_name(s: Symbol) -> Option<std::string::String> {
+        STATE
+            .read()
+            .unwrap()
+            .latex_names
+            .get(&s.get_id())
+            .map(|n| n.to_string())
+    }
+
+    /// Set the physical dimension of the symbol `s`, which is used by [`Atom::check_dimensions`].
+    pub fn set_dimension(s: Symbol, dimension: Dimension) {
+        STATE
+            .write()
+            .unwrap()
+            .dimensions
+            .insert(s.get_id(), dimension);
+    }
+
+    /// Get the physical dimension of the symbol `s`, if one was set.
+    pub fn get_dimension(s: Symbol) -> Option<Dimension> {
+        STATE.read().unwrap().dimensions.get(&s.get_id()).cloned()
+    }
+
+    /// Register the partial derivatives of the function `f`, which are used by
+    /// [`Atom::resolve_derivatives`]. The partial derivative with respect to the `i`th
+    /// argument is given by `partials[i]`, expressed in the wildcards `args`.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::State};
+    /// let f = State::get_symbol("f");
+    /// let x = State::get_symbol("x_");
+    /// State::set_derivative_rule(f, vec![x], vec![Atom::parse("2*x_").unwrap()]).unwrap();
+    ///
+    /// let r = Atom::parse("der(1,f(y^2))").unwrap().resolve_derivatives();
+    /// assert_eq!(r, Atom::parse("2*y^2").unwrap());
+    /// ```
+    pub fn set_derivative_rule(
+        f: Symbol,
+        args: Vec<Symbol>,
+        partials: Vec<Atom>,
+    ) -> Result<(), String> {
+        if args.len() != partials.len() {
+            return Err(format!(
+                "Function {} has {} arguments, but {} partial derivatives are given",
+                Self::get_name(f),
+                args.len(),
+                partials.len()
+            )
+            .into());
+        }
+
+        if let Some(a) = args.iter().find(|a| a.get_wildcard_level() == 0) {
+            return Err(format!("Argument {} is not a wildcard", Self::get_name(*a)).into());
+        }
+
+        STATE
+            .write()
+            .unwrap()
+            .derivative_rules
+            .insert(f.get_id(), (args, partials));
+        Ok(())
+    }
+
+    /// Get the arguments and partial derivatives registered for the function `f`, if any.
+    pub fn get_derivative_rule(f: Symbol) -> Option<(Vec<Symbol>, Vec<Atom>)> {
+        STATE
+            .read()
+            .unwrap()
+            .derivative_rules
+            .get(&f.get_id())
+            .cloned()
+    }
+
+    /// Set the order of terms in a sum and of factors in a product, which is used
+    /// during normalization and therefore determines how expressions are printed.
+    /// Expressions that were normalized with a different order should be normalized
+    /// again, as they are not equal to expressions in the new order.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::{State, TermOrder}};
+    /// let (x, y) = (State::get_symbol("x"), State::get_symbol("y"));
+    /// State::set_term_order(TermOrder::Lexicographic(vec![y, x]));
+    /// let a = Atom::parse("x^2 + 1 + x*y + y^2").unwrap();
+    /// assert_eq!(a.to_string(), "y^2+y*x+x^2+1");
+    /// State::set_term_order(TermOrder::Default);
+    /// ```
+    pub fn set_term_order(order: TermOrder) {
+        let mut o = TERM_ORDER.write().unwrap();
+        CUSTOM_TERM_ORDER.store(order != TermOrder::Default, Ordering::Relaxed);
+        *o = order;
+    }
+
+    /// Get the order of terms in a sum and of factors in a product.
+    pub fn get_term_order() -> TermOrder {
+        TERM_ORDER.read().unwrap().clone()
+    }
+
+    /// Get the term order if it is not the default order.
+    #[inline]
+    pub(crate) fn get_custom_term_order() -> Option<TermOrder> {
+        if CUSTOM_TERM_ORDER.load(Ordering::Relaxed) {
+            Some(Self::get_term_order())
+        } else {
+            None
+        }
+    }
+
+    /// Let the symbol `a` represent a root of the monic polynomial `minimal_poly` in `a`,
+    /// which must be irreducible over the rationals. Integer powers of `a` are then reduced
+    /// during normalization, so that expressions in `a` are exact elements of the number field
+    /// `Q(a)`. Products of sums are only reduced after they are expanded.
+    ///
+    /// The minimal polynomial cannot be changed once it is set, and expressions
+    /// that were normalized before should be normalized again.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::State};
+    /// let a = State::get_symbol("sqrt2");
+    /// State::set_minimal_polynomial(a, &Atom::parse("sqrt2^2 - 2").unwrap()).unwrap();
+    /// let e = Atom::parse("(1 + sqrt2)^2").unwrap().expand();
+    /// assert_eq!(e, Atom::parse("3 + 2*sqrt2").unwrap());
+    /// assert_eq!(Atom::parse("1/sqrt2").unwrap(), Atom::parse("sqrt2/2").unwrap());
+    /// ```
+    pub fn set_minimal_polynomial(a: Symbol, minimal_poly: &Atom) -> Result<(), String> {
+        let poly = minimal_poly.to_minimal_polynomial(a)?;
+        if poly.degree(0) < 2 {
+            return Err(format!(
+                "The minimal polynomial {} must have at least degree 2",
+                minimal_poly
+            )
+            .into());
+        }
+
+        let mut polys = MINIMAL_POLYNOMIALS.write().unwrap();
+        if let Some((_, p)) = polys.iter().find(|(s, _)| s.get_id() == a.get_id()) {
+            if p != &poly {
+                return Err(format!(
+                    "Symbol {} already has a different minimal polynomial {}",
+                    Self::get_name(a),
+                    p
+                )
+                .into());
+            }
+        } else {
+            polys.push((a, poly));
+            HAS_MINIMAL_POLYNOMIALS.store(true, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if a noncommutative symbol may have been defined.
+    #[inline]
+    pub(crate) fn has_noncommutative_symbols() -> bool {
+        HAS_NONCOMMUTATIVE_SYMBOLS.load(Ordering::Relaxed)
+    }
+
+    /// Get the minimal polynomial of `a`, if it is set.
+    #[inline]
+    pub(crate) fn get_minimal_polynomial(
+        a: Symbol,
+    ) -> Option<MultivariatePolynomial<RationalField, u8>> {
+        if !HAS_MINIMAL_POLYNOMIALS.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        MINIMAL_POLYNOMIALS
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(s, _)| s.get_id() == a.get_id())
+            .map(|(_, p)| p.clone())
+    }
+
+    fn set_minimal_polynomials(polys: Vec<(Symbol, MultivariatePolynomial<RationalField, u8>)>) {
+        let mut p = MINIMAL_POLYNOMIALS.write().unwrap();
+        HAS_MINIMAL_POLYNOMIALS.store(!polys.is_empty(), Ordering::Relaxed);
+        *p = polys;
+    }
+
+    /// Set the domain in which numeric literals are interpreted by [`Atom::parse`].
+    /// In a finite field domain, every parsed number is reduced into the field,
+    /// with the exception of exponents.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{
+    /// #     domains::finite_field::{FiniteFieldCore, Zp64},
+    /// #     representations::Atom,
+    /// #     state::{CoefficientDomain, State},
+    /// # };
+    /// let field = State::get_or_insert_finite_field(Zp64::new(7));
+    /// State::set_coefficient_domain(CoefficientDomain::FiniteField(field));
+    /// let a = Atom::parse("3/2").unwrap();
+    /// assert_eq!(a, Atom::parse("5").unwrap()); // 3 * 2^-1 = 5 mod 7
+    /// State::set_coefficient_domain(CoefficientDomain::Rational);
+    /// ```
+    pub fn set_coefficient_domain(domain: CoefficientDomain) {
+        STATE.write().unwrap().coefficient_domain = domain;
+    }
+
+    /// Get the domain in which numeric literals are interpreted when parsing.
+    pub fn get_coefficient_domain() -> CoefficientDomain {
+        STATE.read().unwrap().coefficient_domain
+    }
+
+    /// Set the maximum number of digits of a large rational number in printed output.
+    /// Numbers whose numerator or denominator has more digits are printed as a rounded
+    /// decimal with `digits` significant digits, followed by an ellipsis and the exponent.
+    /// LaTeX output is always exact, and so is output printed with
+    /// [`PrintOptions::file`](crate::printer::PrintOptions::file), which can be parsed again.
+    /// Set to `None` to print all numbers exactly, which is the default.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{representations::Atom, state::State};
+    /// let a = Atom::parse("2^100").unwrap().expand();
+    /// State::set_max_display_digits(Some(5));
+    /// assert_eq!(a.to_string(), "1.2677…e30");
+    /// State::set_max_display_digits(None);
+    /// assert_eq!(a.to_string(), "1267650600228229401496703205376");
+    /// ```
+    pub fn set_max_display_digits(digits: Option<usize>) {
+        if digits == Some(0) {
+            panic!("The number of display digits must be positive");
+        }
+
+        MAX_DISPLAY_DIGITS.store(digits.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Get the maximum number of digits of a large rational number in printed output, if set.
+    pub fn get_max_display_digits() -> Option<usize> {
+        match MAX_DISPLAY_DIGITS.load(Ordering::Relaxed) {
+            0 => None,
+            d => Some(d),
+        }
+    }
+
     /// Get the name for a given symbol.
     pub fn get_name(id: Symbol) -> &'static str {
         &ID_TO_STR[id.get_id() as usize + SYMBOL_OFFSET.load(Ordering::Relaxed)]
     }
 
+    /// Returns `true` iff a symbol with the identifier `id` is defined.
+    pub(crate) fn symbol_exists(id: u32) -> bool {
+        (id as usize) + SYMBOL_OFFSET.load(Ordering::Relaxed) < ID_TO_STR.len()
+    }
+
     pub fn get_finite_field(fi: FiniteFieldIndex) -> &'static Zp64 {
         &FINITE_FIELDS[fi.0]
     }
 
+    /// Get the finite field with index `fi`, if it exists.
+    pub fn try_get_finite_field(fi: FiniteFieldIndex) -> Option<&'static Zp64> {
+        if fi.0 < FINITE_FIELDS.len() {
+            Some(&FINITE_FIELDS[fi.0])
+        } else {
+            None
+        }
+    }
+
+    /// Export all user-defined symbols, ordered by their id. With the `serialization`
+    /// feature enabled, symbols are serialized by name and attributes, so that
+    /// deserializing the exported list registers the symbols in another state.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::state::State;
+    /// let x = State::get_symbol("x");
+    /// assert!(State::dump_symbols().contains(&x));
+    /// ```
+    pub fn dump_symbols() -> Vec<Symbol> {
+        let mut symbols: Vec<_> = STATE
+            .read()
+            .unwrap()
+            .str_to_id
+            .values()
+            .filter(|s| !Self::is_builtin(**s))
+            .cloned()
+            .collect();
+        symbols.sort_by_key(|s| s.get_id());
+        symbols
+    }
+
+    /// Export the primes of all registered finite fields together with their index,
+    /// so that they can be stored alongside expressions that contain finite field elements.
+    pub fn dump_fields() -> Vec<(u64, usize)> {
+        FINITE_FIELDS
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.get_prime(), i))
+            .collect()
+    }
+
+    /// Register the finite fields exported with [`State::dump_fields`] and return
+    /// a map from the exported indices to the indices in the current state. Use
+    /// [`Atom::remap_fields`] to update the finite field elements in imported expressions.
+    /// An error is returned if a modulus is not an odd number larger than 2.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{domains::finite_field::{FiniteFieldCore, Zp64}, state::State};
+    /// let fi = State::get_or_insert_finite_field(Zp64::new(17));
+    /// let dump = State::dump_fields();
+    /// let remap = State::load_fields(&dump).unwrap();
+    /// assert!(remap.is_identity());
+    /// assert_eq!(remap.get(fi), Some(fi));
+    /// ```
+    pub fn load_fields(fields: &[(u64, usize)]) -> Result<FieldRemap, String> {
+        // validate before taking the lock, so that bad data cannot poison it
+        let fields = fields
+            .iter()
+            .map(|(p, i)| {
+                // the finite field only supports odd primes
+                if *p < 3 || p % 2 == 0 {
+                    Err(format!("Invalid prime {}", p))
+                } else {
+                    Ok((*i, Zp64::new(*p)))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut state = STATE.write().unwrap();
+        Ok(FieldRemap {
+            map: fields
+                .into_iter()
+                .map(|(i, f)| (i, state.get_or_insert_finite_field_impl(f)))
+                .collect(),
+        })
+    }
+
     pub fn get_or_insert_finite_field(f: Zp64) -> FiniteFieldIndex {
         STATE.write().unwrap().get_or_insert_finite_field_impl(f)
     }
@@ -256,12 +1128,40 @@ impl State {
         let index = FINITE_FIELDS.push(f);
         FiniteFieldIndex(index)
     }
+
+    pub fn get_extension_field(fi: ExtensionFieldIndex) -> &'static ExtensionField {
+        &EXTENSION_FIELDS[fi.0]
+    }
+
+    /// Register the finite field extension `f`, or get the index of an identical
+    /// extension with the same prime and defining polynomial.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::{domains::finite_field::ExtensionField, state::State};
+    /// let fi = State::get_or_insert_extension_field(ExtensionField::from_degree(3, 2));
+    /// assert_eq!(State::get_extension_field(fi).get_degree(), 2);
+    /// ```
+    pub fn get_or_insert_extension_field(f: ExtensionField) -> ExtensionFieldIndex {
+        let _guard = STATE.write().unwrap();
+        for (i, f2) in EXTENSION_FIELDS.iter().enumerate() {
+            if &f == f2 {
+                return ExtensionFieldIndex(i);
+            }
+        }
+
+        ExtensionFieldIndex(EXTENSION_FIELDS.push(f))
+    }
 }
 
 /// A workspace that stores recyclable atoms. Upon dropping, the atoms automatically returned to a
 /// thread-local workspace (which may be a different one than the one it was created by).
+///
+/// Recycled atoms keep their allocated buffer, so that intermediate results in tight loops,
+/// such as those in normalization, expansion and pattern matching, do not allocate memory.
 pub struct Workspace {
     atom_buffer: RefCell<Vec<Atom>>,
+    atom_buffer_capacity: Cell<usize>,
 }
 
 impl Workspace {
@@ -271,6 +1171,7 @@ impl Workspace {
     const fn new() -> Self {
         Workspace {
             atom_buffer: RefCell::new(Vec::new()),
+            atom_buffer_capacity: Cell::new(Self::ATOM_BUFFER_MAX),
         }
     }
 
@@ -280,6 +1181,30 @@ impl Workspace {
         &WORKSPACE
     }
 
+    /// Get the maximal number of atoms that are kept for recycling.
+    pub fn get_buffer_capacity(&self) -> usize {
+        self.atom_buffer_capacity.get()
+    }
+
+    /// Set the maximal number of atoms that are kept for recycling. Deeply nested
+    /// computations that hold many intermediate atoms at the same time may
+    /// benefit from a larger capacity. Atoms beyond the new capacity are freed.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::state::Workspace;
+    /// Workspace::get_local().with(|ws| {
+    ///     ws.set_buffer_capacity(100);
+    ///     assert_eq!(ws.get_buffer_capacity(), 100);
+    /// });
+    /// ```
+    pub fn set_buffer_capacity(&self, capacity: usize) {
+        self.atom_buffer_capacity.set(capacity);
+        if let Ok(mut a) = self.atom_buffer.try_borrow_mut() {
+            a.truncate(capacity);
+        }
+    }
+
     /// Return a recycled atom from this workspace. The atom may have the same value as before.
     #[inline]
     pub fn new_atom(&self) -> RecycledAtom {
@@ -310,9 +1235,18 @@ impl Workspace {
         owned
     }
 
+    /// Give an atom to the workspace for recycling. The atom is freed
+    /// if the workspace is full.
+    #[inline]
     pub fn return_atom(&self, atom: Atom) {
+        if let Atom::Empty = atom {
+            return;
+        }
+
         if let Ok(mut a) = self.atom_buffer.try_borrow_mut() {
-            a.push(atom);
+            if a.len() < self.atom_buffer_capacity.get() {
+                a.push(atom);
+            }
         }
     }
 }
@@ -402,7 +1336,7 @@ impl Drop for RecycledAtom {
             #[inline(always)]
             |ws| {
                 if let Ok(mut a) = ws.atom_buffer.try_borrow_mut() {
-                    if a.len() < Workspace::ATOM_BUFFER_MAX {
+                    if a.len() < ws.atom_buffer_capacity.get() {
                         a.push(std::mem::replace(&mut self.0, Atom::Empty));
                     }
                 }
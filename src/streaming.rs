@@ -1,10 +1,28 @@
-use std::sync::Mutex;
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
+use ahash::HashMap;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use memmap2::Mmap;
 use rayon::prelude::*;
+use rug::{integer::Order, Integer as MultiPrecisionInteger};
 
 use crate::{
-    representations::{Atom, AtomView},
-    state::{RecycledAtom, Workspace},
+    coefficient::Coefficient,
+    domains::{
+        finite_field::{FiniteFieldCore, Zp64},
+        float::Float,
+        integer::{Integer, IntegerRing},
+        rational::Rational,
+        rational_polynomial::RationalPolynomial,
+    },
+    poly::{polynomial::MultivariatePolynomial, Variable},
+    representations::{Atom, AtomView, Symbol},
+    state::{FunctionAttribute, LinearArguments, RecycledAtom, State, Workspace},
 };
 
 struct TermInputStream {
@@ -41,8 +59,13 @@ impl TermOutputStream {
 
     /// Sort all the terms.
     fn sort(&mut self) {
-        self.mem_buf
-            .par_sort_by(|a, b| a.as_view().cmp_terms(&b.as_view()));
+        if let Some(order) = State::get_custom_term_order() {
+            self.mem_buf
+                .par_sort_by(|a, b| order.cmp_terms(&a.as_view(), &b.as_view()));
+        } else {
+            self.mem_buf
+                .par_sort_by(|a, b| a.as_view().cmp_terms(&b.as_view()));
+        }
 
         let mut out = Vec::with_capacity(self.mem_buf.len());
 
@@ -173,3 +196,1057 @@ where
         self.exp_out.to_expression()
     }
 }
+
+const MAGIC: &[u8; 8] = b"SYMBOLIC";
+const FORMAT_VERSION: u32 = 1;
+
+const RECORD_SYMBOL: u8 = 0;
+const RECORD_TERM: u8 = 1;
+const RECORD_END: u8 = 2;
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// A writer of terms in a versioned binary format that is independent of the
+/// platform and of the in-memory representation of atoms. Symbols are stored by name
+/// and attributes the first time they occur, so that they can be resolved in another
+/// state when the terms are read back with [`TermReader`].
+///
+/// Terms are written one by one, so that a sum that does not fit in memory
+/// can be written as well.
+pub struct TermWriter<W: Write> {
+    writer: W,
+    symbols: HashMap<Symbol, u32>,
+    new_symbols: Vec<Symbol>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> TermWriter<W> {
+    /// Create a new term writer and write the header to `writer`.
+    pub fn new(mut writer: W) -> io::Result<TermWriter<W>> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+
+        Ok(TermWriter {
+            writer,
+            symbols: HashMap::default(),
+            new_symbols: vec![],
+            buffer: vec![],
+        })
+    }
+
+    /// Write a single term.
+    pub fn write_term(&mut self, term: AtomView) -> io::Result<()> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        self.write_atom(term, &mut buffer)?;
+
+        for s in self.new_symbols.drain(..) {
+            let name = State::get_name(s).as_bytes();
+            self.writer.write_u8(RECORD_SYMBOL)?;
+            self.writer.write_u32::<LittleEndian>(name.len() as u32)?;
+            self.writer.write_all(name)?;
+            self.writer.write_u8(s.get_wildcard_level())?;
+            self.writer.write_u8(
+                s.is_symmetric() as u8
+                    | (s.is_antisymmetric() as u8) << 1
+                    | (s.is_linear() as u8) << 2
+                    | (s.is_noncommutative() as u8) << 3
+                    | ((s.get_linear_arguments_mask() != 0) as u8) << 4
+                    | (s.is_real() as u8) << 5,
+            )?;
+            if s.get_linear_arguments_mask() != 0 {
+                self.writer
+                    .write_u32::<LittleEndian>(s.get_linear_arguments_mask())?;
+            }
+        }
+
+        self.writer.write_u8(RECORD_TERM)?;
+        self.writer.write_u64::<LittleEndian>(buffer.len() as u64)?;
+        self.writer.write_all(&buffer)?;
+        self.buffer = buffer;
+        Ok(())
+    }
+
+    /// Write all terms of the expression `expr`.
+    pub fn write_expression(&mut self, expr: AtomView) -> io::Result<()> {
+        if let AtomView::Add(a) = expr {
+            for t in a.iter() {
+                self.write_term(t)?;
+            }
+            Ok(())
+        } else {
+            self.write_term(expr)
+        }
+    }
+
+    /// Mark the end of the terms and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_u8(RECORD_END)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_symbol(&mut self, s: Symbol, dest: &mut Vec<u8>) -> io::Result<()> {
+        let index = match self.symbols.get(&s) {
+            Some(i) => *i,
+            None => {
+                let i = self.symbols.len() as u32;
+                self.symbols.insert(s, i);
+                self.new_symbols.push(s);
+                i
+            }
+        };
+
+        dest.write_u32::<LittleEndian>(index)
+    }
+
+    fn write_atom(&mut self, a: AtomView, dest: &mut Vec<u8>) -> io::Result<()> {
+        match a {
+            AtomView::Num(n) => {
+                dest.write_u8(0)?;
+                self.write_coefficient(&n.get_coeff_view().to_owned(), dest)?;
+            }
+            AtomView::Var(v) => {
+                dest.write_u8(1)?;
+                self.write_symbol(v.get_symbol(), dest)?;
+            }
+            AtomView::Fun(f) => {
+                dest.write_u8(2)?;
+                self.write_symbol(f.get_symbol(), dest)?;
+                dest.write_u64::<LittleEndian>(f.get_nargs() as u64)?;
+                for arg in f.iter() {
+                    self.write_atom(arg, dest)?;
+                }
+            }
+            AtomView::Pow(p) => {
+                dest.write_u8(3)?;
+                let (b, e) = p.get_base_exp();
+                self.write_atom(b, dest)?;
+                self.write_atom(e, dest)?;
+            }
+            AtomView::Mul(m) => {
+                dest.write_u8(4)?;
+                dest.write_u64::<LittleEndian>(m.get_nargs() as u64)?;
+                for arg in m.iter() {
+                    self.write_atom(arg, dest)?;
+                }
+            }
+            AtomView::Add(a) => {
+                dest.write_u8(5)?;
+                dest.write_u64::<LittleEndian>(a.get_nargs() as u64)?;
+                for arg in a.iter() {
+                    self.write_atom(arg, dest)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_coefficient(&mut self, c: &Coefficient, dest: &mut Vec<u8>) -> io::Result<()> {
+        match c {
+            Coefficient::Rational(r) => {
+                dest.write_u8(0)?;
+                write_integer(&r.numerator(), dest)?;
+                write_integer(&r.denominator(), dest)?;
+            }
+            Coefficient::FiniteField(n, fi) => {
+                let f = State::get_finite_field(*fi);
+                dest.write_u8(1)?;
+                dest.write_u64::<LittleEndian>(f.from_element(n))?;
+                dest.write_u64::<LittleEndian>(f.get_prime())?;
+            }
+            Coefficient::RationalPolynomial(r) => {
+                dest.write_u8(2)?;
+                dest.write_u64::<LittleEndian>(r.numerator.variables.len() as u64)?;
+                for v in r.numerator.variables.iter() {
+                    match v {
+                        Variable::Symbol(s) => {
+                            dest.write_u8(0)?;
+                            self.write_symbol(*s, dest)?;
+                        }
+                        Variable::Temporary(t) => {
+                            dest.write_u8(1)?;
+                            dest.write_u64::<LittleEndian>(*t as u64)?;
+                        }
+                        Variable::Array(s, i) => {
+                            dest.write_u8(2)?;
+                            self.write_symbol(*s, dest)?;
+                            dest.write_u64::<LittleEndian>(*i as u64)?;
+                        }
+                        Variable::Function(s, a) => {
+                            dest.write_u8(3)?;
+                            self.write_symbol(*s, dest)?;
+                            self.write_atom(a.as_view(), dest)?;
+                        }
+                        Variable::Other(a) => {
+                            dest.write_u8(4)?;
+                            self.write_atom(a.as_view(), dest)?;
+                        }
+                    }
+                }
+
+                for p in [&r.numerator, &r.denominator] {
+                    dest.write_u64::<LittleEndian>(p.nterms() as u64)?;
+                    for i in 0..p.nterms() {
+                        write_integer(&p.coefficients[i], dest)?;
+                        for e in p.exponents(i) {
+                            dest.write_u16::<LittleEndian>(*e)?;
+                        }
+                    }
+                }
+            }
+            Coefficient::Float(f) => {
+                let (m, e) = f.to_parts();
+                dest.write_u8(3)?;
+                write_integer(&Integer::from_large(m.clone()), dest)?;
+                dest.write_i64::<LittleEndian>(e)?;
+                dest.write_u32::<LittleEndian>(f.prec())?;
+            }
+            Coefficient::Complex(c) => {
+                dest.write_u8(4)?;
+                self.write_coefficient(&c.0, dest)?;
+                self.write_coefficient(&c.1, dest)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_integer(n: &Integer, dest: &mut Vec<u8>) -> io::Result<()> {
+    let large = match n {
+        Integer::Natural(n) => {
+            dest.write_u8(0)?;
+            return dest.write_i64::<LittleEndian>(*n);
+        }
+        Integer::Double(n) => MultiPrecisionInteger::from(*n),
+        Integer::Large(n) => n.clone(),
+    };
+
+    let mut digits = vec![0u8; large.significant_digits::<u8>()];
+    large.write_digits(&mut digits, Order::Lsf);
+    dest.write_u8(1)?;
+    dest.write_u8((large < 0) as u8)?;
+    dest.write_u64::<LittleEndian>(digits.len() as u64)?;
+    dest.write_all(&digits)
+}
+
+fn read_integer(source: &mut &[u8]) -> io::Result<Integer> {
+    match source.read_u8()? {
+        0 => Ok(Integer::Natural(source.read_i64::<LittleEndian>()?)),
+        1 => {
+            let negative = source.read_u8()? != 0;
+            let len = source.read_u64::<LittleEndian>()? as usize;
+            if len > source.len() {
+                return Err(invalid_data("Integer exceeds the term length"));
+            }
+
+            let mut n = MultiPrecisionInteger::from_digits(&source[..len], Order::Lsf);
+            *source = &source[len..];
+            if negative {
+                n = -n;
+            }
+            Ok(Integer::from_large(n))
+        }
+        t => Err(invalid_data(format!("Unknown integer tag {}", t))),
+    }
+}
+
+/// A reader of terms written by [`TermWriter`]. The symbols in the terms are
+/// registered in the current state by name, so that the terms can be read in a
+/// different session than the one they were written in.
+///
+/// The terms are read one by one by iterating over the reader.
+pub struct TermReader<R: Read> {
+    reader: R,
+    decoder: TermDecoder,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> TermReader<R> {
+    /// Create a new term reader and read the header from `reader`.
+    pub fn new(mut reader: R) -> io::Result<TermReader<R>> {
+        read_header(&mut reader)?;
+
+        Ok(TermReader {
+            reader,
+            decoder: TermDecoder::default(),
+            buffer: vec![],
+            done: false,
+        })
+    }
+
+    fn read_term(&mut self) -> io::Result<Option<Atom>> {
+        loop {
+            match self.reader.read_u8()? {
+                RECORD_SYMBOL => {
+                    let s = read_symbol_record(&mut self.reader)?;
+                    self.decoder.symbols.push(s);
+                }
+                RECORD_TERM => {
+                    let len = self.reader.read_u64::<LittleEndian>()? as usize;
+                    self.buffer.resize(len, 0);
+                    self.reader.read_exact(&mut self.buffer)?;
+                    return self.decoder.decode_term(&self.buffer).map(Some);
+                }
+                RECORD_END => return Ok(None),
+                t => return Err(invalid_data(format!("Unknown record tag {}", t))),
+            }
+        }
+    }
+}
+
+/// Read and check the header of a file.
+fn read_header<R: Read>(reader: &mut R) -> io::Result<()> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("Not a Symbolica expression file"));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version > FORMAT_VERSION {
+        return Err(invalid_data(format!(
+            "Unsupported format version {}, the latest supported version is {}",
+            version, FORMAT_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+/// Read a symbol record, without its tag, and register the symbol in the current state.
+fn read_symbol_record<R: Read>(reader: &mut R) -> io::Result<Symbol> {
+    let len = reader.read_u32::<LittleEndian>()? as usize;
+    let mut name = vec![0; len];
+    reader.read_exact(&mut name)?;
+    let name = std::string::String::from_utf8(name).map_err(invalid_data)?;
+    let level = reader.read_u8()?;
+    let flags = reader.read_u8()?;
+
+    let mut attributes = vec![];
+    if flags & 1 != 0 {
+        attributes.push(FunctionAttribute::Symmetric);
+    }
+    if flags & 2 != 0 {
+        attributes.push(FunctionAttribute::Antisymmetric);
+    }
+    if flags & 4 != 0 && flags & 16 == 0 {
+        attributes.push(FunctionAttribute::Linear);
+    }
+    if flags & 8 != 0 {
+        attributes.push(FunctionAttribute::NonCommutative);
+    }
+    if flags & 32 != 0 {
+        attributes.push(FunctionAttribute::Real);
+    }
+    if flags & 16 != 0 {
+        let mask = reader.read_u32::<LittleEndian>()?;
+        attributes.push(FunctionAttribute::LinearIn(LinearArguments::from_mask(
+            mask,
+        )));
+    }
+
+    State::import_symbol(&name, level, &attributes).map_err(|e| invalid_data(e.to_string()))
+}
+
+/// A decoder of the terms in a file, which maps the symbol indices
+/// in the terms to the symbols that were registered when reading the file.
+#[derive(Default)]
+struct TermDecoder {
+    symbols: Vec<Symbol>,
+}
+
+impl TermDecoder {
+    /// Decode and normalize the data of a single term record.
+    fn decode_term(&self, mut source: &[u8]) -> io::Result<Atom> {
+        let term = Workspace::get_local().with(|ws| -> io::Result<Atom> {
+            let mut a = ws.new_atom();
+            self.read_atom(&mut source, ws, &mut a)?;
+            let mut out = Atom::new();
+            a.as_view().normalize(ws, &mut out);
+            Ok(out)
+        })?;
+
+        if !source.is_empty() {
+            return Err(invalid_data("Term has trailing data"));
+        }
+
+        Ok(term)
+    }
+
+    fn read_symbol(&self, source: &mut &[u8]) -> io::Result<Symbol> {
+        let index = source.read_u32::<LittleEndian>()? as usize;
+        self.symbols
+            .get(index)
+            .cloned()
+            .ok_or_else(|| invalid_data(format!("Undefined symbol {}", index)))
+    }
+
+    fn read_atom(&self, source: &mut &[u8], ws: &Workspace, out: &mut Atom) -> io::Result<()> {
+        match source.read_u8()? {
+            0 => {
+                out.to_num(self.read_coefficient(source)?);
+            }
+            1 => {
+                out.to_var(self.read_symbol(source)?);
+            }
+            2 => {
+                let s = self.read_symbol(source)?;
+                let nargs = source.read_u64::<LittleEndian>()?;
+                let f = out.to_fun(s);
+                for _ in 0..nargs {
+                    let mut arg = ws.new_atom();
+                    self.read_atom(source, ws, &mut arg)?;
+                    f.add_arg(arg.as_view());
+                }
+            }
+            3 => {
+                let mut base = ws.new_atom();
+                self.read_atom(source, ws, &mut base)?;
+                let mut exp = ws.new_atom();
+                self.read_atom(source, ws, &mut exp)?;
+                out.to_pow(base.as_view(), exp.as_view());
+            }
+            4 => {
+                let nargs = source.read_u64::<LittleEndian>()?;
+                let m = out.to_mul();
+                for _ in 0..nargs {
+                    let mut arg = ws.new_atom();
+                    self.read_atom(source, ws, &mut arg)?;
+                    m.extend(arg.as_view());
+                }
+            }
+            5 => {
+                let nargs = source.read_u64::<LittleEndian>()?;
+                let a = out.to_add();
+                for _ in 0..nargs {
+                    let mut arg = ws.new_atom();
+                    self.read_atom(source, ws, &mut arg)?;
+                    a.extend(arg.as_view());
+                }
+            }
+            t => return Err(invalid_data(format!("Unknown atom tag {}", t))),
+        }
+
+        Ok(())
+    }
+
+    fn read_coefficient(&self, source: &mut &[u8]) -> io::Result<Coefficient> {
+        match source.read_u8()? {
+            0 => {
+                let num = read_integer(source)?;
+                let den = read_integer(source)?;
+                if den.is_zero() {
+                    return Err(invalid_data("Denominator of a rational number is zero"));
+                }
+                Ok(Coefficient::Rational(Rational::from((num, den))))
+            }
+            1 => {
+                let n = source.read_u64::<LittleEndian>()?;
+                let p = source.read_u64::<LittleEndian>()?;
+                // the finite field only supports odd primes
+                if p < 3 || p % 2 == 0 {
+                    return Err(invalid_data(format!("Invalid prime {}", p)));
+                }
+
+                let fi = State::get_or_insert_finite_field(Zp64::new(p));
+                let f = State::get_finite_field(fi);
+                Ok(Coefficient::FiniteField(f.to_element(n % p), fi))
+            }
+            2 => {
+                let nvars = source.read_u64::<LittleEndian>()?;
+                let mut variables = vec![];
+                for _ in 0..nvars {
+                    variables.push(match source.read_u8()? {
+                        0 => Variable::Symbol(self.read_symbol(source)?),
+                        1 => Variable::Temporary(source.read_u64::<LittleEndian>()? as usize),
+                        2 => Variable::Array(
+                            self.read_symbol(source)?,
+                            source.read_u64::<LittleEndian>()? as usize,
+                        ),
+                        3 => {
+                            let s = self.read_symbol(source)?;
+                            Variable::Function(s, Arc::new(self.read_normalized_atom(source)?))
+                        }
+                        4 => Variable::Other(Arc::new(self.read_normalized_atom(source)?)),
+                        t => return Err(invalid_data(format!("Unknown variable tag {}", t))),
+                    });
+                }
+                let variables = Arc::new(variables);
+
+                let mut polys = vec![];
+                for _ in 0..2 {
+                    let nterms = source.read_u64::<LittleEndian>()?;
+                    let mut p =
+                        MultivariatePolynomial::new(&IntegerRing::new(), None, variables.clone());
+                    let mut exp = vec![0; variables.len()];
+                    for _ in 0..nterms {
+                        let c = read_integer(source)?;
+                        for e in &mut exp {
+                            *e = source.read_u16::<LittleEndian>()?;
+                        }
+                        p.append_monomial(c, &exp);
+                    }
+                    polys.push(p);
+                }
+
+                let denominator = polys.pop().unwrap();
+                let numerator = polys.pop().unwrap();
+                if denominator.is_zero() {
+                    return Err(invalid_data("Denominator of a rational polynomial is zero"));
+                }
+
+                Ok(Coefficient::RationalPolynomial(RationalPolynomial {
+                    numerator,
+                    denominator,
+                }))
+            }
+            3 => {
+                let m = match read_integer(source)? {
+                    Integer::Natural(n) => MultiPrecisionInteger::from(n),
+                    Integer::Double(n) => MultiPrecisionInteger::from(n),
+                    Integer::Large(n) => n,
+                };
+                let exp = source.read_i64::<LittleEndian>()?;
+                let prec = source.read_u32::<LittleEndian>()?;
+                if prec == 0 {
+                    return Err(invalid_data("The precision of a float must be positive"));
+                }
+                Ok(Coefficient::Float(Float::from_parts(m, exp, prec)))
+            }
+            4 => {
+                let re = self.read_coefficient(source)?;
+                let im = self.read_coefficient(source)?;
+                Ok(Coefficient::complex(re, im))
+            }
+            t => Err(invalid_data(format!("Unknown number tag {}", t))),
+        }
+    }
+
+    fn read_normalized_atom(&self, source: &mut &[u8]) -> io::Result<Atom> {
+        Workspace::get_local().with(|ws| {
+            let mut a = ws.new_atom();
+            self.read_atom(source, ws, &mut a)?;
+            let mut out = Atom::new();
+            a.as_view().normalize(ws, &mut out);
+            Ok(out)
+        })
+    }
+}
+
+impl<R: Read> Iterator for TermReader<R> {
+    type Item = io::Result<Atom>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_term() {
+            Ok(Some(t)) => Some(Ok(t)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The size of the pages of the term index of [`MappedTerms`].
+const INDEX_PAGE_SIZE: usize = 1 << 16;
+
+/// The terms of a sum in a file written by [`TermWriter`] or [`Atom::save`], which
+/// is memory-mapped instead of read into memory. The operating system loads the
+/// parts of the file that are used and evicts them when memory is needed, so that
+/// sums that are larger than the available memory can be processed.
+///
+/// When the file is opened, the symbols are registered in the current state and
+/// an index is built that stores the position of the first term in every page
+/// of the file. The index is used to access terms by position and to
+/// process the pages in parallel.
+///
+/// Example:
+/// ```
+/// # use symbolica::representations::Atom;
+/// # use symbolica::streaming::{MappedTerms, TermWriter};
+/// let path = std::env::temp_dir().join("mapped_terms_doc.sym");
+/// Atom::parse("x + 2*y + f(x)").unwrap().save(std::fs::File::create(&path).unwrap()).unwrap();
+///
+/// let terms = MappedTerms::open(&path).unwrap();
+/// assert_eq!(terms.len(), 3);
+///
+/// let mut out = TermWriter::new(vec![]).unwrap();
+/// terms.map(|_, t| &t * &Atom::parse("x").unwrap(), &mut out).unwrap();
+/// let r = Atom::load(out.finish().unwrap().as_slice()).unwrap();
+/// assert_eq!(r, Atom::parse("x^2 + 2*x*y + x*f(x)").unwrap());
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct MappedTerms {
+    map: Mmap,
+    decoder: TermDecoder,
+    /// The position of the first term record that starts in a page and its index.
+    pages: Vec<(usize, usize)>,
+    nterms: usize,
+}
+
+impl MappedTerms {
+    /// Memory-map the file at `path` and index its terms.
+    ///
+    /// The file must not be modified while it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedTerms> {
+        let file = File::open(path)?;
+        // the file is only read and is assumed not to change while it is mapped
+        let map = unsafe { Mmap::map(&file)? };
+
+        let mut source = &map[..];
+        read_header(&mut source)?;
+
+        let mut decoder = TermDecoder::default();
+        let mut pages: Vec<(usize, usize)> = vec![];
+        let mut nterms = 0;
+        loop {
+            let pos = map.len() - source.len();
+            match source.read_u8()? {
+                RECORD_SYMBOL => decoder.symbols.push(read_symbol_record(&mut source)?),
+                RECORD_TERM => {
+                    let len = source.read_u64::<LittleEndian>()? as usize;
+                    if len > source.len() {
+                        return Err(invalid_data("Term exceeds the file length"));
+                    }
+                    source = &source[len..];
+
+                    if pages
+                        .last()
+                        .map(|(p, _)| p / INDEX_PAGE_SIZE != pos / INDEX_PAGE_SIZE)
+                        .unwrap_or(true)
+                    {
+                        pages.push((pos, nterms));
+                    }
+                    nterms += 1;
+                }
+                RECORD_END => break,
+                t => return Err(invalid_data(format!("Unknown record tag {}", t))),
+            }
+        }
+
+        Ok(MappedTerms {
+            map,
+            decoder,
+            pages,
+            nterms,
+        })
+    }
+
+    /// The number of terms.
+    pub fn len(&self) -> usize {
+        self.nterms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nterms == 0
+    }
+
+    /// Get the data of the first term record at or after position `pos`
+    /// and move `pos` past it. Records are validated when the file is opened.
+    fn next_term_data(&self, pos: &mut usize) -> &[u8] {
+        loop {
+            let mut source = &self.map[*pos..];
+            match source.read_u8().unwrap() {
+                RECORD_SYMBOL => {
+                    let len = source.read_u32::<LittleEndian>().unwrap() as usize;
+                    source = &source[len + 1..]; // skip the name and wildcard level
+                    if source.read_u8().unwrap() & 16 != 0 {
+                        source = &source[4..]; // skip the linear arguments
+                    }
+                    *pos = self.map.len() - source.len();
+                }
+                RECORD_TERM => {
+                    let len = source.read_u64::<LittleEndian>().unwrap() as usize;
+                    let start = self.map.len() - source.len();
+                    *pos = start + len;
+                    return &self.map[start..start + len];
+                }
+                _ => unreachable!("Unexpected record in term data"),
+            }
+        }
+    }
+
+    /// Get the term at position `index`.
+    ///
+    /// This function panics if the index is out of range.
+    pub fn get(&self, index: usize) -> io::Result<Atom> {
+        assert!(
+            index < self.nterms,
+            "Index {} is out of range for {} terms",
+            index,
+            self.nterms
+        );
+
+        let page = self.pages.partition_point(|(_, first)| *first <= index) - 1;
+        let (mut pos, first) = self.pages[page];
+        for _ in first..index {
+            self.next_term_data(&mut pos);
+        }
+
+        self.decoder.decode_term(self.next_term_data(&mut pos))
+    }
+
+    /// Iterate over the terms.
+    pub fn iter(&self) -> MappedTermIterator<'_> {
+        MappedTermIterator {
+            terms: self,
+            pos: self.pages.first().map(|(p, _)| *p).unwrap_or(0),
+            remaining: self.nterms,
+        }
+    }
+
+    /// Call `f` on every term of the page with index `page`.
+    fn for_each_in_page(
+        &self,
+        page: usize,
+        mut f: impl FnMut(usize, Atom) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let (mut pos, first) = self.pages[page];
+        let end = self.pages.get(page + 1).map(|p| p.1).unwrap_or(self.nterms);
+
+        for i in first..end {
+            f(i, self.decoder.decode_term(self.next_term_data(&mut pos))?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the indices of all terms for which `f` returns `true`, in increasing order.
+    /// The pages are processed in parallel.
+    ///
+    /// For example, the terms that match a pattern can be found with
+    /// `terms.find(|t| t.match_first(&pat, &conditions, &settings).is_some())`.
+    pub fn find(&self, f: impl Fn(AtomView) -> bool + Send + Sync) -> io::Result<Vec<usize>> {
+        let found = (0..self.pages.len())
+            .into_par_iter()
+            .map(|page| {
+                let mut indices = vec![];
+                self.for_each_in_page(page, |i, t| {
+                    if f(t.as_view()) {
+                        indices.push(i);
+                    }
+                    Ok(())
+                })?;
+                Ok(indices)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(found.into_iter().flatten().collect())
+    }
+
+    /// Map every term using the function `f` and write the result to `writer`,
+    /// in the order of the terms. The pages are processed in parallel and only
+    /// the results of a few pages are kept in memory at a time.
+    ///
+    /// Equal terms in the result are not merged. This can be done by reading
+    /// the result into a [`TermStreamer`], if it fits in memory.
+    pub fn map<W: Write>(
+        &self,
+        f: impl Fn(&Workspace, Atom) -> Atom + Send + Sync,
+        writer: &mut TermWriter<W>,
+    ) -> io::Result<()> {
+        let pages: Vec<usize> = (0..self.pages.len()).collect();
+        for chunk in pages.chunks(rayon::current_num_threads()) {
+            let mapped = chunk
+                .par_iter()
+                .map(|page| {
+                    let mut out = vec![];
+                    self.for_each_in_page(*page, |_, t| {
+                        out.push(Workspace::get_local().with(|ws| f(ws, t)));
+                        Ok(())
+                    })?;
+                    Ok(out)
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+
+            for t in mapped.iter().flatten() {
+                writer.write_expression(t.as_view())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read all terms into a single expression. This may exceed the available memory.
+    pub fn to_expression(&self) -> io::Result<Atom> {
+        let mut add = Atom::new();
+        let a = add.to_add();
+        for t in self.iter() {
+            a.extend(t?.as_view());
+        }
+
+        let mut out = Atom::new();
+        Workspace::get_local().with(|ws| add.as_view().normalize(ws, &mut out));
+        Ok(out)
+    }
+}
+
+/// An iterator over the terms of [`MappedTerms`].
+pub struct MappedTermIterator<'a> {
+    terms: &'a MappedTerms,
+    pos: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for MappedTermIterator<'a> {
+    type Item = io::Result<Atom>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        let data = self.terms.next_term_data(&mut self.pos);
+        Some(self.terms.decoder.decode_term(data))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl Atom {
+    /// Write the expression to `writer` in a versioned binary format that is independent
+    /// of the platform. Use [`TermWriter`] to write a sum term by term.
+    ///
+    /// Example:
+    /// ```
+    /// # use symbolica::representations::Atom;
+    /// let a = Atom::parse("x^2 + f(x, 1/3)").unwrap();
+    /// let mut buf = vec![];
+    /// a.save(&mut buf).unwrap();
+    /// assert_eq!(Atom::load(buf.as_slice()).unwrap(), a);
+    /// ```
+    pub fn save<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut w = TermWriter::new(writer)?;
+        w.write_expression(self.as_view())?;
+        w.finish()?;
+        Ok(())
+    }
+
+    /// Read an expression written by [`Atom::save`] or [`TermWriter`] from `reader`.
+    /// The symbols in the expression are registered in the current state by name.
+    /// Use [`TermReader`] to read a sum term by term.
+    pub fn load<R: Read>(reader: R) -> io::Result<Atom> {
+        let mut add = Atom::new();
+        let a = add.to_add();
+        for t in TermReader::new(reader)? {
+            a.extend(t?.as_view());
+        }
+
+        let mut out = Atom::new();
+        Workspace::get_local().with(|ws| add.as_view().normalize(ws, &mut out));
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use crate::{
+        coefficient::Coefficient,
+        domains::{
+            finite_field::{FiniteFieldCore, Zp64},
+            float::Float,
+        },
+        id::{Condition, MatchSettings, Pattern},
+        representations::{Atom, AtomView, FunctionBuilder},
+        state::{FunctionAttribute, State},
+    };
+
+    use super::{MappedTerms, TermReader, TermWriter, FORMAT_VERSION, MAGIC};
+
+    #[test]
+    fn save_load() {
+        let f =
+            State::get_symbol_with_attributes("io_f", vec![FunctionAttribute::Symmetric]).unwrap();
+        let fi = State::get_or_insert_finite_field(Zp64::new(17));
+        let a = Atom::parse("io_f(io_y, io_x)^(3/7) + 123456789012345678901234567890*io_x - io_y")
+            .unwrap()
+            + &FunctionBuilder::new(f)
+                .add_arg(&Atom::new_num(Coefficient::FiniteField(
+                    State::get_finite_field(fi).to_element(5),
+                    fi,
+                )))
+                .add_arg(&Atom::new_num(Coefficient::Float(Float::from_f64(
+                    0.1, 100,
+                ))))
+                .add_arg(&Atom::new_num(Coefficient::complex(
+                    1.into(),
+                    (2, 3).into(),
+                )))
+                .finish();
+
+        let mut buf = vec![];
+        a.save(&mut buf).unwrap();
+        assert_eq!(Atom::load(buf.as_slice()).unwrap(), a);
+
+        // a corrupted modulus gives an error
+        let b = Atom::new_num(Coefficient::FiniteField(
+            State::get_finite_field(fi).to_element(5),
+            fi,
+        ));
+        let mut buf = vec![];
+        b.save(&mut buf).unwrap();
+        let pos = buf
+            .windows(8)
+            .rposition(|w| w == 17u64.to_le_bytes())
+            .unwrap();
+        for p in [16u64, 2, 0] {
+            buf[pos..pos + 8].copy_from_slice(&p.to_le_bytes());
+            assert_eq!(
+                Atom::load(buf.as_slice()).unwrap_err().kind(),
+                std::io::ErrorKind::InvalidData
+            );
+        }
+
+        let r = Atom::parse("io_x*io_z/(1+io_y)")
+            .unwrap()
+            .set_coefficient_ring(&Arc::new(vec![
+                State::get_symbol("io_y").into(),
+                State::get_symbol("io_z").into(),
+            ]));
+        let mut buf = vec![];
+        r.save(&mut buf).unwrap();
+        assert_eq!(
+            Atom::load(buf.as_slice()).unwrap().to_string(),
+            r.to_string()
+        );
+
+        let mut w = TermWriter::new(vec![]).unwrap();
+        for i in 0..10 {
+            w.write_term(
+                Atom::parse(&format!("{}*io_x^{}", i + 1, i))
+                    .unwrap()
+                    .as_view(),
+            )
+            .unwrap();
+        }
+        let buf = w.finish().unwrap();
+
+        let terms = TermReader::new(buf.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(terms.len(), 10);
+        assert_eq!(terms[3], Atom::parse("4*io_x^3").unwrap());
+    }
+
+    #[test]
+    fn symbol_resolution() {
+        // a file written in another session, containing the symbol `io_new`
+        let mut buf = MAGIC.to_vec();
+        buf.write_u32::<LittleEndian>(FORMAT_VERSION).unwrap();
+        buf.write_u8(0).unwrap();
+        buf.write_u32::<LittleEndian>(6).unwrap();
+        buf.extend(b"io_new");
+        buf.write_u8(0).unwrap();
+        buf.write_u8(4).unwrap();
+        buf.write_u8(1).unwrap();
+        buf.write_u64::<LittleEndian>(5).unwrap();
+        buf.write_u8(1).unwrap();
+        buf.write_u32::<LittleEndian>(0).unwrap();
+        buf.write_u8(2).unwrap();
+
+        let a = Atom::load(buf.as_slice()).unwrap();
+        let s = State::find_symbol("io_new").unwrap();
+        assert!(s.is_linear());
+        assert_eq!(a, Atom::new_var(s));
+
+        buf[8] = FORMAT_VERSION as u8 + 1;
+        assert!(Atom::load(buf.as_slice()).is_err());
+
+        let f = State::get_symbol_with_attributes(
+            "io_lin",
+            vec![
+                FunctionAttribute::linear_in(&[1, 2]),
+                FunctionAttribute::Real,
+            ],
+        )
+        .unwrap();
+        let a = Atom::parse("io_lin(io_x, io_y, io_z)").unwrap();
+        let mut buf = vec![];
+        a.save(&mut buf).unwrap();
+        let b = Atom::load(buf.as_slice()).unwrap();
+        assert_eq!(b, a);
+        let AtomView::Fun(ff) = b.as_view() else {
+            unreachable!()
+        };
+        assert_eq!(ff.get_symbol(), f);
+    }
+
+    #[test]
+    fn mapped_terms() {
+        let path = std::env::temp_dir().join(format!("mapped_terms_{}.sym", std::process::id()));
+
+        let x = Atom::parse("mt_x").unwrap();
+        let f = State::get_symbol("mt_f");
+        let mut w = TermWriter::new(std::fs::File::create(&path).unwrap()).unwrap();
+        for i in 0..20000 {
+            let t = FunctionBuilder::new(f).add_arg(&Atom::new_num(i)).finish() * &x;
+            w.write_term(t.as_view()).unwrap();
+        }
+        w.finish().unwrap();
+
+        let terms = MappedTerms::open(&path).unwrap();
+        assert_eq!(terms.len(), 20000);
+        assert!(terms.pages.len() > 1);
+
+        for i in [0, 1, 4321, 19999] {
+            let t = FunctionBuilder::new(f)
+                .add_arg(&Atom::new_num(i as i64))
+                .finish()
+                * &x;
+            assert_eq!(terms.get(i).unwrap(), t);
+        }
+        assert_eq!(terms.iter().count(), 20000);
+        assert_eq!(
+            terms.iter().nth(12345).unwrap().unwrap(),
+            terms.get(12345).unwrap()
+        );
+
+        let pats = [
+            Pattern::parse("mt_f(7)").unwrap(),
+            Pattern::parse("mt_f(15000)").unwrap(),
+        ];
+        let (conditions, settings) = (Condition::default(), MatchSettings::default());
+        let found = terms
+            .find(|t| {
+                pats.iter()
+                    .any(|p| t.match_first(p, &conditions, &settings).is_some())
+            })
+            .unwrap();
+        assert_eq!(found, [7, 15000]);
+
+        let mut out = TermWriter::new(vec![]).unwrap();
+        terms.map(|_, t| &t / &x, &mut out).unwrap();
+        let data = out.finish().unwrap();
+        let mut reader = TermReader::new(data.as_slice()).unwrap().enumerate();
+        assert!(reader.all(|(i, t)| {
+            t.unwrap()
+                == FunctionBuilder::new(f)
+                    .add_arg(&Atom::new_num(i as i64))
+                    .finish()
+        }));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}